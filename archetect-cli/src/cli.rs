@@ -1,8 +1,13 @@
+use archetect_core::cache;
 use archetect_core::config::{AnswerConfig, AnswerConfigError, AnswerInfo};
+use archetect_core::output::{OutputChoice, OutputStyle, Verbosity};
+use archetect_core::sandbox::{ContainerEngine, SandboxConfig};
 use clap::{crate_authors, crate_description, crate_version};
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use crate::json_log::JsonLogger;
 use crate::vendor::loggerv;
 use log::Level;
+use std::time::Duration;
 
 pub fn get_matches() -> App<'static, 'static> {
     App::new("archetect")
@@ -16,7 +21,15 @@ pub fn get_matches() -> App<'static, 'static> {
                 .long("verbose")
                 .multiple(true)
                 .global(true)
-                .help("Increases the level of verbosity"),
+                .help("Increases the level of verbosity: -v adds debug logging and streams collapsed command output, -vv adds trace logging"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .conflicts_with("verbosity")
+                .help("Only reports errors, hiding print, display, and command output, and summaries"),
         )
         .arg(
             Arg::with_name("offline")
@@ -32,6 +45,165 @@ pub fn get_matches() -> App<'static, 'static> {
                 input.")
                 .long("headless"),
         )
+        .arg(
+            Arg::with_name("tui")
+                .global(true)
+                .help("Answer prompts in a full-screen form, alongside the progress of the render")
+                .long("tui")
+                .conflicts_with("headless"),
+        )
+        .arg(
+            Arg::with_name("interface")
+                .global(true)
+                .help("How prompts are answered: on the terminal, or as lines of JSON over stdin and stdout")
+                .long("interface")
+                .takes_value(true)
+                .possible_values(&["terminal", "jsonl"])
+                .conflicts_with_all(&["headless", "tui"]),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .global(true)
+                .help("How log records are written: as text, or as lines of JSON with structured fields")
+                .long("log-format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .global(true)
+                .help("Whether output and prompts are colored; NO_COLOR disables color when auto")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("unicode")
+                .global(true)
+                .help("Whether output and prompts use unicode symbols and emoji, rather than only ASCII")
+                .long("unicode")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("no-submodules")
+                .global(true)
+                .help("Do not initialize or update git submodules in cached archetype repositories")
+                .long("no-submodules"),
+        )
+        .arg(
+            Arg::with_name("no-lfs")
+                .global(true)
+                .help("Do not fetch Git LFS objects in cached archetype repositories")
+                .long("no-lfs"),
+        )
+        .arg(
+            Arg::with_name("explain-answers")
+                .global(true)
+                .help("After rendering, list every variable's value along with where that value came from")
+                .long("explain-answers"),
+        )
+        .arg(
+            Arg::with_name("keep-going")
+                .global(true)
+                .help("Continue past templates that fail to render, reporting every failure once rendering completes")
+                .long("keep-going"),
+        )
+        .arg(
+            Arg::with_name("trust")
+                .global(true)
+                .long("trust")
+                .help(
+                    "Allow remote archetypes to run the commands they declare without asking. Without it, remote \
+                     archetypes are asked about unless always allowed before, and refused when headless",
+                ),
+        )
+        .arg(
+            Arg::with_name("allow-commands")
+                .global(true)
+                .long("allow-commands")
+                .help(
+                    "Allow templates to run commands with shell_capture() or cmd(), for archetypes trusted to run \
+                     commands",
+                ),
+        )
+        .arg(
+            Arg::with_name("sandbox")
+                .global(true)
+                .long("sandbox")
+                .takes_value(true)
+                .value_name("image")
+                .help(
+                    "Run exec actions in a container of this image, with only the destination mounted, unless the \
+                     archetype declares a sandbox of its own",
+                ),
+        )
+        .arg(
+            Arg::with_name("sandbox-engine")
+                .global(true)
+                .long("sandbox-engine")
+                .takes_value(true)
+                .possible_values(&["docker", "podman"])
+                .requires("sandbox")
+                .help("The container engine to run --sandbox containers with [default: docker]"),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .global(true)
+                .long("max-depth")
+                .takes_value(true)
+                .value_name("depth")
+                .validator(|depth| match depth.parse::<usize>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("`{}` is not a depth; expected a whole number", depth)),
+                })
+                .help("How deeply archetypes may render other archetypes [default: 16]"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .global(true)
+                .long("depth")
+                .takes_value(true)
+                .value_name("commits")
+                .validator(|depth| match depth.parse::<u32>() {
+                    Ok(depth) if depth > 0 => Ok(()),
+                    _ => Err(format!("`{}` is not a depth; expected a whole number above zero", depth)),
+                })
+                .help("Clone remote archetypes shallowly, with only this many commits of each branch"),
+        )
+        .arg(
+            Arg::with_name("deny-warnings")
+                .global(true)
+                .help("Fail if rendering produces warnings, such as unrendered template delimiters or colliding files")
+                .long("deny-warnings"),
+        )
+        .arg(
+            Arg::with_name("validate")
+                .global(true)
+                .help("Report rendered JSON, YAML, TOML, and XML files that are not valid")
+                .long("validate"),
+        )
+        .arg(
+            Arg::with_name("no-history")
+                .global(true)
+                .help("Neither offer recently entered answers as defaults at prompts, nor remember new ones")
+                .long("no-history"),
+        )
+        .arg(
+            Arg::with_name("metadata")
+                .global(true)
+                .help("Record the archetype source, revision, and answers in a metadata file within the destination")
+                .long("metadata"),
+        )
+        .arg(
+            Arg::with_name("backup")
+                .global(true)
+                .help("Copy files the render or upgrade overwrites, and note those it adds, into .archetect/backups within the destination")
+                .long("backup"),
+        )
         .arg(
             Arg::with_name("answer")
                 .short("a")
@@ -59,7 +231,7 @@ pub fn get_matches() -> App<'static, 'static> {
                 .multiple(true)
                 .global(true)
                 .empty_values(true)
-                .help("Enable switches that may trigger functionality within Archetypes")
+                .help("Enable switches that may trigger functionality within Archetypes, and turn on switch variables")
         )
         .arg(
             Arg::with_name("answer-file")
@@ -81,6 +253,7 @@ pub fn get_matches() -> App<'static, 'static> {
                     Err(AnswerConfigError::MissingError) => {
                         Err(format!("{} does not exist or does not contain an answer file", &af))
                     }
+                    Err(error) => Err(format!("{}: {}", &af, error)),
                 }),
         )
         .subcommand(
@@ -131,15 +304,73 @@ pub fn get_matches() -> App<'static, 'static> {
                         )
                         .subcommand(
                             SubCommand::with_name("answers").about("The location where answers are specified."),
+                        )
+                        .subcommand(
+                            SubCommand::with_name("history")
+                                .about("The location where recently entered answers are remembered."),
+                        )
+                        .subcommand(
+                            SubCommand::with_name("overrides")
+                                .about("The location where remote sources are mapped to local working copies."),
+                        )
+                        .subcommand(
+                            SubCommand::with_name("trusted")
+                                .about("The location where remote archetypes always allowed to run commands are kept."),
+                        )
+                        .subcommand(
+                            SubCommand::with_name("settings")
+                                .about("The location of the user config, changed with `archetect config`."),
                         ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Read and change defaults in the user config")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Print the value of a setting")
+                        .arg(setting_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Change a setting")
+                        .arg(setting_arg())
+                        .arg(
+                            Arg::with_name("value")
+                                .help("The new value, such as true or false for offline and headless, or ours, \
+                                       theirs, merge, or ask for conflicts")
+                                .takes_value(true)
+                                .required(true),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("list").about("Print every setting that is set"))
+                .subcommand(SubCommand::with_name("edit").about("Open the user config in $VISUAL or $EDITOR")),
+        )
         .subcommand(
             SubCommand::with_name("cache")
                 .about("Manage/Select from Archetypes cached from Git Repositories")
                 .subcommand(SubCommand::with_name("select"))
                 .subcommand(SubCommand::with_name("clear"))
-                .subcommand(SubCommand::with_name("pull")),
+                .subcommand(SubCommand::with_name("pull"))
+                .subcommand(
+                    SubCommand::with_name("gc")
+                        .about(
+                            "Removes refs checked out from git repositories that have gone unused, and repositories \
+                             whose remotes no longer exist. Also run daily after rendering",
+                        )
+                        .arg(
+                            Arg::with_name("max-age")
+                                .long("max-age")
+                                .takes_value(true)
+                                .value_name("days")
+                                .validator(|days| match days.parse::<u64>() {
+                                    Ok(_) => Ok(()),
+                                    Err(_) => Err(format!("`{}` is not a number of days", days)),
+                                })
+                                .help("How many days a checked out ref may go unused [default: 30]"),
+                        ),
+                ),
         )
         .subcommand(
             SubCommand::with_name("render")
@@ -147,36 +378,292 @@ pub fn get_matches() -> App<'static, 'static> {
                 .about("Creates content from an Archetype")
                 .arg(
                     Arg::with_name("source")
-                        .help("The Archetype source directory or git URL")
+                        .help(
+                            "The Archetype source directory, git URL, or shorthand such as gh:org/repository. Chosen \
+                             from your catalog when not given",
+                        )
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .default_value(".")
+                        .help("The directory the Archetype should be rendered into.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("only")
+                        .long("only")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("glob-or-component")
+                        .help(
+                            "Renders only a component the archetype declares, or the source paths matching a glob \
+                             relative to the archetype, leaving the rest of the destination untouched. Actions such as \
+                             `exec` and `append` are skipped unless they belong to a component selected. May be given \
+                             more than once.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("skip")
+                        .long("skip")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("glob-or-component")
+                        .help(
+                            "Skips rendering a component the archetype declares, or the source paths matching a glob \
+                             relative to the archetype, whatever the archetype's rules say, along with the actions that \
+                             belong to a component skipped. May be given more than once.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .takes_value(true)
+                        .value_name("path")
+                        .help(
+                            "Writes a report of the archetypes, answers, files, actions, and warnings of the render, as \
+                             JSON if the path ends with .json, and YAML otherwise. Sensitive answers are left out.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("plan")
+                        .long("plan")
+                        .help(
+                            "Lists the steps the archetype's actions describe, without writing anything or running \
+                             any commands",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("console")
+                .about("Prompts for an Archetype's answers, then evaluates Tera expressions against the resulting context")
+                .arg(
+                    Arg::with_name("source")
+                        .help("The Archetype source directory, git URL, or shorthand such as gh:org/repository")
                         .takes_value(true)
                         .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("render-file")
+                .about("Renders a single template file with the supplied answers")
+                .arg(
+                    Arg::with_name("template")
+                        .help("The template file to render")
+                        .required(true),
                 )
+                .arg(
+                    Arg::with_name("destination")
+                        .help("The file to write; the result is printed if omitted"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("docs")
+                .about("Generates documentation of an Archetype's variables, components, and actions")
+                .arg(
+                    Arg::with_name("source")
+                        .help("The Archetype source directory, git URL, or shorthand such as gh:org/repository")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["markdown", "html"])
+                        .default_value("markdown")
+                        .help("The format of the documentation"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .help("The file to write the documentation to; it is printed if omitted"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("graph")
+                .about("Prints the Archetypes an Archetype renders as components, recursively, with their versions")
+                .arg(
+                    Arg::with_name("source")
+                        .help("The Archetype source directory, git URL, or shorthand such as gh:org/repository")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["dot", "mermaid"])
+                        .default_value("dot")
+                        .help("The format of the graph"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("eval")
+                .about("Evaluates a Tera expression against the supplied answers, and prints the result")
+                .arg(
+                    Arg::with_name("expression")
+                        .help("An expression such as `'MyProject' | kebab_case`, or a template such as `{{ name }}-svc`")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Renders two versions of an Archetype with the same answers, and lists the files that differ")
+                .arg(
+                    Arg::with_name("from")
+                        .help("The older version, such as https://github.com/archetect/archetype-rust-cli.git#v1.0")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .help("The newer version, such as https://github.com/archetect/archetype-rust-cli.git#v2.0")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("batch")
+                .about("Renders every Archetype listed in a manifest, such as all of the services in a monorepo")
+                .arg(
+                    Arg::with_name("manifest")
+                        .help("A YAML manifest listing the source, destination, and answers of each instance")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("outdated")
+                .about("Reports the git Archetypes that have newer tags or commits than the revisions in use")
+                .arg(
+                    Arg::with_name("sources")
+                        .help("Git sources to check; defaults to every Archetype in the git cache")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("project")
+                        .long("project")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A directory previously rendered with --metadata, checked at the revision it records"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("upgrade")
+                .about("Re-renders a destination from a newer version of the Archetype that produced it")
                 .arg(
                     Arg::with_name("destination")
                         .default_value(".")
-                        .help("The directory the Archetype should be rendered into.")
+                        .help("A directory previously rendered with --metadata.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ref")
+                        .long("ref")
+                        .short("r")
+                        .takes_value(true)
+                        .help("The branch, tag, or commit to upgrade to. Defaults to the latest default branch."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Restores the files a render or upgrade with --backup overwrote, and removes those it added")
+                .arg(
+                    Arg::with_name("destination")
+                        .default_value(".")
+                        .help("A directory previously rendered or upgraded with --backup.")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("id")
+                        .long("id")
+                        .takes_value(true)
+                        .value_name("backup")
+                        .help("The backup to restore, as listed by --list. Defaults to the latest."),
+                )
+                .arg(
+                    Arg::with_name("list")
+                        .long("list")
+                        .help("List the destination's backups, oldest first, rather than restoring one"),
                 ),
         )
 }
 
+fn setting_arg() -> Arg<'static, 'static> {
+    Arg::with_name("setting")
+        .help("The setting: offline, headless, conflicts, hosts.<alias>, or prefixes.<prefix>")
+        .takes_value(true)
+        .required(true)
+}
+
 pub fn configure(matches: &ArgMatches) {
+    if json_logs(matches) {
+        JsonLogger::init(verbosity(matches).log_level()).unwrap();
+        return;
+    }
     loggerv::Logger::new()
+        .force_colors(output_style(matches).color())
         .output(&Level::Error, crate::vendor::loggerv::Output::Stderr)
         .output(&Level::Warn, crate::vendor::loggerv::Output::Stderr)
         .output(&Level::Info, crate::vendor::loggerv::Output::Stderr)
         .output(&Level::Debug, crate::vendor::loggerv::Output::Stderr)
         .output(&Level::Trace, crate::vendor::loggerv::Output::Stderr)
-        .verbosity(matches.occurrences_of("verbosity"))
         .level(false)
         .prefix("archetect")
         .no_module_path()
         .module_path(false)
-        .base_level(log::Level::Info)
+        .max_level(verbosity(matches).log_level())
         .init()
         .unwrap();
 }
 
+/// The styling chosen by `--color` and `--unicode`, resolved against the terminal.
+pub fn json_logs(matches: &ArgMatches) -> bool {
+    matches.value_of("log-format") == Some("json")
+}
+
+pub fn sandbox(matches: &ArgMatches) -> Option<SandboxConfig> {
+    let sandbox = SandboxConfig::new(matches.value_of("sandbox")?);
+    Some(match matches.value_of("sandbox-engine") {
+        Some("podman") => sandbox.with_engine(ContainerEngine::Podman),
+        Some(_) => sandbox.with_engine(ContainerEngine::Docker),
+        None => sandbox,
+    })
+}
+
+pub fn max_depth(matches: &ArgMatches) -> usize {
+    matches
+        .value_of("max-depth")
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(archetect_core::DEFAULT_MAX_DEPTH)
+}
+
+pub fn clone_depth(matches: &ArgMatches) -> Option<u32> {
+    matches.value_of("depth").and_then(|depth| depth.parse().ok())
+}
+
+pub fn max_age(matches: &ArgMatches) -> Duration {
+    matches
+        .value_of("max-age")
+        .and_then(|days| days.parse::<u64>().ok())
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+        .unwrap_or(cache::DEFAULT_MAX_AGE)
+}
+
+pub fn verbosity(matches: &ArgMatches) -> Verbosity {
+    Verbosity::from_flags(matches.is_present("quiet"), matches.occurrences_of("verbosity"))
+}
+
+pub fn output_style(matches: &ArgMatches) -> OutputStyle {
+    let choice = |name: &str| {
+        matches
+            .value_of(name)
+            .and_then(|value| value.parse::<OutputChoice>().ok())
+            .unwrap_or_default()
+    };
+    OutputStyle::detect(choice("color"), choice("unicode"))
+}
+
 const VALID_ANSWER_INPUTS: &str = "Supply a key=value pair as an answer to a variable question. \
                                    This option may be specified more than once.\n\
                                    \nValid Input Examples:\n\
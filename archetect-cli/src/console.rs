@@ -0,0 +1,61 @@
+use std::io::{self, BufRead, Write};
+
+use archetect_core::vendor::tera::Context;
+use archetect_core::{Archetect, ArchetectError, RenderError};
+
+const HELP: &str = "\
+Enter a Tera expression (`name | pascal_case`) or template (`{{ name }}-{{ version }}`) to evaluate it.
+
+  :vars     List the variables in the context
+  :context  Print the full context as JSON
+  :help     Show this message
+  :quit     Exit the console";
+
+/// Reads expressions from stdin and evaluates them against `context` until end of input or `:quit`.
+pub fn run(archetect: &mut Archetect, context: &Context) -> Result<(), ArchetectError> {
+    eprintln!("{}\n", HELP);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    loop {
+        eprint!("> ");
+        io::stderr().flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            eprintln!();
+            return Ok(());
+        }
+
+        match line.trim() {
+            "" => (),
+            ":quit" | ":q" | ":exit" => return Ok(()),
+            ":help" | ":h" => eprintln!("{}", HELP),
+            ":context" => println!("{:#}", context.clone().into_json()),
+            ":vars" => {
+                if let Some(values) = context.clone().into_json().as_object() {
+                    for (identifier, value) in values {
+                        println!("{} = {}", identifier, value);
+                    }
+                }
+            }
+            expression => match evaluate(archetect, expression, context) {
+                Ok(result) => println!("{}", result),
+                Err(error) => eprintln!("{}", error),
+            },
+        }
+    }
+}
+
+/// Evaluates an expression or template against `context`.
+pub fn evaluate(archetect: &mut Archetect, expression: &str, context: &Context) -> Result<String, RenderError> {
+    archetect.render_string(&to_template(expression), context)
+}
+
+/// Bare expressions are wrapped in an output block; anything already containing Tera delimiters is rendered as-is.
+fn to_template(expression: &str) -> String {
+    if expression.contains("{{") || expression.contains("{%") {
+        expression.to_owned()
+    } else {
+        format!("{{{{ {} }}}}", expression)
+    }
+}
@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use archetect_core::events::{Event, EventSubscriber};
+use log::kv::{self, Key, Value, VisitSource};
+use log::{debug, info, trace, Level, Log, Metadata, Record, SetLoggerError};
+use serde_json::{Map, Value as JsonValue};
+
+const EVENTS_TARGET: &str = "archetect::events";
+
+/// Writes each log record to stderr as a line of JSON, with `--log-format json`, for CI log processors and other
+/// machine consumers:
+///
+/// ```json
+/// {"action":"render","duration_ms":12,"level":"info","message":"Finished render","succeeded":true,"target":"archetect::events"}
+/// ```
+pub struct JsonLogger {
+    level: Level,
+}
+
+impl JsonLogger {
+    pub fn init(level: Level) -> Result<(), SetLoggerError> {
+        log::set_max_level(level.to_level_filter());
+        log::set_boxed_logger(Box::new(JsonLogger { level }))
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = Map::new();
+        line.insert("level".to_owned(), record.level().as_str().to_lowercase().into());
+        line.insert("target".to_owned(), record.target().into());
+        line.insert("message".to_owned(), record.args().to_string().into());
+        let _ = record.key_values().visit(&mut Fields(&mut line));
+        let _ = writeln!(io::stderr().lock(), "{}", JsonValue::Object(line));
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+struct Fields<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_bool() {
+            value.into()
+        } else if let Some(value) = value.to_u64() {
+            value.into()
+        } else if let Some(value) = value.to_i64() {
+            value.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().to_owned(), value);
+        Ok(())
+    }
+}
+
+/// Logs render events as records with structured fields, such as the action, file path, and how long each action took,
+/// where text logs would leave them to free-form messages.
+#[derive(Default)]
+pub struct EventLog {
+    started: RefCell<Vec<Instant>>,
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        EventLog::default()
+    }
+}
+
+impl EventSubscriber for EventLog {
+    fn notify(&self, event: &Event) {
+        match event {
+            Event::SourceResolved(source) => {
+                debug!(target: EVENTS_TARGET, source = source.source(); "Resolved {}", source.source())
+            }
+            Event::PromptAsked(prompt) => {
+                debug!(target: EVENTS_TARGET, variable = prompt.identifier(); "Prompting for {}", prompt.identifier())
+            }
+            Event::ActionStarted { action } => {
+                self.started.borrow_mut().push(Instant::now());
+                debug!(target: EVENTS_TARGET, action = action.as_str(); "Started {}", action);
+            }
+            Event::ActionFinished { action, succeeded } => {
+                // Actions nest, so the most recently started action is the one finishing.
+                let duration = self
+                    .started
+                    .borrow_mut()
+                    .pop()
+                    .map(|started| started.elapsed().as_millis() as u64)
+                    .unwrap_or_default();
+                info!(
+                    target: EVENTS_TARGET,
+                    action = action.as_str(), succeeded = *succeeded, duration_ms = duration;
+                    "Finished {}", action
+                );
+            }
+            Event::RenderPlanned { files, bytes } => {
+                debug!(
+                    target: EVENTS_TARGET,
+                    files = *files as u64, bytes = *bytes;
+                    "Planned {} files of {} bytes", files, bytes
+                );
+            }
+            Event::TemplateRendered { template, parse, render } => {
+                let path = template.display().to_string();
+                trace!(
+                    target: EVENTS_TARGET,
+                    path = path.as_str(), parse_us = parse.as_micros() as u64, render_us = render.as_micros() as u64;
+                    "Rendered template {}", path
+                );
+            }
+            Event::FileWritten { destination } => {
+                let path = destination.display().to_string();
+                info!(target: EVENTS_TARGET, path = path.as_str(); "Wrote {}", path);
+            }
+            Event::RenderComplete { files, warnings } => {
+                info!(
+                    target: EVENTS_TARGET,
+                    files = *files as u64, warnings = *warnings as u64;
+                    "Rendered {} files with {} warnings", files, warnings
+                );
+            }
+            _ => (),
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
 
+use ansi_term::{Colour, Style};
 use clap::{ArgMatches, Shell};
 use linked_hash_map::LinkedHashMap;
 use log::{error, info, warn};
@@ -9,20 +12,39 @@ use log::{error, info, warn};
 use archetect_core::{Archetect};
 use archetect_core::{self, ArchetectError};
 use archetect_core::config::{
-    AnswerConfig, AnswerInfo, Catalog, CATALOG_FILE_NAME, CatalogEntry,
+    AnswerConfig, AnswerHistory, AnswerInfo, Catalog, CATALOG_FILE_NAME, CatalogEntry, CatalogError, OverrideConfig,
+    UserConfig,
 };
-use archetect_core::input::select_from_catalog;
+use archetect_core::backup;
+use archetect_core::batch::{self, BatchManifest};
+use archetect_core::cache::{self, CacheCollection};
+use archetect_core::compare::{self, FileChange};
+use archetect_core::docs::{self, DocsFormat};
+use archetect_core::graph::{self, GraphFormat};
+use archetect_core::input::{select_from_catalog, JsonLinesPromptProvider};
+use archetect_core::metadata::RenderMetadata;
+use archetect_core::output::OutputStyle;
+use archetect_core::outdated::{self, TrackedSource};
+use archetect_core::provenance::AnswerOrigin;
+use archetect_core::report::ReportRecorder;
+use archetect_core::upgrade::{self, UpgradeOutcome, UpgradeSummary};
+use archetect_core::setup::{self, Setup};
 use archetect_core::source::{Source};
+use archetect_core::vendor::tera::Context;
 
 mod cli;
+mod console;
+mod json_log;
 pub mod vendor;
+#[cfg(feature = "tui")]
+mod wizard;
 
 fn main() {
     let matches = cli::get_matches().get_matches();
 
     cli::configure(&matches);
 
-    match execute(matches) {
+    match execute(&matches) {
         Ok(()) => (),
         Err(error) => {
             error!("{}", error);
@@ -31,11 +53,129 @@ fn main() {
     }
 }
 
-fn execute(matches: ArgMatches) -> Result<(), ArchetectError> {
-    let mut archetect = Archetect::builder()
+fn execute(matches: &ArgMatches) -> Result<(), ArchetectError> {
+    let output_style = cli::output_style(matches);
+    #[cfg(feature = "tui")]
+    let wizard = if matches.is_present("tui") {
+        Some(Rc::new(wizard::Wizard::new(output_style)))
+    } else {
+        None
+    };
+    #[cfg(not(feature = "tui"))]
+    if matches.is_present("tui") {
+        warn!("This build of archetect does not include the TUI; prompting line by line instead.");
+    }
+
+    let builder = Archetect::builder()
         .with_offline(matches.is_present("offline"))
         .with_headless(matches.is_present("headless"))
-        .build()?;
+        .with_submodules(!matches.is_present("no-submodules"))
+        .with_lfs(!matches.is_present("no-lfs"))
+        .with_metadata(matches.is_present("metadata"))
+        .with_backup(matches.is_present("backup"))
+        .with_keep_going(matches.is_present("keep-going"))
+        .with_max_depth(cli::max_depth(matches))
+        .with_trust(matches.is_present("trust"))
+        .with_allow_commands(matches.is_present("allow-commands"))
+        .with_deny_warnings(matches.is_present("deny-warnings"))
+        .with_validate(matches.is_present("validate"))
+        .with_output_style(output_style)
+        .with_verbosity(cli::verbosity(matches));
+    let builder = if cli::json_logs(matches) {
+        builder.with_event_subscriber(json_log::EventLog::new())
+    } else {
+        builder
+    };
+    let builder = match cli::sandbox(matches) {
+        Some(sandbox) => builder.with_sandbox(sandbox),
+        None => builder,
+    };
+    let builder = match cli::clone_depth(matches) {
+        Some(depth) => builder.with_clone_depth(depth),
+        None => builder,
+    };
+    let only = matches
+        .subcommand_matches("render")
+        .and_then(|matches| matches.values_of("only"))
+        .into_iter()
+        .flatten();
+    let builder = only.fold(builder, |builder, selection| builder.with_only(selection));
+    let skip = matches
+        .subcommand_matches("render")
+        .and_then(|matches| matches.values_of("skip"))
+        .into_iter()
+        .flatten();
+    let builder = skip.fold(builder, |builder, exclusion| builder.with_skip(exclusion));
+    let report = matches
+        .subcommand_matches("render")
+        .and_then(|matches| matches.value_of("report"))
+        .map(|path| (path, Rc::new(ReportRecorder::new())));
+    let builder = match &report {
+        Some((_, recorder)) => builder.with_event_subscriber(recorder.clone()),
+        None => builder,
+    };
+    let builder = match matches.value_of("interface") {
+        // Stdout carries the prompts, so anything else that would be printed there goes to stderr.
+        Some("jsonl") => builder
+            .with_prompt_provider(JsonLinesPromptProvider::new(io::stdin().lock(), io::stdout()))
+            .with_print_output(io::stderr()),
+        _ => builder,
+    };
+    #[cfg(feature = "tui")]
+    let builder = match &wizard {
+        Some(wizard) => builder
+            .with_prompt_provider(wizard.clone())
+            .with_event_subscriber(wizard.clone()),
+        None => builder,
+    };
+
+    let mut archetect = builder.build()?;
+
+    match UserConfig::load(archetect.layout().user_config()) {
+        Ok(user_config) => {
+            archetect.set_offline(matches.is_present("offline") || user_config.offline().unwrap_or_default());
+            archetect.set_headless(matches.is_present("headless") || user_config.headless().unwrap_or_default());
+            archetect.set_default_conflict_strategy(user_config.conflicts());
+            for (alias, host) in user_config.host_aliases() {
+                archetect.add_host_alias(alias, host);
+            }
+            for (prefix, url) in user_config.source_prefixes() {
+                archetect.add_source_prefix(prefix, url);
+            }
+        }
+        Err(error) => warn!("{}", error),
+    }
+
+    // Completions, layout paths, and settings are read by scripts, so are not interrupted by setup.
+    let scripted = matches!(matches.subcommand_name(), Some("completions") | Some("system") | Some("config"));
+    if !scripted && !archetect.headless() && setup::is_first_run(archetect.layout().as_ref().as_ref()) {
+        eprintln!("Welcome to Archetect! Let's set up your defaults, which you can change at any time.");
+        let setup = setup::run(&archetect)?;
+        print_setup(&setup);
+    }
+
+    match OverrideConfig::load(archetect.layout().overrides_config()) {
+        Ok(Some(override_config)) => {
+            for (source, path) in override_config.overrides() {
+                archetect.add_source_override(source, path);
+            }
+            for (alias, host) in override_config.host_aliases() {
+                archetect.add_host_alias(alias, host);
+            }
+            for (prefix, url) in override_config.source_prefixes() {
+                archetect.add_source_prefix(prefix, url);
+            }
+        }
+        Ok(None) => (),
+        Err(error) => warn!("{}", error),
+    }
+
+    if !matches.is_present("no-history") {
+        match AnswerHistory::load(archetect.layout().answer_history()) {
+            Ok(history) => archetect.set_answer_history(Some(history)),
+            Err(error) => warn!("{}", error),
+        }
+    }
 
     let mut answers = LinkedHashMap::new();
 
@@ -64,7 +204,8 @@ fn execute(matches: ArgMatches) -> Result<(), ArchetectError> {
     }
 
     if let Some(matches) = matches.values_of("answer") {
-        for (identifier, answer_info) in matches.map(|m| AnswerInfo::parse(m).unwrap()) {
+        for (identifier, mut answer_info) in matches.map(|m| AnswerInfo::parse(m).unwrap()) {
+            answer_info.set_origin(AnswerOrigin::CommandLine);
             answers.insert(identifier, answer_info);
         }
     }
@@ -75,11 +216,19 @@ fn execute(matches: ArgMatches) -> Result<(), ArchetectError> {
         }
     }
 
+    if let Some(matches) = matches.subcommand_matches("config") {
+        return configure(&archetect, matches);
+    }
+
     if let Some(matches) = matches.subcommand_matches("cache") {
         let git_cache = archetect.layout().git_cache_dir();
         if let Some(_sub_matches) = matches.subcommand_matches("clear") {
             fs::remove_dir_all(&git_cache).expect("Error deleting archetect cache");
         }
+        if let Some(sub_matches) = matches.subcommand_matches("gc") {
+            let collection = cache::collect(&archetect, cli::max_age(sub_matches))?;
+            print_cache_collection(&collection);
+        }
     }
 
     if let Some(matches) = matches.subcommand_matches("completions") {
@@ -106,63 +255,538 @@ fn execute(matches: ArgMatches) -> Result<(), ArchetectError> {
                 ("answers", Some(_)) => eprintln!("{}", archetect.layout().answers_config().display()),
                 ("catalogs", Some(_)) => eprintln!("{}", archetect.layout().catalog_cache_dir().display()),
                 ("config", Some(_)) => eprintln!("{}", archetect.layout().configs_dir().display()),
+                ("history", Some(_)) => eprintln!("{}", archetect.layout().answer_history().display()),
+                ("overrides", Some(_)) => eprintln!("{}", archetect.layout().overrides_config().display()),
+                ("trusted", Some(_)) => eprintln!("{}", archetect.layout().trusted_sources().display()),
+                ("settings", Some(_)) => eprintln!("{}", archetect.layout().user_config().display()),
                 _ => eprintln!("{}", archetect.layout()),
             }
         }
     }
 
     if let Some(matches) = matches.subcommand_matches("render") {
-        let source = matches.value_of("source").unwrap();
+        let source = match matches.value_of("source") {
+            Some(source) => source.to_owned(),
+            None if archetect.headless() => return Err(ArchetectError::HeadlessMissingSource),
+            None => match select_catalog_archetype(&archetect, None)? {
+                Some(source) => source,
+                None => return Err(CatalogError::NotFound(archetect.layout().catalog()).into()),
+            },
+        };
         let destination = PathBuf::from_str(matches.value_of("destination").unwrap()).unwrap();
 
-        let archetype = archetect.load_archetype(source, None)?;
+        if matches.is_present("plan") {
+            // Rendering in memory writes nothing and runs no commands, while still collecting each step described.
+            let archetype = archetect.load_archetype(&source, None)?;
+            archetype.render_in_memory(&mut archetect, &answers)?;
+            print_steps(&archetect, "Steps planned:");
+            return Ok(());
+        }
 
-        if let Ok(answer_config) = AnswerConfig::load(destination.clone()) {
-            for (identifier, answer_info) in answer_config.answers() {
-                answers.insert(identifier.to_owned(), answer_info.clone());
+        let recorder = report.as_ref().map(|(_, recorder)| recorder.as_ref());
+        let result = render(&mut archetect, &source, &destination, &answers, recorder);
+        if let Some((path, recorder)) = &report {
+            recorder.report(&archetect, result.as_ref().err()).save(path)?;
+        }
+        result?;
+        #[cfg(feature = "tui")]
+        if let Some(wizard) = &wizard {
+            wizard.finish()?;
+        }
+        save_answer_history(&archetect);
+        if let Some(collection) = cache::collect_if_due(&archetect, cache::DEFAULT_MAX_AGE) {
+            if !archetect.verbosity().is_quiet() && !collection.is_empty() {
+                print_cache_collection(&collection);
             }
         }
-        archetype.render(&mut archetect, &destination, &answers)?;
+        print_steps(&archetect, "Steps performed:");
+        if matches.is_present("explain-answers") {
+            explain_answers(&archetect);
+        }
     }
 
-    if let Some(matches) = matches.subcommand_matches("catalog") {
-        let default_source = archetect.layout().catalog().to_str().map(|s| s.to_owned()).unwrap();
-        let source = matches.value_of("source").unwrap_or_else(|| &default_source);
-        let source = Source::detect(&archetect, source, None)?;
+    if let Some(matches) = matches.subcommand_matches("console") {
+        let archetype = archetect.load_archetype(matches.value_of("source").unwrap(), None)?;
+
+        // Nothing is written, and no commands run, just to open the console.
+        let context = archetype.execute_in_memory(&mut archetect, &answers)?;
+        #[cfg(feature = "tui")]
+        if let Some(wizard) = &wizard {
+            wizard.finish()?;
+        }
+        save_answer_history(&archetect);
+        console::run(&mut archetect, &context)?;
+    }
 
-        let mut catalog_file = source.local_path().to_owned();
-        if catalog_file.is_dir() {
-            catalog_file.push(CATALOG_FILE_NAME);
+    if let Some(matches) = matches.subcommand_matches("render-file") {
+        let context = answers_context(&answers);
+        let contents = archetect.render_contents(matches.value_of("template").unwrap(), &context)?;
+        match matches.value_of("destination") {
+            Some(destination) => {
+                let destination = PathBuf::from(destination);
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                archetect.write_contents(&destination, &contents)?;
+            }
+            None => print!("{}", contents),
         }
+    }
 
-        if catalog_file.exists() {
-            let catalog_source = Source::detect(&archetect, catalog_file.to_str().unwrap(), None)?;
-            let catalog = Catalog::load(source.clone())?;
+    if let Some(matches) = matches.subcommand_matches("docs") {
+        let archetype = archetect.load_archetype(matches.value_of("source").unwrap(), None)?;
+        let format = match matches.value_of("format") {
+            Some("html") => DocsFormat::Html,
+            _ => DocsFormat::Markdown,
+        };
+        let documentation = docs::generate(&archetype, format);
+        match matches.value_of("output") {
+            Some(output) => fs::write(output, documentation)?,
+            None => print!("{}", documentation),
+        }
+    }
 
-            let catalog_entry = select_from_catalog(&archetect, &catalog, &catalog_source)?;
+    if let Some(matches) = matches.subcommand_matches("graph") {
+        let archetype = archetect.load_archetype(matches.value_of("source").unwrap(), None)?;
+        let format = match matches.value_of("format") {
+            Some("mermaid") => GraphFormat::Mermaid,
+            _ => GraphFormat::Dot,
+        };
+        print!("{}", graph::graph(&archetect, &archetype)?.to_format(format));
+    }
 
-            match catalog_entry {
-                CatalogEntry::Archetype { description: _, source } => {
-                    let destination = PathBuf::from_str(matches.value_of("destination").unwrap()).unwrap();
+    if let Some(matches) = matches.subcommand_matches("eval") {
+        let context = answers_context(&answers);
+        let value = console::evaluate(&mut archetect, matches.value_of("expression").unwrap(), &context)?;
+        archetect.print(&value)?;
+    }
 
-                    let archetype = archetect.load_archetype(&source, None)?;
+    if let Some(matches) = matches.subcommand_matches("compare") {
+        let from = archetect.load_archetype(matches.value_of("from").unwrap(), None)?;
+        let to = archetect.load_archetype(matches.value_of("to").unwrap(), None)?;
+        let comparison = compare::compare(&mut archetect, &from, &to, &answers)?;
+        #[cfg(feature = "tui")]
+        if let Some(wizard) = &wizard {
+            wizard.finish()?;
+        }
+        save_answer_history(&archetect);
 
-                    if let Ok(answer_config) = AnswerConfig::load(destination.clone()) {
-                        for (identifier, answer_info) in answer_config.answers() {
-                            if !answers.contains_key(identifier) {
-                                answers.insert(identifier.to_owned(), answer_info.clone());
-                            }
-                        }
-                    }
-                    archetype.render(&mut archetect, &destination, &answers)?;
-                    return Ok(());
+        for (path, change) in comparison.files() {
+            let change = match change {
+                FileChange::Added => "added",
+                FileChange::Removed => "removed",
+                FileChange::Changed => "changed",
+            };
+            archetect.print(&format!("{:<8} {}", change, path.display()))?;
+        }
+        if !archetect.verbosity().is_quiet() {
+            eprintln!("{} changed, {} unchanged", comparison.files().len(), comparison.unchanged());
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("batch") {
+        let manifest = BatchManifest::load(matches.value_of("manifest").unwrap())?;
+        let report = batch::render_batch(&mut archetect, &manifest, &answers);
+        #[cfg(feature = "tui")]
+        if let Some(wizard) = &wizard {
+            wizard.finish()?;
+        }
+        save_answer_history(&archetect);
+
+        let quiet = archetect.verbosity().is_quiet();
+        for result in report.results() {
+            match result.error() {
+                None if quiet => (),
+                None => {
+                    eprintln!(
+                        "rendered {}  ({} files, {} warnings)",
+                        result.destination().display(),
+                        result.files(),
+                        result.warnings()
+                    );
+                    print_next_steps(&archetect, result.next_steps(), "         ")?;
+                }
+                Some(error) => eprintln!("failed   {}  ({}): {}", result.destination().display(), result.source(), error),
+            }
+        }
+        if !quiet {
+            eprintln!(
+                "\n{} of {} rendered, {} files in total",
+                report.results().len() - report.failures(),
+                report.results().len(),
+                report.files()
+            );
+        }
+        if report.failures() > 0 {
+            return Err(ArchetectError::BatchFailures(report.failures()));
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("outdated") {
+        let mut sources = vec![];
+        for source in matches.values_of("sources").into_iter().flatten() {
+            sources.push(TrackedSource::parse(&archetect, source)?);
+        }
+        for project in matches.values_of("project").into_iter().flatten() {
+            match RenderMetadata::load(project)? {
+                Some(metadata) => sources.push(TrackedSource::from_metadata(&archetect, &metadata)?),
+                None => warn!("{} has no render metadata; render it with --metadata to track its Archetype", project),
+            }
+        }
+        if !matches.is_present("sources") && !matches.is_present("project") {
+            sources = outdated::cached_sources(&archetect)?;
+        }
+
+        let mut updates = 0;
+        for source in &sources {
+            let revision = source.gitref().or(source.commit()).map(short_revision).unwrap_or("default branch");
+            let status = match outdated::check(&archetect, source) {
+                Ok(status) => status,
+                Err(error) => {
+                    warn!("Unable to check {}: {}", source.location(), error);
+                    continue;
                 }
-                _ => unreachable!(),
+            };
+            match (status.latest_tag(), status.latest_commit()) {
+                (Some(tag), _) => println!("{} {} -> {}", source.location(), revision, tag),
+                (None, Some(commit)) => println!("{} {} -> {}", source.location(), revision, short_revision(commit)),
+                (None, None) => println!("{} {} (up to date)", source.location(), revision),
+            }
+            if status.is_outdated() {
+                updates += 1;
+            }
+        }
+        if !archetect.verbosity().is_quiet() {
+            eprintln!("{} of {} Archetypes have updates available", updates, sources.len());
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("upgrade") {
+        let destination = PathBuf::from_str(matches.value_of("destination").unwrap()).unwrap();
+        let summary = upgrade::upgrade(&mut archetect, &destination, matches.value_of("ref"), &answers)?;
+        #[cfg(feature = "tui")]
+        if let Some(wizard) = &wizard {
+            wizard.finish()?;
+        }
+        save_answer_history(&archetect);
+        if matches.is_present("explain-answers") {
+            explain_answers(&archetect);
+        }
+
+        if !archetect.verbosity().is_quiet() {
+            print_upgrade_summary(&summary);
+        }
+        for release in summary.releases() {
+            for deprecation in release.deprecations() {
+                warn!("[{}] Deprecated: {}", release.version(), deprecation);
+            }
+        }
+        if summary.has_conflicts() {
+            warn!("Upgrade produced conflicts; resolve the conflict markers in the files listed above.");
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("restore") {
+        let destination = PathBuf::from_str(matches.value_of("destination").unwrap()).unwrap();
+        if matches.is_present("list") {
+            for backup in backup::list(&destination)? {
+                println!("{}", backup.file_name().unwrap_or_default().to_string_lossy());
             }
         } else {
-            info!("No catalog file exists at {:?}.", catalog_file);
+            let backup = match backup::find(&destination, matches.value_of("id"))? {
+                Some(backup) => backup,
+                None => {
+                    let id = matches.value_of("id").unwrap_or_default();
+                    return Err(ArchetectError::BackupNotFound(destination.join(backup::BACKUPS_DIR).join(id)));
+                }
+            };
+            let restoration = backup::restore(&backup, &destination)?;
+            for file in restoration.restored() {
+                info!("Restored {}", file.display());
+            }
+            for file in restoration.removed() {
+                info!("Removed {}", file.display());
+            }
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("catalog") {
+        if let Some(source) = select_catalog_archetype(&archetect, matches.value_of("source"))? {
+            let destination = PathBuf::from_str(matches.value_of("destination").unwrap()).unwrap();
+
+            let archetype = archetect.load_archetype(&source, None)?;
+
+            if let Ok(answer_config) = AnswerConfig::load(destination.clone()) {
+                for (identifier, answer_info) in answer_config.answers() {
+                    if !answers.contains_key(identifier) {
+                        answers.insert(identifier.to_owned(), answer_info.clone());
+                    }
+                }
+            }
+            archetype.render(&mut archetect, &destination, &answers)?;
+            print_steps(&archetect, "Steps performed:");
+            if matches.is_present("explain-answers") {
+                explain_answers(&archetect);
+            }
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads or changes the user config, validating settings and their values, or edits it by hand.
+fn configure(archetect: &Archetect, matches: &ArgMatches) -> Result<(), ArchetectError> {
+    let path = archetect.layout().user_config();
+    match matches.subcommand() {
+        ("get", Some(matches)) => {
+            if let Some(value) = UserConfig::load(&path)?.get(matches.value_of("setting").unwrap())? {
+                println!("{}", value);
+            }
         }
+        ("set", Some(matches)) => {
+            let mut user_config = UserConfig::load(&path)?;
+            user_config.set(matches.value_of("setting").unwrap(), matches.value_of("value").unwrap())?;
+            user_config.save(&path)?;
+        }
+        ("list", Some(_)) => {
+            for (setting, value) in UserConfig::load(&path)?.entries() {
+                println!("{} = {}", setting, value);
+            }
+        }
+        ("edit", Some(_)) => {
+            if !path.exists() {
+                UserConfig::new().save(&path)?;
+            }
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_owned());
+            let mut words = editor.split_whitespace();
+            let status = std::process::Command::new(words.next().unwrap_or("vi"))
+                .args(words)
+                .arg(&path)
+                .status()?;
+            if !status.success() {
+                warn!("{} exited with {}", editor, status);
+            }
+            UserConfig::load(&path)?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Asks for an archetype from the catalog at `source`, or the user's catalog, returning the archetype's source, or
+/// nothing if there is no catalog.
+fn select_catalog_archetype(archetect: &Archetect, source: Option<&str>) -> Result<Option<String>, ArchetectError> {
+    let default_catalog = archetect.layout().catalog();
+    if source.is_none() && !default_catalog.exists() {
+        info!("No catalog file exists at {:?}.", default_catalog);
+        return Ok(None);
+    }
+    let default_source = default_catalog.to_str().map(|s| s.to_owned()).unwrap();
+    let source = Source::detect(archetect, source.unwrap_or(&default_source), None)?;
+
+    let mut catalog_file = source.local_path().to_owned();
+    if catalog_file.is_dir() {
+        catalog_file.push(CATALOG_FILE_NAME);
+    }
+    if !catalog_file.exists() {
+        info!("No catalog file exists at {:?}.", catalog_file);
+        return Ok(None);
+    }
+
+    let catalog_source = Source::detect(archetect, catalog_file.to_str().unwrap(), None)?;
+    let catalog = Catalog::load(source)?;
+    match select_from_catalog(archetect, &catalog, &catalog_source)? {
+        CatalogEntry::Archetype { description: _, source } => Ok(Some(source)),
+        _ => unreachable!(),
     }
+}
+
+fn render(
+    archetect: &mut Archetect,
+    source: &str,
+    destination: &Path,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+    recorder: Option<&ReportRecorder>,
+) -> Result<(), ArchetectError> {
+    let archetype = archetect.load_archetype(source, None)?;
 
+    // Matrix answers render once per combination, each to the destination templated with its answers.
+    for mut answers in batch::expand_matrix(answers) {
+        let destination = batch::render_destination(archetect, destination, &answers)?;
+        if let Ok(answer_config) = AnswerConfig::load(destination.clone()) {
+            for (identifier, answer_info) in answer_config.answers() {
+                answers.insert(identifier.to_owned(), answer_info.clone());
+            }
+        }
+        let result = archetype.render(archetect, &destination, &answers);
+        if let Some(recorder) = recorder {
+            recorder.record_warnings(archetect.warnings());
+        }
+        result?;
+        if !archetect.verbosity().is_quiet() {
+            print_next_steps(archetect, archetect.next_steps(), "")?;
+        }
+    }
     Ok(())
 }
+
+fn print_setup(setup: &Setup) {
+    for directory in setup.directories() {
+        eprintln!("Created {}", directory.display());
+    }
+    for file in setup.files() {
+        eprintln!("Wrote {}", file.display());
+    }
+    eprintln!();
+}
+
+fn print_cache_collection(collection: &CacheCollection) {
+    for worktree in collection.worktrees() {
+        eprintln!("Removed unused checkout {}", worktree.display());
+    }
+    for repository in collection.repositories() {
+        eprintln!("Removed stale clone {}", repository.display());
+    }
+}
+
+fn print_upgrade_summary(summary: &UpgradeSummary) {
+    let describe = |metadata: &RenderMetadata| {
+        let revision = metadata.commit().or(metadata.gitref()).unwrap_or("unversioned");
+        format!("{} ({})", metadata.source(), revision)
+    };
+    eprintln!("Upgraded from {}", describe(summary.from()));
+    if let Some(to) = summary.to() {
+        eprintln!("           to {}", describe(to));
+    }
+    eprintln!();
+    for (path, outcome) in summary.files() {
+        if outcome != &UpgradeOutcome::Updated {
+            eprintln!("{:<16} {}", format!("{:?}", outcome), path.display());
+        }
+    }
+    for release in summary.releases() {
+        if let Some(notes) = release.notes() {
+            eprintln!("\n{}: {}", release.version(), notes.trim());
+        }
+    }
+    if let Some(changelog) = summary.changelog() {
+        eprintln!("\n{}", changelog);
+    }
+}
+
+fn print_steps(archetect: &Archetect, heading: &str) {
+    if archetect.steps().is_empty() || archetect.verbosity().is_quiet() {
+        return;
+    }
+    eprintln!("\n{}", heading);
+    for (index, step) in archetect.steps().iter().enumerate() {
+        eprintln!("{:>3}. {}", index + 1, step);
+    }
+}
+
+/// Prints the next steps of the archetypes rendered, with markdown headings, bullets, `code`, and **bold** text
+/// styled as the output style allows.
+fn print_next_steps(archetect: &Archetect, next_steps: &[String], indent: &str) -> Result<(), ArchetectError> {
+    for block in next_steps {
+        archetect.print("")?;
+        for line in block.lines() {
+            archetect.print(&format!("{}{}", indent, markdown_line(line, archetect.output_style())))?;
+        }
+    }
+    Ok(())
+}
+
+fn markdown_line(line: &str, style: OutputStyle) -> String {
+    let styled = style.color();
+    let text = line.trim_start();
+    let indent = &line[..line.len() - text.len()];
+    if text.starts_with('#') {
+        let heading = text.trim_start_matches('#').trim();
+        return if styled {
+            Style::new().bold().underline().paint(heading).to_string()
+        } else {
+            heading.to_owned()
+        };
+    }
+    match text.strip_prefix("- ").or_else(|| text.strip_prefix("* ")) {
+        Some(item) => format!("{}  {} {}", indent, style.symbol("•", "-"), markdown_spans(item, styled)),
+        None => format!("{}{}", indent, markdown_spans(text, styled)),
+    }
+}
+
+/// Styles `code` spans and **bold** text, dropping their markers so that commands can be copied as shown.
+fn markdown_spans(text: &str, styled: bool) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+    let markers = [("`", Colour::Cyan.normal()), ("**", Style::new().bold())];
+    while let Some((start, marker, style)) = markers
+        .iter()
+        .filter_map(|(marker, style)| rest.find(marker).map(|start| (start, *marker, *style)))
+        .min_by_key(|(start, _, _)| *start)
+    {
+        let span_start = start + marker.len();
+        let span_end = match rest[span_start..].find(marker) {
+            Some(length) => span_start + length,
+            None => break,
+        };
+        output.push_str(&rest[..start]);
+        let span = &rest[span_start..span_end];
+        if styled {
+            output.push_str(&style.paint(span).to_string());
+        } else {
+            output.push_str(span);
+        }
+        rest = &rest[span_end + marker.len()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// A context of the answers' values, for rendering outside of an Archetype.
+fn answers_context(answers: &LinkedHashMap<String, AnswerInfo>) -> Context {
+    let mut context = Context::new();
+    for (identifier, answer_info) in answers {
+        if let Some(value) = answer_info.value() {
+            context.insert(identifier.as_str(), value);
+        }
+    }
+    context
+}
+
+fn save_answer_history(archetect: &Archetect) {
+    if let Some(history) = archetect.answer_history() {
+        if let Err(error) = history.save(archetect.layout().answer_history()) {
+            warn!("{}", error);
+        }
+    }
+}
+
+fn explain_answers(archetect: &Archetect) {
+    let provenance = archetect.answer_provenance();
+    let width = provenance.iter().map(|p| p.identifier().len()).max().unwrap_or_default();
+    let mut archetype = None;
+    for entry in provenance {
+        if archetype != Some(entry.archetype()) {
+            archetype = Some(entry.archetype());
+            eprintln!("\n{}", entry.archetype());
+        }
+        let value = if entry.sensitive() {
+            "********".to_owned()
+        } else {
+            entry.value().to_string()
+        };
+        eprintln!("  {:<width$}  {}  ({})", entry.identifier(), value, entry.origin(), width = width);
+    }
+}
+
+/// Abbreviates commit SHAs, leaving branch and tag names as they are.
+fn short_revision(revision: &str) -> &str {
+    if revision.len() == 40 && revision.chars().all(|c| c.is_ascii_hexdigit()) {
+        &revision[..7]
+    } else {
+        revision
+    }
+}
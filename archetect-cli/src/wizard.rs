@@ -0,0 +1,400 @@
+use std::cell::RefCell;
+use std::io::{self, Stderr};
+
+use archetect_core::config::VariableType;
+use archetect_core::events::{Event, EventSubscriber};
+use archetect_core::input::{Prompt, PromptProvider};
+use archetect_core::output::OutputStyle;
+use archetect_core::ArchetectError;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event as TerminalEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::border;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use serde_json::Value;
+
+const HELP: &str = "Enter: accept  Tab: review answers  Esc: cancel";
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// A full-screen form for answering prompts, with the progress of the render alongside it, enabled with `--tui`.
+///
+/// Each prompt is added to the form as the archetype reaches it, below the answers given so far. Earlier answers can be
+/// reviewed, but not changed, as later defaults and prompts may already depend on them. The terminal switches to the
+/// alternate screen at the first prompt or action, and is restored by [`Wizard::finish`], or when the wizard is dropped.
+#[derive(Default)]
+pub struct Wizard {
+    state: RefCell<WizardState>,
+    terminal: RefCell<Option<Terminal<CrosstermBackend<Stderr>>>>,
+}
+
+#[derive(Default)]
+struct WizardState {
+    fields: Vec<Field>,
+    progress: Vec<String>,
+    complete: Option<(usize, usize)>,
+    style: OutputStyle,
+}
+
+struct Field {
+    message: String,
+    answer: String,
+}
+
+/// The answer being entered for the current prompt.
+struct Editor<'a> {
+    prompt: &'a Prompt,
+    input: String,
+    option: usize,
+    focus: usize,
+    answered: usize,
+}
+
+impl Wizard {
+    pub fn new(style: OutputStyle) -> Wizard {
+        Wizard {
+            state: RefCell::new(WizardState {
+                style,
+                ..Default::default()
+            }),
+            terminal: RefCell::default(),
+        }
+    }
+
+    /// Shows a summary of the answers and the rendered files until a key is pressed, then restores the terminal.
+    pub fn finish(&self) -> io::Result<()> {
+        if self.terminal.borrow().is_none() {
+            return Ok(());
+        }
+        self.draw(|frame, state| draw_summary(frame, state))?;
+        loop {
+            if let TerminalEvent::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    break;
+                }
+            }
+        }
+        self.restore()
+    }
+
+    fn draw<F: FnOnce(&mut Frame<'_>, &WizardState)>(&self, render: F) -> io::Result<()> {
+        let mut terminal = self.terminal.borrow_mut();
+        if terminal.is_none() {
+            terminal::enable_raw_mode()?;
+            execute!(io::stderr(), EnterAlternateScreen)?;
+            *terminal = Some(Terminal::new(CrosstermBackend::new(io::stderr()))?);
+        }
+        let state = self.state.borrow();
+        if let Some(terminal) = terminal.as_mut() {
+            terminal.draw(|frame| render(frame, &state))?;
+        }
+        Ok(())
+    }
+
+    fn restore(&self) -> io::Result<()> {
+        if self.terminal.borrow_mut().take().is_some() {
+            terminal::disable_raw_mode()?;
+            execute!(io::stderr(), LeaveAlternateScreen)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Wizard {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+impl PromptProvider for Wizard {
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError> {
+        let mut editor = Editor::new(prompt, self.state.borrow().fields.len());
+        loop {
+            self.draw(|frame, state| draw_form(frame, state, Some(&editor)))?;
+            let key = match event::read()? {
+                TerminalEvent::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            if key.code == KeyCode::Esc
+                || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+            {
+                return Err(ArchetectError::UnansweredPrompt(prompt.identifier().to_owned()));
+            }
+            if key.code == KeyCode::Enter {
+                if let Ok(value) = editor.answer() {
+                    self.state.borrow_mut().fields.push(Field {
+                        message: prompt.message().to_owned(),
+                        answer: display(&value),
+                    });
+                    return Ok(Some(value));
+                }
+                continue;
+            }
+            editor.edit(key);
+        }
+    }
+}
+
+impl EventSubscriber for Wizard {
+    fn notify(&self, event: &Event) {
+        {
+            let mut state = self.state.borrow_mut();
+            match event {
+                Event::ActionStarted { action } => {
+                    let marker = state.style.symbol("▸", ">");
+                    state.progress.push(format!("{} {}", marker, action))
+                }
+                Event::RenderPlanned { files, .. } => state.progress.push(format!("  {} files to render", files)),
+                Event::FileWritten { destination } => state.progress.push(format!("  {}", destination.display())),
+                Event::RenderComplete { files, warnings } => state.complete = Some((*files, *warnings)),
+                _ => return,
+            }
+        }
+        // Progress is best effort; a terminal that cannot be drawn to fails the next prompt instead.
+        let _ = self.draw(|frame, state| draw_form(frame, state, None));
+    }
+}
+
+impl<'a> Editor<'a> {
+    fn new(prompt: &'a Prompt, answered: usize) -> Editor<'a> {
+        let option = match (prompt.variable_type(), prompt.default()) {
+            (VariableType::Enum(options), Some(default)) => {
+                options.iter().position(|option| option == default).unwrap_or_default()
+            }
+            _ => 0,
+        };
+        Editor {
+            prompt,
+            input: String::new(),
+            option,
+            focus: answered,
+            answered,
+        }
+    }
+
+    fn edit(&mut self, key: KeyEvent) {
+        let options = match self.prompt.variable_type() {
+            VariableType::Enum(options) => options.len(),
+            _ => 0,
+        };
+        match key.code {
+            KeyCode::Tab => self.focus = (self.focus + 1) % (self.answered + 1),
+            KeyCode::BackTab => self.focus = (self.focus + self.answered) % (self.answered + 1),
+            KeyCode::Up | KeyCode::Left if options > 0 => self.option = (self.option + options - 1) % options,
+            KeyCode::Down | KeyCode::Right if options > 0 => self.option = (self.option + 1) % options,
+            KeyCode::Backspace if options == 0 => {
+                self.input.pop();
+                self.focus = self.answered;
+            }
+            KeyCode::Char(c) if options == 0 => {
+                self.input.push(c);
+                self.focus = self.answered;
+            }
+            _ => (),
+        }
+    }
+
+    /// The value of the answer, or what is expected when the answer is not acceptable. An empty answer accepts the
+    /// default.
+    fn answer(&self) -> Result<Value, &'static str> {
+        let answer = match self.prompt.variable_type() {
+            VariableType::Enum(options) => options.get(self.option).map(String::as_str).unwrap_or_default(),
+            _ if self.input.is_empty() => self.prompt.default().unwrap_or_default(),
+            _ => &self.input,
+        };
+        self.prompt.parse(answer).ok_or(match self.prompt.variable_type() {
+            VariableType::String => "An answer is required",
+            VariableType::Int => "Enter a whole number",
+            VariableType::Bool | VariableType::Switch => "Enter yes or no",
+            VariableType::Enum(_) => "Select one of the options",
+            VariableType::Array => "Enter at least one item, separated by commas",
+        })
+    }
+
+    fn input_line(&self, style: OutputStyle) -> Line<'_> {
+        match self.prompt.variable_type() {
+            VariableType::Enum(options) => Line::from(vec![
+                Span::raw(format!("{} ", style.symbol("◀", "<"))),
+                Span::styled(
+                    options.get(self.option).map(String::as_str).unwrap_or_default(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(
+                    " {}  ({} of {})",
+                    style.symbol("▶", ">"),
+                    self.option + 1,
+                    options.len()
+                )),
+            ]),
+            _ if self.input.is_empty() => Line::from(Span::styled(
+                self.prompt.default().unwrap_or_default(),
+                colored(style, Color::DarkGray),
+            )),
+            _ => Line::from(self.input.as_str()),
+        }
+    }
+}
+
+fn draw_form(frame: &mut Frame<'_>, state: &WizardState, editor: Option<&Editor<'_>>) {
+    let help = editor.and_then(|editor| editor.prompt.help_text());
+    let help_height = help.as_ref().map_or(0, |help| help.lines().count() as u16 + 2);
+    let [title, body, input, help_area, status] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(3),
+        Constraint::Length(help_height),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [form, progress] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+
+    frame.render_widget(
+        Paragraph::new(Span::styled("Archetect", Style::default().add_modifier(Modifier::BOLD))),
+        title,
+    );
+
+    let width = state
+        .fields
+        .iter()
+        .map(|field| field.message.len())
+        .chain(editor.map(|editor| editor.prompt.message().len()))
+        .max()
+        .unwrap_or_default();
+    let mut items: Vec<ListItem<'_>> = state
+        .fields
+        .iter()
+        .map(|field| ListItem::new(format!("{:<width$}  {}", field.message, field.answer, width = width)))
+        .collect();
+    if let Some(editor) = editor {
+        let mut line = vec![Span::raw(format!(
+            "{:<width$}  ",
+            editor.prompt.message(),
+            width = width
+        ))];
+        line.extend(editor.input_line(state.style).spans);
+        items.push(ListItem::new(Line::from(line)));
+    }
+    let mut list_state = ListState::default().with_selected(editor.map(|editor| editor.focus));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(bordered(state.style).title(" Answers "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        form,
+        &mut list_state,
+    );
+
+    draw_progress(frame, state, progress);
+
+    match editor {
+        Some(editor) => {
+            frame.render_widget(
+                Paragraph::new(editor.input_line(state.style))
+                    .block(bordered(state.style).title(format!(" {} ", editor.prompt.message()))),
+                input,
+            );
+            if let Some(help) = help {
+                frame.render_widget(
+                    Paragraph::new(help)
+                        .wrap(Wrap { trim: false })
+                        .block(bordered(state.style).title(" Help ")),
+                    help_area,
+                );
+            }
+            let status_line = if editor.focus < editor.answered {
+                Span::raw("Answered earlier; answers are fixed once later prompts may depend on them")
+            } else {
+                match editor.answer() {
+                    Ok(_) => Span::raw(HELP),
+                    Err(expected) => Span::styled(expected, colored(state.style, Color::Red)),
+                }
+            };
+            frame.render_widget(Paragraph::new(status_line), status);
+        }
+        None => {
+            frame.render_widget(
+                Paragraph::new(state.style.symbol("Rendering…", "Rendering...")).block(bordered(state.style)),
+                input,
+            );
+        }
+    }
+}
+
+fn draw_progress(frame: &mut Frame<'_>, state: &WizardState, area: Rect) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = state.progress.len().saturating_sub(visible);
+    let items: Vec<ListItem<'_>> = state.progress[skip..]
+        .iter()
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    frame.render_widget(List::new(items).block(bordered(state.style).title(" Progress ")), area);
+}
+
+fn draw_summary(frame: &mut Frame<'_>, state: &WizardState) {
+    let [answers, totals] = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+
+    let width = state
+        .fields
+        .iter()
+        .map(|field| field.message.len())
+        .max()
+        .unwrap_or_default();
+    let items: Vec<ListItem<'_>> = state
+        .fields
+        .iter()
+        .map(|field| ListItem::new(format!("{:<width$}  {}", field.message, field.answer, width = width)))
+        .collect();
+    frame.render_widget(
+        List::new(items).block(bordered(state.style).title(" Summary ")),
+        answers,
+    );
+
+    let (files, warnings) = state.complete.unwrap_or_default();
+    frame.render_widget(
+        Paragraph::new(format!(
+            "Rendered {} files with {} warnings. Press any key to exit.",
+            files, warnings
+        ))
+        .block(bordered(state.style)),
+        totals,
+    );
+}
+
+fn bordered(style: OutputStyle) -> Block<'static> {
+    let block = Block::default().borders(Borders::ALL);
+    if style.unicode() {
+        block
+    } else {
+        block.border_set(ASCII_BORDER)
+    }
+}
+
+fn colored(style: OutputStyle, color: Color) -> Style {
+    if style.color() {
+        Style::default().fg(color)
+    } else {
+        Style::default()
+    }
+}
+
+/// Answers as they would be typed: lists are separated by commas.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.to_owned(),
+        Value::Array(values) => values.iter().map(display).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
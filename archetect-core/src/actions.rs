@@ -2,27 +2,51 @@ use std::path::Path;
 
 use linked_hash_map::LinkedHashMap;
 use log::{debug, error, info, trace, warn};
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::actions::conditionals::IfAction;
+use crate::actions::allocate::AllocateAction;
+use crate::actions::append::AppendAction;
+#[cfg(feature = "toml")]
+use crate::actions::cargo::CargoWorkspaceAction;
+use crate::actions::conditionals::{Condition, IfAction};
+use crate::actions::custom::{CustomActionId, CUSTOM_ACTION_PREFIX};
+#[cfg(feature = "exec")]
 use crate::actions::exec::ExecAction;
 use crate::actions::foreach::{ForAction, ForEachAction};
+use crate::actions::jvm::{GradleModuleAction, MavenModuleAction};
+use crate::actions::native::{ChmodAction, WhichAction};
+use crate::actions::node::PackageJsonAction;
 use crate::actions::render::RenderAction;
 use crate::actions::rules::RuleType;
-use crate::config::{AnswerInfo, VariableInfo};
+use crate::config::{AnswerInfo, OsCondition, VariableInfo};
+use crate::events::Event;
 use crate::rendering::Renderable;
 use crate::rules::RulesContext;
+use crate::scope::ScopedContext;
 use crate::{Archetect, ArchetectError, Archetype};
 use crate::vendor::tera::Context;
 
+pub mod allocate;
+pub mod append;
+#[cfg(feature = "toml")]
+pub mod cargo;
 pub mod conditionals;
+pub mod custom;
+#[cfg(feature = "exec")]
 pub mod exec;
 pub mod foreach;
+pub mod jvm;
 pub mod load;
+pub mod native;
+pub mod node;
 pub mod render;
 pub mod rules;
 pub mod set;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub enum ActionId {
     #[serde(rename = "set")]
     Set(LinkedHashMap<String, VariableInfo>),
@@ -45,8 +69,32 @@ pub enum ActionId {
     #[serde(rename = "rules")]
     Rules(Vec<RuleType>),
 
+    #[cfg(feature = "exec")]
     #[serde(rename = "exec")]
     Exec(ExecAction),
+    #[serde(rename = "chmod")]
+    Chmod(ChmodAction),
+    #[serde(rename = "mkdir")]
+    Mkdir(String),
+    #[serde(rename = "touch")]
+    Touch(String),
+    #[serde(rename = "env-check")]
+    EnvCheck(Vec<String>),
+    #[serde(rename = "which")]
+    Which(WhichAction),
+    #[serde(rename = "append")]
+    Append(AppendAction),
+    #[serde(rename = "allocate")]
+    Allocate(AllocateAction),
+    #[cfg(feature = "toml")]
+    #[serde(rename = "cargo-workspace")]
+    CargoWorkspace(CargoWorkspaceAction),
+    #[serde(rename = "gradle-module")]
+    GradleModule(GradleModuleAction),
+    #[serde(rename = "maven-module")]
+    MavenModule(MavenModuleAction),
+    #[serde(rename = "package-json")]
+    PackageJson(PackageJsonAction),
 
     // Output
     #[serde(rename = "trace")]
@@ -63,9 +111,199 @@ pub enum ActionId {
     Print(String),
     #[serde(rename = "display")]
     Display(String),
+
+    /// An action carrying `when:` or `description:` alongside its own key.
+    #[serde(skip)]
+    Step(StepOptions, Box<ActionId>),
+    /// An action registered by the application embedding Archetect, under a key beginning with `x-`.
+    #[serde(skip)]
+    Custom(CustomActionId),
+}
+
+/// Keys that any action may carry next to its own, applying to the action as a step of the script.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StepOptions {
+    /// A templated condition; the action only executes if it renders `true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    when: Option<String>,
+    /// The platforms the action executes on, such as `windows` or `[linux, macos]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    when_os: Option<OsCondition>,
+    /// A templated, human readable description of what the action does, collected into the run summary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// The component the action belongs to, so that it runs when the component is selected with `--only`, and not
+    /// when it is skipped with `--skip`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<String>,
+}
+
+impl StepOptions {
+    pub fn when(&self) -> Option<&str> {
+        self.when.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn when_os(&self) -> Option<&OsCondition> {
+        self.when_os.as_ref()
+    }
+
+    pub fn component(&self) -> Option<&str> {
+        self.component.as_deref()
+    }
+}
+
+const STEP_KEYS: [&str; 4] = ["when", "when_os", "description", "component"];
+
+impl<'de> Deserialize<'de> for ActionId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = serde_yaml::Value::deserialize(deserializer)?;
+        let mut options = serde_yaml::Mapping::new();
+        if let serde_yaml::Value::Mapping(mapping) = &mut value {
+            for key in STEP_KEYS.iter() {
+                let key = serde_yaml::Value::from(*key);
+                match mapping.remove(&key) {
+                    // Allow `when: false` as well as templated conditions
+                    Some(serde_yaml::Value::Bool(flag)) => {
+                        options.insert(key, serde_yaml::Value::from(flag.to_string()));
+                    }
+                    Some(option) => {
+                        options.insert(key, option);
+                    }
+                    None => (),
+                }
+            }
+        }
+        let action = match custom_action(value) {
+            Ok(action) => ActionId::Custom(action),
+            Err(value) => ActionId::deserialize(value).map_err(D::Error::custom)?,
+        };
+        if options.is_empty() {
+            return Ok(action);
+        }
+        let options = StepOptions::deserialize(serde_yaml::Value::Mapping(options)).map_err(D::Error::custom)?;
+        Ok(ActionId::Step(options, Box::new(action)))
+    }
+}
+
+/// Takes a mapping with a single `x-` key as a custom action, returning anything else as it was.
+fn custom_action(value: serde_yaml::Value) -> Result<CustomActionId, serde_yaml::Value> {
+    match value {
+        serde_yaml::Value::Mapping(mapping) if mapping.len() == 1 => {
+            let (key, config) = mapping.iter().next().unwrap();
+            match key.as_str() {
+                Some(key) if key.starts_with(CUSTOM_ACTION_PREFIX) => Ok(CustomActionId::new(key, config.clone())),
+                _ => Err(serde_yaml::Value::Mapping(mapping)),
+            }
+        }
+        value => Err(value),
+    }
+}
+
+impl Serialize for ActionId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ActionId::Step(options, action) => {
+                let mut value = serde_yaml::to_value(action).map_err(S::Error::custom)?;
+                let options = serde_yaml::to_value(options).map_err(S::Error::custom)?;
+                if let (serde_yaml::Value::Mapping(mapping), serde_yaml::Value::Mapping(options)) = (&mut value, options) {
+                    for (key, option) in options {
+                        mapping.insert(key, option);
+                    }
+                }
+                value.serialize(serializer)
+            }
+            ActionId::Custom(action) => {
+                let mut mapping = serde_yaml::Mapping::new();
+                mapping.insert(action.key().into(), action.config().clone());
+                mapping.serialize(serializer)
+            }
+            _ => ActionId::serialize(self, serializer),
+        }
+    }
 }
 
 impl ActionId {
+    /// The action's key in a script, such as `render` or `for-each`.
+    pub fn name(&self) -> &str {
+        match self {
+            ActionId::Set(_) => "set",
+            ActionId::Scope(_) => "scope",
+            ActionId::Actions(_) => "actions",
+            ActionId::Render(_) => "render",
+            ActionId::ForEach(_) => "for-each",
+            ActionId::For(_) => "for",
+            ActionId::Loop(_) => "loop",
+            ActionId::Break => "break",
+            ActionId::If(_) => "if",
+            ActionId::Rules(_) => "rules",
+            #[cfg(feature = "exec")]
+            ActionId::Exec(_) => "exec",
+            ActionId::Chmod(_) => "chmod",
+            ActionId::Mkdir(_) => "mkdir",
+            ActionId::Touch(_) => "touch",
+            ActionId::EnvCheck(_) => "env-check",
+            ActionId::Which(_) => "which",
+            ActionId::Append(_) => "append",
+            ActionId::Allocate(_) => "allocate",
+            #[cfg(feature = "toml")]
+            ActionId::CargoWorkspace(_) => "cargo-workspace",
+            ActionId::GradleModule(_) => "gradle-module",
+            ActionId::MavenModule(_) => "maven-module",
+            ActionId::PackageJson(_) => "package-json",
+            ActionId::LogTrace(_) => "trace",
+            ActionId::LogDebug(_) => "debug",
+            ActionId::LogInfo(_) => "info",
+            ActionId::LogWarn(_) => "warn",
+            ActionId::LogError(_) => "error",
+            ActionId::Print(_) => "print",
+            ActionId::Display(_) => "display",
+            ActionId::Step(_, action) => action.name(),
+            ActionId::Custom(action) => action.key(),
+        }
+    }
+
+    /// Whether the action changes the destination other than by rendering an archetype's source, as commands and edits
+    /// to build files do. These only run in part of an archetype being rendered when they belong to a component
+    /// selected.
+    pub fn changes_destination(&self) -> bool {
+        match self {
+            #[cfg(feature = "exec")]
+            ActionId::Exec(_) => true,
+            #[cfg(feature = "toml")]
+            ActionId::CargoWorkspace(_) => true,
+            ActionId::Chmod(_)
+            | ActionId::Mkdir(_)
+            | ActionId::Touch(_)
+            | ActionId::Append(_)
+            | ActionId::GradleModule(_)
+            | ActionId::MavenModule(_)
+            | ActionId::PackageJson(_)
+            | ActionId::Custom(_) => true,
+            ActionId::Step(_, action) => action.changes_destination(),
+            _ => false,
+        }
+    }
+
+    /// The actions nested directly within this one, such as the branches of an `if`, or the body of a loop.
+    pub fn nested_actions(&self) -> Vec<&ActionId> {
+        match self {
+            ActionId::Scope(actions) | ActionId::Actions(actions) | ActionId::Loop(actions) => actions.iter().collect(),
+            ActionId::ForEach(action) => action.actions().iter().collect(),
+            ActionId::For(action) => action.actions().iter().collect(),
+            ActionId::If(action) => action
+                .then_actions()
+                .iter()
+                .chain(action.else_actions().into_iter().flatten())
+                .collect(),
+            ActionId::Step(_, action) => vec![action.as_ref()],
+            _ => vec![],
+        }
+    }
+
     pub fn execute<D: AsRef<Path>>(
         &self,
         archetect: &mut Archetect,
@@ -76,9 +314,38 @@ impl ActionId {
         context: &mut Context,
     ) -> Result<(), ArchetectError> {
         let destination = destination.as_ref();
+        // Lists of actions and steps are not actions in their own right; events are emitted for what they contain.
+        if let ActionId::Actions(_) | ActionId::Step(_, _) = self {
+            return self.execute_action(archetect, archetype, destination, rules_context, answers, context);
+        }
+        if self.changes_destination() && !rules_context.is_action_selected() {
+            info!("Skipping `{}`, as it is not part of what was selected to render", self.name());
+            return Ok(());
+        }
+        archetect.emit(Event::ActionStarted {
+            action: self.name().to_owned(),
+        });
+        let result = self.execute_action(archetect, archetype, destination, rules_context, answers, context);
+        archetect.check_context_size(context);
+        archetect.emit(Event::ActionFinished {
+            action: self.name().to_owned(),
+            succeeded: result.is_ok(),
+        });
+        result
+    }
+
+    fn execute_action(
+        &self,
+        archetect: &mut Archetect,
+        archetype: &Archetype,
+        destination: &Path,
+        rules_context: &mut RulesContext,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
         match self {
             ActionId::Set(variables) => {
-                set::populate_context(archetect, variables, answers, context)?;
+                set::populate_context(archetect, variables, archetype.configuration().groups(), answers, context)?;
             }
             ActionId::Render(action) => {
                 action.execute(archetect, archetype, destination, rules_context, answers, context)?
@@ -98,12 +365,22 @@ impl ActionId {
             ActionId::LogInfo(message) => info!("{}", message.render(archetect, context)?),
             ActionId::LogWarn(message) => warn!("{}", message.render(archetect, context)?),
             ActionId::LogError(message) => error!("{}", message.render(archetect, context)?),
-            ActionId::Print(message) => println!("{}", message.render(archetect, context)?),
-            ActionId::Display(message) => eprintln!("{}", message.render(archetect, context)?),
+            ActionId::Print(message) => {
+                let message = message.render(archetect, context)?;
+                if !archetect.verbosity().is_quiet() {
+                    archetect.print(&message)?;
+                }
+            }
+            ActionId::Display(message) => {
+                let message = message.render(archetect, context)?;
+                if !archetect.verbosity().is_quiet() {
+                    eprintln!("{}", message);
+                }
+            }
 
             ActionId::Scope(actions) => {
                 let mut rules_context = rules_context.clone();
-                let mut scope_context = context.clone();
+                let mut scope_context = ScopedContext::new(context);
                 let action: ActionId = actions.into();
                 action.execute(
                     archetect,
@@ -130,7 +407,7 @@ impl ActionId {
             }
 
             ActionId::Loop(actions) => {
-                let mut context = context.clone();
+                let mut context = ScopedContext::new(context);
                 let mut rules_context = rules_context.clone();
                 rules_context.set_break_triggered(false);
 
@@ -152,9 +429,62 @@ impl ActionId {
             ActionId::Break => {
                 rules_context.set_break_triggered(true);
             }
+            #[cfg(feature = "exec")]
             ActionId::Exec(action) => {
                 action.execute(archetect, archetype, destination, rules_context, answers, context)?;
             }
+            ActionId::Chmod(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::Mkdir(path) => native::mkdir(archetect, destination, path, context)?,
+            ActionId::Touch(path) => native::touch(archetect, destination, path, context)?,
+            ActionId::EnvCheck(variables) => native::env_check(variables)?,
+            ActionId::Which(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::Append(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::Allocate(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            #[cfg(feature = "toml")]
+            ActionId::CargoWorkspace(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::GradleModule(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::MavenModule(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::PackageJson(action) => {
+                action.execute(archetect, archetype, destination, rules_context, answers, context)?;
+            }
+            ActionId::Custom(action) => action.execute(archetect, archetype, destination, answers, context)?,
+            ActionId::Step(options, action) => {
+                if options.when_os().is_some_and(|when_os| !when_os.matches_current()) {
+                    return Ok(());
+                }
+                if let Some(condition) = options.when() {
+                    let condition = Condition::IsTrue(condition.to_owned());
+                    if !condition.evaluate(archetect, archetype, destination, context)? {
+                        return Ok(());
+                    }
+                }
+                if let Some(description) = options.description() {
+                    let description = description.render(archetect, context)?;
+                    archetect.record_step(description);
+                }
+                // Actions within a component belong to it too, until one says otherwise.
+                let outer = rules_context.component().map(|component| component.to_owned());
+                if let Some(component) = options.component() {
+                    rules_context.set_component(Some(component.to_owned()));
+                }
+                let result = action.execute(archetect, archetype, destination, rules_context, answers, context);
+                rules_context.set_component(outer);
+                result?;
+            }
         }
 
         Ok(())
@@ -249,4 +579,83 @@ mod tests {
                   source: "git@github.com:archetect/archetype-rust-cli.git""#};
         assert_eq!(strip_newline(&yaml), strip_newline(expected));
     }
+
+    #[test]
+    fn test_deserialize_native_actions() {
+        let yaml = indoc! {r#"
+            - mkdir: "{{ name }}/logs"
+            - touch: "{{ name }}/logs/.keep"
+            - chmod:
+                path: bin/run.sh
+                mode: "755"
+            - env-check: [JAVA_HOME]
+            - which:
+                command: docker
+                variable: docker_path
+                required: true
+        "#};
+
+        let actions: Vec<ActionId> = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(&actions[0], ActionId::Mkdir(path) if path == "{{ name }}/logs"));
+        assert!(matches!(&actions[1], ActionId::Touch(_)));
+        assert!(matches!(&actions[2], ActionId::Chmod(_)));
+        assert!(matches!(&actions[3], ActionId::EnvCheck(variables) if variables == &vec!["JAVA_HOME".to_owned()]));
+        assert!(matches!(&actions[4], ActionId::Which(_)));
+    }
+
+    #[test]
+    fn test_when_condition() {
+        let yaml = indoc! {r#"
+            - info: Always
+            - render:
+                directory:
+                  source: docker
+              when: "{{ use_docker }}"
+            - mkdir: logs
+              when: false
+        "#};
+
+        let actions: Vec<ActionId> = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(&actions[0], ActionId::LogInfo(_)));
+        match &actions[1] {
+            ActionId::Step(options, action) => {
+                assert_eq!(options.when(), Some("{{ use_docker }}"));
+                assert_eq!(options.description(), None);
+                assert!(matches!(action.as_ref(), ActionId::Render(_)));
+            }
+            _ => panic!("Expected a conditional render"),
+        }
+        assert!(matches!(&actions[2], ActionId::Step(options, _) if options.when() == Some("false")));
+
+        let yaml = serde_yaml::to_string(&actions[1]).unwrap();
+        let expected = indoc! {r#"
+            ---
+            render:
+              directory:
+                source: docker
+            when: "{{ use_docker }}""#};
+        assert_eq!(strip_newline(&yaml), strip_newline(expected));
+    }
+
+    #[cfg(feature = "exec")]
+    #[test]
+    fn test_step_description() {
+        let yaml = indoc! {r#"
+            - exec:
+                command: git
+                args: [init]
+              description: "Initialize a git repository for {{ name }}"
+              when: "{{ git }}"
+        "#};
+
+        let actions: Vec<ActionId> = serde_yaml::from_str(yaml).unwrap();
+        match &actions[0] {
+            ActionId::Step(options, action) => {
+                assert_eq!(options.description(), Some("Initialize a git repository for {{ name }}"));
+                assert_eq!(options.when(), Some("{{ git }}"));
+                assert!(matches!(action.as_ref(), ActionId::Exec(_)));
+            }
+            _ => panic!("Expected a described exec"),
+        }
+    }
 }
@@ -0,0 +1,307 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use linked_hash_map::LinkedHashMap;
+use log::debug;
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::rules::RulesContext;
+use crate::utils::lock_file;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Allocates the lowest number not yet taken in a state file, such as the next service port or module ordinal, and
+/// sets it as a variable. The state file records each allocation by key, so rendering again with the same key gets the
+/// same number back:
+///
+/// ```yaml
+/// - allocate:
+///     variable: http_port
+///     state: ~/.archetect/ports.yml
+///     key: "{{ name }}"
+///     start: 8080
+///     end: 8999
+/// ```
+///
+/// A relative `state` path is in the destination; an absolute one is a registry shared by everything rendered on the
+/// machine. The state file is locked while a number is allocated, by a lock file beside a registry, or in the cache
+/// for state in the destination, so that none is left in what was rendered.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AllocateAction {
+    variable: String,
+    state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<i64>,
+}
+
+impl AllocateAction {
+    pub fn new<V: Into<String>, S: Into<String>>(variable: V, state: S) -> AllocateAction {
+        AllocateAction {
+            variable: variable.into(),
+            state: state.into(),
+            key: None,
+            start: None,
+            end: None,
+        }
+    }
+
+    pub fn with_key<K: Into<String>>(mut self, key: K) -> AllocateAction {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn with_start(mut self, start: i64) -> AllocateAction {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn with_end(mut self, end: i64) -> AllocateAction {
+        self.end = Some(end);
+        self
+    }
+}
+
+impl Action for AllocateAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let state = archetect.render_string(&self.state, context)?;
+        let state = shellexpand::tilde(&state).into_owned();
+        let in_destination = Path::new(&state).is_relative();
+        let path = destination.as_ref().join(state);
+        let key = match &self.key {
+            Some(key) => Some(archetect.render_string(key, context)?),
+            None => None,
+        };
+
+        // Renders into memory never touch the filesystem, so there is nothing to lock.
+        let _lock = if archetect.in_memory() {
+            None
+        } else {
+            Some(StateLock::acquire(&lock_path(archetect, &path, in_destination))?)
+        };
+
+        let state_error = |message: String| ArchetectError::AllocationStateError {
+            path: path.clone(),
+            message,
+        };
+        let mut allocations = match archetect.read_contents(&path)? {
+            Some(contents) if !contents.trim().is_empty() => {
+                serde_yaml::from_str::<LinkedHashMap<String, i64>>(&contents)
+                    .map_err(|error| state_error(error.to_string()))?
+            }
+            _ => LinkedHashMap::new(),
+        };
+
+        let number = match key.as_ref().and_then(|key| allocations.get(key)) {
+            Some(number) => *number,
+            None => {
+                let start = self.start.unwrap_or(1);
+                let end = self.end.unwrap_or(i64::MAX);
+                let number = (start..=end)
+                    .find(|number| !allocations.values().any(|allocated| allocated == number))
+                    .ok_or_else(|| ArchetectError::AllocationExhausted {
+                        path: path.clone(),
+                        start,
+                        end,
+                    })?;
+                let key = key.unwrap_or_else(|| format!("{}-{}", self.variable, number));
+                debug!("[allocate] {} = {} in {}", key, number, path.display());
+                allocations.insert(key, number);
+
+                let contents = serde_yaml::to_string(&allocations).map_err(|error| state_error(error.to_string()))?;
+                if let Some(parent) = path.parent() {
+                    archetect.create_dir_all(parent)?;
+                }
+                archetect.write_contents(&path, &contents)?;
+                number
+            }
+        };
+
+        context.insert(self.variable.as_str(), &number);
+        Ok(())
+    }
+}
+
+/// The lock file for the state file at `state`: a `.lock` file beside it, or for state in the destination, one in the
+/// cache named for its path.
+fn lock_path(archetect: &Archetect, state: &Path, in_destination: bool) -> PathBuf {
+    if in_destination {
+        let absolute = match (state.parent().and_then(|parent| fs::canonicalize(parent).ok()), state.file_name()) {
+            (Some(parent), Some(file_name)) => parent.join(file_name),
+            _ => state.to_owned(),
+        };
+        let key = farmhash::fingerprint64(absolute.to_string_lossy().as_bytes());
+        return archetect.layout().cache_dir().join("locks").join(format!("{:016x}.lock", key));
+    }
+    let mut path = state.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Exclusive access to a state file, held by locking its lock file until dropped.
+struct StateLock {
+    _file: File,
+}
+
+impl StateLock {
+    fn acquire(path: &Path) -> Result<StateLock, ArchetectError> {
+        match lock_file(path, LOCK_TIMEOUT)? {
+            Some(file) => Ok(StateLock { _file: file }),
+            None => Err(ArchetectError::AllocationLocked(path.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::RootedSystemLayout;
+
+    fn archetype(script: &str) -> (tempfile::TempDir, Archetype, Archetect) {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+        let archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+        (archetype_dir, archetype, archetect)
+    }
+
+    #[test]
+    fn test_allocate_from_registry() {
+        let registry_dir = tempfile::tempdir().unwrap();
+        let registry = registry_dir.path().join("ports.yml");
+        fs::write(&registry, "billing: 8080\nledger: 8082\n").unwrap();
+        // Left behind by a render that was killed while allocating.
+        fs::write(registry_dir.path().join("ports.yml.lock"), "").unwrap();
+
+        let script = format!(
+            "script:
+  - set:
+      name:
+        value: payments
+  - allocate:
+      variable: port
+      state: {}
+      key: \"{{{{ name }}}}\"
+      start: 8080
+  - append:
+      destination: port.txt
+      contents: \"{{{{ port }}}}\"
+",
+            registry.display()
+        );
+        let (_archetype_dir, archetype, mut archetect) = archetype(&script);
+        let destination = tempfile::tempdir().unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.path().join("port.txt")).unwrap(),
+            "8081"
+        );
+
+        // Rendering again with the same key keeps the same number.
+        let destination = tempfile::tempdir().unwrap();
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.path().join("port.txt")).unwrap(),
+            "8081"
+        );
+
+        let allocations: LinkedHashMap<String, i64> =
+            serde_yaml::from_str(&fs::read_to_string(&registry).unwrap()).unwrap();
+        assert_eq!(allocations.get("payments"), Some(&8081));
+        assert_eq!(allocations.len(), 3);
+    }
+
+    #[test]
+    fn test_allocate_in_destination() {
+        let script = "script:
+  - allocate:
+      variable: first
+      state: .archetect/ordinals.yml
+  - allocate:
+      variable: second
+      state: .archetect/ordinals.yml
+  - append:
+      destination: ordinals.txt
+      contents: \"{{ first }} {{ second }}\"
+";
+        let (_archetype_dir, archetype, mut archetect) = archetype(script);
+        let files = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(files.get_str("ordinals.txt"), Some("1 2"));
+        assert_eq!(
+            files.get_str(".archetect/ordinals.yml"),
+            Some("---\nfirst-1: 1\nsecond-2: 2\n")
+        );
+    }
+
+    #[test]
+    fn test_allocate_exhausted() {
+        let script = "script:
+  - allocate:
+      variable: first
+      state: ordinals.yml
+      end: 1
+  - allocate:
+      variable: second
+      state: ordinals.yml
+      end: 1
+";
+        let (_archetype_dir, archetype, mut archetect) = archetype(script);
+        let error = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ArchetectError::AllocationExhausted { start: 1, end: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_no_lock_in_destination() {
+        let home = tempfile::tempdir().unwrap();
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            "script:\n  - allocate:\n      variable: ordinal\n      state: ordinals.yml\n",
+        )
+        .unwrap();
+        let mut archetect = Archetect::builder()
+            .with_headless(true)
+            .with_layout(RootedSystemLayout::new(home.path()).unwrap())
+            .build()
+            .unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert!(destination.path().join("ordinals.yml").is_file());
+        assert!(!destination.path().join("ordinals.yml.lock").exists());
+        let lock = lock_path(&archetect, &destination.path().join("ordinals.yml"), true);
+        assert!(lock.starts_with(archetect.layout().cache_dir()));
+        assert!(lock.is_file());
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use linked_hash_map::LinkedHashMap;
+use log::debug;
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::managed_block::{self, CommentSyntax};
+use crate::rules::RulesContext;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+/// Appends templated contents to a file in the destination, creating it if necessary. With a `block` id, the contents
+/// are written as a managed block between comment markers, which later runs update in place rather than appending
+/// again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppendAction {
+    destination: String,
+    contents: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block: Option<String>,
+}
+
+impl AppendAction {
+    pub fn new<D: Into<String>, C: Into<String>>(destination: D, contents: C) -> AppendAction {
+        AppendAction {
+            destination: destination.into(),
+            contents: contents.into(),
+            block: None,
+        }
+    }
+
+    pub fn with_block<B: Into<String>>(mut self, block: B) -> AppendAction {
+        self.block = Some(block.into());
+        self
+    }
+}
+
+impl Action for AppendAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let path = destination.as_ref().join(archetect.render_string(&self.destination, context)?);
+        let contents = archetect.render_string(&self.contents, context)?;
+        let existing = archetect.read_contents(&path)?.unwrap_or_default();
+
+        let updated = match &self.block {
+            Some(block) => {
+                let block = archetect.render_string(block, context)?;
+                debug!("[append] Updating block `{}` in {}", block, path.display());
+                managed_block::upsert_block(&existing, &block, &contents, &CommentSyntax::for_path(&path))
+                    .map_err(|_| ArchetectError::UnterminatedManagedBlock { path: path.clone(), block })?
+            }
+            None => {
+                debug!("[append] {}", path.display());
+                managed_block::append(&existing, &contents)
+            }
+        };
+
+        if let Some(parent) = path.parent() {
+            archetect.create_dir_all(parent)?;
+        }
+        archetect.write_contents(&path, &updated)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_append_block_is_idempotent() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        let script = "script:
+  - set:
+      name:
+        value: service
+  - append:
+      destination: .gitignore
+      contents: target/
+  - append:
+      destination: .gitignore
+      contents: .idea/
+      block: ide
+  - append:
+      destination: .gitignore
+      contents: \"{{ name }}.iml\"
+      block: ide
+";
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(
+            files.get_str(".gitignore"),
+            Some("target/\n# archetect:begin ide\nservice.iml\n# archetect:end\n")
+        );
+    }
+}
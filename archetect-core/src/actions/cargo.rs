@@ -0,0 +1,304 @@
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use linked_hash_map::LinkedHashMap;
+use log::{debug, warn};
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::rules::RulesContext;
+use crate::utils::{relative_components, resolve_path};
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+const MANIFEST: &str = "Cargo.toml";
+
+/// Registers a crate as a member of a Cargo workspace, editing the workspace's `Cargo.toml` in place so that its
+/// formatting and comments are kept. Crates already covered by a member, including by a glob, are left alone, as are
+/// crates the workspace excludes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CargoWorkspaceAction {
+    /// The crate's directory, relative to the destination. Defaults to the destination itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member: Option<String>,
+    /// The workspace's directory, relative to the destination. Defaults to the nearest directory above the crate
+    /// with a `Cargo.toml` declaring a `[workspace]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace: Option<String>,
+}
+
+impl CargoWorkspaceAction {
+    pub fn new() -> CargoWorkspaceAction {
+        CargoWorkspaceAction {
+            member: None,
+            workspace: None,
+        }
+    }
+
+    pub fn with_member<M: Into<String>>(mut self, member: M) -> CargoWorkspaceAction {
+        self.member = Some(member.into());
+        self
+    }
+
+    pub fn with_workspace<W: Into<String>>(mut self, workspace: W) -> CargoWorkspaceAction {
+        self.workspace = Some(workspace.into());
+        self
+    }
+}
+
+impl Default for CargoWorkspaceAction {
+    fn default() -> Self {
+        CargoWorkspaceAction::new()
+    }
+}
+
+impl Action for CargoWorkspaceAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let destination = destination.as_ref();
+        let member = match &self.member {
+            Some(member) => destination.join(archetect.render_string(member, context)?),
+            None => destination.to_owned(),
+        };
+        let member = resolve_path(&member, archetect.in_memory());
+
+        let (workspace, manifest) = match &self.workspace {
+            Some(workspace) => {
+                let workspace = destination.join(archetect.render_string(workspace, context)?);
+                let workspace = resolve_path(&workspace, archetect.in_memory());
+                match archetect.read_contents(workspace.join(MANIFEST))? {
+                    Some(manifest) => (workspace, manifest),
+                    None => return Err(ArchetectError::CargoWorkspaceNotFound(workspace)),
+                }
+            }
+            None => find_workspace(archetect, &member)?
+                .ok_or_else(|| ArchetectError::CargoWorkspaceNotFound(member.clone()))?,
+        };
+
+        let entry = match relative_components(&member, &workspace) {
+            Some(components) => components.join("/"),
+            None => {
+                return Err(ArchetectError::CargoManifestError {
+                    path: workspace.join(MANIFEST),
+                    message: format!("`{}` is not a directory within the workspace", member.display()),
+                })
+            }
+        };
+
+        let manifest_path = workspace.join(MANIFEST);
+        if is_excluded(&manifest, &entry) {
+            warn!(
+                "[cargo-workspace] Not adding `{}`, as {} excludes it from the workspace",
+                entry,
+                manifest_path.display()
+            );
+            return Ok(());
+        }
+        let updated = add_member(&manifest, &entry).map_err(|message| ArchetectError::CargoManifestError {
+            path: manifest_path.clone(),
+            message,
+        })?;
+        match updated {
+            Some(updated) => {
+                debug!("[cargo-workspace] Adding `{}` to {}", entry, manifest_path.display());
+                archetect.write_contents(&manifest_path, &updated)?;
+            }
+            None => debug!(
+                "[cargo-workspace] `{}` is already a member of {}",
+                entry,
+                manifest_path.display()
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Finds the nearest workspace manifest at or above `member`, returning the workspace's directory and manifest.
+fn find_workspace(archetect: &Archetect, member: &Path) -> Result<Option<(PathBuf, String)>, ArchetectError> {
+    for directory in member.ancestors() {
+        if let Some(manifest) = archetect.read_contents(directory.join(MANIFEST))? {
+            if manifest
+                .parse::<DocumentMut>()
+                .is_ok_and(|document| document.contains_key("workspace"))
+            {
+                return Ok(Some((directory.to_owned(), manifest)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Adds `member` to the workspace members of `manifest`, returning the updated manifest, or `None` if it is already a
+/// member.
+fn add_member(manifest: &str, member: &str) -> Result<Option<String>, String> {
+    let mut document = manifest.parse::<DocumentMut>().map_err(|error| error.to_string())?;
+    let workspace = document
+        .entry("workspace")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| "`workspace` is not a table".to_owned())?;
+    let members = workspace
+        .entry("members")
+        .or_insert(Item::Value(Value::Array(Array::new())))
+        .as_array_mut()
+        .ok_or_else(|| "`workspace.members` is not an array".to_owned())?;
+
+    // As with Cargo, a `*` in a member's glob does not match across directories.
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    let covered = members.iter().filter_map(|existing| existing.as_str()).any(|existing| {
+        existing.trim_end_matches('/') == member
+            || Pattern::new(existing).is_ok_and(|pattern| pattern.matches_with(member, options))
+    });
+    if covered {
+        return Ok(None);
+    }
+
+    // Indent the new member like the last one, so multi-line lists stay one member per line. Any comment before the
+    // last member belongs to it, so only the whitespace after its final newline is copied.
+    let mut value = Value::from(member);
+    if let Some(last) = members.iter().last() {
+        let prefix = last
+            .decor()
+            .prefix()
+            .and_then(|prefix| prefix.as_str())
+            .unwrap_or_default();
+        value.decor_mut().set_prefix(match prefix.rfind('\n') {
+            Some(newline) => &prefix[newline..],
+            None => " ",
+        });
+    }
+    members.push_formatted(value);
+    Ok(Some(document.to_string()))
+}
+
+/// Whether `member` is within a path listed in the `workspace.exclude` of `manifest`.
+fn is_excluded(manifest: &str, member: &str) -> bool {
+    let document = match manifest.parse::<DocumentMut>() {
+        Ok(document) => document,
+        Err(_) => return false,
+    };
+    let excluded = match document.get("workspace").and_then(|workspace| workspace.get("exclude")) {
+        Some(excluded) => excluded,
+        None => return false,
+    };
+    let is_excluded = excluded
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|excluded| excluded.as_str())
+        .any(|excluded| Path::new(member).starts_with(excluded.trim_end_matches('/')));
+    is_excluded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_add_member() {
+        let manifest = "[workspace]
+# Crates, in build order
+members = [
+    \"core\",   # the library
+    \"cli\",
+]
+
+[profile.release]
+lto = true
+";
+        let expected = "[workspace]
+# Crates, in build order
+members = [
+    \"core\",   # the library
+    \"cli\",
+    \"services/billing\",
+]
+
+[profile.release]
+lto = true
+";
+        assert_eq!(
+            add_member(manifest, "services/billing").unwrap().as_deref(),
+            Some(expected)
+        );
+        assert_eq!(add_member(expected, "services/billing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_add_member_covered_by_glob() {
+        let manifest = "[workspace]\nmembers = [\"services/*\"]\n";
+        assert_eq!(add_member(manifest, "services/billing").unwrap(), None);
+        assert_eq!(
+            add_member(manifest, "tools/xtask").unwrap().as_deref(),
+            Some("[workspace]\nmembers = [\"services/*\", \"tools/xtask\"]\n")
+        );
+        assert_eq!(
+            add_member(manifest, "services/billing/api").unwrap().as_deref(),
+            Some("[workspace]\nmembers = [\"services/*\", \"services/billing/api\"]\n")
+        );
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let manifest = "[workspace]\nmembers = [\"services/*\"]\nexclude = [\"services/legacy\", \"vendor/\"]\n";
+        assert!(is_excluded(manifest, "services/legacy"));
+        assert!(is_excluded(manifest, "vendor/openssl"));
+        assert!(!is_excluded(manifest, "services/legacy-billing"));
+        assert!(!is_excluded(manifest, "services/billing"));
+        assert!(!is_excluded("[workspace]\n", "services/billing"));
+    }
+
+    #[test]
+    fn test_add_member_without_members() {
+        let manifest = "[package]\nname = \"root\"\n\n[workspace]\n";
+        assert_eq!(
+            add_member(manifest, "cli").unwrap().as_deref(),
+            Some("[package]\nname = \"root\"\n\n[workspace]\nmembers = [\"cli\"]\n")
+        );
+        assert!(add_member("workspace = 1\n", "cli").is_err());
+    }
+
+    #[test]
+    fn test_cargo_workspace_action() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        let script = "script:
+  - set:
+      name:
+        value: billing
+  - append:
+      destination: Cargo.toml
+      contents: \"[workspace]\\nmembers = [\\\"core\\\"]\"
+  - cargo-workspace:
+      member: \"services/{{ name }}\"
+  - cargo-workspace:
+      member: \"services/{{ name }}\"
+      workspace: .
+";
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+        let files = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            files.get_str("Cargo.toml"),
+            Some("[workspace]\nmembers = [\"core\", \"services/billing\"]\n")
+        );
+    }
+}
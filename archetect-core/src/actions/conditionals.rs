@@ -80,19 +80,19 @@ impl Condition {
             Condition::PathExists(path) => {
                 let path = archetect.render_string(path, context)?;
                 let path = destination.as_ref().join(path);
-                Ok(path.exists())
+                Ok(archetect.path_exists(path))
             }
             Condition::IsFile(path) => {
                 let path = archetect.render_string(path, context)?;
                 let path = destination.as_ref().join(path);
-                let exists = path.exists() && path.is_file();
+                let exists = archetect.is_file(&path);
                 trace!("[File Exists] {}: {}", path.display(), exists);
                 Ok(exists)
             }
             Condition::IsDirectory(path) => {
                 let path = archetect.render_string(path, context)?;
                 let path = destination.as_ref().join(path);
-                Ok(path.exists() && path.is_dir())
+                Ok(archetect.is_dir(path))
             }
             Condition::SwitchEnabled(switch) => Ok(archetect.switches().contains(switch)),
             Condition::Not(condition) => {
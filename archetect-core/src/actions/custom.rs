@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use linked_hash_map::LinkedHashMap;
+
+use crate::config::AnswerInfo;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype, RenderError};
+
+/// Keys of custom actions begin with this, so that they can never collide with Archetect's own actions.
+pub const CUSTOM_ACTION_PREFIX: &str = "x-";
+
+/// An action type supplied by an application embedding Archetect, executed wherever an archetype's script uses the
+/// key it is registered under, such as `x-company-provision:`. The key's value is passed to the action as YAML.
+///
+/// Closures taking the same arguments as [`CustomAction::execute`] are actions.
+pub trait CustomAction {
+    fn execute(&self, config: &serde_yaml::Value, invocation: &mut ActionInvocation<'_>) -> Result<(), ArchetectError>;
+}
+
+impl<F> CustomAction for F
+where
+    F: Fn(&serde_yaml::Value, &mut ActionInvocation<'_>) -> Result<(), ArchetectError>,
+{
+    fn execute(&self, config: &serde_yaml::Value, invocation: &mut ActionInvocation<'_>) -> Result<(), ArchetectError> {
+        self(config, invocation)
+    }
+}
+
+/// What a [`CustomAction`] is executed against: the archetype, its destination, and the variables set so far.
+pub struct ActionInvocation<'a> {
+    archetect: &'a mut Archetect,
+    archetype: &'a Archetype,
+    destination: &'a Path,
+    answers: &'a LinkedHashMap<String, AnswerInfo>,
+    context: &'a mut Context,
+}
+
+impl<'a> ActionInvocation<'a> {
+    pub fn archetect(&mut self) -> &mut Archetect {
+        self.archetect
+    }
+
+    pub fn archetype(&self) -> &Archetype {
+        self.archetype
+    }
+
+    pub fn destination(&self) -> &Path {
+        self.destination
+    }
+
+    pub fn answers(&self) -> &LinkedHashMap<String, AnswerInfo> {
+        self.answers
+    }
+
+    pub fn context(&self) -> &Context {
+        self.context
+    }
+
+    /// The variables later actions will see, for actions that set variables of their own.
+    pub fn context_mut(&mut self) -> &mut Context {
+        self.context
+    }
+
+    /// Renders `template` with the variables set so far.
+    pub fn render(&mut self, template: &str) -> Result<String, RenderError> {
+        self.archetect.render_string(template, self.context)
+    }
+}
+
+/// Custom actions by key.
+#[derive(Clone, Default)]
+pub struct ActionRegistry {
+    actions: HashMap<String, Rc<dyn CustomAction>>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> ActionRegistry {
+        ActionRegistry::default()
+    }
+
+    /// Executes `action` for `key`, replacing any action already registered for it. `key` must begin with
+    /// [`CUSTOM_ACTION_PREFIX`].
+    pub fn register<K: Into<String>, A: CustomAction + 'static>(
+        &mut self,
+        key: K,
+        action: A,
+    ) -> Result<(), ArchetectError> {
+        let key = key.into();
+        if !key.starts_with(CUSTOM_ACTION_PREFIX) {
+            return Err(ArchetectError::InvalidActionKey(key));
+        }
+        self.actions.insert(key, Rc::new(action));
+        Ok(())
+    }
+
+    pub fn with_action<K: Into<String>, A: CustomAction + 'static>(
+        mut self,
+        key: K,
+        action: A,
+    ) -> Result<ActionRegistry, ArchetectError> {
+        self.register(key, action)?;
+        Ok(self)
+    }
+
+    pub fn action(&self, key: &str) -> Option<Rc<dyn CustomAction>> {
+        self.actions.get(key).cloned()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(|key| key.as_str())
+    }
+}
+
+/// A custom action's key and configuration as they appear in a script, executed by whichever [`CustomAction`] is
+/// registered for the key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomActionId {
+    key: String,
+    config: serde_yaml::Value,
+}
+
+impl CustomActionId {
+    pub fn new<K: Into<String>>(key: K, config: serde_yaml::Value) -> CustomActionId {
+        CustomActionId {
+            key: key.into(),
+            config,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn config(&self) -> &serde_yaml::Value {
+        &self.config
+    }
+
+    pub fn execute(
+        &self,
+        archetect: &mut Archetect,
+        archetype: &Archetype,
+        destination: &Path,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let action = archetect
+            .actions()
+            .action(&self.key)
+            .ok_or_else(|| ArchetectError::UnknownAction(self.key.clone()))?;
+        let mut invocation = ActionInvocation {
+            archetect,
+            archetype,
+            destination,
+            answers,
+            context,
+        };
+        action.execute(&self.config, &mut invocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::actions::ActionId;
+    use crate::file_tree::FileTree;
+    use crate::{Archetect, ArchetectError, Archetype};
+
+    use super::{ActionInvocation, ActionRegistry, CustomActionId};
+
+    fn archetype(script: &str) -> Archetype {
+        let mut files = FileTree::new();
+        files.insert("archetype.yml", script);
+        Archetype::from_files(files).unwrap()
+    }
+
+    fn provision(config: &serde_yaml::Value, invocation: &mut ActionInvocation<'_>) -> Result<(), ArchetectError> {
+        let service = config["service"].as_str().unwrap_or_default();
+        let service = invocation.render(service)?;
+        let destination = invocation.destination().join("provisioned.txt");
+        invocation.archetect().write_contents(destination, &service)?;
+        invocation.context_mut().insert("provisioned", &true);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_action() {
+        let mut archetect = Archetect::builder()
+            .with_headless(true)
+            .with_action("x-acme-provision", provision)
+            .unwrap()
+            .build()
+            .unwrap();
+        let archetype = archetype(concat!(
+            "script:\n",
+            "  - set:\n      name:\n        value: billing\n",
+            "  - x-acme-provision:\n      service: \"{{ name }}-service\"\n",
+            "  - x-acme-provision:\n      service: skipped\n    when: \"{{ not provisioned }}\"\n",
+        ));
+
+        let files = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(files.get_str("provisioned.txt"), Some("billing-service"));
+    }
+
+    #[test]
+    fn test_unknown_action() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetype("script:\n  - x-acme-provision: {}\n");
+
+        assert!(matches!(
+            archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()),
+            Err(ArchetectError::UnknownAction(key)) if key == "x-acme-provision"
+        ));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let actions: Vec<ActionId> = serde_yaml::from_str("- x-acme-provision:\n    service: billing\n").unwrap();
+        assert!(matches!(
+            &actions[0],
+            ActionId::Custom(action) if action == &CustomActionId::new(
+                "x-acme-provision",
+                serde_yaml::from_str("service: billing").unwrap(),
+            )
+        ));
+        assert_eq!(
+            serde_yaml::to_string(&actions).unwrap(),
+            "---\n- x-acme-provision:\n    service: billing\n"
+        );
+
+        assert!(serde_yaml::from_str::<Vec<ActionId>>("- acme-provision: {}\n").is_err());
+    }
+
+    #[test]
+    fn test_register_without_prefix() {
+        assert!(matches!(
+            Archetect::builder().with_action("provision", provision),
+            Err(ArchetectError::InvalidActionKey(key)) if key == "provision"
+        ));
+        let mut registry = ActionRegistry::new();
+        assert!(registry.register("x-acme-provision", provision).is_ok());
+        assert!(registry.register("acme-provision", provision).is_err());
+        assert_eq!(registry.keys().collect::<Vec<_>>(), vec!["x-acme-provision"]);
+    }
+}
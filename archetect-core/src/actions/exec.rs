@@ -1,12 +1,17 @@
 use std::collections::hash_map::RandomState;
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
 
 use linked_hash_map::LinkedHashMap;
-use log::{debug, warn};
+use log::{debug, info, log, warn, Level};
+use serde_json::Value;
 
 use crate::actions::Action;
 use crate::config::VariableInfo;
+use crate::output::Verbosity;
 use crate::rules::RulesContext;
 use crate::{Archetect, ArchetectError, Archetype};
 use crate::vendor::tera::Context;
@@ -19,7 +24,13 @@ pub struct ExecAction {
     #[serde(skip_serializing_if = "Option::is_none")]
     env: Option<LinkedHashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    export: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collapse: Option<bool>,
 }
 
 impl ExecAction {
@@ -28,7 +39,10 @@ impl ExecAction {
             command: command.into(),
             args: None,
             env: None,
+            export: None,
             cwd: None,
+            prefix: None,
+            collapse: None,
         }
     }
 
@@ -60,6 +74,21 @@ impl ExecAction {
         env.insert(key.into(), value.into());
     }
 
+    /// The variables exported to the command as `ARCHETECT_VAR_<NAME>` environment variables.
+    pub fn export(&self) -> Option<&Vec<String>> {
+        self.export.as_ref()
+    }
+
+    pub fn with_export<V: Into<String>>(mut self, variable: V) -> ExecAction {
+        self.add_export(variable);
+        self
+    }
+
+    pub fn add_export<V: Into<String>>(&mut self, variable: V) {
+        let export = self.export.get_or_insert_with(Default::default);
+        export.push(variable.into());
+    }
+
     pub fn cwd(&self) -> Option<&String> {
         self.cwd.as_ref()
     }
@@ -72,59 +101,152 @@ impl ExecAction {
     pub fn set_working_directory<D: Into<String>>(&mut self, directory: D) {
         self.cwd = Some(directory.into());
     }
+
+    pub fn prefix(&self) -> Option<&String> {
+        self.prefix.as_ref()
+    }
+
+    /// Sets the label that each line of the command's output is prefixed with. Commands with neither a prefix nor
+    /// `collapse` write to the terminal directly, as they would if run by hand.
+    pub fn with_prefix<P: Into<String>>(mut self, prefix: P) -> ExecAction {
+        self.set_prefix(prefix);
+        self
+    }
+
+    pub fn set_prefix<P: Into<String>>(&mut self, prefix: P) {
+        self.prefix = Some(prefix.into());
+    }
+
+    pub fn collapse(&self) -> bool {
+        self.collapse.unwrap_or_default()
+    }
+
+    /// Holds back the command's output, showing it only if the command fails.
+    pub fn with_collapse(mut self, collapse: bool) -> ExecAction {
+        self.set_collapse(collapse);
+        self
+    }
+
+    pub fn set_collapse(&mut self, collapse: bool) {
+        self.collapse = Some(collapse);
+    }
 }
 
 impl Action for ExecAction {
     fn execute<D: AsRef<Path>>(
         &self,
         archetect: &mut Archetect,
-        _archetype: &Archetype,
+        archetype: &Archetype,
         destination: D,
         _rules_context: &mut RulesContext,
         _answers: &LinkedHashMap<String, VariableInfo, RandomState>,
         context: &mut Context,
     ) -> Result<(), ArchetectError> {
-        let mut command = Command::new(&self.command);
+        archetect.check_trust(archetype)?;
+        if archetect.in_memory() {
+            warn!("[exec] Skipping `{}` while rendering in memory", self.command);
+            return Ok(());
+        }
 
-        if let Some(args) = self.args() {
-            for arg in args {
-                command.arg(archetect.render_string(arg, context)?);
-            }
+        let mut args = vec![];
+        for arg in self.args().into_iter().flatten() {
+            args.push(archetect.render_string(arg, context)?);
         }
 
-        if let Some(env) = self.env() {
-            for (key, value) in env {
-                command.env(
-                    archetect.render_string(key, context)?,
-                    archetect.render_string(value, context)?,
-                );
-            }
+        let mut env = self.exported_variables(archetect, context);
+        for (key, value) in self.env().into_iter().flatten() {
+            env.push((
+                archetect.render_string(key, context)?,
+                archetect.render_string(value, context)?,
+            ));
         }
 
-        if let Some(cwd) = &self.cwd {
-            if let Ok(cwd) = shellexpand::full(cwd) {
-                let cwd = Path::new(cwd.as_ref());
-                if cwd.is_relative() {
-                    command.current_dir(
-                        destination
-                            .as_ref()
-                            .join(archetect.render_string(cwd.display().to_string().as_str(), context)?),
-                    );
-                } else {
-                    command.current_dir(archetect.render_string(cwd.display().to_string().as_str(), context)?);
+        // Relative working directories are within the destination.
+        let cwd = match &self.cwd {
+            Some(cwd) => match shellexpand::full(cwd) {
+                Ok(cwd) => Some(destination.as_ref().join(archetect.render_string(cwd.as_ref(), context)?)),
+                Err(_) => None,
+            },
+            None => Some(destination.as_ref().to_owned()),
+        };
+
+        let sandbox = archetype.configuration().sandbox().or_else(|| archetect.sandbox());
+        let mut command = match sandbox {
+            Some(sandbox) => {
+                let cwd = cwd.unwrap_or_else(|| destination.as_ref().to_owned());
+                sandbox.command(destination.as_ref(), &cwd, &self.command, &args, &env)?
+            }
+            None => {
+                let mut command = Command::new(&self.command);
+                command.args(&args).envs(env);
+                if let Some(cwd) = cwd {
+                    command.current_dir(cwd);
                 }
+                command
             }
-        } else {
-            command.current_dir(destination);
-        }
+        };
+
+        let prefix = match &self.prefix {
+            Some(prefix) => archetect.render_string(prefix, context)?,
+            None => Path::new(&self.command)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.command.clone()),
+        };
+        // Output is held back when quiet, and only streamed despite `collapse` when verbose.
+        let verbosity = archetect.verbosity();
+        let collapse = verbosity.is_quiet() || (self.collapse() && verbosity < Verbosity::Verbose);
+        // Output is only captured to be prefixed or collapsed, leaving commands that detect a terminal to find one.
+        let captured = collapse || self.prefix.is_some();
 
         debug!("[exec] Executing: {:?}", command);
-        match command.status() {
+        if captured {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let mut child = command.spawn().map_err(|source| ArchetectError::CommandError {
+            command: self.command.clone(),
+            source,
+        })?;
+
+        // Lines from both streams are funneled through one channel so they are emitted in the order they arrive.
+        let (sender, receiver) = mpsc::channel();
+        let readers = vec![
+            child.stdout.take().map(|stdout| forward_lines(stdout, sender.clone())),
+            child.stderr.take().map(|stderr| forward_lines(stderr, sender.clone())),
+        ];
+        drop(sender);
+
+        let mut collapsed = vec![];
+        for line in receiver {
+            if collapse {
+                collapsed.push(line);
+            } else {
+                info!("[{}] {}", prefix, line);
+            }
+        }
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        match child.wait() {
+            Ok(status) if status.success() => {
+                debug!("[exec] Status: {}", status);
+                if !collapsed.is_empty() {
+                    info!("[{}] Completed ({} lines of output collapsed)", prefix, collapsed.len());
+                }
+            }
             Ok(status) => {
-                debug!("[exec] Status: {}", status.code().unwrap());
+                let level = if verbosity.is_quiet() { Level::Error } else { Level::Warn };
+                for line in collapsed {
+                    log!(level, "[{}] {}", prefix, line);
+                }
+                log!(level, "[{}] Exited with {}", prefix, status);
             }
-            Err(error) => {
-                warn!("[exec] Error: {}", error);
+            Err(source) => {
+                return Err(ArchetectError::CommandError {
+                    command: self.command.clone(),
+                    source,
+                })
             }
         }
 
@@ -132,12 +254,115 @@ impl Action for ExecAction {
     }
 }
 
+impl ExecAction {
+    /// The environment variables for the variables this action exports, along with the enabled switches when any are
+    /// exported. Sensitive variables are never exported, and those that are not set are left out.
+    fn exported_variables(&self, archetect: &Archetect, context: &Context) -> Vec<(String, String)> {
+        let variables = match self.export() {
+            Some(variables) => variables,
+            None => return vec![],
+        };
+        let mut env = vec![];
+        for variable in variables {
+            if archetect.is_sensitive(variable) {
+                warn!("[exec] Not exporting `{}`, as it is sensitive", variable);
+                continue;
+            }
+            let value = match context.get(variable) {
+                Some(Value::Null) | None => {
+                    debug!("[exec] Not exporting `{}`, as it is not set", variable);
+                    continue;
+                }
+                Some(Value::String(value)) => value.to_owned(),
+                Some(value) => value.to_string(),
+            };
+            env.push((env_var_name(variable), value));
+        }
+        let mut switches = archetect.switches().iter().map(|switch| switch.as_str()).collect::<Vec<&str>>();
+        switches.sort();
+        env.push(("ARCHETECT_SWITCHES".to_owned(), switches.join(",")));
+        env
+    }
+}
+
+/// The environment variable `variable` is exported as, such as `ARCHETECT_VAR_PROJECT_NAME` for `project-name`.
+fn env_var_name(variable: &str) -> String {
+    let name = variable
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect::<String>();
+    format!("ARCHETECT_VAR_{}", name)
+}
+
+fn forward_lines<R: Read + Send + 'static>(stream: R, sender: Sender<String>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs;
+    use std::sync::{Mutex, Once};
+
     use crate::actions::exec::ExecAction;
+    use crate::{Archetect, ArchetectError};
     use linked_hash_map::LinkedHashMap;
+    use log::{LevelFilter, Log, Metadata, Record};
     use serde_yaml;
 
+    static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static LOGGER: Once = Once::new();
+
+    struct CapturingLogger;
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            LOGGED.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Renders an archetype running `script` through `sh` as its one exec action, configured by `options`, and
+    /// returns the result along with the lines logged for `prefix`, which must be unique to the test.
+    fn exec(script: &str, options: &str, prefix: &str) -> (Result<(), ArchetectError>, Vec<String>) {
+        LOGGER.call_once(|| {
+            log::set_boxed_logger(Box::new(CapturingLogger)).unwrap();
+            log::set_max_level(LevelFilter::Info);
+        });
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            format!(
+                "script:\n  - exec:\n      command: sh\n      args: [ \"-c\", \"{}\" ]\n{}",
+                script, options
+            ),
+        )
+        .unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        let result = archetype.render(&mut archetect, destination.path(), &LinkedHashMap::new());
+        let label = format!("[{}]", prefix);
+        let logged = LOGGED
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|line| line.starts_with(&label))
+            .cloned()
+            .collect();
+        (result, logged)
+    }
+
     #[test]
     fn test_serialize() {
         let mut env = LinkedHashMap::new();
@@ -150,9 +375,88 @@ mod tests {
             command: "mvn".to_string(),
             args: Some(vec!["install".to_owned()]),
             env: Some(env),
+            export: Some(vec!["project_name".to_owned()]),
             cwd: None,
+            prefix: Some("maven".to_owned()),
+            collapse: Some(true),
         };
 
         println!("{}", serde_yaml::to_string(&action).unwrap());
     }
+
+    #[test]
+    fn test_exported_variables() {
+        use crate::provenance::{AnswerOrigin, AnswerProvenance};
+        use crate::vendor::tera::Context;
+        use crate::Archetect;
+
+        let mut archetect = Archetect::builder().build().unwrap();
+        archetect.enable_switch("docker");
+        archetect.enable_switch("ci");
+        archetect.record_answer(
+            AnswerProvenance::new("service", "token", "secret".into(), AnswerOrigin::Default).with_sensitive(true),
+        );
+        let mut context = Context::new();
+        context.insert("project-name", "billing");
+        context.insert("port", &8080);
+        context.insert("token", "secret");
+
+        let action = ExecAction::new("./setup.sh")
+            .with_export("project-name")
+            .with_export("port")
+            .with_export("token")
+            .with_export("missing");
+        assert_eq!(
+            action.exported_variables(&archetect, &context),
+            vec![
+                ("ARCHETECT_VAR_PROJECT_NAME".to_owned(), "billing".to_owned()),
+                ("ARCHETECT_VAR_PORT".to_owned(), "8080".to_owned()),
+                ("ARCHETECT_SWITCHES".to_owned(), "ci,docker".to_owned()),
+            ]
+        );
+        assert!(ExecAction::new("./setup.sh").exported_variables(&archetect, &context).is_empty());
+    }
+
+    #[test]
+    fn test_prefixed_output() {
+        let (result, logged) = exec("echo one; echo two >&2", "      prefix: prefixed\n", "prefixed");
+        result.unwrap();
+        assert_eq!(logged.len(), 2);
+        assert!(logged.contains(&"[prefixed] one".to_owned()));
+        assert!(logged.contains(&"[prefixed] two".to_owned()));
+    }
+
+    #[test]
+    fn test_collapsed_output() {
+        let (result, logged) = exec("echo hidden", "      prefix: collapsed\n      collapse: true\n", "collapsed");
+        result.unwrap();
+        assert_eq!(logged, vec!["[collapsed] Completed (1 lines of output collapsed)"]);
+
+        // A command that fails shows what it held back.
+        let (result, logged) = exec(
+            "echo shown; exit 3",
+            "      prefix: collapsed-failure\n      collapse: true\n",
+            "collapsed-failure",
+        );
+        result.unwrap();
+        assert_eq!(logged[0], "[collapsed-failure] shown");
+        assert!(logged[1].starts_with("[collapsed-failure] Exited with"));
+    }
+
+    #[test]
+    fn test_spawn_error() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            "script:\n  - exec:\n      command: archetect-missing-command\n",
+        )
+        .unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        match archetype.render(&mut archetect, destination.path(), &LinkedHashMap::new()) {
+            Err(ArchetectError::CommandError { command, .. }) => assert_eq!(command, "archetect-missing-command"),
+            result => panic!("Expected the command not to run, but got {:?}", result),
+        }
+    }
 }
@@ -5,6 +5,7 @@ use linked_hash_map::LinkedHashMap;
 use crate::actions::{Action, ActionId, LoopContext};
 use crate::config::VariableInfo;
 use crate::rules::RulesContext;
+use crate::scope::ScopedContext;
 use crate::{Archetect, ArchetectError, Archetype};
 use crate::vendor::tera::Context;
 
@@ -48,9 +49,9 @@ impl Action for ForEachAction {
     ) -> Result<(), ArchetectError> {
         match &self.source {
             ForEachSource::Variable(identifier) => {
-                if let Some(value) = context.get(identifier) {
+                if let Some(value) = context.get_shared(identifier) {
                     if let Some(items) = value.as_array() {
-                        let mut context = context.clone();
+                        let mut context = ScopedContext::new(context);
                         let mut rules_context = rules_context.clone();
                         rules_context.set_break_triggered(false);
 
@@ -84,7 +85,7 @@ impl Action for ForEachAction {
                             }
                         };
 
-                        let mut context = context.clone();
+                        let mut context = ScopedContext::new(context);
                         let mut rules_context = rules_context.clone();
                         rules_context.set_break_triggered(false);
                         let loop_context = LoopContext::new();
@@ -107,7 +108,7 @@ impl Action for ForEachAction {
                 let input = archetect.render_string(&options.input, context)?;
                 let splits = input.split(&options.separator);
 
-                let mut context = context.clone();
+                let mut context = ScopedContext::new(context);
                 let mut rules_context = rules_context.clone();
                 rules_context.set_break_triggered(false);
 
@@ -189,9 +190,9 @@ impl Action for ForAction {
                 value,
             } => {
                 let format = value;
-                if let Some(value) = context.get(identifier) {
+                if let Some(value) = context.get_shared(identifier) {
                     if let Some(items) = value.as_array() {
-                        let mut context = context.clone();
+                        let mut context = ScopedContext::new(context);
                         let mut rules_context = rules_context.clone();
                         rules_context.set_break_triggered(false);
 
@@ -229,7 +230,7 @@ impl Action for ForAction {
                             }
                         };
 
-                        let mut context = context.clone();
+                        let mut context = ScopedContext::new(context);
                         let mut rules_context = rules_context.clone();
                         rules_context.set_break_triggered(false);
                         let loop_context = LoopContext::new();
@@ -263,7 +264,7 @@ impl Action for ForAction {
                 let separator = separator.clone().unwrap_or(",".to_owned());
                 let splits = input.split(&separator);
 
-                let mut context = context.clone();
+                let mut context = ScopedContext::new(context);
                 let mut rules_context = rules_context.clone();
                 rules_context.set_break_triggered(false);
 
@@ -0,0 +1,463 @@
+use std::path::{Path, PathBuf};
+
+use linked_hash_map::LinkedHashMap;
+use log::debug;
+use regex::Regex;
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::rules::RulesContext;
+use crate::utils::{relative_components, resolve_path};
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+const GRADLE_SETTINGS: [&str; 2] = ["settings.gradle.kts", "settings.gradle"];
+const MAVEN_POM: &str = "pom.xml";
+
+lazy_static! {
+    static ref QUOTED: Regex = Regex::new(r#"["']([^"'\n]*)["']"#).unwrap();
+    static ref MAVEN_MODULE: Regex = Regex::new(r"<module>\s*([^<]*?)\s*</module>").unwrap();
+    static ref XML_MARKUP: Regex = Regex::new(r"(?s)<!--.*?-->|<!\[CDATA\[.*?\]\]>|<[?!][^>]*>|<(/?)([^\s/>]+)[^>]*?(/?)>").unwrap();
+}
+
+/// The line ending `contents` uses, so that edits keep to it.
+fn line_ending(contents: &str) -> &'static str {
+    if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Includes a module in a Gradle build, adding it to the `settings.gradle` or `settings.gradle.kts` of the root
+/// project in the style of the includes already there. Modules already included are left alone.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GradleModuleAction {
+    /// The module's directory, relative to the destination. Defaults to the destination itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<String>,
+    /// The root project's directory, relative to the destination. Defaults to the nearest directory above the module
+    /// with Gradle settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+}
+
+/// Adds a module to the `<modules>` of a parent Maven `pom.xml`, creating the section if necessary. Modules already
+/// listed are left alone.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MavenModuleAction {
+    /// The module's directory, relative to the destination. Defaults to the destination itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<String>,
+    /// The parent project's directory, relative to the destination. Defaults to the nearest directory above the
+    /// module with a `pom.xml`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+}
+
+impl GradleModuleAction {
+    pub fn with_module<M: Into<String>>(mut self, module: M) -> GradleModuleAction {
+        self.module = Some(module.into());
+        self
+    }
+
+    pub fn with_project<P: Into<String>>(mut self, project: P) -> GradleModuleAction {
+        self.project = Some(project.into());
+        self
+    }
+}
+
+impl MavenModuleAction {
+    pub fn with_module<M: Into<String>>(mut self, module: M) -> MavenModuleAction {
+        self.module = Some(module.into());
+        self
+    }
+
+    pub fn with_project<P: Into<String>>(mut self, project: P) -> MavenModuleAction {
+        self.project = Some(project.into());
+        self
+    }
+}
+
+impl Action for GradleModuleAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let (module, project) = locate(archetect, destination.as_ref(), &self.module, &self.project, context)?;
+        let (settings_path, settings) = match find_build_file(archetect, &module, &project, &GRADLE_SETTINGS)? {
+            Some(found) => found,
+            None => return Err(ArchetectError::GradleSettingsNotFound(project.unwrap_or(module))),
+        };
+        let root = settings_path.parent().unwrap_or_else(|| Path::new(""));
+        let components = relative_components(&module, root).ok_or_else(|| ArchetectError::BuildFileError {
+            path: settings_path.clone(),
+            message: format!("`{}` is not a directory within the project", module.display()),
+        })?;
+
+        let project_path = components.join(":");
+        let kotlin = settings_path.extension().is_some_and(|extension| extension == "kts");
+        match include_module(&settings, &project_path, kotlin) {
+            Some(updated) => {
+                debug!(
+                    "[gradle-module] Including `{}` in {}",
+                    project_path,
+                    settings_path.display()
+                );
+                archetect.write_contents(&settings_path, &updated)?;
+            }
+            None => debug!("[gradle-module] `{}` is already included", project_path),
+        }
+        Ok(())
+    }
+}
+
+impl Action for MavenModuleAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let (module, project) = locate(archetect, destination.as_ref(), &self.module, &self.project, context)?;
+        let (pom_path, pom) = match find_build_file(archetect, &module, &project, &[MAVEN_POM])? {
+            Some(found) => found,
+            None => return Err(ArchetectError::MavenParentNotFound(project.unwrap_or(module))),
+        };
+        let root = pom_path.parent().unwrap_or_else(|| Path::new(""));
+        let build_file_error = |message: String| ArchetectError::BuildFileError {
+            path: pom_path.clone(),
+            message,
+        };
+        let entry = relative_components(&module, root)
+            .ok_or_else(|| build_file_error(format!("`{}` is not a directory within the project", module.display())))?
+            .join("/");
+
+        match add_maven_module(&pom, &entry).map_err(build_file_error)? {
+            Some(updated) => {
+                debug!("[maven-module] Adding `{}` to {}", entry, pom_path.display());
+                archetect.write_contents(&pom_path, &updated)?;
+            }
+            None => debug!("[maven-module] `{}` is already a module", entry),
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the module's directory, and the project's directory if one was given.
+fn locate(
+    archetect: &mut Archetect,
+    destination: &Path,
+    module: &Option<String>,
+    project: &Option<String>,
+    context: &Context,
+) -> Result<(PathBuf, Option<PathBuf>), ArchetectError> {
+    let in_memory = archetect.in_memory();
+    let module = match module {
+        Some(module) => destination.join(archetect.render_string(module, context)?),
+        None => destination.to_owned(),
+    };
+    let project = match project {
+        Some(project) => Some(resolve_path(
+            &destination.join(archetect.render_string(project, context)?),
+            in_memory,
+        )),
+        None => None,
+    };
+    Ok((resolve_path(&module, in_memory), project))
+}
+
+/// Reads the first of `names` in `project`, or if no project was given, in the nearest directory above `module`
+/// containing one, returning its path and contents.
+fn find_build_file(
+    archetect: &Archetect,
+    module: &Path,
+    project: &Option<PathBuf>,
+    names: &[&str],
+) -> Result<Option<(PathBuf, String)>, ArchetectError> {
+    let directories: Vec<&Path> = match project {
+        Some(project) => vec![project.as_path()],
+        None => module.ancestors().skip(1).collect(),
+    };
+    for directory in directories {
+        for name in names {
+            let path = directory.join(name);
+            if let Some(contents) = archetect.read_contents(&path)? {
+                return Ok(Some((path, contents)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Adds an include of `project_path`, such as `services:billing`, to Gradle `settings`, following the last existing
+/// include, or returns `None` if it is already included.
+fn include_module(settings: &str, project_path: &str, kotlin: bool) -> Option<String> {
+    let lines = settings.lines().collect::<Vec<&str>>();
+
+    // Each include statement, as its first and last lines, since includes may span lines
+    let mut includes = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let trimmed = lines[index].trim_start();
+        let is_include = trimmed.starts_with("include")
+            && trimmed["include".len()..].starts_with(|c: char| c == '(' || c.is_whitespace());
+        if is_include {
+            let start = index;
+            let mut depth = 0;
+            loop {
+                depth += lines[index].matches('(').count() as i32 - lines[index].matches(')').count() as i32;
+                if depth <= 0 || index + 1 == lines.len() {
+                    break;
+                }
+                index += 1;
+            }
+            includes.push((start, index));
+        }
+        index += 1;
+    }
+
+    let included = includes.iter().any(|(start, end)| {
+        lines[*start..=*end].iter().any(|line| {
+            QUOTED
+                .captures_iter(line)
+                .any(|captures| captures[1].trim_start_matches(':') == project_path)
+        })
+    });
+    if included {
+        return None;
+    }
+
+    let include = match includes.last() {
+        Some((start, end)) => {
+            let statement = lines[*start..=*end].join("\n");
+            let line = lines[*start];
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let parenthesized = line.trim_start()["include".len()..].trim_start().starts_with('(');
+            let (quote, colon) = match QUOTED.captures(&statement) {
+                Some(captures) => (
+                    captures[0].chars().next().unwrap(),
+                    if captures[1].starts_with(':') { ":" } else { "" },
+                ),
+                None => ('"', ":"),
+            };
+            if parenthesized {
+                format!("{}include({}{}{}{})", indent, quote, colon, project_path, quote)
+            } else {
+                format!("{}include {}{}{}{}", indent, quote, colon, project_path, quote)
+            }
+        }
+        None if kotlin => format!("include(\":{}\")", project_path),
+        None => format!("include ':{}'", project_path),
+    };
+
+    let newline = line_ending(settings);
+    let mut updated = lines.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+    let position = includes.last().map_or(updated.len(), |(_, end)| end + 1);
+    updated.insert(position, include);
+    Some(updated.join(newline) + newline)
+}
+
+/// Adds `module` to the `<modules>` of a Maven `pom`, indented like the modules already there, or returns `None` if
+/// it is already listed.
+fn add_maven_module(pom: &str, module: &str) -> Result<Option<String>, String> {
+    let newline = line_ending(pom);
+    let element = format!("<module>{}</module>", module);
+    let line_start = |position: usize| pom[..position].rfind('\n').map_or(0, |newline| newline + 1);
+    let indent_at = |position: usize| {
+        let start = line_start(position);
+        let prefix = &pom[start..position];
+        if prefix.trim().is_empty() {
+            Some(prefix)
+        } else {
+            None
+        }
+    };
+
+    if let Some((open, close)) = project_modules(pom) {
+        let section = &pom[open..close];
+        let listed = MAVEN_MODULE
+            .captures_iter(section)
+            .any(|captures| captures[1].trim_end_matches('/') == module);
+        if listed {
+            return Ok(None);
+        }
+
+        let updated = match indent_at(close) {
+            Some(close_indent) => {
+                let indent = MAVEN_MODULE
+                    .find_iter(section)
+                    .last()
+                    .and_then(|last| indent_at(open + last.start()))
+                    .map(|indent| indent.to_owned())
+                    .unwrap_or_else(|| format!("{}    ", close_indent));
+                let start = line_start(close);
+                format!("{}{}{}{}{}", &pom[..start], indent, element, newline, &pom[start..])
+            }
+            None => format!("{}{}{}", &pom[..close], element, &pom[close..]),
+        };
+        return Ok(Some(updated));
+    }
+
+    let close = pom
+        .rfind("</project>")
+        .ok_or_else(|| "no `</project>` element was found".to_owned())?;
+    let unit = ["<modelVersion>", "<groupId>", "<artifactId>"]
+        .iter()
+        .filter_map(|tag| pom.find(tag).and_then(indent_at))
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("    ");
+    let start = line_start(close);
+    let modules = format!("{0}<modules>{2}{0}{0}{1}{2}{0}</modules>{2}", unit, element, newline);
+    Ok(Some(format!("{}{}{}", &pom[..start], modules, &pom[start..])))
+}
+
+/// Where the `<modules>` directly within a pom's `<project>` opens and closes, skipping any within profiles, comments,
+/// or elsewhere.
+fn project_modules(pom: &str) -> Option<(usize, usize)> {
+    let mut open_elements = vec![];
+    let mut modules = None;
+    for captures in XML_MARKUP.captures_iter(pom) {
+        let name = match captures.get(2) {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+        let position = captures.get(0).unwrap().start();
+        if &captures[1] == "/" {
+            open_elements.pop();
+            if name == "modules" && open_elements.len() == 1 {
+                if let Some(open) = modules {
+                    return Some((open, position));
+                }
+            }
+        } else if &captures[3] != "/" {
+            if name == "modules" && open_elements == ["project"] {
+                modules = Some(position);
+            }
+            open_elements.push(name);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_include_module() {
+        let settings = "rootProject.name = 'shop'\n\ninclude ':core'\ninclude ':api'\n\nenableFeaturePreview('X')\n";
+        assert_eq!(
+            include_module(settings, "services:billing", false).as_deref(),
+            Some("rootProject.name = 'shop'\n\ninclude ':core'\ninclude ':api'\ninclude ':services:billing'\n\nenableFeaturePreview('X')\n")
+        );
+        assert_eq!(include_module(settings, "api", false), None);
+
+        let settings = "include(\n    \"core\",\n    \"api\"\n)\n";
+        assert_eq!(
+            include_module(settings, "cli", true).as_deref(),
+            Some("include(\n    \"core\",\n    \"api\"\n)\ninclude(\"cli\")\n")
+        );
+        assert_eq!(include_module(settings, "core", true), None);
+
+        assert_eq!(
+            include_module("rootProject.name = \"shop\"", "core", true).as_deref(),
+            Some("rootProject.name = \"shop\"\ninclude(\":core\")\n")
+        );
+        assert_eq!(
+            include_module("includeBuild 'core'\n", "core", false).as_deref(),
+            Some("includeBuild 'core'\ninclude ':core'\n")
+        );
+        assert_eq!(
+            include_module("include ':core'\r\n\r\nrootProject.name = 'shop'\r\n", "api", false).as_deref(),
+            Some("include ':core'\r\ninclude ':api'\r\n\r\nrootProject.name = 'shop'\r\n")
+        );
+    }
+
+    #[test]
+    fn test_add_maven_module() {
+        let pom = "<project>\n  <artifactId>shop</artifactId>\n  <modules>\n    <module>core</module>\n  </modules>\n</project>\n";
+        assert_eq!(
+            add_maven_module(pom, "services/billing").unwrap().as_deref(),
+            Some("<project>\n  <artifactId>shop</artifactId>\n  <modules>\n    <module>core</module>\n    <module>services/billing</module>\n  </modules>\n</project>\n")
+        );
+        assert_eq!(add_maven_module(pom, "core").unwrap(), None);
+
+        let pom = "<project>\n\t<artifactId>shop</artifactId>\n</project>\n";
+        assert_eq!(
+            add_maven_module(pom, "core").unwrap().as_deref(),
+            Some("<project>\n\t<artifactId>shop</artifactId>\n\t<modules>\n\t\t<module>core</module>\n\t</modules>\n</project>\n")
+        );
+
+        assert_eq!(
+            add_maven_module("<project><modules><module>a</module></modules></project>", "b")
+                .unwrap()
+                .as_deref(),
+            Some("<project><modules><module>a</module><module>b</module></modules></project>")
+        );
+        assert!(add_maven_module("<settings/>", "core").is_err());
+
+        let pom = "<?xml version=\"1.0\"?>\r\n<project>\r\n  <artifactId>shop</artifactId>\r\n  <!-- <modules> -->\r\n  <profiles>\r\n    <profile>\r\n      \
+                   <modules>\r\n        <module>it</module>\r\n      </modules>\r\n    </profile>\r\n  </profiles>\r\n\
+                   </project>\r\n";
+        assert_eq!(
+            add_maven_module(pom, "core").unwrap().as_deref(),
+            Some(
+                "<?xml version=\"1.0\"?>\r\n<project>\r\n  <artifactId>shop</artifactId>\r\n  <!-- <modules> -->\r\n  <profiles>\r\n    <profile>\r\n      \
+                 <modules>\r\n        <module>it</module>\r\n      </modules>\r\n    </profile>\r\n  </profiles>\r\n  \
+                 <modules>\r\n    <module>core</module>\r\n  </modules>\r\n</project>\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_module_actions() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        let script = "script:
+  - append:
+      destination: settings.gradle.kts
+      contents: \"include(\\\":core\\\")\"
+  - append:
+      destination: pom.xml
+      contents: \"<project>\\n    <modules>\\n        <module>core</module>\\n    </modules>\\n</project>\"
+  - append:
+      destination: billing/pom.xml
+      contents: \"<project/>\"
+  - gradle-module:
+      module: billing
+  - maven-module:
+      module: billing
+  - maven-module:
+      module: billing
+      project: .
+";
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+        let files = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            files.get_str("settings.gradle.kts"),
+            Some("include(\":core\")\ninclude(\":billing\")\n")
+        );
+        assert_eq!(
+            files.get_str("pom.xml"),
+            Some("<project>\n    <modules>\n        <module>core</module>\n        <module>billing</module>\n    </modules>\n</project>")
+        );
+        assert_eq!(files.get_str("billing/pom.xml"), Some("<project/>"));
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "exec")]
 use crate::actions::exec::ExecAction;
 use linked_hash_map::LinkedHashMap;
 
@@ -20,6 +21,7 @@ pub enum LoadOptions {
         #[serde(skip_serializing_if = "Option::is_none")]
         headers: Option<LinkedHashMap<String, String>>,
     },
+    #[cfg(feature = "exec")]
     #[serde(rename = "exec")]
     Exec(ExecAction),
     #[serde(rename = "inline")]
@@ -29,9 +31,9 @@ pub enum LoadOptions {
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
-    use serde_json;
     use serde_yaml;
 
+    #[cfg(feature = "exec")]
     use crate::actions::exec::ExecAction;
     use crate::actions::load::{LoadAction, LoadOptions};
 
@@ -116,6 +118,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "exec")]
     #[test]
     fn test_serialize_from_exec() {
         let action = LoadAction {
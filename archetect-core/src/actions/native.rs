@@ -0,0 +1,263 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use linked_hash_map::LinkedHashMap;
+use log::{debug, warn};
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::rules::RulesContext;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+/// Sets the permissions of a path within the destination, given as an octal mode. Permissions are a Unix concept, so
+/// this does nothing on other platforms.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChmodAction {
+    path: String,
+    mode: String,
+}
+
+impl ChmodAction {
+    pub fn new<P: Into<String>, M: Into<String>>(path: P, mode: M) -> ChmodAction {
+        ChmodAction {
+            path: path.into(),
+            mode: mode.into(),
+        }
+    }
+}
+
+impl Action for ChmodAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let path = destination.as_ref().join(archetect.render_string(&self.path, context)?);
+        let mode = archetect.render_string(&self.mode, context)?;
+        let mode = match u32::from_str_radix(mode.trim(), 8) {
+            Ok(mode) if mode <= 0o7777 => mode,
+            _ => return Err(ArchetectError::InvalidFileMode(mode)),
+        };
+
+        if archetect.in_memory() {
+            debug!("[chmod] Skipping {} while rendering in memory", path.display());
+            return Ok(());
+        }
+
+        debug!("[chmod] {:o} {}", mode, path.display());
+        set_mode(&path, mode)
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), ArchetectError> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, _mode: u32) -> Result<(), ArchetectError> {
+    debug!("[chmod] Permissions are not supported on this platform; leaving {} unchanged", path.display());
+    Ok(())
+}
+
+/// Locates a command on the `PATH`, optionally storing its location in a context variable, and failing the render if
+/// it is required but cannot be found.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WhichAction {
+    command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variable: Option<String>,
+    #[serde(default)]
+    required: bool,
+}
+
+impl WhichAction {
+    pub fn new<C: Into<String>>(command: C) -> WhichAction {
+        WhichAction {
+            command: command.into(),
+            variable: None,
+            required: false,
+        }
+    }
+
+    pub fn with_variable<V: Into<String>>(mut self, variable: V) -> WhichAction {
+        self.variable = Some(variable.into());
+        self
+    }
+
+    pub fn with_required(mut self, required: bool) -> WhichAction {
+        self.required = required;
+        self
+    }
+}
+
+impl Action for WhichAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        _destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let command = archetect.render_string(&self.command, context)?;
+        match which(&command) {
+            Some(path) => {
+                debug!("[which] {}: {}", command, path.display());
+                if let Some(variable) = &self.variable {
+                    context.insert(variable.as_str(), &path.display().to_string());
+                }
+                Ok(())
+            }
+            None if self.required => Err(ArchetectError::CommandNotFound(command)),
+            None => {
+                warn!("[which] `{}` was not found on the PATH", command);
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn mkdir<D: AsRef<Path>>(
+    archetect: &mut Archetect,
+    destination: D,
+    path: &str,
+    context: &Context,
+) -> Result<(), ArchetectError> {
+    let path = destination.as_ref().join(archetect.render_string(path, context)?);
+    debug!("[mkdir] {}", path.display());
+    archetect.create_dir_all(path)?;
+    Ok(())
+}
+
+/// Creates an empty file if it does not exist, or updates its modification time if it does.
+pub fn touch<D: AsRef<Path>>(
+    archetect: &mut Archetect,
+    destination: D,
+    path: &str,
+    context: &Context,
+) -> Result<(), ArchetectError> {
+    let path = destination.as_ref().join(archetect.render_string(path, context)?);
+    debug!("[touch] {}", path.display());
+    if archetect.in_memory() {
+        if !archetect.is_file(&path) {
+            archetect.write_contents(&path, "")?;
+        }
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Fails the render, listing every missing variable at once, unless all of `variables` are set in the environment.
+pub fn env_check(variables: &[String]) -> Result<(), ArchetectError> {
+    let missing = variables
+        .iter()
+        .filter(|variable| env::var_os(variable).is_none())
+        .cloned()
+        .collect::<Vec<String>>();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ArchetectError::MissingEnvironmentVariables(missing))
+    }
+}
+
+/// Finds `command` on the `PATH`, honoring `PATHEXT` on Windows. Commands containing a path separator are checked
+/// directly.
+pub fn which(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return Some(path.to_owned()).filter(|path| is_executable(path));
+    }
+
+    let extensions = if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_owned())
+            .split(';')
+            .map(|extension| extension.to_owned())
+            .chain(std::iter::once(String::new()))
+            .collect::<Vec<String>>()
+    } else {
+        vec![String::new()]
+    };
+
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .flat_map(|directory| {
+            extensions
+                .iter()
+                .map(move |extension| directory.join(format!("{}{}", command, extension)))
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mkdir_and_touch() {
+        let destination = tempfile::tempdir().unwrap();
+        let mut archetect = Archetect::build().unwrap();
+        let mut context = Context::new();
+        context.insert("name", "service");
+
+        mkdir(&mut archetect, destination.path(), "{{ name }}/logs", &context).unwrap();
+        assert!(destination.path().join("service/logs").is_dir());
+
+        touch(&mut archetect, destination.path(), "{{ name }}/config/.keep", &context).unwrap();
+        assert!(destination.path().join("service/config/.keep").is_file());
+
+        fs::write(destination.path().join("service/README.md"), "# Service").unwrap();
+        touch(&mut archetect, destination.path(), "{{ name }}/README.md", &context).unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("service/README.md")).unwrap(), "# Service");
+    }
+
+    #[test]
+    fn test_env_check() {
+        assert!(env_check(&["PATH".to_owned()]).is_ok());
+        match env_check(&["PATH".to_owned(), "ARCHETECT_TEST_UNSET_VARIABLE".to_owned()]) {
+            Err(ArchetectError::MissingEnvironmentVariables(missing)) => {
+                assert_eq!(missing, vec!["ARCHETECT_TEST_UNSET_VARIABLE".to_owned()])
+            }
+            _ => panic!("Expected missing environment variables"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_which() {
+        assert!(which("sh").is_some());
+        assert_eq!(which("/bin/sh"), Some(PathBuf::from("/bin/sh")));
+        assert!(which("archetect-test-missing-command").is_none());
+    }
+}
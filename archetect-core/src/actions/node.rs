@@ -0,0 +1,353 @@
+use std::path::Path;
+
+use linked_hash_map::LinkedHashMap;
+use log::debug;
+use semver::Version;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use serde_json::value::RawValue;
+
+use crate::actions::Action;
+use crate::config::AnswerInfo;
+use crate::rules::RulesContext;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, Archetype};
+
+/// Adds dependencies, dev dependencies, and scripts to a `package.json`, creating it if necessary. Only the sections
+/// being added to are rewritten, so the rest of the file is kept as it was; dependencies are kept sorted, as npm does,
+/// and scripts are added after existing ones. Entries already present are left alone unless `overwrite` is true.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PackageJsonAction {
+    /// The `package.json` to update, relative to the destination. Defaults to `package.json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(default, skip_serializing_if = "LinkedHashMap::is_empty")]
+    dependencies: LinkedHashMap<String, String>,
+    #[serde(
+        rename = "dev-dependencies",
+        default,
+        skip_serializing_if = "LinkedHashMap::is_empty"
+    )]
+    dev_dependencies: LinkedHashMap<String, String>,
+    #[serde(default, skip_serializing_if = "LinkedHashMap::is_empty")]
+    scripts: LinkedHashMap<String, String>,
+    /// How plain versions, such as `4.18.2`, are turned into ranges.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<RangeStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overwrite: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RangeStrategy {
+    #[serde(rename = "EXACT", alias = "exact")]
+    Exact,
+    /// Compatible updates, as `^4.18.2`
+    #[serde(rename = "CARET", alias = "caret")]
+    Caret,
+    /// Patch updates, as `~4.18.2`
+    #[serde(rename = "TILDE", alias = "tilde")]
+    Tilde,
+}
+
+impl PackageJsonAction {
+    pub fn with_path<P: Into<String>>(mut self, path: P) -> PackageJsonAction {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn add_dependency<N: Into<String>, V: Into<String>>(&mut self, name: N, version: V) {
+        self.dependencies.insert(name.into(), version.into());
+    }
+
+    pub fn with_dependency<N: Into<String>, V: Into<String>>(mut self, name: N, version: V) -> PackageJsonAction {
+        self.add_dependency(name, version);
+        self
+    }
+
+    pub fn add_dev_dependency<N: Into<String>, V: Into<String>>(&mut self, name: N, version: V) {
+        self.dev_dependencies.insert(name.into(), version.into());
+    }
+
+    pub fn with_dev_dependency<N: Into<String>, V: Into<String>>(mut self, name: N, version: V) -> PackageJsonAction {
+        self.add_dev_dependency(name, version);
+        self
+    }
+
+    pub fn add_script<N: Into<String>, C: Into<String>>(&mut self, name: N, command: C) {
+        self.scripts.insert(name.into(), command.into());
+    }
+
+    pub fn with_script<N: Into<String>, C: Into<String>>(mut self, name: N, command: C) -> PackageJsonAction {
+        self.add_script(name, command);
+        self
+    }
+
+    pub fn with_range(mut self, range: RangeStrategy) -> PackageJsonAction {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_overwrite(mut self, overwrite: bool) -> PackageJsonAction {
+        self.overwrite = Some(overwrite);
+        self
+    }
+}
+
+impl RangeStrategy {
+    /// Applies this strategy to a plain version; ranges, tags, and URLs are returned as they are.
+    pub fn apply(&self, version: &str) -> String {
+        if Version::parse(version).is_err() {
+            return version.to_owned();
+        }
+        match self {
+            RangeStrategy::Exact => version.to_owned(),
+            RangeStrategy::Caret => format!("^{}", version),
+            RangeStrategy::Tilde => format!("~{}", version),
+        }
+    }
+}
+
+impl Action for PackageJsonAction {
+    fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        _archetype: &Archetype,
+        destination: D,
+        _rules_context: &mut RulesContext,
+        _answers: &LinkedHashMap<String, AnswerInfo>,
+        context: &mut Context,
+    ) -> Result<(), ArchetectError> {
+        let path = match &self.path {
+            Some(path) => destination.as_ref().join(archetect.render_string(path, context)?),
+            None => destination.as_ref().join("package.json"),
+        };
+
+        let mut sections = vec![];
+        for (section, entries, sorted) in [
+            ("scripts", &self.scripts, false),
+            ("dependencies", &self.dependencies, true),
+            ("devDependencies", &self.dev_dependencies, true),
+        ] {
+            let mut rendered = LinkedHashMap::new();
+            for (key, value) in entries {
+                let key = archetect.render_string(key, context)?;
+                let mut value = archetect.render_string(value, context)?;
+                if let (Some(range), true) = (self.range, section != "scripts") {
+                    value = range.apply(&value);
+                }
+                rendered.insert(key, value);
+            }
+            if !rendered.is_empty() {
+                sections.push(Section {
+                    name: section,
+                    entries: rendered,
+                    sorted,
+                });
+            }
+        }
+
+        let existing = archetect.read_contents(&path)?;
+        let updated =
+            update_package(existing.as_deref(), &sections, self.overwrite.unwrap_or_default()).map_err(|message| {
+                ArchetectError::BuildFileError {
+                    path: path.clone(),
+                    message,
+                }
+            })?;
+        match updated {
+            Some(updated) => {
+                debug!("[package-json] Updating {}", path.display());
+                if let Some(parent) = path.parent() {
+                    archetect.create_dir_all(parent)?;
+                }
+                archetect.write_contents(&path, &updated)?;
+            }
+            None => debug!("[package-json] {} is up to date", path.display()),
+        }
+        Ok(())
+    }
+}
+
+struct Section<'a> {
+    name: &'a str,
+    entries: LinkedHashMap<String, String>,
+    sorted: bool,
+}
+
+/// Merges `sections` into a package, returning the updated package, or `None` if nothing changed. Sections that are
+/// not changed are written back exactly as they were read.
+fn update_package(package: Option<&str>, sections: &[Section], overwrite: bool) -> Result<Option<String>, String> {
+    let contents = package.filter(|package| !package.trim().is_empty()).unwrap_or("{}\n");
+    let mut document: LinkedHashMap<String, Box<RawValue>> =
+        serde_json::from_str(contents).map_err(|error| error.to_string())?;
+    let indent = contents
+        .lines()
+        .nth(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .filter(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+
+    let mut changed = false;
+    for section in sections {
+        let mut entries: LinkedHashMap<String, String> = match document.get(section.name) {
+            Some(raw) => serde_json::from_str(raw.get())
+                .map_err(|_| format!("`{}` is not an object of strings", section.name))?,
+            None => LinkedHashMap::new(),
+        };
+
+        let mut section_changed = false;
+        for (key, value) in &section.entries {
+            // Replaced in place, as inserting an existing key would move it to the end.
+            match entries.get_mut(key) {
+                Some(existing) if existing == value => (),
+                Some(_) if !overwrite => debug!("[package-json] Keeping the existing `{}` in {}", key, section.name),
+                Some(existing) => {
+                    *existing = value.to_owned();
+                    section_changed = true;
+                }
+                None => {
+                    entries.insert(key.to_owned(), value.to_owned());
+                    section_changed = true;
+                }
+            }
+        }
+        if !section_changed {
+            continue;
+        }
+        if section.sorted {
+            let mut sorted = entries.into_iter().collect::<Vec<(String, String)>>();
+            sorted.sort_by(|(left, _), (right, _)| left.cmp(right));
+            entries = sorted.into_iter().collect();
+        }
+
+        // Nested one level deep in the package, so continuation lines take an extra indent.
+        let json = to_pretty_string(&entries, indent)?.replace('\n', &format!("\n{}", indent));
+        let json = RawValue::from_string(json).map_err(|error| error.to_string())?;
+        match document.get_mut(section.name) {
+            Some(existing) => *existing = json,
+            None => {
+                document.insert(section.name.to_owned(), json);
+            }
+        }
+        changed = true;
+    }
+
+    if !changed {
+        return Ok(None);
+    }
+    Ok(Some(to_pretty_string(&document, indent)? + "\n"))
+}
+
+fn to_pretty_string<T: serde::Serialize>(value: &T, indent: &str) -> Result<String, String> {
+    let mut output = vec![];
+    let mut serializer = Serializer::with_formatter(&mut output, PrettyFormatter::with_indent(indent.as_bytes()));
+    value.serialize(&mut serializer).map_err(|error| error.to_string())?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn section(name: &'static str, entries: &[(&str, &str)], sorted: bool) -> Section<'static> {
+        Section {
+            name,
+            entries: entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            sorted,
+        }
+    }
+
+    #[test]
+    fn test_update_package() {
+        let package = r#"{
+    "name": "shop",
+    "version": "1.0.0",
+    "scripts": {
+        "start": "node index.js"
+    },
+    "dependencies": {
+        "react": "^18.0.0",
+        "express": "^4.0.0"
+    },
+    "browserslist": ["defaults"]
+}
+"#;
+        let expected = r#"{
+    "name": "shop",
+    "version": "1.0.0",
+    "scripts": {
+        "start": "node index.js",
+        "test": "jest"
+    },
+    "dependencies": {
+        "axios": "^1.6.0",
+        "express": "^4.0.0",
+        "react": "^18.0.0"
+    },
+    "browserslist": ["defaults"],
+    "devDependencies": {
+        "jest": "^29.0.0"
+    }
+}
+"#;
+        let sections = [
+            section("scripts", &[("test", "jest"), ("start", "node server.js")], false),
+            section("dependencies", &[("axios", "^1.6.0")], true),
+            section("devDependencies", &[("jest", "^29.0.0")], true),
+        ];
+        assert_eq!(
+            update_package(Some(package), &sections, false).unwrap().as_deref(),
+            Some(expected)
+        );
+        assert_eq!(update_package(Some(expected), &sections, false).unwrap(), None);
+
+        let sections = [section("scripts", &[("start", "node server.js")], false)];
+        let updated = update_package(Some(expected), &sections, true).unwrap().unwrap();
+        assert!(updated.contains("\"start\": \"node server.js\""));
+
+        assert!(update_package(Some("{\"scripts\": []}"), &sections, false).is_err());
+    }
+
+    #[test]
+    fn test_range_strategy() {
+        assert_eq!(RangeStrategy::Caret.apply("4.18.2"), "^4.18.2");
+        assert_eq!(RangeStrategy::Tilde.apply("1.0.0-beta.1"), "~1.0.0-beta.1");
+        assert_eq!(RangeStrategy::Exact.apply("4.18.2"), "4.18.2");
+        assert_eq!(RangeStrategy::Caret.apply(">=2"), ">=2");
+        assert_eq!(RangeStrategy::Caret.apply("latest"), "latest");
+    }
+
+    #[test]
+    fn test_package_json_action() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        let script = "script:
+  - set:
+      name:
+        value: shop
+  - package-json:
+      scripts:
+        start: \"node {{ name }}.js\"
+      dependencies:
+        express: 4.18.2
+      range: caret
+";
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+        let files = archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            files.get_str("package.json"),
+            Some("{\n  \"scripts\": {\n    \"start\": \"node shop.js\"\n  },\n  \"dependencies\": {\n    \"express\": \"^4.18.2\"\n  }\n}\n")
+        );
+    }
+}
@@ -4,10 +4,11 @@ use linked_hash_map::LinkedHashMap;
 
 use crate::actions::{set, Action};
 use crate::config::AnswerInfo;
+use crate::provenance::AnswerOrigin;
+use crate::events::Event;
 use crate::rules::RulesContext;
 use crate::vendor::tera::Context;
 use crate::{Archetect, ArchetectError, Archetype};
-use std::fs;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum RenderAction {
@@ -64,6 +65,14 @@ impl ArchetypeOptions {
         self
     }
 
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> Option<&str> {
+        self.destination.as_deref()
+    }
+
     pub fn with_inherited_answer(mut self, key: String) -> ArchetypeOptions {
         self.answers_include.get_or_insert_with(|| Vec::new()).push(key);
         self
@@ -97,7 +106,15 @@ impl Action for RenderAction {
                 } else {
                     destination.as_ref().to_owned()
                 };
-                fs::create_dir_all(destination.as_path())?;
+                archetect.create_dir_all(destination.as_path())?;
+                // Indexing walks the source a second time, so it is only done for subscribers to the plan.
+                if archetect.has_subscribers() {
+                    let index = archetect.index_directory(&source, rules_context)?;
+                    archetect.emit(Event::RenderPlanned {
+                        files: index.written().count(),
+                        bytes: index.written_size(),
+                    });
+                }
                 archetect.render_directory(context, source, destination, rules_context)?;
             }
 
@@ -107,6 +124,7 @@ impl Action for RenderAction {
                 } else {
                     destination.as_ref().to_owned()
                 };
+                let origin = AnswerOrigin::Inherited(archetype.source().source().to_owned());
                 let archetype = archetect.load_archetype(&options.source, Some(archetype.source().clone()))?;
 
                 let mut scoped_answers = LinkedHashMap::new();
@@ -115,7 +133,8 @@ impl Action for RenderAction {
                     for identifier in answers_include {
                         if let Some(value) = context.get(identifier) {
                             if let Some(string) = value.as_str() {
-                                scoped_answers.insert(identifier.to_owned(), AnswerInfo::with_value(string).build());
+                                let answer = AnswerInfo::with_value(string).with_origin(origin.clone()).build();
+                                scoped_answers.insert(identifier.to_owned(), answer);
                             }
                         }
                     }
@@ -126,7 +145,8 @@ impl Action for RenderAction {
                 // with an empty context and unable to satisfy any variables.
                 if let Some(answers) = &options.answers {
                     let rendered_answers = set::render_answers(archetect, answers, context)?;
-                    for (key, value) in rendered_answers {
+                    for (key, mut value) in rendered_answers {
+                        value.set_origin(origin.clone());
                         scoped_answers.insert(key, value);
                     }
                 };
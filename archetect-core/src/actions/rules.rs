@@ -1,6 +1,6 @@
 use crate::actions::Action;
 use crate::config::{RuleConfig, VariableInfo};
-use crate::rules::RulesContext;
+use crate::rules::{CollisionPolicy, EmptyDirectoryPolicy, RulesContext};
 use crate::vendor::tera::Context;
 use crate::{Archetect, ArchetectError, Archetype};
 use linked_hash_map::LinkedHashMap;
@@ -16,7 +16,12 @@ pub enum RuleType {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DestinationOptions {
-    overwrite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overwrite: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collisions: Option<CollisionPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    empty_directories: Option<EmptyDirectoryPolicy>,
 }
 
 impl Action for RuleType {
@@ -34,7 +39,15 @@ impl Action for RuleType {
                 rules_context.insert_path_rules(rules);
             }
             RuleType::DestinationRules(options) => {
-                rules_context.set_overwrite(options.overwrite);
+                if let Some(overwrite) = options.overwrite {
+                    rules_context.set_overwrite(overwrite);
+                }
+                if let Some(collisions) = options.collisions {
+                    rules_context.set_collisions(collisions);
+                }
+                if let Some(empty_directories) = options.empty_directories {
+                    rules_context.set_empty_directories(empty_directories);
+                }
             }
         }
         Ok(())
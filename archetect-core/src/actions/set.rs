@@ -1,29 +1,39 @@
-use std::collections::{HashMap, HashSet};
-
 use linked_hash_map::LinkedHashMap;
 use log::{trace, warn};
-use crate::vendor::read_input::prelude::*;
 use serde_json::Value;
 
-use crate::config::{AnswerInfo, VariableInfo, VariableType};
+use crate::config::{AnswerInfo, VariableGroup, VariableInfo, VariableType};
+use crate::events::Event;
+use crate::input::{Prompt, ACCEPTABLE_BOOLEANS};
+use crate::provenance::{AnswerOrigin, AnswerProvenance};
 use crate::vendor::tera::Context;
 use crate::{Archetect, ArchetectError};
 
-const ACCEPTABLE_BOOLEANS: [&str; 8] = ["y", "yes", "true", "t", "n", "no", "false", "f"];
-
 pub fn populate_context(
     archetect: &mut Archetect,
     variables: &LinkedHashMap<String, VariableInfo>,
+    groups: &[VariableGroup],
     answers: &LinkedHashMap<String, AnswerInfo>,
     context: &mut Context,
 ) -> Result<(), ArchetectError> {
-    for (identifier, variable_info) in variables {
-        if let Some(answer) = answers.get(identifier) {
+    for (identifier, variable_info, group) in group_variables(variables, groups) {
+        if variable_info.variable_type() == VariableType::Switch && switch_enabled(archetect, identifier, variable_info) {
+            context.insert(identifier, &true);
+            record_answer(archetect, identifier, variable_info, context, AnswerOrigin::Switch);
+            continue;
+        }
+
+        let answer = lookup_answer(identifier, variable_info, answers);
+        if let Some(answer) = answer {
             if let Some(value) = answer.value() {
                 // If there is an answer for this variable, it has an explicit value, and it is an acceptable answer,
                 // use that.
                 match insert_answered_variable(archetect, identifier, value, &variable_info, context)? {
-                    None => continue,
+                    None => {
+                        let origin = answer.origin().cloned().unwrap_or(AnswerOrigin::Unspecified);
+                        record_answer(archetect, identifier, variable_info, context, origin);
+                        continue;
+                    }
                     Some(warning) => warn!("{}", warning),
                 }
             }
@@ -32,14 +42,17 @@ pub fn populate_context(
                 // If no answer was provided, there is an explicit value on the variable definition, and it is an
                 // acceptable value, use that.
                 match insert_answered_variable(archetect, identifier, value, &variable_info, context)? {
-                    None => continue,
+                    None => {
+                        record_answer(archetect, identifier, variable_info, context, AnswerOrigin::Derived);
+                        continue;
+                    }
                     Some(warning) => warn!("{}", warning),
                 }
             }
         }
 
         // Determine if a default can be provided.
-        let default = if let Some(answer) = answers.get(identifier) {
+        let default = if let Some(answer) = answer {
             if let Some(default) = answer.default() {
                 Some(archetect.render_string(default, context)?)
             } else if let Some(default) = variable_info.default() {
@@ -52,12 +65,34 @@ pub fn populate_context(
         } else {
             None
         };
+        // Otherwise, the author's name and email default to the detected git identity.
+        let default = default.or_else(|| match identifier.as_str() {
+            "author_name" | "author_email" => context.get(identifier).and_then(Value::as_str).map(|value| value.to_owned()),
+            _ => None,
+        });
+        // Switches are off unless turned on.
+        let default = default.or_else(|| match variable_info.variable_type() {
+            VariableType::Switch => Some("false".to_owned()),
+            _ => None,
+        });
+
+        // A shared variable already prompted for by another archetype during this run is not asked for again.
+        if variable_info.shared() {
+            if let Some(value) = archetect.shared_answer(identifier, &variable_info.variable_type()).cloned() {
+                context.insert(identifier, &value);
+                record_answer(archetect, identifier, variable_info, context, AnswerOrigin::Shared);
+                continue;
+            }
+        }
 
         // No answer or explict value provided.  Check to see if we're in headless mode before prompting for a value.
         if archetect.headless() {
             if let Some(default) = default {
                 match insert_answered_variable(archetect, identifier, &default, &variable_info, context)? {
-                    None => continue,
+                    None => {
+                        record_answer(archetect, identifier, variable_info, context, AnswerOrigin::Default);
+                        continue;
+                    }
                     Some(message) => {
                         return Err(ArchetectError::HeadlessInvalidDefault { identifier: identifier.to_owned(), default, message })
                     },
@@ -67,28 +102,141 @@ pub fn populate_context(
         }
         // If we've made it this far, there was not an acceptable answer or explicit value provided.  We need to prompt
         // for a valid value.
-        let mut prompt = if let Some(prompt) = variable_info.prompt() {
-            format!("{} ", archetect.render_string(prompt.trim(), context)?)
+        // A list's default is offered as its items, each rendered as they are when answering headless, so that
+        // accepting it gives the same list either way.
+        let default = match (variable_info.variable_type(), default) {
+            (VariableType::Array, Some(default)) => {
+                let items = convert_to_list(archetect, context, &default)?;
+                Some(items.iter().filter_map(Value::as_str).collect::<Vec<&str>>().join(", "))
+            }
+            (_, default) => default,
+        };
+        let message = if let Some(prompt) = variable_info.prompt() {
+            archetect.render_string(prompt.trim(), context)?
         } else {
-            format!("{}: ", identifier)
+            format!("{}:", identifier)
         };
+        let mut prompt = Prompt::new(identifier, message)
+            .with_variable_type(variable_info.variable_type())
+            .with_required(variable_info.required());
+        if let Some(group) = group {
+            prompt = prompt.with_group(group.heading());
+        }
+        if let Some(help) = variable_info.help() {
+            prompt = prompt.with_help(archetect.render_string(help, context)?);
+        }
+        if let Some(example) = variable_info.example() {
+            prompt = prompt.with_example(archetect.render_string(example, context)?);
+        }
+        if let Some(docs_url) = variable_info.docs_url() {
+            prompt = prompt.with_docs_url(archetect.render_string(docs_url, context)?);
+        }
+        // The answer last entered for the variable, in this or an earlier run, is offered ahead of its default.
+        let recent = archetect
+            .answer_history()
+            .filter(|_| !variable_info.sensitive())
+            .and_then(|history| history.recent(identifier))
+            .map(|recent| recent.to_owned());
+        if let Some(default) = recent.or(default) {
+            prompt = prompt.with_default(default);
+        }
 
-        let value = match variable_info.variable_type() {
-            VariableType::Enum(values) => prompt_for_enum(&mut prompt, &values, &default),
-            VariableType::Bool => prompt_for_bool(&mut prompt, &default),
-            VariableType::Int => prompt_for_int(&mut prompt, &default),
-            VariableType::Array => prompt_for_list(archetect, context, &mut prompt, &default, variable_info)?,
-            VariableType::String => prompt_for_string(&mut prompt, &default, variable_info.required()),
+        archetect.emit(Event::PromptAsked(prompt.clone()));
+        let value = match archetect.prompt_provider() {
+            Some(provider) => provider.prompt(&prompt)?,
+            None => None,
         };
 
         if let Some(value) = value {
             context.insert(identifier, &value);
+            if !variable_info.sensitive() {
+                archetect.remember_answer(identifier, &value);
+            }
+            if variable_info.shared() {
+                archetect.share_answer(identifier, variable_info.variable_type(), value);
+            }
+            record_answer(archetect, identifier, variable_info, context, AnswerOrigin::Prompt);
+        }
+    }
+
+    // Switch variables that were answered or prompted on are enabled as switches, for `switch-enabled` conditions.
+    for (identifier, variable_info) in variables {
+        if variable_info.variable_type() == VariableType::Switch && context.get(identifier) == Some(&Value::Bool(true)) {
+            archetect.enable_switch(identifier.as_str());
         }
     }
 
     Ok(())
 }
 
+fn switch_enabled(archetect: &Archetect, identifier: &str, variable_info: &VariableInfo) -> bool {
+    let switches = archetect.switches();
+    switches.contains(identifier) || variable_info.aliases().iter().any(|alias| switches.contains(alias.as_str()))
+}
+
+/// Orders variables group by group, in the order the groups are declared, after any ungrouped variables. Variables
+/// otherwise keep the order they are declared in.
+fn group_variables<'a>(
+    variables: &'a LinkedHashMap<String, VariableInfo>,
+    groups: &'a [VariableGroup],
+) -> Vec<(&'a String, &'a VariableInfo, Option<&'a VariableGroup>)> {
+    let mut grouped = variables
+        .iter()
+        .map(|(identifier, variable_info)| {
+            let group = variable_info.group().and_then(|id| {
+                let group = groups.iter().position(|group| group.id() == id);
+                if group.is_none() {
+                    warn!("Variable `{}` belongs to the undeclared group `{}`.", identifier, id);
+                }
+                group
+            });
+            (identifier, variable_info, group)
+        })
+        .collect::<Vec<_>>();
+    grouped.sort_by_key(|(_, _, group)| *group);
+    grouped
+        .into_iter()
+        .map(|(identifier, variable_info, group)| (identifier, variable_info, group.map(|group| &groups[group])))
+        .collect()
+}
+
+fn record_answer(
+    archetect: &mut Archetect,
+    identifier: &str,
+    variable_info: &VariableInfo,
+    context: &Context,
+    origin: AnswerOrigin,
+) {
+    let archetype = context
+        .get("archetype")
+        .and_then(|archetype| archetype.get("source"))
+        .and_then(|source| source.as_str())
+        .unwrap_or_default();
+    let value = context.get(identifier).cloned().unwrap_or(Value::Null);
+    archetect.record_answer(
+        AnswerProvenance::new(archetype, identifier, value, origin).with_sensitive(variable_info.sensitive()),
+    );
+}
+
+/// Finds the answer for a variable by its identifier, falling back to any of its aliases so that answers recorded
+/// under a variable's former name continue to apply.
+fn lookup_answer<'a>(
+    identifier: &str,
+    variable_info: &VariableInfo,
+    answers: &'a LinkedHashMap<String, AnswerInfo>,
+) -> Option<&'a AnswerInfo> {
+    if let Some(answer) = answers.get(identifier) {
+        return Some(answer);
+    }
+    for alias in variable_info.aliases() {
+        if let Some(answer) = answers.get(alias) {
+            warn!("The answer `{}` is deprecated; it has been renamed to `{}`.", alias, identifier);
+            return Some(answer);
+        }
+    }
+    None
+}
+
 fn insert_answered_variable(archetect: &mut Archetect, identifier: &str, value: &str, variable_info: &VariableInfo,
                             context: &mut Context) -> Result<Option<String>, ArchetectError> {
 
@@ -103,7 +251,7 @@ fn insert_answered_variable(archetect: &mut Archetect, identifier: &str, value:
                 return Ok(None);
             }
         }
-        VariableType::Bool => {
+        VariableType::Bool | VariableType::Switch => {
             let value = value.to_lowercase();
             // If the provided answer is anything that resembled a boolean value, use that; otherwise, we'll
             // have to prompt the user for a valid answer
@@ -155,175 +303,6 @@ fn convert_to_list(archetect: &mut Archetect, context: &Context, value: &str) ->
     Ok(values)
 }
 
-fn prompt_for_string(prompt: &mut String, default: &Option<String>, required: bool) -> Option<Value> {
-    if let Some(default) = &default {
-        prompt.push_str(format!("[{}] ", default).as_str());
-    };
-    let mut input_builder = input::<String>().prompting_on_stderr().msg(&prompt);
-
-    if required {
-        input_builder = input_builder
-
-            .add_test(|value| value.len() > 0)
-            .repeat_msg(&prompt)
-            .err("Please provide a value.");
-    }
-
-    let value = if let Some(default) = &default {
-        input_builder.default(default.clone().to_owned()).get()
-    } else {
-        input_builder.get()
-    };
-    Some(Value::String(value))
-}
-
-fn prompt_for_int(prompt: &mut String, default: &Option<String>) -> Option<Value> {
-    let default = default.as_ref().map_or(None, |value| value.parse::<i64>().ok());
-
-    if let Some(default) = default {
-        prompt.push_str(format!("[{}] ", default).as_str());
-    }
-
-    let input_builder = input::<i64>()
-        .prompting_on_stderr()
-        .msg(&prompt)
-        .err("Please specify an integer.")
-        .repeat_msg(&prompt);
-
-    let value = if let Some(default) = default {
-        input_builder.default(default).get()
-    } else {
-        input_builder.get()
-    };
-
-    Some(Value::from(value))
-}
-
-fn prompt_for_bool(prompt: &mut String, default: &Option<String>) -> Option<Value> {
-    let default = default.as_ref().map_or(None, |value| {
-        let value = value.to_lowercase();
-        if ACCEPTABLE_BOOLEANS.contains(&value.as_str()) {
-            Some(value.to_owned())
-        } else {
-            None
-        }
-    });
-
-    if let Some(default) = default.clone() {
-        prompt.push_str(format!("[{}] ", default).as_str());
-    }
-
-    let input_builder = input::<String>()
-        .prompting_on_stderr()
-        .add_test(|value| ACCEPTABLE_BOOLEANS.contains(&value.to_lowercase().as_str()))
-        .msg(&prompt)
-        .err(format!("Please specify a value of {:?}.", ACCEPTABLE_BOOLEANS))
-        .repeat_msg(&prompt);
-
-    let value = if let Some(default) = default.clone() {
-        input_builder.default(default.to_owned()).get()
-    } else {
-        input_builder.get()
-    };
-
-    let value = match ACCEPTABLE_BOOLEANS.iter().position(|i| i == &value.as_str()).unwrap() {
-        0..=3 => true,
-        _ => false,
-    };
-
-    Some(Value::Bool(value))
-}
-
-fn prompt_for_list(
-    archetect: &mut Archetect,
-    context: &Context,
-    prompt: &mut String,
-    default: &Option<String>,
-    variable_info: &VariableInfo,
-) -> Result<Option<Value>, ArchetectError> {
-    if let Some(default) = &default {
-        prompt.push_str(format!("[{}] ", default).as_str());
-    };
-    
-    eprintln!("{}", &prompt);
-
-    let mut results = vec![];
-
-    loop {
-        let requirements_met = if let Some(default) = default {
-            !default.trim().is_empty()
-        } else {
-            !results.is_empty()
-        };
-
-        let mut input_builder = input::<String>().prompting_on_stderr().msg(" - ");
-
-        if variable_info.required() {
-            input_builder = input_builder
-                .add_test(move |value| requirements_met || !value.trim().is_empty())
-                .err("This list requires at least one item.")
-                .repeat_msg(" - ")
-        }
-        let item = input_builder.get();
-
-        if item.trim().is_empty() {
-            break;
-        }
-
-        results.push(Value::String(item));
-    }
-
-    if results.len() > 0 || !variable_info.required() {
-        Ok(Some(Value::Array(results)))
-    } else if let Some(default) = default {
-        Ok(Some(Value::Array(convert_to_list(archetect, context, default)?)))
-    } else {
-        Ok(None)
-    }
-
-}
-
-fn prompt_for_enum(prompt: &mut String, options: &Vec<String>, default: &Option<String>) -> Option<Value> {
-    eprintln!("{}", &prompt);
-    let choices = options
-        .iter()
-        .enumerate()
-        .map(|(id, entry)| (id + 1, entry.clone()))
-        .collect::<HashMap<_, _>>();
-
-    for (id, option) in options.iter().enumerate() {
-        eprintln!("{:>2}) {}", id + 1, option);
-    }
-
-    let mut message = String::from("Select an entry: ");
-    if let Some(default) = default {
-        if options.contains(default) {
-            message.push_str(format!("[{}] ", default).as_str());
-        }
-    };
-
-    let test_values = choices.keys().map(|v| *v).collect::<HashSet<_>>();
-
-    let input_builder = input::<usize>()
-        .prompting_on_stderr()
-        .msg(&message)
-        .add_test(move |value| test_values.contains(value))
-        .err("Please enter the number of a selection from the list.")
-        .repeat_msg(&message);
-
-    let value = if let Some(default) = default {
-        if let Some(index) = options.iter().position(|e| e.eq(default)) {
-            input_builder.default(index + 1).get()
-        } else {
-            input_builder.get()
-        }
-    } else {
-        input_builder.get()
-    };
-
-    Some(Value::String(choices.get(&value).unwrap().to_owned()))
-}
-
 pub fn render_answers(
     archetect: &mut Archetect,
     answers: &LinkedHashMap<String, AnswerInfo>,
@@ -398,9 +377,31 @@ pub enum VariableDescriptor {
 
 #[cfg(test)]
 mod tests {
-    use crate::actions::set::VariableDescriptor;
+    use std::fs;
+    use std::rc::Rc;
+
+    use crate::actions::set::{lookup_answer, VariableDescriptor};
+    use crate::config::{AnswerHistory, AnswerInfo, VariableInfo};
+    use crate::file_tree::FileTree;
+    use crate::input::ScriptedPromptProvider;
+    use crate::provenance::AnswerOrigin;
+    use crate::{Archetect, Archetype};
     use linked_hash_map::LinkedHashMap;
 
+    #[test]
+    fn test_lookup_answer_by_alias() {
+        let variable = VariableInfo::with_prompt("Author:").with_alias("author").build();
+
+        let mut answers = LinkedHashMap::new();
+        answers.insert("author".to_owned(), AnswerInfo::with_value("Jane Doe").build());
+        assert_eq!(lookup_answer("author_name", &variable, &answers).unwrap().value(), Some("Jane Doe"));
+
+        answers.insert("author_name".to_owned(), AnswerInfo::with_value("John Doe").build());
+        assert_eq!(lookup_answer("author_name", &variable, &answers).unwrap().value(), Some("John Doe"));
+
+        assert!(lookup_answer("author_name", &VariableInfo::new().build(), &LinkedHashMap::new()).is_none());
+    }
+
     #[test]
     fn test_serialize() {
         let object = VariableDescriptor::Object {
@@ -463,4 +464,191 @@ mod tests {
         }
         results
     }
+
+    #[test]
+    fn test_shared_variables_prompted_once() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            concat!(
+                "script:\n",
+                "  - render:\n      archetype:\n        source: child-a\n",
+                "  - render:\n      archetype:\n        source: child-b\n",
+            ),
+        )
+        .unwrap();
+        for child in &["child-a", "child-b"] {
+            let child_dir = archetype_dir.path().join(child);
+            fs::create_dir_all(child_dir.join("contents")).unwrap();
+            fs::write(
+                child_dir.join("archetype.yml"),
+                concat!(
+                    "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n        shared: true\n",
+                    "      team:\n        prompt: \"Team:\"\n",
+                    "  - render:\n      directory:\n        source: contents\n",
+                ),
+            )
+            .unwrap();
+            fs::write(child_dir.join("contents").join(format!("{}.txt", child)), "{{ name }} {{ team }}").unwrap();
+        }
+
+        let provider = Rc::new(
+            ScriptedPromptProvider::new()
+                .with_answer("name", "billing")
+                .with_answer("team", "payments"),
+        );
+        let mut archetect = Archetect::builder().with_prompt_provider(provider.clone()).build().unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(files.get_str("child-a.txt"), Some("billing payments"));
+        assert_eq!(files.get_str("child-b.txt"), Some("billing payments"));
+        let prompted: Vec<String> = provider.prompted().iter().map(|prompt| prompt.identifier().to_owned()).collect();
+        assert_eq!(prompted, vec!["name", "team", "team"]);
+        let origins: Vec<&AnswerOrigin> = archetect
+            .answer_provenance()
+            .iter()
+            .filter(|provenance| provenance.identifier() == "name")
+            .map(|provenance| provenance.origin())
+            .collect();
+        assert_eq!(origins, vec![&AnswerOrigin::Prompt, &AnswerOrigin::Shared]);
+    }
+
+    #[test]
+    fn test_answer_history() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n      author:\n        prompt: \"Author:\"\n        default: Anonymous\n",
+                "      token:\n        prompt: \"Token:\"\n        sensitive: true\n",
+                "      team:\n        prompt: \"Team:\"\n",
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let provider = ScriptedPromptProvider::new()
+            .with_answer("token", "s3cr3t")
+            .with_answer("team", "payments");
+        let history = AnswerHistory::new().with_answer("author", "Jane Doe").with_answer("token", "stale");
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(provider)
+            .with_answer_history(history)
+            .build()
+            .unwrap();
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        let author = archetect.answer_provenance().iter().find(|provenance| provenance.identifier() == "author");
+        assert_eq!(author.unwrap().value(), "Jane Doe");
+
+        let history = archetect.answer_history().unwrap();
+        assert_eq!(history.recent("author"), Some("Jane Doe"));
+        assert_eq!(history.recent("team"), Some("payments"));
+        assert_eq!(history.recent("token"), Some("stale"));
+    }
+
+    #[test]
+    fn test_grouped_prompts() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "groups:\n",
+                "  - id: project\n    heading: Project Info\n",
+                "  - id: persistence\n    heading: Persistence\n",
+                "script:\n  - set:\n",
+                "      database:\n        prompt: \"Database:\"\n        group: persistence\n",
+                "      name:\n        prompt: \"Name:\"\n        group: project\n",
+                "      author:\n        prompt: \"Author:\"\n",
+                "      description:\n        prompt: \"Description:\"\n        group: project\n",
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+        assert_eq!(archetype.configuration().groups()[1].heading(), "Persistence");
+
+        let provider = Rc::new(
+            ScriptedPromptProvider::new()
+                .with_answer("database", "postgres")
+                .with_answer("name", "billing")
+                .with_answer("author", "Jane Doe")
+                .with_answer("description", "Bills customers"),
+        );
+        let mut archetect = Archetect::builder().with_prompt_provider(provider.clone()).build().unwrap();
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        let prompted: Vec<(String, Option<String>)> = provider
+            .prompted()
+            .iter()
+            .map(|prompt| (prompt.identifier().to_owned(), prompt.group().map(str::to_owned)))
+            .collect();
+        assert_eq!(
+            prompted,
+            vec![
+                ("author".to_owned(), None),
+                ("name".to_owned(), Some("Project Info".to_owned())),
+                ("description".to_owned(), Some("Project Info".to_owned())),
+                ("database".to_owned(), Some("Persistence".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_defaults() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n      name:\n        value: billing\n",
+                "      services:\n        type: list\n",
+                "        default: \"{% raw %}{{ name }}{% endraw %}-api, , {{ name }}-web\"\n",
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+        let expected = serde_json::json!(["billing-api", "billing-web"]);
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let context = archetype.execute_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(context.get("services"), Some(&expected));
+
+        let provider = Rc::new(ScriptedPromptProvider::new());
+        let mut archetect = Archetect::builder().with_prompt_provider(provider.clone()).build().unwrap();
+        let context = archetype.execute_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(context.get("services"), Some(&expected));
+        assert_eq!(provider.prompted()[0].default(), Some("billing-api, billing-web"));
+    }
+
+    #[test]
+    fn test_switch_variables() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n",
+                "      docker:\n        type: switch\n",
+                "      ci:\n        type: switch\n",
+                "      metrics:\n        type: switch\n        default: \"yes\"\n",
+                "  - render:\n      directory:\n        source: contents\n",
+                "  - if:\n      switch-enabled: metrics\n      then:\n",
+                "        - render:\n            directory:\n              source: metrics\n",
+            ),
+        );
+        files.insert("contents/switches.txt", "{{ docker }} {{ ci }} {{ metrics }}");
+        files.insert("metrics/metrics.txt", "enabled");
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        archetect.enable_switch("docker");
+        let rendered = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(rendered.get_str("switches.txt"), Some("true false true"));
+        assert_eq!(rendered.get_str("metrics.txt"), Some("enabled"));
+        let origins: Vec<&AnswerOrigin> = archetect
+            .answer_provenance()
+            .iter()
+            .map(|provenance| provenance.origin())
+            .collect();
+        assert_eq!(origins, vec![&AnswerOrigin::Switch, &AnswerOrigin::Default, &AnswerOrigin::Default]);
+    }
 }
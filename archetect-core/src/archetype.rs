@@ -1,19 +1,41 @@
+use std::borrow::Cow;
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::Arc;
 
 use linked_hash_map::LinkedHashMap;
+use log::{debug, info, warn};
+use semver::Version;
 
 use crate::actions::ActionId;
-use crate::config::{AnswerInfo, ArchetypeConfig};
+use crate::config::{AnswerConfig, AnswerInfo, ArchetypeConfig, ConflictStrategies};
+use crate::editorconfig::EditorConfig;
+use crate::events::Event;
 use crate::errors::RenderError;
+use crate::file_tree::FileTree;
+use crate::formatters::FormatterConfig;
+use crate::metadata::{RenderMetadata, METADATA_FILE_NAME};
+use crate::protect::ProtectedFiles;
+use crate::report::RenderedArchetype;
+use crate::requirements::Requirements;
 use crate::rules::RulesContext;
+use crate::selection::SourceSelection;
 use crate::vendor::tera::Context;
 use crate::source::{Source, SourceError};
+use crate::target::TargetInfo;
+use crate::utils::{command_output, sorted_entries};
+use crate::validation::{self, ValidationPolicy};
+use crate::warnings::RenderWarning;
 use crate::{Archetect, ArchetectError};
 
 pub struct Archetype {
     source: Source,
     config: ArchetypeConfig,
+    /// The archetype's files, for archetypes loaded from memory rather than a source.
+    files: Option<Rc<FileTree>>,
 }
 
 impl Archetype {
@@ -23,11 +45,80 @@ impl Archetype {
         let archetype = Archetype {
             config,
             source: source.clone(),
+            files: None,
         };
 
         Ok(archetype)
     }
 
+    /// Loads an archetype from its files held in memory, with paths relative to the archetype's root, such as for
+    /// previewing archetypes where there is no filesystem. Such archetypes cannot render archetypes relative to them.
+    pub fn from_files(files: FileTree) -> Result<Archetype, ArchetypeError> {
+        let (path, config) = ["archetype.yml", "archetype.yaml"]
+            .iter()
+            .find_map(|candidate| files.get_str(candidate).map(|config| (candidate, config)))
+            .ok_or(ArchetypeError::ArchetypeConfigMissing)?;
+        let config = ArchetypeConfig::parse(*path, config)?;
+
+        Ok(Archetype {
+            config,
+            source: Source::LocalDirectory { path: PathBuf::new() },
+            files: Some(Rc::new(files)),
+        })
+    }
+
+    /// A fingerprint of the commands this archetype runs, through its script and formatters, and through its templates
+    /// when `allow_commands` lets them run any. Changes to templates that run no commands leave it alone.
+    pub fn fingerprint(&self, allow_commands: bool) -> String {
+        let commands = serde_yaml::to_string(&(self.config.actions(), self.config.formatters())).unwrap_or_default();
+        let mut fingerprint = commands.into_bytes();
+        if allow_commands {
+            for (path, contents) in self.command_templates() {
+                fingerprint.extend_from_slice(path.to_string_lossy().as_bytes());
+                fingerprint.extend_from_slice(&contents);
+            }
+        }
+        format!("{:016x}", farmhash::fingerprint64(&fingerprint))
+    }
+
+    /// The files of this archetype calling `shell_capture()` or `cmd()`, by their paths relative to it.
+    fn command_templates(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        let runs_commands = |contents: &[u8]| {
+            [&b"shell_capture("[..], &b"cmd("[..]]
+                .iter()
+                .any(|call| contents.windows(call.len()).any(|window| window == *call))
+        };
+        let mut templates = vec![];
+        match &self.files {
+            Some(files) => {
+                for (path, contents) in files.files() {
+                    if runs_commands(contents) {
+                        templates.push((path.to_owned(), contents.to_vec()));
+                    }
+                }
+            }
+            None => {
+                let root = self.source.directory();
+                let mut directories = vec![root.to_owned()];
+                while let Some(directory) = directories.pop() {
+                    for path in sorted_entries(&directory).unwrap_or_default() {
+                        if path.is_dir() {
+                            if path.file_name().is_some_and(|name| name != ".git") {
+                                directories.push(path);
+                            }
+                        } else if let Ok(contents) = fs::read(&path) {
+                            if runs_commands(&contents) {
+                                templates.push((path.strip_prefix(root).unwrap_or(&path).to_owned(), contents));
+                            }
+                        }
+                    }
+                }
+                templates.sort();
+            }
+        }
+        templates
+    }
+
     pub fn configuration(&self) -> &ArchetypeConfig {
         &self.config
     }
@@ -43,14 +134,147 @@ impl Archetype {
         answers: &LinkedHashMap<String, AnswerInfo>,
     ) -> Result<(), ArchetectError> {
         let destination = destination.as_ref();
-        fs::create_dir_all(destination)?;
+        // An upgrade backs up the destination it merges into, rather than those it renders into.
+        let backing_up = archetect.render_depth() == 0 && archetect.begin_backup(destination);
+        let result = self.render_and_record(archetect, destination, answers);
+        if backing_up {
+            archetect.finish_backup();
+        }
+        if archetect.render_depth() == 0 {
+            archetect.release_locks();
+        }
+        result
+    }
+
+    fn render_and_record(
+        &self,
+        archetect: &mut Archetect,
+        destination: &Path,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<(), ArchetectError> {
+        let context = self.execute(archetect, destination, answers)?;
+
+        // Only the outermost archetype reports on the render and describes the destination as a whole.
+        if archetect.render_depth() == 0 {
+            finish_render(archetect)?;
+        }
+        if archetect.metadata() && archetect.render_depth() == 0 {
+            archetect.preserve(&destination.join(METADATA_FILE_NAME))?;
+            RenderMetadata::from_context(&context)
+                .without_answers(|identifier| archetect.is_sensitive(identifier))
+                .save(destination)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders this archetype into a [`FileTree`] instead of the filesystem, for inspecting or snapshot testing
+    /// generated content. Exec actions are skipped, and no render metadata is recorded.
+    pub fn render_in_memory(
+        &self,
+        archetect: &mut Archetect,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<FileTree, ArchetectError> {
+        let previous = archetect.replace_file_tree(Some(FileTree::new()));
+        let result = self.execute(archetect, Path::new(""), answers);
+        let file_tree = archetect.replace_file_tree(previous).unwrap_or_default();
+        if archetect.render_depth() == 0 {
+            archetect.release_locks();
+        }
+        result?;
+        finish_render(archetect)?;
+        Ok(file_tree)
+    }
+
+    /// Runs this archetype's script as [`Archetype::render_in_memory`] does, returning the context as it stands once the
+    /// script completes rather than what it rendered. Nothing is written, nor are commands run, so it is suited to
+    /// inspecting the answers and values an archetype arrives at.
+    pub fn execute_in_memory(
+        &self,
+        archetect: &mut Archetect,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<Context, ArchetectError> {
+        let previous = archetect.replace_file_tree(Some(FileTree::new()));
+        let result = self.execute(archetect, Path::new(""), answers);
+        archetect.replace_file_tree(previous);
+        if archetect.render_depth() == 0 {
+            archetect.release_locks();
+        }
+        result
+    }
+
+    /// Runs this archetype's script against `destination`, returning the context as it stands once the script
+    /// completes, with the script's variables layered over Archetect's built-in values.
+    pub fn execute<D: AsRef<Path>>(
+        &self,
+        archetect: &mut Archetect,
+        destination: D,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<Context, ArchetectError> {
+        let destination = destination.as_ref();
+        // The destination is inspected before anything, including its directory, is created.
+        let target_info = if archetect.in_memory() {
+            TargetInfo::default()
+        } else {
+            TargetInfo::detect(destination)
+        };
+        let mut answers = Cow::Borrowed(answers);
+        if !archetect.in_memory() {
+            // Archetypes rendered by others receive only the answers passed down to them, not the workspace's.
+            let workspace = match archetect.render_depth() {
+                0 => workspace_answers(destination)?,
+                _ => None,
+            };
+            if let Some(workspace) = workspace {
+                // Workspace answers take the lowest precedence, applying only to what was not otherwise answered.
+                for (identifier, answer) in workspace.answers() {
+                    if !answers.contains_key(identifier) {
+                        answers.to_mut().insert(identifier.to_owned(), answer.clone());
+                    }
+                }
+            }
+            self.check_preconditions(destination, &target_info)?;
+            self.check_tools(archetect, &answers)?;
+        }
+        archetect.create_dir_all(destination)?;
+        if archetect.render_depth() == 0 {
+            archetect.reset_render_state();
+            archetect.set_protected(if archetect.in_memory() {
+                ProtectedFiles::default()
+            } else {
+                ProtectedFiles::load(destination)?
+            });
+            let conflicts = if archetect.in_memory() {
+                ConflictStrategies::default()
+            } else {
+                AnswerConfig::load(destination).map(|config| config.conflicts().clone()).unwrap_or_default()
+            };
+            archetect.set_conflict_strategies(destination, conflicts);
+        }
 
         let mut rules_context = RulesContext::new();
+        rules_context.set_headers(self.config.headers().cloned());
+        if !archetect.only().is_empty() {
+            let selection = SourceSelection::resolve(self.source().directory(), &self.config, archetect.only());
+            rules_context.set_selection(Some(selection));
+        }
+        if !archetect.skipped().is_empty() {
+            let exclusion = SourceSelection::resolve(self.source().directory(), &self.config, archetect.skipped());
+            rules_context.set_exclusion(Some(exclusion));
+        }
+        rules_context.set_editorconfig(match &self.files {
+            Some(files) => EditorConfig::discover_with(
+                destination,
+                !archetect.in_memory(),
+                files.get_str(".editorconfig"),
+            )?,
+            None => EditorConfig::discover(destination, !archetect.in_memory(), self.source().local_path())?,
+        });
         let mut context = Context::new();
 
         let archetect_info = ArchetectInfo {
             offline: archetect.offline(),
-            version: clap::crate_version!().to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
         };
         context.insert("archetect", &archetect_info);
 
@@ -58,21 +282,232 @@ impl Archetype {
             source: self.source().source().to_owned(),
             destination: destination.to_str().unwrap().to_owned(),
             local_path: self.source().local_path().to_str().unwrap().to_owned(),
+            version: self.config.version().map(|version| version.to_string()),
+            gitref: self.source().gitref().map(|gitref| gitref.to_owned()),
+            commit: self.source().commit(),
         };
         context.insert("archetype", &archetype_info);
+        archetect.record_archetype(RenderedArchetype::new(
+            archetype_info.source.clone(),
+            archetype_info.version.clone(),
+            archetype_info.gitref.clone(),
+            archetype_info.commit.clone(),
+        ));
+
+        let render_info = RenderInfo {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        context.insert("render", &render_info);
+
+        context.insert("system", &SystemInfo::detect());
+        context.insert("target", &target_info);
+
+        // Prompts for these default to the user's git identity, when it can be determined.
+        let identity = archetect.git_identity();
+        if let Some(author_name) = &identity.name {
+            context.insert("author_name", author_name);
+        }
+        if let Some(author_email) = &identity.email {
+            context.insert("author_email", author_email);
+        }
 
+        // Re-rendering over a stamped destination crosses from the recorded version to this one.
+        if !archetect.in_memory() {
+            if let Ok(Some(previous)) = RenderMetadata::load(destination) {
+                let previous_version = previous.archetype_version().and_then(|version| Version::parse(version).ok());
+                self.announce_releases(previous_version.as_ref());
+            }
+        }
+
+        // The archetype's own variables are layered over the values built in above, rather than mixed with them.
+        let mut context = Context::with_parent(Arc::new(context));
         let root_action = ActionId::from(self.config.actions());
 
-        root_action.execute(archetect, self, destination, &mut rules_context, answers, &mut context)
+        archetect.enter_archetype(self.source())?;
+        let sources = archetect.replace_source_tree(self.files.clone());
+        let commands = archetect.permit_commands(self, destination);
+        let result = root_action.execute(archetect, self, destination, &mut rules_context, &answers, &mut context);
+        archetect.restore_commands(commands);
+        archetect.replace_source_tree(sources);
+        archetect.exit_archetype();
+        result?;
+
+        self.format(archetect, destination);
+        self.validate(archetect, destination)?;
+
+        if let Some(next_steps) = self.config.next_steps() {
+            let next_steps = archetect.render_string(next_steps, &context)?;
+            archetect.add_next_steps(next_steps.trim().to_owned());
+        }
+
+        Ok(context)
+    }
+
+    /// Fails with every precondition `destination` does not meet, so that an unsuitable destination is reported
+    /// before any prompting or rendering rather than leaving it half-rendered.
+    fn check_preconditions(&self, destination: &Path, target_info: &TargetInfo) -> Result<(), ArchetectError> {
+        let failures = self
+            .config
+            .preconditions()
+            .iter()
+            .filter(|precondition| !precondition.check(destination, target_info))
+            .map(|precondition| precondition.to_string())
+            .collect::<Vec<String>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ArchetectError::PreconditionsFailed {
+                destination: destination.to_owned(),
+                failures,
+            })
+        }
+    }
+
+    /// Fails if a tool this archetype requires, given the switches and answers it is rendered with, is missing or of
+    /// the wrong version. Tools are only required when commands may run, so not when rendering in memory.
+    fn check_tools(
+        &self,
+        archetect: &Archetect,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<(), ArchetectError> {
+        if self.files.is_some() {
+            return Ok(());
+        }
+        let requirements = Requirements::load(self.source().local_path()).map_err(|cause| SourceError::RequirementsError {
+            path: self.source().source().to_owned(),
+            cause,
+        })?;
+        if let Some(requirements) = requirements {
+            requirements
+                .verify_tools(archetect, answers)
+                .map_err(|cause| SourceError::RequirementsError {
+                    path: self.source().source().to_owned(),
+                    cause,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Runs this archetype's formatters over the files rendered into `destination` so far.
+    fn format(&self, archetect: &mut Archetect, destination: &Path) {
+        let formatters = self.config.formatters();
+        if formatters.is_empty() {
+            return;
+        }
+        if archetect.in_memory() {
+            debug!("[format] Skipping formatters while rendering in memory");
+            return;
+        }
+        if formatters.iter().any(|formatter| matches!(formatter, FormatterConfig::Custom(_))) {
+            if let Err(error) = archetect.check_trust(self) {
+                warn!("[format] Skipping formatters: {}", error);
+                return;
+            }
+        }
+
+        let files = archetect
+            .rendered_destinations()
+            .filter(|file| file.starts_with(destination))
+            .map(|file| file.to_owned())
+            .collect::<Vec<PathBuf>>();
+        for formatter in formatters {
+            formatter.format(&files);
+        }
+    }
+
+    /// Validates the files rendered into `destination` so far, recording those that are invalid as warnings, or
+    /// failing if this archetype's policy is to error. Only the outermost archetype validates, covering the files of
+    /// the archetypes it renders, so each file is checked once.
+    fn validate(&self, archetect: &mut Archetect, destination: &Path) -> Result<(), ArchetectError> {
+        if archetect.render_depth() > 0 {
+            return Ok(());
+        }
+        let policy = match (self.config.validation(), archetect.validate()) {
+            (Some(policy), _) => policy,
+            (None, true) => ValidationPolicy::Warn,
+            (None, false) => return Ok(()),
+        };
+
+        let mut files = archetect
+            .rendered_destinations()
+            .filter(|file| file.starts_with(destination))
+            .map(|file| file.to_owned())
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        let mut invalid = vec![];
+        for file in files {
+            if archetect.validators().validator(&file).is_none() || validation::is_helm_template(archetect, &file) {
+                continue;
+            }
+            if let Ok(Some(contents)) = archetect.read_contents(&file) {
+                if let Err(error) = archetect.validators().validate(&file, &contents) {
+                    invalid.push(error);
+                }
+            }
+        }
+
+        match policy {
+            ValidationPolicy::Error if !invalid.is_empty() => Err(ArchetectError::InvalidOutput(invalid)),
+            _ => {
+                for error in invalid {
+                    archetect.add_warning(RenderWarning::InvalidOutput(error));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Logs the release notes and deprecations for every release after `from`, up to this archetype's version.
+    pub fn announce_releases(&self, from: Option<&Version>) {
+        for release in self.config.releases_since(from) {
+            if let Some(notes) = release.notes() {
+                info!("[{}] {}", release.version(), notes.trim());
+            }
+            for deprecation in release.deprecations() {
+                warn!("[{}] Deprecated: {}", release.version(), deprecation);
+            }
+        }
     }
 }
 
+/// Reports the errors deferred under `keep_going`, and summarizes any warnings, failing if they are denied.
+fn finish_render(archetect: &mut Archetect) -> Result<(), ArchetectError> {
+    archetect.emit(Event::RenderComplete {
+        files: archetect.rendered_destinations().count(),
+        warnings: archetect.warnings().len(),
+    });
+    let render_errors = archetect.take_render_errors();
+    if !render_errors.is_empty() {
+        return Err(ArchetectError::RenderErrors(render_errors));
+    }
+    let warnings = archetect.warnings();
+    if !warnings.is_empty() {
+        warn!("Rendering produced {} warning(s):", warnings.len());
+        for warning in warnings {
+            warn!("  {}", warning);
+        }
+        if archetect.deny_warnings() {
+            return Err(ArchetectError::DeniedWarnings(warnings.len()));
+        }
+    }
+    Ok(())
+}
+
 // TODO: Rework to capture working directory
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ArchetypeInfo {
     source: String,
     destination: String,
     local_path: String,
+    version: Option<String>,
+    #[serde(rename = "ref")]
+    gitref: Option<String>,
+    commit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderInfo {
+    timestamp: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -81,6 +516,86 @@ pub struct ArchetectInfo {
     version: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemInfo {
+    hostname: String,
+    username: String,
+    os: String,
+    family: String,
+    arch: String,
+}
+
+impl SystemInfo {
+    /// Describes the machine rendering an archetype, leaving blank anything that cannot be determined.
+    pub fn detect() -> SystemInfo {
+        SystemInfo {
+            hostname: hostname().unwrap_or_default(),
+            username: env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default(),
+            os: env::consts::OS.to_owned(),
+            family: env::consts::FAMILY.to_owned(),
+            arch: env::consts::ARCH.to_owned(),
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    let hostname = env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| command_output(&mut Command::new("hostname")))?;
+    let hostname = hostname.trim();
+    if hostname.is_empty() {
+        None
+    } else {
+        Some(hostname.to_owned())
+    }
+}
+
+/// The answers shared by the workspace `destination` is within, if it has an answer file.
+fn workspace_answers(destination: &Path) -> Result<Option<AnswerConfig>, ArchetectError> {
+    let path = match AnswerConfig::discover_workspace(destination) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    debug!("Using workspace answers from '{}'", path.display());
+    match AnswerConfig::load(&path) {
+        Ok(workspace) => Ok(Some(workspace)),
+        Err(source) => Err(ArchetectError::AnswerConfigError {
+            path: path.display().to_string(),
+            source,
+        }),
+    }
+}
+
+/// Reads `key` from the user's git configuration, if git is installed and the key is set.
+/// The user's name and email as git knows them, in `directory`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct GitIdentity {
+    pub(crate) name: Option<String>,
+    pub(crate) email: Option<String>,
+}
+
+impl GitIdentity {
+    pub(crate) fn detect(directory: &Path) -> GitIdentity {
+        GitIdentity {
+            name: git_config(directory, "user.name"),
+            email: git_config(directory, "user.email"),
+        }
+    }
+}
+
+#[cfg(not(feature = "git"))]
+fn git_config(_directory: &Path, _key: &str) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "git")]
+fn git_config(directory: &Path, key: &str) -> Option<String> {
+    command_output(Command::new("git").current_dir(directory).args(["config", "--get", key]))
+        .filter(|value| !value.is_empty())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ArchetypeError {
     #[error("The specified archetype is missing an archetype.yml or archetype.yaml file")]
@@ -106,8 +621,922 @@ pub enum ArchetypeError {
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::fs;
+    use std::path::{Path, PathBuf};
     use glob::Pattern;
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::config::{AnswerConfig, AnswerInfo, ConflictStrategy};
+    use crate::file_tree::FileTree;
+    use crate::warnings::RenderWarning;
+    use crate::{Archetect, ArchetectError, Archetype, ArchetypeError, RenderError};
+
+    const RENDER_CONTENTS: &str = "script:\n  - render:\n      directory:\n        source: contents\n";
+    const NO_FILES: &[(&str, &str)] = &[];
+
+    /// Writes an archetype with `config` as its `archetype.yml` and `files` within its `contents` directory, by their
+    /// paths there, and loads it. The archetype is removed once the directory returned is dropped.
+    fn archetype<C: AsRef<[u8]>>(
+        archetect: &Archetect,
+        config: &str,
+        files: &[(&str, C)],
+    ) -> (tempfile::TempDir, Archetype) {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(archetype_dir.path().join("archetype.yml"), config).unwrap();
+        let contents = archetype_dir.path().join("contents");
+        fs::create_dir_all(&contents).unwrap();
+        for (path, file) in files {
+            let path = contents.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        (archetype_dir, archetype)
+    }
+
+    #[test]
+    fn test_render_in_memory() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n  - render:\n      directory:\n        source: contents\n",
+            &[("{{ name }}/README.md", "# {{ name | title_case }}\n")],
+        );
+        let mut answers = LinkedHashMap::new();
+        answers.insert("name".to_owned(), AnswerInfo::with_value("order-service").build());
+
+        let file_tree = archetype.render_in_memory(&mut archetect, &answers).unwrap();
+
+        assert_eq!(file_tree.to_string(), "--- order-service/README.md\n# Order Service\n");
+        assert!(!archetect.in_memory());
+        assert!(!Path::new("order-service").exists());
+    }
+
+    #[cfg(feature = "exec")]
+    #[test]
+    fn test_execute_in_memory() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      name:\n        value: billing\n  - exec:\n      command: touch\n      args: [executed]\n",
+            NO_FILES,
+        );
+        let context = archetype.execute_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(context.get("name"), Some(&serde_json::json!("billing")));
+        assert!(!archetect.in_memory());
+        assert!(!Path::new("executed").exists());
+    }
+
+    #[test]
+    fn test_from_files() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            "script:\n  - set:\n      name:\n        value: billing\n  - render:\n      directory:\n        source: contents\n",
+        );
+        files.insert(".editorconfig", "[*.md]\ninsert_final_newline = true\n");
+        files.insert("contents/{{ name }}/README.md", "# {{ name | title_case }}");
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = Archetype::from_files(files).unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(files.get_str("billing/README.md"), Some("# Billing\n"));
+
+        assert!(matches!(
+            Archetype::from_files(FileTree::new()),
+            Err(ArchetypeError::ArchetypeConfigMissing)
+        ));
+    }
+
+    #[test]
+    fn test_headers() {
+        let config = "headers:
+  contents: \"Copyright {{ owner }}\"
+  patterns: [\"*.rs\", \"*.sh\"]
+script:
+  - set:
+      owner:
+        value: Acme
+  - render:
+      directory:
+        source: contents
+";
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            config,
+            &[
+                ("main.rs", "fn main() {}\n"),
+                ("run.sh", "#!/bin/sh\nexit 0\n"),
+                ("README.md", "# Readme\n"),
+            ],
+        );
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(files.get_str("main.rs"), Some("// Copyright Acme\n\nfn main() {}\n"));
+        assert_eq!(files.get_str("run.sh"), Some("#!/bin/sh\n# Copyright Acme\n\nexit 0\n"));
+        assert_eq!(files.get_str("README.md"), Some("# Readme\n"));
+    }
+
+    #[test]
+    fn test_context_defaults() {
+        let config = "script:
+  - set:
+      author_name:
+        value: Acme
+  - set:
+      author_name:
+        prompt: \"Author:\"
+  - render:
+      directory:
+        source: contents
+";
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            config,
+            &[(
+                "system.txt",
+                "{{ author_name }} {{ system.os }}/{{ system.arch }} ({{ system.family }})",
+            )],
+        );
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        let expected = format!(
+            "Acme {}/{} ({})",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            std::env::consts::FAMILY
+        );
+        assert_eq!(files.get_str("system.txt"), Some(expected.as_str()));
+
+        // Only the author's details default to what is already in the context.
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            "script:\n  - set:\n      owner:\n        value: Acme\n  - set:\n      owner:\n        prompt: \"Owner:\"\n",
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+        let result = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new());
+        assert!(matches!(result, Err(ArchetectError::HeadlessMissingAnswer(identifier)) if identifier == "owner"));
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_git_identity() {
+        use std::process::Command;
+
+        use super::GitIdentity;
+
+        let repository = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git").current_dir(repository.path()).args(args).status().unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.name", "Jane Doe"]);
+        git(&["config", "user.email", "jane@example.com"]);
+
+        let identity = GitIdentity::detect(repository.path());
+        assert_eq!(identity.name.as_deref(), Some("Jane Doe"));
+        assert_eq!(identity.email.as_deref(), Some("jane@example.com"));
+    }
+
+    #[test]
+    fn test_scoped_variables() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n",
+                "  - set:\n      name:\n        value: billing\n",
+                "  - scope:\n      - set:\n          name:\n            value: payments\n",
+                "          inner:\n            value: scoped\n",
+                "  - for:\n      split:\n        in: \"a, b\"\n      do:\n",
+                "        - set:\n            last:\n              value: \"{{ name }}-{{ item }}\"\n",
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+        let destination = tempfile::tempdir().unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let context = archetype
+            .execute(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(context.local_keys().collect::<Vec<_>>(), vec!["name"]);
+        assert_eq!(context.get("name").and_then(|name| name.as_str()), Some("billing"));
+        assert!(context.parent().is_some_and(|builtins| builtins.contains_key("archetype")));
+        assert!(!context.contains_key("inner"));
+        assert!(!context.contains_key("last"));
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn test_encodings() {
+        use crate::encoding::Encoding;
+
+        let config = "script:
+  - set:
+      name:
+        value: Café
+  - rules:
+      - source:
+          legacy:
+            patterns:
+              - glob: \"*.ini\"
+            encoding: latin1
+            output-encoding: utf-16le
+  - render:
+      directory:
+        source: contents
+";
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(&archetect, config, &[("settings.ini", b"\xa9 {{ name }}")]);
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        let expected = Encoding::for_label("utf-16le").unwrap().encode("\u{feff}© Café").unwrap();
+        assert_eq!(files.get("settings.ini"), Some(expected.as_slice()));
+    }
+
+    #[test]
+    fn test_bom() {
+        let config = "script:
+  - rules:
+      - source:
+          dotnet:
+            patterns:
+              - glob: \"*.csproj\"
+            bom: true
+  - render:
+      directory:
+        source: contents
+";
+        let mut archetect = Archetect::builder().build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            config,
+            &[("Service.csproj", "<Project />"), ("README.md", "\u{feff}# Service")],
+        );
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(files.get_str("Service.csproj"), Some("\u{feff}<Project />"));
+        assert_eq!(files.get_str("README.md"), Some("# Service"));
+    }
+
+    #[test]
+    fn test_keep_going() {
+        let mut archetect = Archetect::builder().with_keep_going(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            RENDER_CONTENTS,
+            &[("first.txt", "{{ missing }}"), ("second.txt", "{% if %}"), ("valid.txt", "valid")],
+        );
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::RenderErrors(errors)) => assert_eq!(errors.len(), 2),
+            _ => panic!("Expected both template errors to be reported"),
+        }
+
+        let mut archetect = Archetect::builder().build().unwrap();
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::RenderError(_)) => (),
+            _ => panic!("Expected rendering to stop at the first template error"),
+        }
+    }
+
+    #[test]
+    fn test_destination_collisions() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        let renders = "
+  - render:
+      directory:
+        source: first
+  - render:
+      directory:
+        source: second
+";
+        fs::write(archetype_dir.path().join("archetype.yml"), format!("script:{}", renders)).unwrap();
+        for (directory, contents) in &[("first", "first"), ("second", "second")] {
+            fs::create_dir_all(archetype_dir.path().join(directory)).unwrap();
+            fs::write(archetype_dir.path().join(directory).join("README.md"), contents).unwrap();
+            fs::write(archetype_dir.path().join(directory).join("LICENSE"), "MIT").unwrap();
+        }
+
+        let mut archetect = Archetect::builder().build().unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(files.get_str("README.md"), Some("first"));
+        assert!(matches!(
+            archetect.warnings(),
+            [RenderWarning::DestinationCollision { destination, .. }] if destination == Path::new("README.md")
+        ));
+
+        let script = format!("script:\n  - rules:\n      - destination:\n          collisions: ERROR{}", renders);
+        fs::write(archetype_dir.path().join("archetype.yml"), script).unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::RenderError(RenderError::DestinationConflict { destination, .. })) => {
+                assert_eq!(destination, Path::new("README.md"))
+            }
+            _ => panic!("Expected a destination conflict"),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut archetect = Archetect::builder().build().unwrap();
+        let (archetype_dir, archetype) = archetype(
+            &archetect,
+            &format!("validate: true\n{}", RENDER_CONTENTS),
+            &[
+                ("chart/Chart.yaml", "apiVersion: v2\nname: billing\nversion: 0.1.0\n"),
+                (
+                    "chart/templates/service.yaml",
+                    "{% raw %}metadata:\n  name: {{ .Release.Name }}\n{% endraw %}",
+                ),
+                ("settings.json", "{\"port\": 8080,}"),
+            ],
+        );
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        let invalid = archetect
+            .warnings()
+            .iter()
+            .filter_map(|warning| match warning {
+                RenderWarning::InvalidOutput(invalid) => Some(invalid.destination()),
+                _ => None,
+            })
+            .collect::<Vec<&Path>>();
+        assert_eq!(invalid, vec![Path::new("settings.json")]);
+
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            format!("validate: ERROR\n{}", RENDER_CONTENTS),
+        )
+        .unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::InvalidOutput(invalid)) => assert_eq!(invalid.len(), 1),
+            _ => panic!("Expected invalid output"),
+        }
+    }
+
+    #[test]
+    fn test_preconditions() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "preconditions:\n  - empty\n  - exists: Cargo.toml\n  - absent: src/main.rs\n\
+             script:\n  - set:\n      name:\n        prompt: \"Name:\"\n",
+            NO_FILES,
+        );
+        let destination = tempfile::tempdir().unwrap();
+        fs::create_dir_all(destination.path().join("src")).unwrap();
+        fs::write(destination.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        // Preconditions fail before the headless render fails for want of an answer.
+        match archetype.render(&mut archetect, destination.path(), &LinkedHashMap::new()) {
+            Err(ArchetectError::PreconditionsFailed { failures, .. }) => assert_eq!(
+                failures,
+                vec!["must be empty", "must contain `Cargo.toml`", "must not contain `src/main.rs`"]
+            ),
+            _ => panic!("Expected failed preconditions"),
+        }
+    }
+
+    #[test]
+    fn test_protected_files() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - rules:\n      - destination:\n          overwrite: true\n\
+             \x20 - render:\n      directory:\n        source: contents\n",
+            &[("README.md", "# Rendered\n"), ("main.rs", "fn main() {}\n")],
+        );
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(destination.path().join(".archetect-protect"), "README.md\n").unwrap();
+        fs::write(destination.path().join("README.md"), "# By hand\n").unwrap();
+        fs::write(destination.path().join("main.rs"), "").unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("README.md")).unwrap(), "# By hand\n");
+        assert_eq!(fs::read_to_string(destination.path().join("main.rs")).unwrap(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_only() {
+        let mut archetect = Archetect::builder()
+            .with_headless(true)
+            .with_only("ci")
+            .with_only("contents/src/lib.rs")
+            .build()
+            .unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "components:\n  ci: [ \"contents/.github\" ]\n\
+             script:\n  - render:\n      directory:\n        source: contents\n\
+             \x20 - append:\n      destination: CODEOWNERS\n      contents: \"* @platform\"\n    component: ci\n\
+             \x20 - append:\n      destination: README.md\n      contents: \"# Service\"\n\
+             \x20 - mkdir: logs\n",
+            &[
+                (".github/workflows/ci.yml", "on: push\n"),
+                ("src/lib.rs", "pub fn run() {}\n"),
+                ("src/main.rs", "fn main() {}\n"),
+                ("Cargo.toml", "[package]\n"),
+            ],
+        );
+
+        let destination = tempfile::tempdir().unwrap();
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert!(destination.path().join(".github/workflows/ci.yml").is_file());
+        assert!(destination.path().join("src/lib.rs").is_file());
+        assert!(!destination.path().join("src/main.rs").exists());
+        assert!(!destination.path().join("Cargo.toml").exists());
+        // Actions that change the destination run only as part of a component selected.
+        assert!(destination.path().join("CODEOWNERS").is_file());
+        assert!(!destination.path().join("README.md").exists());
+        assert!(!destination.path().join("logs").exists());
+    }
+
+    #[cfg(feature = "exec")]
+    #[test]
+    fn test_check_trust() {
+        use std::rc::Rc;
+
+        use crate::config::ArchetypeConfig;
+        use crate::input::ScriptedPromptProvider;
+        use crate::source::Source;
+        use crate::system::RootedSystemLayout;
+
+        let home = tempfile::tempdir().unwrap();
+        let url = "https://github.com/acme/rust-service.git";
+        let remote = |script: &str| Archetype {
+            config: ArchetypeConfig::parse("archetype.yml", script).unwrap(),
+            source: Source::RemoteGit {
+                url: url.to_owned(),
+                path: home.path().join("cache"),
+                gitref: None,
+            },
+            files: None,
+        };
+        let builder = || Archetect::builder().with_layout(RootedSystemLayout::new(home.path()).unwrap());
+        let archetype = remote("script:\n  - exec:\n      command: make\n");
+
+        let mut archetect = builder().with_headless(true).build().unwrap();
+        assert!(matches!(archetect.check_trust(&archetype), Err(ArchetectError::UntrustedSource(_))));
+
+        let provider = ScriptedPromptProvider::new().with_answer(format!("trust:{}", url), "always allow");
+        let mut archetect = builder().with_prompt_provider(provider).build().unwrap();
+        archetect.check_trust(&archetype).unwrap();
+
+        // Always allowed, so not asked about again until its commands change.
+        let provider = Rc::new(ScriptedPromptProvider::new());
+        let mut archetect = builder().with_prompt_provider(provider.clone()).build().unwrap();
+        archetect.check_trust(&archetype).unwrap();
+        assert!(provider.prompted().is_empty());
+
+        let provider = Rc::new(ScriptedPromptProvider::new());
+        let mut archetect = builder().with_prompt_provider(provider.clone()).build().unwrap();
+        let changed = remote("script:\n  - exec:\n      command: curl\n");
+        assert!(matches!(archetect.check_trust(&changed), Err(ArchetectError::UntrustedSource(_))));
+        assert_eq!(provider.prompted().len(), 1);
+
+        // Allowing it once allows those commands for the rest of the run, but not others it is loaded with later.
+        let provider = Rc::new(ScriptedPromptProvider::new().with_answer(format!("trust:{}", url), "allow once"));
+        let mut archetect = builder().with_prompt_provider(provider.clone()).build().unwrap();
+        archetect.check_trust(&changed).unwrap();
+        archetect.check_trust(&changed).unwrap();
+        assert_eq!(provider.prompted().len(), 1);
+        archetect.check_trust(&remote("script:\n  - exec:\n      command: wget\n")).unwrap();
+        assert_eq!(provider.prompted().len(), 2);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let archetype = |greeting: &str, readme: &str| {
+            let mut files = FileTree::new();
+            files.insert("archetype.yml", "script:\n  - render:\n      directory:\n        source: contents\n");
+            files.insert("contents/greeting.txt", greeting);
+            files.insert("contents/README.md", readme);
+            Archetype::from_files(files).unwrap()
+        };
+        let original = archetype("{{ cmd(command=\"echo hello\") }}", "# Service");
+        let reworded = archetype("{{ cmd(command=\"echo hello\") }}", "# Billing");
+        let changed = archetype("{{ cmd(command=\"curl example.com\") }}", "# Service");
+
+        assert_eq!(original.fingerprint(true), reworded.fingerprint(true));
+        assert_ne!(original.fingerprint(true), changed.fingerprint(true));
+        // Templates run no commands unless they are allowed to.
+        assert_eq!(original.fingerprint(false), changed.fingerprint(false));
+        assert_ne!(original.fingerprint(false), original.fingerprint(true));
+    }
+
+    #[cfg(feature = "exec")]
+    #[test]
+    fn test_allow_commands() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            RENDER_CONTENTS,
+            &[("greeting.txt", "{{ shell_capture(command=\"echo\", args=[\"hello\"]) }}")],
+        );
+
+        let destination = tempfile::tempdir().unwrap();
+        assert!(archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .is_err());
+
+        let mut archetect = Archetect::builder()
+            .with_headless(true)
+            .with_allow_commands(true)
+            .build()
+            .unwrap();
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.path().join("greeting.txt")).unwrap().trim(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn test_archetype_cycles() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let renders = |other: &Path| format!("script:\n  - render:\n      archetype:\n        source: \"{}\"\n", other.display());
+        fs::write(first.path().join("archetype.yml"), renders(second.path())).unwrap();
+        fs::write(second.path().join("archetype.yml"), renders(first.path())).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let archetype = archetect.load_archetype(first.path().to_str().unwrap(), None).unwrap();
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::ArchetypeCycle(chain)) => assert_eq!(chain.len(), 3),
+            _ => panic!("Expected a cycle"),
+        }
+        assert_eq!(archetect.render_depth(), 0);
+
+        let mut archetect = Archetect::builder().with_headless(true).with_max_depth(0).build().unwrap();
+        match archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()) {
+            Err(ArchetectError::MaxDepthExceeded { max_depth, chain }) => {
+                assert_eq!(max_depth, 0);
+                assert_eq!(chain.len(), 2);
+            }
+            _ => panic!("Expected the maximum depth to be exceeded"),
+        }
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut archetect = Archetect::builder()
+            .with_headless(true)
+            .with_skip("contents/.github")
+            .build()
+            .unwrap();
+        let (archetype_dir, archetype) = archetype(
+            &archetect,
+            "components:\n  ci: [ \"contents/.github\" ]\n\
+             script:\n  - rules:\n      - source:\n          workflows: { patterns: [ glob: \"**/*.yml\" ], action: COPY }\n\
+             \x20 - render:\n      directory:\n        source: contents\n\
+             \x20 - append:\n      destination: .github/CODEOWNERS\n      contents: \"* @platform\"\n    component: ci\n\
+             \x20 - append:\n      destination: README.md\n      contents: \"# Service\"\n",
+            &[(".github/workflows/ci.yml", "on: push\n"), ("Cargo.toml", "[package]\n")],
+        );
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert!(files.is_file("Cargo.toml"));
+        assert!(!files.exists(".github/workflows"));
+        assert!(files.is_file("README.md"));
+
+        // Skipping a component skips the actions belonging to it, as well as its source.
+        let mut archetect = Archetect::builder().with_headless(true).with_skip("ci").build().unwrap();
+        let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert!(!files.exists(".github"));
+        assert!(files.is_file("README.md"));
+    }
+
+    #[test]
+    fn test_when_os() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n",
+                "  - rules:\n      - source:\n",
+                "          batch: { patterns: [ glob: \"**/*.bat\" ], action: SKIP, when_os: unix }\n",
+                "          shell: { patterns: [ glob: \"**/*.sh\" ], action: SKIP, when_os: windows }\n",
+                "  - set:\n      shell:\n        value: sh\n",
+                "  - set:\n      shell:\n        value: cmd\n    when_os: windows\n",
+                "  - render:\n      directory:\n        source: contents\n",
+            ),
+        );
+        files.insert("contents/build.sh", "{{ shell }}");
+        files.insert("contents/build.bat", "{{ shell }}");
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        if cfg!(windows) {
+            assert_eq!(files.get_str("build.bat"), Some("cmd"));
+            assert!(!files.exists("build.sh"));
+        } else {
+            assert_eq!(files.get_str("build.sh"), Some("sh"));
+            assert!(!files.exists("build.bat"));
+        }
+    }
+
+    #[test]
+    fn test_case_conflicts() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            RENDER_CONTENTS,
+            &[("README.md", "# Readme\n"), ("Readme.md", "# Readme\n"), ("main.rs", "fn main() {}\n")],
+        );
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        let conflicts = archetect
+            .warnings()
+            .iter()
+            .filter(|warning| matches!(warning, RenderWarning::CaseConflict { .. }))
+            .count();
+        assert_eq!(conflicts, 1);
+    }
+
+    #[test]
+    fn test_context_size_limit() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            format!(
+                "script:\n  - set:\n      description:\n        value: {}\n  - set:\n      name:\n        value: billing\n",
+                "x".repeat(2000)
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let mut archetect = Archetect::builder().with_context_size_limit(1000).build().unwrap();
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        let warnings = archetect
+            .warnings()
+            .iter()
+            .filter(|warning| matches!(warning, RenderWarning::LargeContext { limit: 1000, .. }))
+            .count();
+        assert_eq!(warnings, 1);
+
+        let mut archetect = Archetect::builder().build().unwrap();
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert!(archetect.warnings().is_empty());
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_conflict_strategies() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            RENDER_CONTENTS,
+            &[
+                ("ci.yml", "rendered\n"),
+                ("main.rs", "rendered\n"),
+                ("notes.md", "rendered\n"),
+                ("README.md", "# Billing\nRendered\n"),
+            ],
+        );
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(
+            destination.path().join("archetect.yml"),
+            "conflicts:\n  ci.yml: theirs\n  main.rs: ours\n  README.md: merge\n  \"*\": ask\n",
+        )
+        .unwrap();
+        for file in &["ci.yml", "main.rs", "notes.md"] {
+            fs::write(destination.path().join(file), "by hand\n").unwrap();
+        }
+        fs::write(destination.path().join("README.md"), "# Billing\nBy hand\n").unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        let read = |file: &str| fs::read_to_string(destination.path().join(file)).unwrap();
+        assert_eq!(read("ci.yml"), "rendered\n");
+        assert_eq!(read("main.rs"), "by hand\n");
+        // Asking is not possible when headless, so the existing file is kept.
+        assert_eq!(read("notes.md"), "by hand\n");
+        let readme = read("README.md");
+        assert!(readme.starts_with("# Billing\n<<<<<<< existing\nBy hand\n"));
+        assert!(readme.contains("Rendered\n>>>>>>> rendered"));
+        assert!(archetect
+            .warnings()
+            .iter()
+            .any(|warning| matches!(warning, RenderWarning::MergeConflict { .. })));
+    }
+
+    #[test]
+    fn test_backup() {
+        let mut archetect = Archetect::builder().with_headless(true).with_backup(true).build().unwrap();
+        let (_archetype_dir, archetype) =
+            archetype(&archetect, RENDER_CONTENTS, &[("main.rs", "rendered\n"), ("lib.rs", "rendered\n")]);
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(destination.path().join("archetect.yml"), "conflicts:\n  \"*\": theirs\n").unwrap();
+        fs::write(destination.path().join("main.rs"), "by hand\n").unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("main.rs")).unwrap(), "rendered\n");
+        assert_eq!(archetect.backups().len(), 1);
+        let backup = &archetect.backups()[0];
+        assert_eq!(backup.files(), &[PathBuf::from("main.rs")]);
+
+        assert_eq!(backup.added(), &[PathBuf::from("lib.rs")]);
+
+        crate::backup::restore(backup.directory(), destination.path()).unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("main.rs")).unwrap(), "by hand\n");
+        assert!(!destination.path().join("lib.rs").exists());
+    }
+
+    #[test]
+    fn test_workspace_answers() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      company:\n        prompt: \"Company:\"\n      project:\n        prompt: \"Project:\"\n\
+             \x20 - render:\n      directory:\n        source: contents\n\
+             \x20 - render:\n      archetype:\n        source: child\n        inherit-answers: [ project ]\n",
+            &[("README.md", "{{ company }} {{ project }}\n")],
+        );
+        let child = archetype_dir.path().join("child");
+        fs::create_dir_all(child.join("contents")).unwrap();
+        fs::write(
+            child.join("archetype.yml"),
+            "script:\n  - set:\n      company:\n        prompt: \"Company:\"\n        default: Unscoped\n\
+             \x20     project:\n        prompt: \"Project:\"\n\
+             \x20 - render:\n      directory:\n        source: contents\n",
+        )
+        .unwrap();
+        fs::write(child.join("contents").join("CHILD.md"), "{{ company }} {{ project }}\n").unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        fs::create_dir_all(workspace.path().join(".archetect")).unwrap();
+        fs::write(
+            workspace.path().join(".archetect").join("workspace.yaml"),
+            "answers:\n  company:\n    value: Acme\n  project:\n    value: Workspace\n",
+        )
+        .unwrap();
+        let destination = workspace.path().join("services").join("billing");
+        assert_eq!(
+            AnswerConfig::discover_workspace(&destination),
+            Some(fs::canonicalize(workspace.path()).unwrap().join(".archetect").join("workspace.yaml"))
+        );
+
+        let mut answers = LinkedHashMap::new();
+        answers.insert("project".to_owned(), AnswerInfo::with_value("Billing").build());
+        archetype.render(&mut archetect, &destination, &answers).unwrap();
+        assert_eq!(fs::read_to_string(destination.join("README.md")).unwrap(), "Acme Billing\n");
+        // Only the outermost archetype takes the workspace's answers; those it renders get what it passes them.
+        assert_eq!(fs::read_to_string(destination.join("CHILD.md")).unwrap(), "Unscoped Billing\n");
+    }
+
+    #[test]
+    fn test_empty_directories() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(archetype_dir.path().join("contents").join("src").join("test").join("resources")).unwrap();
+        fs::write(archetype_dir.path().join("contents").join("README.md"), "rendered\n").unwrap();
+        let render = |policy: &str| {
+            fs::write(
+                archetype_dir.path().join("archetype.yml"),
+                format!(
+                    "script:\n  - rules:\n      - destination:\n          empty_directories: {}\n\
+                     \x20 - render:\n      directory:\n        source: contents\n",
+                    policy
+                ),
+            )
+            .unwrap();
+            let destination = tempfile::tempdir().unwrap();
+            let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+            let archetype = archetect.load_archetype(archetype_dir.path().to_str().unwrap(), None).unwrap();
+            archetype
+                .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+                .unwrap();
+            destination
+        };
+
+        let destination = render("gitkeep");
+        assert!(destination.path().join("src/test/resources/.gitkeep").is_file());
+        let destination = render("prune");
+        assert!(!destination.path().join("src").exists());
+        assert!(destination.path().join("README.md").is_file());
+        let destination = render("keep");
+        assert!(destination.path().join("src/test/resources").is_dir());
+    }
+
+    #[test]
+    fn test_default_conflict_strategy() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        archetect.set_default_conflict_strategy(Some(ConflictStrategy::Theirs));
+        let (_archetype_dir, archetype) = archetype(&archetect, RENDER_CONTENTS, &[("ci.yml", "rendered\n")]);
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(destination.path().join("ci.yml"), "by hand\n").unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("ci.yml")).unwrap(), "rendered\n");
+    }
+
+    #[test]
+    fn test_existing_files_kept() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) =
+            archetype(&archetect, RENDER_CONTENTS, &[("ci.yml", "rendered\n"), ("main.rs", "rendered\n")]);
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(destination.path().join("ci.yml"), "by hand\n").unwrap();
+
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        assert_eq!(fs::read_to_string(destination.path().join("ci.yml")).unwrap(), "by hand\n");
+        assert_eq!(fs::read_to_string(destination.path().join("main.rs")).unwrap(), "rendered\n");
+    }
+
+    #[test]
+    fn test_source_rules() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      name:\n        value: billing\n\
+             \x20 - rules:\n      - source:\n          charts: { patterns: [ glob: \"**/*.yaml\" ], action: COPY }\n\
+             \x20         drafts: { patterns: [ glob: \"**/*.draft\" ], action: SKIP }\n\
+             \x20 - render:\n      directory:\n        source: contents\n",
+            &[
+                ("chart/values.yaml", "name: {{ name }}\n"),
+                ("notes.draft", "{{ name }}\n"),
+                ("README.md", "# {{ name }}\n"),
+            ],
+        );
+
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(files.get_str("chart/values.yaml"), Some("name: {{ name }}\n"));
+        assert!(!files.exists("notes.draft"));
+        assert_eq!(files.get_str("README.md"), Some("# billing\n"));
+    }
+
+    #[test]
+    fn test_next_steps() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      project_name:\n        prompt: \"Project Name:\"\n\
+             next_steps: |\n  ## Next Steps\n  - `cd {{ project_name }} && cargo run`\n",
+            NO_FILES,
+        );
+        let mut answers = LinkedHashMap::new();
+        answers.insert("project_name".to_owned(), AnswerInfo::with_value("billing").build());
+        archetype.render_in_memory(&mut archetect, &answers).unwrap();
+        assert_eq!(
+            archetect.next_steps(),
+            &["## Next Steps\n- `cd billing && cargo run`".to_owned()]
+        );
+
+        // A render that fails shows no next steps.
+        assert!(archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).is_err());
+        assert!(archetect.next_steps().is_empty());
+    }
+
+    #[test]
+    fn test_steps_in_memory() {
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      name:\n        value: billing\n\
+             \x20 - exec:\n      command: touch\n      args: [executed]\n    description: \"Touch {{ name }}\"\n",
+            NO_FILES,
+        );
+        // Steps are described as they would be performed, without running their commands.
+        let files = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(archetect.steps(), &["Touch billing".to_owned()]);
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_sensitive_metadata() {
+        let mut archetect = Archetect::builder().with_headless(true).with_metadata(true).build().unwrap();
+        let (_archetype_dir, archetype) = archetype(
+            &archetect,
+            "script:\n  - set:\n      name:\n        value: billing\n\
+             \x20     token:\n        value: hunter2\n        sensitive: true\n",
+            NO_FILES,
+        );
+        let destination = tempfile::tempdir().unwrap();
+        archetype
+            .render(&mut archetect, destination.path(), &LinkedHashMap::new())
+            .unwrap();
+        let metadata = fs::read_to_string(destination.path().join(crate::metadata::METADATA_FILE_NAME)).unwrap();
+        assert!(metadata.contains("billing"));
+        assert!(!metadata.contains("token"));
+        assert!(!metadata.contains("hunter2"));
+    }
 
     #[test]
     fn test_glob_full_directory_path() {
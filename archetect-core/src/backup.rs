@@ -0,0 +1,246 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use log::debug;
+
+use crate::utils::sorted_entries;
+
+/// Where backups are kept, within the destination they were taken of.
+pub const BACKUPS_DIR: &str = ".archetect/backups";
+
+/// Lists the files a render or upgrade added, one per line, within the backup directory.
+const ADDED_MANIFEST: &str = ".archetect-added";
+
+/// The files a render or upgrade overwrote in a destination, copied as they were beforehand into
+/// `.archetect/backups/<timestamp>/` so that they can be put back with [`restore`], along with those it added, so that
+/// they can be removed. Files are copied when they are about to be overwritten, so the backup holds exactly what was
+/// changed, and is not written at all if nothing was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    destination: PathBuf,
+    directory: PathBuf,
+    files: Vec<PathBuf>,
+    added: Vec<PathBuf>,
+}
+
+impl Backup {
+    /// Begins a backup of `destination`, named for the time it is taken.
+    pub fn begin<P: Into<PathBuf>>(destination: P) -> Backup {
+        let destination = destination.into();
+        let backups = destination.join(BACKUPS_DIR);
+        let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut directory = backups.join(&timestamp);
+        let mut attempt = 1;
+        while directory.exists() {
+            attempt += 1;
+            directory = backups.join(format!("{}-{}", timestamp, attempt));
+        }
+        Backup {
+            destination,
+            directory,
+            files: vec![],
+            added: vec![],
+        }
+    }
+
+    /// The destination the backup was taken of.
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
+    /// Where the files are copied to.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The files backed up, relative to the destination.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The files added, rather than overwritten, relative to the destination.
+    pub fn added(&self) -> &[PathBuf] {
+        &self.added
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.added.is_empty()
+    }
+
+    /// Copies the file at `path` into the backup, or records it as added should it not exist yet, unless it is not
+    /// within the destination or was already backed up.
+    pub(crate) fn preserve(&mut self, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            return Ok(());
+        }
+        let relative = match path.strip_prefix(&self.destination) {
+            Ok(relative) if !relative.starts_with(BACKUPS_DIR) => relative.to_owned(),
+            _ => {
+                debug!("Not backing up {}, outside of {}", path.display(), self.destination.display());
+                return Ok(());
+            }
+        };
+        if self.files.contains(&relative) || self.added.contains(&relative) {
+            return Ok(());
+        }
+        self.create_directory()?;
+        if !path.exists() {
+            let mut manifest = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.directory.join(ADDED_MANIFEST))?;
+            writeln!(manifest, "{}", relative.display())?;
+            self.added.push(relative);
+            return Ok(());
+        }
+        let copy = self.directory.join(&relative);
+        if let Some(parent) = copy.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &copy)?;
+        self.files.push(relative);
+        Ok(())
+    }
+
+    /// Creates the backup's directory, keeping the backups out of version control.
+    fn create_directory(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        let ignore = self.destination.join(BACKUPS_DIR).join(".gitignore");
+        if !ignore.exists() {
+            fs::write(ignore, "*\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// The files [`restore`] put back, and those it removed for having been added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Restoration {
+    restored: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+}
+
+impl Restoration {
+    /// The files copied back from the backup, relative to the destination.
+    pub fn restored(&self) -> &[PathBuf] {
+        &self.restored
+    }
+
+    /// The files removed for having been added, relative to the destination.
+    pub fn removed(&self) -> &[PathBuf] {
+        &self.removed
+    }
+}
+
+/// The backups taken of `destination`, oldest first.
+pub fn list<P: AsRef<Path>>(destination: P) -> io::Result<Vec<PathBuf>> {
+    let backups = destination.as_ref().join(BACKUPS_DIR);
+    if !backups.is_dir() {
+        return Ok(vec![]);
+    }
+    Ok(sorted_entries(&backups)?.into_iter().filter(|path| path.is_dir()).collect())
+}
+
+/// The backup of `destination` named `id`, or its latest backup, should it exist. Only names within the backups
+/// directory are looked up, not paths leading out of it.
+pub fn find<P: AsRef<Path>>(destination: P, id: Option<&str>) -> io::Result<Option<PathBuf>> {
+    match id {
+        Some(id) => {
+            let mut components = Path::new(id).components();
+            if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+                return Ok(None);
+            }
+            let backup = destination.as_ref().join(BACKUPS_DIR).join(id);
+            Ok(Some(backup).filter(|backup| backup.is_dir()))
+        }
+        None => Ok(list(destination)?.pop()),
+    }
+}
+
+/// Copies the files of the backup in `backup` back into `destination`, and removes those the render added, rather than
+/// overwrote. The directories holding added files are left in place.
+pub fn restore<B: AsRef<Path>, D: AsRef<Path>>(backup: B, destination: D) -> io::Result<Restoration> {
+    let backup = backup.as_ref();
+    let destination = destination.as_ref();
+    let mut restoration = Restoration::default();
+    restore_directory(backup, backup, destination, &mut restoration.restored)?;
+    let manifest = backup.join(ADDED_MANIFEST);
+    if manifest.is_file() {
+        for relative in fs::read_to_string(manifest)?.lines().map(PathBuf::from) {
+            let path = destination.join(&relative);
+            if path.is_file() {
+                fs::remove_file(path)?;
+                restoration.removed.push(relative);
+            }
+        }
+    }
+    Ok(restoration)
+}
+
+fn restore_directory(backup: &Path, directory: &Path, destination: &Path, restored: &mut Vec<PathBuf>) -> io::Result<()> {
+    for path in sorted_entries(directory)? {
+        if path.is_dir() {
+            restore_directory(backup, &path, destination, restored)?;
+            continue;
+        }
+        if directory == backup && path.ends_with(ADDED_MANIFEST) {
+            continue;
+        }
+        let relative = path.strip_prefix(backup).unwrap_or(&path).to_owned();
+        let target = destination.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&path, &target)?;
+        restored.push(relative);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_and_restore() {
+        let destination = tempfile::tempdir().unwrap();
+        fs::create_dir_all(destination.path().join("src")).unwrap();
+        fs::write(destination.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(destination.path().join("README.md"), "# Billing\n").unwrap();
+
+        let mut backup = Backup::begin(destination.path());
+        backup.preserve(&destination.path().join("src/main.rs")).unwrap();
+        backup.preserve(&destination.path().join("src/main.rs")).unwrap();
+        backup.preserve(&destination.path().join("src/added.rs")).unwrap();
+        backup.preserve(&destination.path().join("src")).unwrap();
+        assert_eq!(backup.files(), &[PathBuf::from("src/main.rs")]);
+        assert_eq!(backup.added(), &[PathBuf::from("src/added.rs")]);
+        assert!(backup.directory().starts_with(destination.path().join(BACKUPS_DIR)));
+        assert_eq!(
+            fs::read_to_string(destination.path().join(BACKUPS_DIR).join(".gitignore")).unwrap(),
+            "*\n"
+        );
+        assert_ne!(Backup::begin(destination.path()).directory(), backup.directory());
+
+        fs::write(destination.path().join("src/main.rs"), "fn main() { todo!() }\n").unwrap();
+        fs::write(destination.path().join("src/added.rs"), "").unwrap();
+        assert_eq!(list(destination.path()).unwrap(), vec![backup.directory().to_owned()]);
+        assert_eq!(find(destination.path(), None).unwrap().as_deref(), Some(backup.directory()));
+        assert!(find(destination.path(), Some("19700101-000000")).unwrap().is_none());
+        let id = backup.directory().file_name().unwrap().to_str().unwrap();
+        for outside in ["", ".", "..", "../..", "/", "src/..", &format!("../{}/{}", BACKUPS_DIR, id)] {
+            assert!(find(destination.path(), Some(outside)).unwrap().is_none(), "{}", outside);
+        }
+        assert_eq!(find(destination.path(), Some(id)).unwrap().as_deref(), Some(backup.directory()));
+        let restoration = restore(backup.directory(), destination.path()).unwrap();
+        assert_eq!(restoration.restored(), &[PathBuf::from("src/main.rs")]);
+        assert_eq!(restoration.removed(), &[PathBuf::from("src/added.rs")]);
+        assert_eq!(
+            fs::read_to_string(destination.path().join("src/main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+        assert!(!destination.path().join("src/added.rs").exists());
+        assert!(destination.path().join("README.md").is_file());
+    }
+}
@@ -0,0 +1,429 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use linked_hash_map::LinkedHashMap;
+
+use crate::config::{AnswerConfig, AnswerInfo};
+use crate::provenance::AnswerOrigin;
+use crate::vendor::tera::Context;
+use crate::{Archetect, ArchetectError, RenderError};
+
+/// A list of archetypes to render in one run, such as every service in a monorepo, read with `archetect batch`.
+/// Answers given for the whole manifest apply to every instance; each instance's own answers take precedence over them.
+/// Destinations are relative to the manifest, and are templated with the answers, so that an instance with
+/// [matrix](expand_matrix) answers renders each combination to its own destination.
+///
+/// ```yaml
+/// answers:
+///   organization:
+///     value: acme
+/// instances:
+///   - source: https://github.com/acme/service-archetype.git#v2.0
+///     destination: services/billing
+///     answers:
+///       name:
+///         value: billing
+///   - source: https://github.com/acme/service-archetype.git#v2.0
+///     destination: services/ledger
+///     answers:
+///       name:
+///         value: ledger
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BatchManifest {
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    answers: LinkedHashMap<String, AnswerInfo>,
+    #[serde(default)]
+    instances: Vec<BatchInstance>,
+    #[serde(skip)]
+    directory: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchInstance {
+    source: String,
+    destination: PathBuf,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    answers: LinkedHashMap<String, AnswerInfo>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    #[error("Error parsing batch manifest `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error reading batch manifest `{path}`: {source}")]
+    IoError { path: PathBuf, source: std::io::Error },
+}
+
+impl BatchManifest {
+    pub fn new() -> BatchManifest {
+        BatchManifest::default()
+    }
+
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<BatchManifest, BatchError> {
+        let path = path.into();
+        let manifest = match fs::read_to_string(&path) {
+            Ok(manifest) => manifest,
+            Err(source) => return Err(BatchError::IoError { path, source }),
+        };
+        let mut manifest = match serde_yaml::from_str::<BatchManifest>(&manifest) {
+            Ok(manifest) => manifest,
+            Err(source) => return Err(BatchError::ParseError { path, source }),
+        };
+
+        let origin = AnswerOrigin::AnswerFile(path.display().to_string());
+        let answers = manifest.answers.iter_mut().chain(
+            manifest
+                .instances
+                .iter_mut()
+                .flat_map(|instance| instance.answers.iter_mut()),
+        );
+        for (_, answer) in answers {
+            answer.set_origin(origin.clone());
+        }
+        manifest.directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    pub fn add_answer<I: Into<String>>(&mut self, identifier: I, answer: AnswerInfo) {
+        self.answers.insert(identifier.into(), answer);
+    }
+
+    pub fn with_answer<I: Into<String>>(mut self, identifier: I, answer: AnswerInfo) -> BatchManifest {
+        self.add_answer(identifier, answer);
+        self
+    }
+
+    pub fn add_instance(&mut self, instance: BatchInstance) {
+        self.instances.push(instance);
+    }
+
+    pub fn with_instance(mut self, instance: BatchInstance) -> BatchManifest {
+        self.add_instance(instance);
+        self
+    }
+
+    pub fn answers(&self) -> &LinkedHashMap<String, AnswerInfo> {
+        &self.answers
+    }
+
+    pub fn instances(&self) -> &[BatchInstance] {
+        &self.instances
+    }
+
+    /// The destination of `instance`, resolved against the directory containing the manifest.
+    pub fn destination(&self, instance: &BatchInstance) -> PathBuf {
+        self.directory.join(&instance.destination)
+    }
+}
+
+impl BatchInstance {
+    pub fn new<S: Into<String>, D: Into<PathBuf>>(source: S, destination: D) -> BatchInstance {
+        BatchInstance {
+            source: source.into(),
+            destination: destination.into(),
+            answers: LinkedHashMap::new(),
+        }
+    }
+
+    pub fn with_answer<I: Into<String>>(mut self, identifier: I, answer: AnswerInfo) -> BatchInstance {
+        self.answers.insert(identifier.into(), answer);
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn answers(&self) -> &LinkedHashMap<String, AnswerInfo> {
+        &self.answers
+    }
+}
+
+/// The outcome of rendering one instance of a [`BatchManifest`].
+#[derive(Debug)]
+pub struct BatchResult {
+    source: String,
+    destination: PathBuf,
+    files: usize,
+    warnings: usize,
+    next_steps: Vec<String>,
+    error: Option<ArchetectError>,
+}
+
+impl BatchResult {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
+    /// The number of files rendered, including any rendered before a failure.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
+    /// The rendered `next_steps` of the archetypes rendered, if rendering succeeded.
+    pub fn next_steps(&self) -> &[String] {
+        &self.next_steps
+    }
+
+    pub fn error(&self) -> Option<&ArchetectError> {
+        self.error.as_ref()
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    results: Vec<BatchResult>,
+}
+
+impl BatchReport {
+    /// The outcome of each instance, in the order of the manifest.
+    pub fn results(&self) -> &[BatchResult] {
+        &self.results
+    }
+
+    pub fn failures(&self) -> usize {
+        self.results.iter().filter(|result| !result.succeeded()).count()
+    }
+
+    pub fn files(&self) -> usize {
+        self.results.iter().map(BatchResult::files).sum()
+    }
+}
+
+/// Renders every instance of `manifest` in order. An instance that fails is recorded in the report, and the rest are
+/// still rendered. The manifest's answers take precedence over `answers`, and the instance's own over both; answers
+/// recorded in the destination fill in any that remain. An instance with matrix answers is rendered once for each
+/// combination of their values, to a destination templated with them.
+pub fn render_batch(
+    archetect: &mut Archetect,
+    manifest: &BatchManifest,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+) -> BatchReport {
+    let mut report = BatchReport::default();
+    for instance in manifest.instances() {
+        let mut instance_answers = answers.clone();
+        instance_answers.extend(manifest.answers().clone());
+        instance_answers.extend(instance.answers().clone());
+
+        for mut instance_answers in expand_matrix(&instance_answers) {
+            archetect.reset_render_state();
+            let destination = match render_destination(archetect, &instance.destination, &instance_answers) {
+                Ok(destination) => manifest.directory.join(destination),
+                Err(error) => {
+                    report.results.push(BatchResult {
+                        source: instance.source().to_owned(),
+                        destination: manifest.destination(instance),
+                        files: 0,
+                        warnings: 0,
+                        next_steps: vec![],
+                        error: Some(error.into()),
+                    });
+                    continue;
+                }
+            };
+
+            if let Ok(answer_config) = AnswerConfig::load(destination.clone()) {
+                for (identifier, answer) in answer_config.answers() {
+                    if !instance_answers.contains_key(identifier) {
+                        instance_answers.insert(identifier.to_owned(), answer.clone());
+                    }
+                }
+            }
+
+            let result = archetect
+                .load_archetype(instance.source(), None)
+                .map_err(ArchetectError::from)
+                .and_then(|archetype| archetype.render(archetect, &destination, &instance_answers));
+            report.results.push(BatchResult {
+                source: instance.source().to_owned(),
+                destination,
+                files: archetect.rendered_destinations().count(),
+                warnings: archetect.warnings().len(),
+                next_steps: archetect.next_steps().to_vec(),
+                error: result.err(),
+            });
+        }
+    }
+    report
+}
+
+/// Expands answers marked as a `matrix`, whose values are lists, into one set of answers for each combination of their
+/// items. The first matrix answer varies slowest. Answers without a matrix are the only combination.
+///
+/// ```yaml
+/// answers:
+///   environment:
+///     value: [dev, staging, prod]
+///     matrix: true
+///   region:
+///     value: [us, eu]
+///     matrix: true
+/// ```
+pub fn expand_matrix(answers: &LinkedHashMap<String, AnswerInfo>) -> Vec<LinkedHashMap<String, AnswerInfo>> {
+    let mut combinations = vec![answers.clone()];
+    for (identifier, answer) in answers.iter().filter(|(_, answer)| answer.matrix()) {
+        let items = answer
+            .value()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>();
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                items.iter().map(move |item| {
+                    let mut combination = combination.clone();
+                    let mut item = AnswerInfo::with_value(*item);
+                    if let Some(origin) = answer.origin() {
+                        item = item.with_origin(origin.clone());
+                    }
+                    combination.insert(identifier.to_owned(), item.build());
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// Renders a destination such as `config/{{ environment }}` with the values of `answers`.
+pub fn render_destination(
+    archetect: &mut Archetect,
+    destination: &Path,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+) -> Result<PathBuf, RenderError> {
+    let mut context = Context::new();
+    for (identifier, answer) in answers {
+        if let Some(value) = answer.value() {
+            context.insert(identifier.as_str(), value);
+        }
+    }
+    archetect
+        .render_string(&destination.to_string_lossy(), &context)
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::config::AnswerInfo;
+    use crate::Archetect;
+
+    use super::{expand_matrix, render_batch, render_destination, BatchManifest};
+
+    #[test]
+    fn test_render_batch() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n      organization:\n        prompt: \"Org:\"\n  \
+             - render:\n      directory:\n        source: contents\n",
+        )
+        .unwrap();
+        fs::create_dir(archetype_dir.path().join("contents")).unwrap();
+        fs::write(
+            archetype_dir.path().join("contents").join("README.md"),
+            "{{ organization }}/{{ name }}",
+        )
+        .unwrap();
+        let source = archetype_dir.path().display();
+
+        let workspace = tempfile::tempdir().unwrap();
+        let manifest_path = workspace.path().join("batch.yml");
+        fs::write(
+            &manifest_path,
+            format!(
+                "answers:\n  organization:\n    value: acme\n\
+                 instances:\n\
+                 \x20 - source: {source}\n    destination: services/billing\n    answers:\n      name:\n        value: billing\n\
+                 \x20 - source: {source}/missing\n    destination: services/broken\n\
+                 \x20 - source: {source}\n    destination: services/ledger\n    answers:\n      name:\n        value: ledger\n",
+                source = source
+            ),
+        )
+        .unwrap();
+
+        let manifest = BatchManifest::load(&manifest_path).unwrap();
+        let mut archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let mut answers = LinkedHashMap::new();
+        answers.insert("organization".to_owned(), AnswerInfo::with_value("initech").build());
+        let report = render_batch(&mut archetect, &manifest, &answers);
+
+        assert_eq!(report.results().len(), 3);
+        assert_eq!(report.failures(), 1);
+        assert_eq!(report.files(), 2);
+        assert!(report.results()[1].error().is_some());
+        assert_eq!(report.results()[1].files(), 0);
+        assert_eq!(
+            fs::read_to_string(workspace.path().join("services/billing/README.md")).unwrap(),
+            "acme/billing"
+        );
+        assert_eq!(
+            fs::read_to_string(workspace.path().join("services/ledger/README.md")).unwrap(),
+            "acme/ledger"
+        );
+    }
+
+    #[test]
+    fn test_expand_matrix() {
+        let answers: LinkedHashMap<String, AnswerInfo> = serde_yaml::from_str(
+            "name:\n  value: billing\n\
+             environment:\n  value: [dev, staging, prod]\n  matrix: true\n\
+             region:\n  value: us, eu\n  matrix: true\n\
+             features:\n  value: [http, grpc]\n",
+        )
+        .unwrap();
+        assert_eq!(answers["features"].value(), Some("http, grpc"));
+
+        let combinations = expand_matrix(&answers);
+        let values: Vec<(&str, &str)> = combinations
+            .iter()
+            .map(|answers| {
+                (
+                    answers["environment"].value().unwrap(),
+                    answers["region"].value().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("dev", "us"),
+                ("dev", "eu"),
+                ("staging", "us"),
+                ("staging", "eu"),
+                ("prod", "us"),
+                ("prod", "eu")
+            ]
+        );
+        assert!(combinations
+            .iter()
+            .all(|answers| answers["name"].value() == Some("billing")));
+
+        let mut archetect = Archetect::builder().build().unwrap();
+        let destination = render_destination(
+            &mut archetect,
+            Path::new("config/{{ environment }}-{{ region }}"),
+            &combinations[3],
+        );
+        assert_eq!(destination.unwrap(), Path::new("config/staging-eu"));
+    }
+}
@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use log::{debug, warn};
+
+use crate::outdated::parse_worktrees;
+use crate::source::git::{git_output, lock_repository, worktree_lock_path};
+use crate::source::SourceError;
+use crate::utils::{lock_file, sorted_entries};
+use crate::Archetect;
+
+/// How long a ref checked out in the git cache may go unused before it is collected, by default.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How often the git cache is collected after rendering.
+const COLLECTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Records when the git cache was last collected, within it.
+const LAST_COLLECTED: &str = ".last-collected";
+
+/// What was removed from the git cache by [`collect`].
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheCollection {
+    worktrees: Vec<PathBuf>,
+    repositories: Vec<PathBuf>,
+}
+
+impl CacheCollection {
+    /// The checkouts of refs that went unused.
+    pub fn worktrees(&self) -> &[PathBuf] {
+        &self.worktrees
+    }
+
+    /// The repositories whose remotes no longer exist, that have no checkouts left, or that were cloned before refs
+    /// were checked out separately.
+    pub fn repositories(&self) -> &[PathBuf] {
+        &self.repositories
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.worktrees.is_empty() && self.repositories.is_empty()
+    }
+}
+
+/// Records that the ref checked out at `worktree` was used, so that it is not collected for a while.
+pub(crate) fn touch(worktree: &Path) {
+    let result = fs::File::options()
+        .write(true)
+        .open(worktree.join(".git"))
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(error) = result {
+        debug!("Could not record the use of {}: {}", worktree.display(), error);
+    }
+}
+
+/// Removes refs checked out in the git cache that have not been used for `max_age`, and repositories whose remotes no
+/// longer exist or that are left with nothing checked out. Remotes are only checked when online.
+pub fn collect(archetect: &Archetect, max_age: Duration) -> Result<CacheCollection, SourceError> {
+    let mut collection = CacheCollection::default();
+    let cache_dir = archetect.layout().git_cache_dir();
+    if !cache_dir.exists() {
+        return Ok(collection);
+    }
+
+    for path in sorted_entries(&cache_dir)? {
+        if path.extension().is_some_and(|extension| extension == "git") && path.join("HEAD").exists() {
+            collect_repository(archetect, &path, max_age, &mut collection)?;
+        } else if path.join(".git").is_dir() {
+            // Clones from before refs were checked out into worktrees of their own are no longer used.
+            fs::remove_dir_all(&path)?;
+            collection.repositories.push(path);
+        }
+    }
+
+    fs::write(cache_dir.join(LAST_COLLECTED), "")?;
+    Ok(collection)
+}
+
+/// Collects the git cache as [`collect`] does, unless it was collected recently, warning rather than failing should
+/// collection not succeed.
+pub fn collect_if_due(archetect: &Archetect, max_age: Duration) -> Option<CacheCollection> {
+    let marker = archetect.layout().git_cache_dir().join(LAST_COLLECTED);
+    let due = match fs::metadata(&marker).and_then(|metadata| metadata.modified()) {
+        Ok(collected) => collected.elapsed().map_or(true, |elapsed| elapsed >= COLLECTION_INTERVAL),
+        Err(_) => true,
+    };
+    if !due {
+        return None;
+    }
+    match collect(archetect, max_age) {
+        Ok(collection) => Some(collection),
+        Err(error) => {
+            warn!("Error collecting the git cache: {}", error);
+            None
+        }
+    }
+}
+
+fn collect_repository(
+    archetect: &Archetect,
+    repository: &Path,
+    max_age: Duration,
+    collection: &mut CacheCollection,
+) -> Result<(), SourceError> {
+    let _lock = lock_repository(repository)?;
+    let url = git_output(Command::new("git").current_dir(repository).args(["remote", "get-url", "origin"]))?;
+    let removed = !archetect.offline() && remote_exists(repository, &url) == Some(false);
+    if removed {
+        debug!("Removing {}, as {} no longer exists", repository.display(), url);
+    }
+
+    let worktrees = git_output(Command::new("git").current_dir(repository).args(["worktree", "list", "--porcelain"]))?;
+    let worktrees = parse_worktrees(&worktrees);
+    let mut remaining = worktrees.len();
+    for (worktree, _) in worktrees {
+        if !removed && !is_unused(&worktree, max_age) {
+            continue;
+        }
+        // Worktrees being rendered from are left to whoever is reading them.
+        let _worktree_lock = match lock_file(&worktree_lock_path(&worktree), Duration::ZERO)? {
+            Some(lock) => lock,
+            None => continue,
+        };
+        if !removed {
+            debug!("Removing {}, unused for {:?}", worktree.display(), max_age);
+        }
+        git_output(
+            Command::new("git")
+                .current_dir(repository)
+                .args(["worktree", "remove", "--force"])
+                .arg(&worktree),
+        )?;
+        if !removed {
+            collection.worktrees.push(worktree);
+        }
+        remaining -= 1;
+    }
+    git_output(Command::new("git").current_dir(repository).args(["worktree", "prune"]))?;
+
+    if remaining == 0 {
+        remove_repository(repository)?;
+        collection.repositories.push(repository.to_owned());
+    }
+    Ok(())
+}
+
+/// Whether the ref checked out at `worktree` has gone unused for `max_age`, which a worktree whose directory is
+/// missing has.
+fn is_unused(worktree: &Path, max_age: Duration) -> bool {
+    match fs::metadata(worktree.join(".git")).and_then(|metadata| metadata.modified()) {
+        Ok(used) => used.elapsed().is_ok_and(|elapsed| elapsed >= max_age),
+        Err(_) => true,
+    }
+}
+
+fn remove_repository(repository: &Path) -> Result<(), SourceError> {
+    let worktrees = repository.with_extension("worktrees");
+    if worktrees.exists() {
+        fs::remove_dir_all(worktrees)?;
+    }
+    fs::remove_dir_all(repository)?;
+    Ok(())
+}
+
+/// Whether the remote at `url` still exists, if that can be told. Remotes that cannot be reached, or that ask for
+/// credentials, may exist.
+fn remote_exists(repository: &Path, url: &str) -> Option<bool> {
+    let local = Path::new(url);
+    if local.is_absolute() {
+        return Some(local.exists());
+    }
+    let output = Command::new("git")
+        .current_dir(repository)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["ls-remote", "--exit-code", "origin", "HEAD"])
+        .output()
+        .ok()?;
+    // Exiting with 2 means the remote was reached, but has no HEAD.
+    if matches!(output.status.code(), Some(0) | Some(2)) {
+        return Some(true);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
+    let missing = ["not found", "does not exist", "does not appear to be a git repository"];
+    if missing.iter().any(|message| stderr.contains(message)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::git::cache_git_repo;
+    use crate::system::RootedSystemLayout;
+
+    #[test]
+    fn test_collect() {
+        let root = tempfile::tempdir().unwrap();
+        let remote = root.path().join("remote");
+        let git = |args: &[&str]| {
+            git_output(
+                Command::new("git")
+                    .current_dir(&remote)
+                    .args(["-c", "user.name=Archetect", "-c", "user.email=archetect@example.com"])
+                    .args(args),
+            )
+            .unwrap()
+        };
+        fs::create_dir_all(&remote).unwrap();
+        git(&["init", "-q", "-b", "main"]);
+        fs::write(remote.join("archetype.yml"), "script: []\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "Initial"]);
+        git(&["tag", "v1.0.0"]);
+
+        let archetect = Archetect::builder()
+            .with_layout(RootedSystemLayout::new(root.path().join("home")).unwrap())
+            .build()
+            .unwrap();
+        let url = remote.to_str().unwrap();
+        let repository = archetect.layout().git_cache_dir().join("remote.git");
+        let main = cache_git_repo(url, &None, &repository, &archetect).unwrap();
+        let tagged = cache_git_repo(url, &Some("v1.0.0".to_owned()), &repository, &archetect).unwrap();
+        let legacy = archetect.layout().git_cache_dir().join("1234");
+        fs::create_dir_all(legacy.join(".git")).unwrap();
+
+        let collection = collect(&archetect, DEFAULT_MAX_AGE).unwrap();
+        assert_eq!(collection.repositories(), &[legacy]);
+        assert!(collection.worktrees().is_empty());
+        assert!(main.exists());
+        assert!(collect_if_due(&archetect, DEFAULT_MAX_AGE).is_none());
+
+        // Worktrees are not collected while they are being rendered from.
+        let collection = collect(&archetect, Duration::ZERO).unwrap();
+        assert!(collection.worktrees().is_empty());
+        assert!(collection.repositories().is_empty());
+        assert!(main.exists());
+
+        archetect.release_locks();
+        let collection = collect(&archetect, Duration::ZERO).unwrap();
+        assert_eq!(collection.worktrees().len(), 2);
+        assert_eq!(collection.repositories().len(), 1);
+        assert!(!main.exists());
+        assert!(!tagged.exists());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use linked_hash_map::LinkedHashMap;
+
+use crate::config::AnswerInfo;
+use crate::metadata::value_to_answer;
+use crate::provenance::AnswerOrigin;
+use crate::{Archetect, ArchetectError, Archetype};
+
+/// How a rendered file differs between two versions of an archetype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    /// The file is only rendered by the newer version.
+    Added,
+    /// The file is only rendered by the older version.
+    Removed,
+    /// Both versions render the file, with different contents.
+    Changed,
+}
+
+#[derive(Debug, Default)]
+pub struct Comparison {
+    files: LinkedHashMap<PathBuf, FileChange>,
+    unchanged: usize,
+}
+
+impl Comparison {
+    /// The files that differ, by path.
+    pub fn files(&self) -> &LinkedHashMap<PathBuf, FileChange> {
+        &self.files
+    }
+
+    pub fn change(&self, path: &Path) -> Option<FileChange> {
+        self.files.get(path).copied()
+    }
+
+    /// The number of files both versions render identically.
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Renders `from` and `to` in memory with the same answers, and reports the files that differ between them. Anything
+/// prompted for while rendering `from` is answered the same way for `to`, rather than being asked again.
+pub fn compare(
+    archetect: &mut Archetect,
+    from: &Archetype,
+    to: &Archetype,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+) -> Result<Comparison, ArchetectError> {
+    let prompted = archetect.answer_provenance().len();
+    let from_files = from.render_in_memory(archetect, answers)?;
+
+    let mut answers = answers.clone();
+    for provenance in &archetect.answer_provenance()[prompted..] {
+        if !matches!(provenance.origin(), AnswerOrigin::Prompt | AnswerOrigin::Shared) {
+            continue;
+        }
+        if answers.contains_key(provenance.identifier()) {
+            continue;
+        }
+        if let Some(answer) = value_to_answer(provenance.value()) {
+            let answer = AnswerInfo::with_value(answer).with_origin(AnswerOrigin::Prompt).build();
+            answers.insert(provenance.identifier().to_owned(), answer);
+        }
+    }
+    let to_files = to.render_in_memory(archetect, &answers)?;
+
+    let paths = from_files.paths().chain(to_files.paths()).collect::<BTreeSet<_>>();
+    let mut comparison = Comparison::default();
+    for path in paths {
+        let change = match (from_files.get(path), to_files.get(path)) {
+            (Some(from), Some(to)) if from == to => {
+                comparison.unchanged += 1;
+                continue;
+            }
+            (Some(_), Some(_)) => FileChange::Changed,
+            (None, _) => FileChange::Added,
+            (_, None) => FileChange::Removed,
+        };
+        comparison.files.insert(path.to_owned(), change);
+    }
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::file_tree::FileTree;
+    use crate::input::ScriptedPromptProvider;
+    use crate::{Archetect, Archetype};
+
+    use super::{compare, FileChange};
+
+    fn archetype(files: &[(&str, &str)]) -> Archetype {
+        let mut tree = FileTree::new();
+        tree.insert(
+            "archetype.yml",
+            "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n  - render:\n      directory:\n        source: contents\n",
+        );
+        for (path, contents) in files {
+            tree.insert(Path::new("contents").join(path), *contents);
+        }
+        Archetype::from_files(tree).unwrap()
+    }
+
+    #[test]
+    fn test_compare() {
+        let from = archetype(&[
+            ("README.md", "# {{ name }}\n"),
+            ("src/main.rs", "fn main() {}\n"),
+            ("build.sh", "make\n"),
+        ]);
+        let to = archetype(&[
+            ("README.md", "# {{ name }}\n"),
+            ("src/main.rs", "fn main() {\n    println!(\"{{ name }}\");\n}\n"),
+            ("Dockerfile", "FROM scratch\n"),
+        ]);
+
+        let provider = Rc::new(ScriptedPromptProvider::new().with_answer("name", "billing"));
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(provider.clone())
+            .build()
+            .unwrap();
+        let comparison = compare(&mut archetect, &from, &to, &LinkedHashMap::new()).unwrap();
+
+        assert_eq!(provider.prompted().len(), 1);
+        assert_eq!(comparison.unchanged(), 1);
+        assert_eq!(comparison.change(Path::new("src/main.rs")), Some(FileChange::Changed));
+        assert_eq!(comparison.change(Path::new("Dockerfile")), Some(FileChange::Added));
+        assert_eq!(comparison.change(Path::new("build.sh")), Some(FileChange::Removed));
+        assert_eq!(comparison.change(Path::new("README.md")), None);
+        assert_eq!(
+            comparison.files().keys().collect::<Vec<_>>(),
+            vec![Path::new("Dockerfile"), Path::new("build.sh"), Path::new("src/main.rs")]
+        );
+    }
+}
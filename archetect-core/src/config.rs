@@ -1,11 +1,34 @@
 mod answers;
 mod archetype;
+#[cfg(feature = "catalog")]
 mod catalog;
+mod conflict;
+mod group;
+mod history;
+mod overrides;
+mod platform;
+mod precondition;
+mod release;
 mod rule;
+mod trust;
+mod user;
 mod variable;
 
-pub use answers::{AnswerConfig, AnswerConfigError, AnswerInfo};
+pub use answers::{AnswerConfig, AnswerConfigError, AnswerInfo, WORKSPACE_ANSWER_FILES};
 pub use archetype::ArchetypeConfig;
-pub use catalog::{Catalog, CatalogEntry, CatalogError, CATALOG_FILE_NAME};
+#[cfg(feature = "catalog")]
+pub use catalog::{Catalog, CatalogEntry, CatalogError};
+pub use conflict::{ConflictStrategies, ConflictStrategy};
+pub use group::VariableGroup;
+pub use history::{AnswerHistory, AnswerHistoryError};
+pub use overrides::{OverrideConfig, OverrideConfigError};
+pub use platform::OsCondition;
+pub use precondition::Precondition;
+pub use release::ReleaseNotes;
 pub use rule::{Pattern, RuleAction, RuleConfig};
+pub use trust::{TrustedSource, TrustedSources, TrustedSourcesError};
+pub use user::{UserConfig, UserConfigError, SETTINGS};
 pub use variable::{VariableInfo, VariableInfoBuilder, VariableType};
+
+/// The file name of catalogs, including the user catalog.
+pub const CATALOG_FILE_NAME: &str = "catalog.yml";
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use linked_hash_map::LinkedHashMap;
 use log::debug;
@@ -7,14 +7,21 @@ use pest::error::Error as PestError;
 use pest::iterators::Pair;
 use pest::Parser;
 
-use crate::config::VariableInfo;
+use crate::config::{ConflictStrategies, VariableInfo};
+use crate::provenance::AnswerOrigin;
+use crate::utils::resolve_path;
 
 pub type AnswerInfo = VariableInfo;
 
+/// The answer files shared by everything rendered within a workspace, such as a monorepo, relative to its root.
+pub const WORKSPACE_ANSWER_FILES: &[&str] = &[".archetect/workspace.yaml", ".archetect/workspace.yml"];
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AnswerConfig {
-    #[serde(skip_serializing_if = "LinkedHashMap::is_empty")]
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
     answers: LinkedHashMap<String, AnswerInfo>,
+    #[serde(skip_serializing_if = "ConflictStrategies::is_empty", default)]
+    conflicts: ConflictStrategies,
 }
 
 #[derive(Debug, PartialEq, thiserror::Error)]
@@ -23,6 +30,8 @@ pub enum AnswerConfigError {
     ParseError(String),
     #[error("Missing answer config")]
     MissingError,
+    #[error("Error writing answer config: {0}")]
+    WriteError(String),
 }
 
 impl From<serde_yaml::Error> for AnswerConfigError {
@@ -54,21 +63,41 @@ impl AnswerConfig {
                 let answers = path.join(answer_file_name);
                 if answers.exists() {
                     debug!("Reading Archetect config from '{}'", &answers.display());
-                    let config = fs::read_to_string(answers)?;
+                    let config = fs::read_to_string(&answers)?;
                     let config = serde_yaml::from_str::<AnswerConfig>(&config)?;
-                    return Ok(config);
+                    return Ok(config.with_origin(AnswerOrigin::AnswerFile(answers.display().to_string())));
                 }
             }
         } else {
-            let config = fs::read_to_string(path)?;
+            let config = fs::read_to_string(&path)?;
             let config = serde_yaml::from_str::<AnswerConfig>(&config)?;
-            return Ok(config);
+            return Ok(config.with_origin(AnswerOrigin::AnswerFile(path.display().to_string())));
         }
 
         // TODO: Return Ok(None) instead of error
         Err(AnswerConfigError::MissingError)
     }
 
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AnswerConfigError> {
+        let yaml = serde_yaml::to_string(&self)?;
+        fs::write(path, yaml).map_err(|error| AnswerConfigError::WriteError(error.to_string()))
+    }
+
+    /// The workspace answer file in `destination` or the nearest directory above it that has one, holding answers
+    /// shared across the workspace, such as the company name or registry URLs. The search stops short of the user's
+    /// home directory, whose `.archetect` directory holds Archetect's own configuration rather than a workspace's.
+    pub fn discover_workspace<P: AsRef<Path>>(destination: P) -> Option<PathBuf> {
+        let home = directories::UserDirs::new().map(|dirs| dirs.home_dir().to_owned());
+        discover_workspace_below(destination.as_ref(), home.as_deref())
+    }
+
+    fn with_origin(mut self, origin: AnswerOrigin) -> AnswerConfig {
+        for (_, answer) in self.answers.iter_mut() {
+            answer.set_origin(origin.clone());
+        }
+        self
+    }
+
     pub fn add_answer(&mut self, identifier: &str, value: &str) {
         self.answers
             .insert(identifier.to_owned(), AnswerInfo::with_value(value).build());
@@ -82,12 +111,18 @@ impl AnswerConfig {
     pub fn answers(&self) -> &LinkedHashMap<String, AnswerInfo> {
         &self.answers
     }
+
+    /// How files of the destination are treated when re-rendered with different contents.
+    pub fn conflicts(&self) -> &ConflictStrategies {
+        &self.conflicts
+    }
 }
 
 impl Default for AnswerConfig {
     fn default() -> Self {
         AnswerConfig {
             answers: LinkedHashMap::new(),
+            conflicts: ConflictStrategies::default(),
         }
     }
 }
@@ -139,6 +174,22 @@ impl AnswerInfo {
     }
 }
 
+/// The workspace answer file in `destination` or the nearest directory above it that has one, searching no further up
+/// than the directory below `boundary`, when `destination` is within it.
+fn discover_workspace_below(destination: &Path, boundary: Option<&Path>) -> Option<PathBuf> {
+    let absolute = fs::canonicalize(destination).unwrap_or_else(|_| resolve_path(destination, false));
+    let boundary = boundary.map(|boundary| fs::canonicalize(boundary).unwrap_or_else(|_| boundary.to_owned()));
+    absolute
+        .ancestors()
+        .take_while(|directory| Some(*directory) != boundary.as_deref())
+        .find_map(|directory| {
+            WORKSPACE_ANSWER_FILES
+                .iter()
+                .map(|file_name| directory.join(file_name))
+                .find(|path| path.is_file())
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,4 +327,21 @@ mod tests {
 
         println!("{}", serde_yaml::to_string(&config).unwrap());
     }
+
+    #[test]
+    fn test_discover_workspace_below_home() {
+        let home = tempfile::tempdir().unwrap();
+        let destination = home.path().join("projects").join("billing");
+        fs::create_dir_all(home.path().join(".archetect")).unwrap();
+        fs::write(home.path().join(".archetect/workspace.yaml"), "answers: {}\n").unwrap();
+        assert_eq!(discover_workspace_below(&destination, Some(home.path())), None);
+
+        let workspace = home.path().join("projects");
+        fs::create_dir_all(workspace.join(".archetect")).unwrap();
+        fs::write(workspace.join(".archetect/workspace.yml"), "answers: {}\n").unwrap();
+        assert_eq!(
+            discover_workspace_below(&destination, Some(home.path())),
+            Some(fs::canonicalize(&workspace).unwrap().join(".archetect/workspace.yml"))
+        );
+    }
 }
@@ -1,5 +1,12 @@
 use crate::actions::ActionId;
+use crate::config::{Precondition, ReleaseNotes, VariableGroup};
+use crate::formatters::FormatterConfig;
+use crate::headers::HeadersConfig;
+use crate::sandbox::SandboxConfig;
+use crate::validation::ValidationPolicy;
 use crate::ArchetypeError;
+use linked_hash_map::LinkedHashMap;
+use semver::Version;
 use std::fs;
 use std::path::PathBuf;
 
@@ -8,6 +15,8 @@ pub struct ArchetypeConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     authors: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     languages: Option<Vec<String>>,
@@ -15,10 +24,36 @@ pub struct ArchetypeConfig {
     frameworks: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    releases: Option<Vec<ReleaseNotes>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<HeadersConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatters: Option<Vec<FormatterConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validate: Option<ValidateConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sandbox: Option<SandboxConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<VariableGroup>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<LinkedHashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preconditions: Option<Vec<Precondition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_steps: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", alias = "actions")]
     script: Option<Vec<ActionId>>,
 }
 
+/// Either `true` to warn of invalid output, or a [`ValidationPolicy`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum ValidateConfig {
+    Enabled(bool),
+    Policy(ValidationPolicy),
+}
+
 impl ArchetypeConfig {
     pub fn new() -> ArchetypeConfig {
         ArchetypeConfig::default()
@@ -41,18 +76,63 @@ impl ArchetypeConfig {
             Err(ArchetypeError::ArchetypeConfigNotFound { path })
         } else {
             let config = fs::read_to_string(&path)?;
-            return match serde_yaml::from_str::<ArchetypeConfig>(&config) {
-                Ok(config) => Ok(config),
-                Err(source) => Err(ArchetypeError::YamlError { path, source }),
-            }
+            ArchetypeConfig::parse(path, &config)
         }
     }
 
+    /// Parses the contents of an archetype's config file, which is named by `path` in any error.
+    pub fn parse<P: Into<PathBuf>>(path: P, config: &str) -> Result<ArchetypeConfig, ArchetypeError> {
+        serde_yaml::from_str::<ArchetypeConfig>(config).map_err(|source| ArchetypeError::YamlError {
+            path: path.into(),
+            source,
+        })
+    }
+
     pub fn with_description(mut self, description: &str) -> ArchetypeConfig {
         self.description = Some(description.into());
         self
     }
 
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn with_version(mut self, version: Version) -> ArchetypeConfig {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    pub fn with_release(mut self, release: ReleaseNotes) -> ArchetypeConfig {
+        self.add_release(release);
+        self
+    }
+
+    pub fn add_release(&mut self, release: ReleaseNotes) {
+        let releases = self.releases.get_or_insert_with(|| Vec::new());
+        releases.push(release);
+    }
+
+    pub fn releases(&self) -> &[ReleaseNotes] {
+        self.releases.as_ref().map(|r| r.as_slice()).unwrap_or_default()
+    }
+
+    /// Releases newer than `from` (or all releases, if `from` is unknown), up to and including this archetype's
+    /// version, in ascending order.
+    pub fn releases_since(&self, from: Option<&Version>) -> Vec<&ReleaseNotes> {
+        let mut releases = self
+            .releases()
+            .iter()
+            .filter(|release| from.iter().all(|from| release.version() > from))
+            .filter(|release| self.version().iter().all(|to| release.version() <= *to))
+            .collect::<Vec<&ReleaseNotes>>();
+        releases.sort_by(|left, right| left.version().cmp(right.version()));
+        releases
+    }
+
     pub fn add_author(&mut self, author: &str) {
         let authors = self.authors.get_or_insert_with(|| vec![]);
         authors.push(author.into());
@@ -114,6 +194,107 @@ impl ArchetypeConfig {
         self
     }
 
+    pub fn with_headers(mut self, headers: HeadersConfig) -> ArchetypeConfig {
+        self.headers = Some(headers);
+        self
+    }
+
+    pub fn headers(&self) -> Option<&HeadersConfig> {
+        self.headers.as_ref()
+    }
+
+    pub fn with_formatter(mut self, formatter: FormatterConfig) -> ArchetypeConfig {
+        self.add_formatter(formatter);
+        self
+    }
+
+    pub fn add_formatter(&mut self, formatter: FormatterConfig) {
+        let formatters = self.formatters.get_or_insert_with(Vec::new);
+        formatters.push(formatter);
+    }
+
+    pub fn formatters(&self) -> &[FormatterConfig] {
+        self.formatters.as_deref().unwrap_or_default()
+    }
+
+    pub fn with_validation(mut self, policy: ValidationPolicy) -> ArchetypeConfig {
+        self.validate = Some(ValidateConfig::Policy(policy));
+        self
+    }
+
+    /// How the files this archetype renders are validated once its script completes, if they are.
+    pub fn validation(&self) -> Option<ValidationPolicy> {
+        match self.validate {
+            Some(ValidateConfig::Enabled(true)) => Some(ValidationPolicy::Warn),
+            Some(ValidateConfig::Policy(policy)) => Some(policy),
+            Some(ValidateConfig::Enabled(false)) | None => None,
+        }
+    }
+
+    pub fn with_sandbox(mut self, sandbox: SandboxConfig) -> ArchetypeConfig {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// The container this archetype's `exec` actions run in, if it declares one.
+    pub fn sandbox(&self) -> Option<&SandboxConfig> {
+        self.sandbox.as_ref()
+    }
+
+    pub fn with_group(mut self, group: VariableGroup) -> ArchetypeConfig {
+        self.add_group(group);
+        self
+    }
+
+    pub fn add_group(&mut self, group: VariableGroup) {
+        self.groups.get_or_insert_with(Vec::new).push(group);
+    }
+
+    /// The sections variables are grouped into, in the order they are prompted for.
+    pub fn groups(&self) -> &[VariableGroup] {
+        self.groups.as_deref().unwrap_or_default()
+    }
+
+    pub fn with_component<N: Into<String>>(mut self, name: N, patterns: Vec<String>) -> ArchetypeConfig {
+        self.add_component(name, patterns);
+        self
+    }
+
+    pub fn add_component<N: Into<String>>(&mut self, name: N, patterns: Vec<String>) {
+        self.components.get_or_insert_with(LinkedHashMap::new).insert(name.into(), patterns);
+    }
+
+    /// The source globs, relative to the archetype, of a named part of this archetype that can be rendered on its own,
+    /// such as `ci` for `contents/.github/**`.
+    pub fn component(&self, name: &str) -> Option<&[String]> {
+        self.components.as_ref()?.get(name).map(|patterns| patterns.as_slice())
+    }
+
+    pub fn with_precondition(mut self, precondition: Precondition) -> ArchetypeConfig {
+        self.add_precondition(precondition);
+        self
+    }
+
+    pub fn add_precondition(&mut self, precondition: Precondition) {
+        self.preconditions.get_or_insert_with(Vec::new).push(precondition);
+    }
+
+    /// The states the destination must be in before this archetype renders into it.
+    pub fn preconditions(&self) -> &[Precondition] {
+        self.preconditions.as_deref().unwrap_or_default()
+    }
+
+    pub fn with_next_steps<N: Into<String>>(mut self, next_steps: N) -> ArchetypeConfig {
+        self.next_steps = Some(next_steps.into());
+        self
+    }
+
+    /// A template of what to do with the destination once rendered, such as
+    /// `cd {{ project_name }} && cargo run`, shown after a successful render.
+    pub fn next_steps(&self) -> Option<&str> {
+        self.next_steps.as_deref()
+    }
+
     pub fn add_action(&mut self, action: ActionId) {
         let actions = self.script.get_or_insert_with(|| Vec::new());
         actions.push(action);
@@ -128,10 +309,20 @@ impl Default for ArchetypeConfig {
     fn default() -> Self {
         ArchetypeConfig {
             description: None,
+            version: None,
             authors: None,
             languages: None,
             frameworks: None,
             tags: None,
+            releases: None,
+            headers: None,
+            formatters: None,
+            validate: None,
+            sandbox: None,
+            groups: None,
+            components: None,
+            preconditions: None,
+            next_steps: None,
             script: None,
         }
     }
@@ -173,4 +364,21 @@ mod tests {
         let output = serde_yaml::to_string(&config).unwrap();
         println!("{}", output);
     }
+
+    #[test]
+    fn test_releases_since() {
+        let config = ArchetypeConfig::default()
+            .with_version(Version::parse("2.0.0").unwrap())
+            .with_release(ReleaseNotes::new(Version::parse("2.0.0").unwrap()).with_deprecation("`author` renamed"))
+            .with_release(ReleaseNotes::new(Version::parse("1.0.0").unwrap()))
+            .with_release(ReleaseNotes::new(Version::parse("1.1.0").unwrap()))
+            .with_release(ReleaseNotes::new(Version::parse("3.0.0").unwrap()));
+
+        let versions = |releases: Vec<&ReleaseNotes>| {
+            releases.iter().map(|r| r.version().to_string()).collect::<Vec<String>>()
+        };
+
+        assert_eq!(versions(config.releases_since(Some(&Version::parse("1.0.0").unwrap()))), vec!["1.1.0", "2.0.0"]);
+        assert_eq!(versions(config.releases_since(None)), vec!["1.0.0", "1.1.0", "2.0.0"]);
+    }
 }
@@ -1,9 +1,8 @@
+use crate::config::CATALOG_FILE_NAME;
 use crate::source::{Source, SourceError};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-pub const CATALOG_FILE_NAME: &str = "catalog.yml";
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Catalog {
     entries: Vec<CatalogEntry>,
@@ -19,6 +18,13 @@ impl Catalog {
         let catalog_path = match source {
             Source::LocalFile { path } => path,
             Source::RemoteHttp { url: _, path } => path,
+            Source::RemoteBlob { url: _, path } => {
+                if path.is_dir() {
+                    path.join(CATALOG_FILE_NAME)
+                } else {
+                    path
+                }
+            }
             Source::RemoteGit { url: _, path, gitref: _ } => path.join(CATALOG_FILE_NAME),
             Source::LocalDirectory { path } => path.join(CATALOG_FILE_NAME),
         };
@@ -38,6 +44,15 @@ impl Catalog {
         Ok(())
     }
 
+    pub fn add_entry(&mut self, entry: CatalogEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn with_entry(mut self, entry: CatalogEntry) -> Catalog {
+        self.add_entry(entry);
+        self
+    }
+
     pub fn entries(&self) -> &[CatalogEntry] {
         self.entries.as_slice()
     }
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use linked_hash_map::LinkedHashMap;
+use log::warn;
+
+/// How an existing file is treated when an archetype is rendered over it with different contents.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Keep the existing file.
+    Ours,
+    /// Replace the existing file with the rendered one.
+    Theirs,
+    /// Merge the rendered file into the existing one, writing conflict markers where they disagree. Copied files, which
+    /// are not merged, are replaced.
+    Merge,
+    /// Ask which of the above to do, keeping the existing file when prompting is not possible.
+    Ask,
+}
+
+/// The conflict strategies a project declares for areas of itself, in the `conflicts` section of its answer file, so
+/// that its owners rather than the archetype decide what re-rendering may change:
+///
+/// ```yaml
+/// conflicts:
+///   ".github/**": theirs
+///   "src/**": ours
+///   "**": ask
+/// ```
+///
+/// Patterns are globs relative to the destination, and the first that matches a file applies. Files matching none are
+/// treated as the archetype's rules say.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct ConflictStrategies {
+    strategies: LinkedHashMap<String, ConflictStrategy>,
+}
+
+impl ConflictStrategies {
+    pub fn new() -> ConflictStrategies {
+        ConflictStrategies::default()
+    }
+
+    pub fn with_strategy<P: Into<String>>(mut self, pattern: P, strategy: ConflictStrategy) -> ConflictStrategies {
+        self.add_strategy(pattern, strategy);
+        self
+    }
+
+    pub fn add_strategy<P: Into<String>>(&mut self, pattern: P, strategy: ConflictStrategy) {
+        self.strategies.insert(pattern.into(), strategy);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strategies.is_empty()
+    }
+
+    /// The strategy for `path`, relative to the destination.
+    pub fn strategy_for<P: AsRef<Path>>(&self, path: P) -> Option<ConflictStrategy> {
+        let path = path.as_ref();
+        self.strategies
+            .iter()
+            .find(|(pattern, _)| match glob::Pattern::new(pattern.trim_start_matches('/')) {
+                Ok(pattern) => pattern.matches_path(path),
+                Err(error) => {
+                    warn!("Ignoring invalid conflict pattern `{}`: {}", pattern, error);
+                    false
+                }
+            })
+            .map(|(_, strategy)| *strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategy_for() {
+        let strategies: ConflictStrategies =
+            serde_yaml::from_str("\".github/**\": theirs\n\"src/**\": ours\n\"*.md\": merge\n").unwrap();
+        assert_eq!(
+            strategies.strategy_for(".github/workflows/ci.yml"),
+            Some(ConflictStrategy::Theirs)
+        );
+        assert_eq!(strategies.strategy_for("src/main.rs"), Some(ConflictStrategy::Ours));
+        assert_eq!(strategies.strategy_for("README.md"), Some(ConflictStrategy::Merge));
+        assert_eq!(strategies.strategy_for("Cargo.toml"), None);
+
+        let strategies = ConflictStrategies::new()
+            .with_strategy("src/generated/**", ConflictStrategy::Theirs)
+            .with_strategy("src/**", ConflictStrategy::Ours);
+        assert_eq!(
+            strategies.strategy_for("src/generated/api.rs"),
+            Some(ConflictStrategy::Theirs)
+        );
+    }
+}
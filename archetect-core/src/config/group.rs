@@ -0,0 +1,53 @@
+/// A section of related variables, such as Project Info, Persistence, or CI, declared in `archetype.yml`. Variables
+/// join a group by naming it, and are prompted for group by group, in the order the groups are declared:
+///
+/// ```yaml
+/// groups:
+///   - id: project
+///     heading: Project Info
+///   - id: persistence
+///     heading: Persistence
+///     description: Where the service keeps its data.
+/// script:
+///   - set:
+///       database:
+///         prompt: "Database:"
+///         group: persistence
+///       name:
+///         prompt: "Name:"
+///         group: project
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct VariableGroup {
+    id: String,
+    heading: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+impl VariableGroup {
+    pub fn new<I: Into<String>, H: Into<String>>(id: I, heading: H) -> VariableGroup {
+        VariableGroup {
+            id: id.into(),
+            heading: heading.into(),
+            description: None,
+        }
+    }
+
+    pub fn with_description<D: Into<String>>(mut self, description: D) -> VariableGroup {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn heading(&self) -> &str {
+        &self.heading
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::PathBuf;
+
+use linked_hash_map::LinkedHashMap;
+use serde_json::Value;
+
+use crate::metadata::value_to_answer;
+
+/// The answers most recently entered at prompts, by variable name, offered as the defaults the next time the same
+/// variables are prompted for. Answers to sensitive variables are never remembered.
+///
+/// ```yaml
+/// answers:
+///   author_name: Jane Doe
+///   organization: acme
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AnswerHistory {
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    answers: LinkedHashMap<String, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnswerHistoryError {
+    #[error("Error parsing answer history `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error serializing answer history: {0}")]
+    SerializeError(serde_yaml::Error),
+    #[error("Error accessing answer history `{path}`: {source}")]
+    IoError { path: PathBuf, source: std::io::Error },
+}
+
+impl AnswerHistory {
+    pub fn new() -> AnswerHistory {
+        AnswerHistory::default()
+    }
+
+    /// Loads the history at `path`, which is empty if the file does not exist yet.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<AnswerHistory, AnswerHistoryError> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(AnswerHistory::default());
+        }
+        let history = match fs::read_to_string(&path) {
+            Ok(history) => history,
+            Err(source) => return Err(AnswerHistoryError::IoError { path, source }),
+        };
+        match serde_yaml::from_str::<AnswerHistory>(&history) {
+            Ok(history) => Ok(history),
+            Err(source) => Err(AnswerHistoryError::ParseError { path, source }),
+        }
+    }
+
+    pub fn save<P: Into<PathBuf>>(&self, path: P) -> Result<(), AnswerHistoryError> {
+        let path = path.into();
+        let yaml = serde_yaml::to_string(&self).map_err(AnswerHistoryError::SerializeError)?;
+        if let Some(parent) = path.parent() {
+            if let Err(source) = fs::create_dir_all(parent) {
+                return Err(AnswerHistoryError::IoError { path, source });
+            }
+        }
+        fs::write(&path, yaml).map_err(|source| AnswerHistoryError::IoError { path, source })
+    }
+
+    /// The answer last entered for `identifier`.
+    pub fn recent(&self, identifier: &str) -> Option<&str> {
+        self.answers.get(identifier).map(|answer| answer.as_str())
+    }
+
+    pub fn add_answer<I: Into<String>, A: Into<String>>(&mut self, identifier: I, answer: A) {
+        self.answers.insert(identifier.into(), answer.into());
+    }
+
+    pub fn with_answer<I: Into<String>, A: Into<String>>(mut self, identifier: I, answer: A) -> AnswerHistory {
+        self.add_answer(identifier, answer);
+        self
+    }
+
+    /// Remembers a prompted value in the form it would be typed at the prompt: lists are separated by commas.
+    pub(crate) fn remember(&mut self, identifier: &str, value: &Value) {
+        if let Some(answer) = value_to_answer(value) {
+            self.add_answer(identifier, answer);
+        }
+    }
+
+    pub fn answers(&self) -> &LinkedHashMap<String, String> {
+        &self.answers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let path = config_dir.path().join("archetect").join("history.yml");
+        assert!(AnswerHistory::load(&path).unwrap().answers().is_empty());
+
+        let mut history = AnswerHistory::new();
+        history.remember("author_name", &json!("Jane Doe"));
+        history.remember("publish", &json!(true));
+        history.remember("features", &json!(["http", "grpc"]));
+        history.save(&path).unwrap();
+
+        let history = AnswerHistory::load(&path).unwrap();
+        assert_eq!(history.recent("author_name"), Some("Jane Doe"));
+        assert_eq!(history.recent("publish"), Some("true"));
+        assert_eq!(history.recent("features"), Some("http, grpc"));
+        assert_eq!(history.recent("organization"), None);
+    }
+}
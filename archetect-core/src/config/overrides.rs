@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::PathBuf;
+
+use linked_hash_map::LinkedHashMap;
+
+/// Maps remote archetype sources to local working copies, so that archetypes under development are used in place of
+/// their published versions wherever they are referenced, git hosts to the hosts they are aliases of, so that a
+/// repository is cached once however it is addressed, and shorthand prefixes to the repository URLs they stand for, in
+/// addition to `gh:`, `gl:`, and `bb:`.
+///
+/// ```yaml
+/// overrides:
+///   "git@github.com:archetect/archetype-rust-cli.git": ~/projects/archetype-rust-cli
+/// hosts:
+///   ssh.github.com: github.com
+/// prefixes:
+///   corp: "git@git.corp.example:"
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OverrideConfig {
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    overrides: LinkedHashMap<String, String>,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    hosts: LinkedHashMap<String, String>,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    prefixes: LinkedHashMap<String, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OverrideConfigError {
+    #[error("Error parsing override config `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error reading override config `{path}`: {source}")]
+    IoError { path: PathBuf, source: std::io::Error },
+}
+
+impl OverrideConfig {
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Option<OverrideConfig>, OverrideConfigError> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config = match fs::read_to_string(&path) {
+            Ok(config) => config,
+            Err(source) => return Err(OverrideConfigError::IoError { path, source }),
+        };
+        match serde_yaml::from_str::<OverrideConfig>(&config) {
+            Ok(config) => Ok(Some(config)),
+            Err(source) => Err(OverrideConfigError::ParseError { path, source }),
+        }
+    }
+
+    pub fn add_override<S: Into<String>, P: Into<String>>(&mut self, source: S, path: P) {
+        self.overrides.insert(source.into(), path.into());
+    }
+
+    pub fn with_override<S: Into<String>, P: Into<String>>(mut self, source: S, path: P) -> OverrideConfig {
+        self.add_override(source, path);
+        self
+    }
+
+    pub fn overrides(&self) -> &LinkedHashMap<String, String> {
+        &self.overrides
+    }
+
+    pub fn add_host_alias<A: Into<String>, H: Into<String>>(&mut self, alias: A, host: H) {
+        self.hosts.insert(alias.into(), host.into());
+    }
+
+    pub fn with_host_alias<A: Into<String>, H: Into<String>>(mut self, alias: A, host: H) -> OverrideConfig {
+        self.add_host_alias(alias, host);
+        self
+    }
+
+    pub fn add_source_prefix<P: Into<String>, U: Into<String>>(&mut self, prefix: P, url: U) {
+        self.prefixes.insert(prefix.into(), url.into());
+    }
+
+    pub fn with_source_prefix<P: Into<String>, U: Into<String>>(mut self, prefix: P, url: U) -> OverrideConfig {
+        self.add_source_prefix(prefix, url);
+        self
+    }
+
+    /// The starts of repository URLs, keyed by the shorthand prefixes that stand for them.
+    pub fn source_prefixes(&self) -> &LinkedHashMap<String, String> {
+        &self.prefixes
+    }
+
+    /// Git hosts, keyed by the aliases they are known by.
+    pub fn host_aliases(&self) -> &LinkedHashMap<String, String> {
+        &self.hosts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_deserialize() {
+        let yaml = indoc! {r#"
+            overrides:
+              "git@github.com:archetect/archetype-rust-cli.git": ~/projects/archetype-rust-cli
+            hosts:
+              ssh.github.com: github.com
+            prefixes:
+              corp: "git@git.corp.example:"
+        "#};
+        let config: OverrideConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.overrides().get("git@github.com:archetect/archetype-rust-cli.git"),
+            Some(&"~/projects/archetype-rust-cli".to_owned())
+        );
+        assert_eq!(config.host_aliases().get("ssh.github.com"), Some(&"github.com".to_owned()));
+        assert_eq!(config.source_prefixes().get("corp"), Some(&"git@git.corp.example:".to_owned()));
+    }
+}
@@ -0,0 +1,54 @@
+use std::env;
+
+/// The platforms an action or rule applies to, by operating system, such as `windows`, `linux`, or `macos`, or by
+/// family, `unix` or `windows`, given as one or a list:
+///
+/// ```yaml
+/// when_os: windows
+/// when_os: [linux, macos]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum OsCondition {
+    One(String),
+    Any(Vec<String>),
+}
+
+impl OsCondition {
+    /// Whether Archetect is running on one of the platforms.
+    pub fn matches_current(&self) -> bool {
+        self.matches(env::consts::OS, env::consts::FAMILY)
+    }
+
+    /// Whether the platform with `os` and `family`, as named by [`std::env::consts`], is one of the platforms.
+    pub fn matches(&self, os: &str, family: &str) -> bool {
+        let platforms = match self {
+            OsCondition::One(platform) => std::slice::from_ref(platform),
+            OsCondition::Any(platforms) => platforms.as_slice(),
+        };
+        platforms
+            .iter()
+            .any(|platform| platform.eq_ignore_ascii_case(os) || platform.eq_ignore_ascii_case(family))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let windows: OsCondition = serde_yaml::from_str("windows").unwrap();
+        assert!(windows.matches("windows", "windows"));
+        assert!(!windows.matches("linux", "unix"));
+
+        let unix: OsCondition = serde_yaml::from_str("[Linux, macos]").unwrap();
+        assert!(unix.matches("linux", "unix"));
+        assert!(unix.matches("macos", "unix"));
+        assert!(!unix.matches("freebsd", "unix"));
+
+        let family = OsCondition::One("unix".to_owned());
+        assert!(family.matches("freebsd", "unix"));
+        assert_eq!(family.matches_current(), cfg!(unix));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::target::{Ecosystem, TargetInfo};
+
+/// A state the destination must be in for an archetype to render into it, declared in `archetype.yml`. Preconditions
+/// are checked before the archetype prompts for anything or renders anything:
+///
+/// ```yaml
+/// preconditions:
+///   - git-repository
+///   - workspace: cargo
+///   - absent: src/main.rs
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Precondition {
+    /// The destination must be empty, or not yet exist.
+    Empty,
+    /// The destination must be within a git repository.
+    GitRepository,
+    /// The destination must be within a workspace, or multi-module build, of the ecosystem.
+    Workspace(Ecosystem),
+    /// The path, relative to the destination, must exist.
+    Exists(String),
+    /// The path, relative to the destination, must not exist.
+    Absent(String),
+}
+
+impl Precondition {
+    /// Whether `destination`, as described by `target`, is in the required state.
+    pub fn check(&self, destination: &Path, target: &TargetInfo) -> bool {
+        match self {
+            Precondition::Empty => !target.exists(),
+            Precondition::GitRepository => {
+                absolute(destination).ancestors().any(|directory| directory.join(".git").exists())
+            }
+            Precondition::Workspace(ecosystem) => target
+                .workspace()
+                .is_some_and(|workspace| workspace.ecosystem() == *ecosystem),
+            Precondition::Exists(path) => destination.join(path).exists(),
+            Precondition::Absent(path) => !destination.join(path).exists(),
+        }
+    }
+}
+
+impl Display for Precondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Precondition::Empty => write!(f, "must be empty"),
+            Precondition::GitRepository => write!(f, "must be within a git repository"),
+            Precondition::Workspace(ecosystem) => write!(f, "must be within a {:?} workspace", ecosystem),
+            Precondition::Exists(path) => write!(f, "must contain `{}`", path),
+            Precondition::Absent(path) => write!(f, "must not contain `{}`", path),
+        }
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    match std::env::current_dir() {
+        Ok(current_dir) => current_dir.join(path),
+        Err(_) => path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let preconditions: Vec<Precondition> =
+            serde_yaml::from_str("- empty\n- git-repository\n- workspace: cargo\n- absent: src/main.rs\n").unwrap();
+        assert_eq!(
+            preconditions,
+            vec![
+                Precondition::Empty,
+                Precondition::GitRepository,
+                Precondition::Workspace(Ecosystem::Cargo),
+                Precondition::Absent("src/main.rs".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let workspace = tempfile::tempdir().unwrap();
+        fs::write(workspace.path().join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        let destination = workspace.path().join("crates").join("orders");
+        let target = TargetInfo::detect(&destination);
+
+        assert!(Precondition::Empty.check(&destination, &target));
+        assert!(Precondition::Workspace(Ecosystem::Cargo).check(&destination, &target));
+        assert!(!Precondition::Workspace(Ecosystem::Npm).check(&destination, &target));
+        assert!(!Precondition::GitRepository.check(&destination, &target));
+        assert!(Precondition::Absent("src/main.rs".to_owned()).check(&destination, &target));
+
+        fs::create_dir_all(destination.join("src")).unwrap();
+        fs::write(destination.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        let target = TargetInfo::detect(&destination);
+        assert!(!Precondition::Empty.check(&destination, &target));
+        assert!(Precondition::Exists("src/main.rs".to_owned()).check(&destination, &target));
+        assert!(!Precondition::Absent("src/main.rs".to_owned()).check(&destination, &target));
+
+        fs::create_dir_all(workspace.path().join(".git")).unwrap();
+        assert!(Precondition::GitRepository.check(&destination, &target));
+    }
+}
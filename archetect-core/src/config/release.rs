@@ -0,0 +1,52 @@
+use semver::Version;
+
+/// Notes for a single archetype release, declared in `archetype.yml`:
+///
+/// ```yaml
+/// version: 2.0.0
+/// releases:
+///   - version: 2.0.0
+///     notes: Switched the default persistence layer to SQLx.
+///     deprecations:
+///       - "variable `author` renamed to `author_name`"
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ReleaseNotes {
+    version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    deprecations: Vec<String>,
+}
+
+impl ReleaseNotes {
+    pub fn new(version: Version) -> ReleaseNotes {
+        ReleaseNotes {
+            version,
+            notes: None,
+            deprecations: vec![],
+        }
+    }
+
+    pub fn with_notes<N: Into<String>>(mut self, notes: N) -> ReleaseNotes {
+        self.notes = Some(notes.into());
+        self
+    }
+
+    pub fn with_deprecation<D: Into<String>>(mut self, deprecation: D) -> ReleaseNotes {
+        self.deprecations.push(deprecation.into());
+        self
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    pub fn deprecations(&self) -> &[String] {
+        self.deprecations.as_slice()
+    }
+}
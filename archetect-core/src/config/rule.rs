@@ -1,3 +1,6 @@
+use crate::config::OsCondition;
+use crate::encoding::Encoding;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RuleConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -7,6 +10,14 @@ pub struct RuleConfig {
     filter: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     action: Option<RuleAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<Encoding>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "output-encoding")]
+    output_encoding: Option<Encoding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bom: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    when_os: Option<OsCondition>,
 }
 
 impl RuleConfig {
@@ -16,6 +27,10 @@ impl RuleConfig {
             patterns: vec![],
             filter: None,
             action: None,
+            encoding: None,
+            output_encoding: None,
+            bom: None,
+            when_os: None,
         }
     }
 
@@ -61,6 +76,52 @@ impl RuleConfig {
     pub fn filter(&self) -> Option<bool> {
         self.filter
     }
+
+    /// Declares the encoding of matching templates, which rendered files are also written in unless an output
+    /// encoding is declared.
+    pub fn with_encoding(mut self, encoding: Encoding) -> RuleConfig {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub fn with_output_encoding(mut self, encoding: Encoding) -> RuleConfig {
+        self.output_encoding = Some(encoding);
+        self
+    }
+
+    pub fn encoding(&self) -> Option<&Encoding> {
+        self.encoding.as_ref()
+    }
+
+    pub fn output_encoding(&self) -> Option<&Encoding> {
+        self.output_encoding.as_ref().or(self.encoding.as_ref())
+    }
+
+    /// Requires (`true`) or forbids (`false`) a byte order mark at the start of matching rendered files. When not set,
+    /// UTF-16 files are written with one, and other files as rendered.
+    pub fn with_bom(mut self, bom: bool) -> RuleConfig {
+        self.bom = Some(bom);
+        self
+    }
+
+    pub fn bom(&self) -> Option<bool> {
+        self.bom
+    }
+
+    /// Only applies the rule on the given platforms, such as skipping `.bat` scripts other than on Windows.
+    pub fn with_when_os(mut self, when_os: OsCondition) -> RuleConfig {
+        self.when_os = Some(when_os);
+        self
+    }
+
+    pub fn when_os(&self) -> Option<&OsCondition> {
+        self.when_os.as_ref()
+    }
+
+    /// Whether the rule applies on the platform Archetect is running on.
+    pub fn applies(&self) -> bool {
+        self.when_os.as_ref().is_none_or(|when_os| when_os.matches_current())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialOrd, PartialEq, Clone)]
@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::PathBuf;
+
+use linked_hash_map::LinkedHashMap;
+
+/// Remote archetypes that were always allowed to run commands, by source, each with the fingerprint of the commands
+/// that were trusted. A source whose commands have changed since is no longer trusted, so is asked about again.
+///
+/// ```yaml
+/// sources:
+///   "https://github.com/acme/rust-service.git":
+///     fingerprint: 9f3b2c41d07ae615
+///     commit: 5d2c0e8f1a7b9c3d4e5f60718293a4b5c6d7e8f9
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TrustedSources {
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    sources: LinkedHashMap<String, TrustedSource>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TrustedSource {
+    fingerprint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrustedSourcesError {
+    #[error("Error parsing trusted sources `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error serializing trusted sources: {0}")]
+    SerializeError(serde_yaml::Error),
+    #[error("Error accessing trusted sources `{path}`: {source}")]
+    IoError { path: PathBuf, source: std::io::Error },
+}
+
+impl TrustedSource {
+    pub fn new<F: Into<String>>(fingerprint: F, commit: Option<String>) -> TrustedSource {
+        TrustedSource {
+            fingerprint: fingerprint.into(),
+            commit,
+        }
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The commit the source was at when it was trusted, for reference.
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+}
+
+impl TrustedSources {
+    pub fn new() -> TrustedSources {
+        TrustedSources::default()
+    }
+
+    /// Loads the trusted sources at `path`, which are none if the file does not exist yet.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<TrustedSources, TrustedSourcesError> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(TrustedSources::default());
+        }
+        let sources = match fs::read_to_string(&path) {
+            Ok(sources) => sources,
+            Err(source) => return Err(TrustedSourcesError::IoError { path, source }),
+        };
+        match serde_yaml::from_str::<TrustedSources>(&sources) {
+            Ok(sources) => Ok(sources),
+            Err(source) => Err(TrustedSourcesError::ParseError { path, source }),
+        }
+    }
+
+    pub fn save<P: Into<PathBuf>>(&self, path: P) -> Result<(), TrustedSourcesError> {
+        let path = path.into();
+        let yaml = serde_yaml::to_string(&self).map_err(TrustedSourcesError::SerializeError)?;
+        if let Some(parent) = path.parent() {
+            if let Err(source) = fs::create_dir_all(parent) {
+                return Err(TrustedSourcesError::IoError { path, source });
+            }
+        }
+        fs::write(&path, yaml).map_err(|source| TrustedSourcesError::IoError { path, source })
+    }
+
+    pub fn add_source<S: Into<String>>(&mut self, source: S, trusted: TrustedSource) {
+        self.sources.insert(source.into(), trusted);
+    }
+
+    pub fn with_source<S: Into<String>>(mut self, source: S, trusted: TrustedSource) -> TrustedSources {
+        self.add_source(source, trusted);
+        self
+    }
+
+    pub fn source(&self, source: &str) -> Option<&TrustedSource> {
+        self.sources.get(source)
+    }
+
+    /// Whether `source` was trusted with a script matching `fingerprint`.
+    pub fn is_trusted(&self, source: &str, fingerprint: &str) -> bool {
+        self.source(source)
+            .is_some_and(|trusted| trusted.fingerprint == fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let path = config_dir.path().join("archetect").join("trusted.yml");
+        let source = "https://github.com/acme/rust-service.git";
+        assert!(!TrustedSources::load(&path).unwrap().is_trusted(source, "9f3b2c41d07ae615"));
+
+        TrustedSources::new()
+            .with_source(source, TrustedSource::new("9f3b2c41d07ae615", Some("5d2c0e8".to_owned())))
+            .save(&path)
+            .unwrap();
+
+        let sources = TrustedSources::load(&path).unwrap();
+        assert!(sources.is_trusted(source, "9f3b2c41d07ae615"));
+        assert!(!sources.is_trusted(source, "0000000000000000"));
+        assert_eq!(sources.source(source).unwrap().commit(), Some("5d2c0e8"));
+    }
+}
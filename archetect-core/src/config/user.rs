@@ -0,0 +1,243 @@
+use std::fs;
+use std::path::PathBuf;
+
+use linked_hash_map::LinkedHashMap;
+
+use crate::config::ConflictStrategy;
+use crate::input::parse_bool;
+
+/// The settings in the user's configuration, as `archetect config` names them.
+pub const SETTINGS: &[&str] = &["offline", "headless", "conflicts", "hosts.<alias>", "prefixes.<prefix>"];
+
+/// Defaults for every run of Archetect, read and changed with `archetect config`:
+///
+/// ```yaml
+/// offline: true
+/// headless: false
+/// conflicts: ask
+/// hosts:
+///   ssh.github.com: github.com
+/// prefixes:
+///   corp: "git@git.corp.example:"
+/// ```
+///
+/// `conflicts` is how existing files are treated when rendered over, where the destination does not say. Hosts and
+/// prefixes add to those in the override config.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offline: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headless: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflicts: Option<ConflictStrategy>,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    hosts: LinkedHashMap<String, String>,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    prefixes: LinkedHashMap<String, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UserConfigError {
+    #[error("Error parsing user config `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error serializing user config: {0}")]
+    SerializeError(serde_yaml::Error),
+    #[error("Error accessing user config `{path}`: {source}")]
+    IoError { path: PathBuf, source: std::io::Error },
+    #[error("Unknown setting `{0}`; expected one of {}", SETTINGS.join(", "))]
+    UnknownSetting(String),
+    #[error("`{value}` is not a valid value for `{setting}`; expected {expected}")]
+    InvalidValue {
+        setting: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl UserConfig {
+    pub fn new() -> UserConfig {
+        UserConfig::default()
+    }
+
+    /// Loads the user config at `path`, which is empty if the file does not exist yet.
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<UserConfig, UserConfigError> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(UserConfig::default());
+        }
+        let config = match fs::read_to_string(&path) {
+            Ok(config) => config,
+            Err(source) => return Err(UserConfigError::IoError { path, source }),
+        };
+        if config.trim().is_empty() {
+            return Ok(UserConfig::default());
+        }
+        match serde_yaml::from_str::<UserConfig>(&config) {
+            Ok(config) => Ok(config),
+            Err(source) => Err(UserConfigError::ParseError { path, source }),
+        }
+    }
+
+    pub fn save<P: Into<PathBuf>>(&self, path: P) -> Result<(), UserConfigError> {
+        let path = path.into();
+        let yaml = serde_yaml::to_string(&self).map_err(UserConfigError::SerializeError)?;
+        if let Some(parent) = path.parent() {
+            if let Err(source) = fs::create_dir_all(parent) {
+                return Err(UserConfigError::IoError { path, source });
+            }
+        }
+        fs::write(&path, yaml).map_err(|source| UserConfigError::IoError { path, source })
+    }
+
+    pub fn offline(&self) -> Option<bool> {
+        self.offline
+    }
+
+    pub fn headless(&self) -> Option<bool> {
+        self.headless
+    }
+
+    pub fn conflicts(&self) -> Option<ConflictStrategy> {
+        self.conflicts
+    }
+
+    /// Git hosts, keyed by the aliases they are known by.
+    pub fn host_aliases(&self) -> &LinkedHashMap<String, String> {
+        &self.hosts
+    }
+
+    /// The starts of repository URLs, keyed by the shorthand prefixes that stand for them.
+    pub fn source_prefixes(&self) -> &LinkedHashMap<String, String> {
+        &self.prefixes
+    }
+
+    /// The value of `setting`, as it would be given to [`UserConfig::set`], if it is set.
+    pub fn get(&self, setting: &str) -> Result<Option<String>, UserConfigError> {
+        let value = match parse_setting(setting)? {
+            Setting::Offline => self.offline.map(|offline| offline.to_string()),
+            Setting::Headless => self.headless.map(|headless| headless.to_string()),
+            Setting::Conflicts => self.conflicts.map(strategy_name),
+            Setting::Host(alias) => self.hosts.get(alias).cloned(),
+            Setting::Prefix(prefix) => self.prefixes.get(prefix).cloned(),
+        };
+        Ok(value)
+    }
+
+    /// Sets `setting` to `value`, parsed as the setting's type.
+    pub fn set(&mut self, setting: &str, value: &str) -> Result<(), UserConfigError> {
+        let invalid = |expected| UserConfigError::InvalidValue {
+            setting: setting.to_owned(),
+            value: value.to_owned(),
+            expected,
+        };
+        match parse_setting(setting)? {
+            Setting::Offline => self.offline = Some(parse_bool(value).ok_or_else(|| invalid("true or false"))?),
+            Setting::Headless => self.headless = Some(parse_bool(value).ok_or_else(|| invalid("true or false"))?),
+            Setting::Conflicts => {
+                let strategy = serde_yaml::from_str::<ConflictStrategy>(&value.to_lowercase())
+                    .map_err(|_| invalid("ours, theirs, merge, or ask"))?;
+                self.conflicts = Some(strategy);
+            }
+            Setting::Host(alias) => {
+                if value.trim().is_empty() {
+                    return Err(invalid("a host"));
+                }
+                self.hosts.insert(alias.to_owned(), value.trim().to_owned());
+            }
+            Setting::Prefix(prefix) => {
+                if value.trim().is_empty() {
+                    return Err(invalid("the start of repository URLs"));
+                }
+                self.prefixes.insert(prefix.to_owned(), value.trim().to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Every setting that is set, with its value, in the order of [`SETTINGS`].
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![];
+        if let Some(offline) = self.offline {
+            entries.push(("offline".to_owned(), offline.to_string()));
+        }
+        if let Some(headless) = self.headless {
+            entries.push(("headless".to_owned(), headless.to_string()));
+        }
+        if let Some(conflicts) = self.conflicts {
+            entries.push(("conflicts".to_owned(), strategy_name(conflicts)));
+        }
+        for (alias, host) in &self.hosts {
+            entries.push((format!("hosts.{}", alias), host.to_owned()));
+        }
+        for (prefix, url) in &self.prefixes {
+            entries.push((format!("prefixes.{}", prefix), url.to_owned()));
+        }
+        entries
+    }
+}
+
+enum Setting<'a> {
+    Offline,
+    Headless,
+    Conflicts,
+    Host(&'a str),
+    Prefix(&'a str),
+}
+
+fn parse_setting(setting: &str) -> Result<Setting<'_>, UserConfigError> {
+    let parsed = match setting.split_once('.') {
+        None if setting == "offline" => Setting::Offline,
+        None if setting == "headless" => Setting::Headless,
+        None if setting == "conflicts" => Setting::Conflicts,
+        Some(("hosts", alias)) if !alias.is_empty() => Setting::Host(alias),
+        Some(("prefixes", prefix)) if !prefix.is_empty() => Setting::Prefix(prefix),
+        _ => return Err(UserConfigError::UnknownSetting(setting.to_owned())),
+    };
+    Ok(parsed)
+}
+
+fn strategy_name(strategy: ConflictStrategy) -> String {
+    serde_yaml::to_string(&strategy)
+        .map(|name| name.trim_start_matches("---").trim().to_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_set() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let path = config_dir.path().join("config.yml");
+        let mut config = UserConfig::load(&path).unwrap();
+        assert_eq!(config.get("offline").unwrap(), None);
+
+        config.set("offline", "yes").unwrap();
+        config.set("conflicts", "Theirs").unwrap();
+        config.set("hosts.ssh.github.com", "github.com").unwrap();
+        assert!(matches!(config.set("offline", "sometimes"), Err(UserConfigError::InvalidValue { .. })));
+        assert!(matches!(config.set("conflicts", "replace"), Err(UserConfigError::InvalidValue { .. })));
+        assert!(matches!(config.set("color", "true"), Err(UserConfigError::UnknownSetting(_))));
+        assert!(matches!(config.get("hosts."), Err(UserConfigError::UnknownSetting(_))));
+        config.save(&path).unwrap();
+
+        let config = UserConfig::load(&path).unwrap();
+        assert_eq!(config.offline(), Some(true));
+        assert_eq!(config.get("conflicts").unwrap(), Some("theirs".to_owned()));
+        assert_eq!(config.get("hosts.ssh.github.com").unwrap(), Some("github.com".to_owned()));
+        assert_eq!(
+            config.entries(),
+            vec![
+                ("offline".to_owned(), "true".to_owned()),
+                ("conflicts".to_owned(), "theirs".to_owned()),
+                ("hosts.ssh.github.com".to_owned(), "github.com".to_owned()),
+            ]
+        );
+
+        fs::write(&path, "offline: true\ncolour: false\n").unwrap();
+        assert!(matches!(UserConfig::load(&path), Err(UserConfigError::ParseError { .. })));
+    }
+}
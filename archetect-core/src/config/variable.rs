@@ -1,6 +1,11 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::provenance::AnswerOrigin;
+
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub struct VariableInfo {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "deserialize_value")]
     value: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     default: Option<String>,
@@ -10,6 +15,24 @@ pub struct VariableInfo {
     required: Option<bool>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     variable_type: Option<VariableType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aliases: Option<Vec<String>>,
+    #[serde(skip)]
+    origin: Option<AnswerOrigin>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sensitive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    example: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "docs-url")]
+    docs_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matrix: Option<bool>,
 }
 
 impl VariableInfo {
@@ -21,6 +44,15 @@ impl VariableInfo {
                 prompt: None,
                 required: None,
                 variable_type: None,
+                aliases: None,
+                origin: None,
+                shared: None,
+                sensitive: None,
+                group: None,
+                help: None,
+                example: None,
+                docs_url: None,
+                matrix: None,
             },
         }
     }
@@ -33,6 +65,15 @@ impl VariableInfo {
                 prompt: None,
                 required: None,
                 variable_type: None,
+                aliases: None,
+                origin: None,
+                shared: None,
+                sensitive: None,
+                group: None,
+                help: None,
+                example: None,
+                docs_url: None,
+                matrix: None,
             },
         }
     }
@@ -45,6 +86,15 @@ impl VariableInfo {
                 prompt: None,
                 required: None,
                 variable_type: None,
+                aliases: None,
+                origin: None,
+                shared: None,
+                sensitive: None,
+                group: None,
+                help: None,
+                example: None,
+                docs_url: None,
+                matrix: None,
             },
         }
     }
@@ -57,6 +107,15 @@ impl VariableInfo {
                 default: None,
                 required: None,
                 variable_type: None,
+                aliases: None,
+                origin: None,
+                shared: None,
+                sensitive: None,
+                group: None,
+                help: None,
+                example: None,
+                docs_url: None,
+                matrix: None,
             },
         }
     }
@@ -90,6 +149,55 @@ impl VariableInfo {
         self.required.unwrap_or(true)
     }
 
+    /// Former names of this variable, under which answers from older answer files are still accepted.
+    pub fn aliases(&self) -> &[String] {
+        self.aliases.as_deref().unwrap_or_default()
+    }
+
+    /// Where this answer was supplied from, when used as an answer rather than a variable definition.
+    pub fn origin(&self) -> Option<&AnswerOrigin> {
+        self.origin.as_ref()
+    }
+
+    pub fn set_origin(&mut self, origin: AnswerOrigin) {
+        self.origin = Some(origin);
+    }
+
+    /// Whether a prompted answer to this variable is reused by every other archetype in the same run that declares a
+    /// shared variable of the same name and type, rather than each of them asking again.
+    pub fn shared(&self) -> bool {
+        self.shared.unwrap_or(false)
+    }
+
+    /// Whether answers to this variable, such as passwords or tokens, are kept out of the answer history.
+    pub fn sensitive(&self) -> bool {
+        self.sensitive.unwrap_or(false)
+    }
+
+    /// The id of the [`VariableGroup`](crate::config::VariableGroup) this variable is prompted for with.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// An explanation of the variable, shown at its prompt on request.
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    /// An example answer, such as `com.acme.billing` for a base package.
+    pub fn example(&self) -> Option<&str> {
+        self.example.as_deref()
+    }
+
+    pub fn docs_url(&self) -> Option<&str> {
+        self.docs_url.as_deref()
+    }
+
+    /// Whether this answer is a list of values to render once each, rather than a single value.
+    pub fn matrix(&self) -> bool {
+        self.matrix.unwrap_or(false)
+    }
+
     pub fn has_derived_value(&self) -> bool {
         self.value.is_some()
     }
@@ -107,6 +215,24 @@ pub enum VariableType {
     Enum(Vec<String>),
     #[serde(rename = "array", alias = "list")]
     Array,
+    /// A yes/no value, turned on without prompting by enabling a switch of the same name, such as with `--switch`.
+    /// Switches that are not enabled are prompted for, and default to off.
+    #[serde(rename = "switch")]
+    Switch,
+}
+
+impl VariableType {
+    /// The name of the type, as it is written in an archetype.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariableType::String => "string",
+            VariableType::Int => "int",
+            VariableType::Bool => "bool",
+            VariableType::Enum(_) => "enum",
+            VariableType::Array => "array",
+            VariableType::Switch => "switch",
+        }
+    }
 }
 
 pub struct VariableInfoBuilder {
@@ -134,7 +260,73 @@ impl VariableInfoBuilder {
         self
     }
 
+    pub fn with_alias<A: Into<String>>(mut self, alias: A) -> VariableInfoBuilder {
+        self.variable_info.aliases.get_or_insert_with(Vec::new).push(alias.into());
+        self
+    }
+
+    pub fn with_shared(mut self, shared: bool) -> VariableInfoBuilder {
+        self.variable_info.shared = Some(shared);
+        self
+    }
+
+    pub fn with_help<H: Into<String>>(mut self, help: H) -> VariableInfoBuilder {
+        self.variable_info.help = Some(help.into());
+        self
+    }
+
+    pub fn with_example<E: Into<String>>(mut self, example: E) -> VariableInfoBuilder {
+        self.variable_info.example = Some(example.into());
+        self
+    }
+
+    pub fn with_docs_url<U: Into<String>>(mut self, docs_url: U) -> VariableInfoBuilder {
+        self.variable_info.docs_url = Some(docs_url.into());
+        self
+    }
+
+    pub fn with_group<G: Into<String>>(mut self, group: G) -> VariableInfoBuilder {
+        self.variable_info.group = Some(group.into());
+        self
+    }
+
+    pub fn with_sensitive(mut self, sensitive: bool) -> VariableInfoBuilder {
+        self.variable_info.sensitive = Some(sensitive);
+        self
+    }
+
+    pub fn with_matrix(mut self, matrix: bool) -> VariableInfoBuilder {
+        self.variable_info.matrix = Some(matrix);
+        self
+    }
+
+    pub fn with_origin(mut self, origin: AnswerOrigin) -> VariableInfoBuilder {
+        self.variable_info.origin = Some(origin);
+        self
+    }
+
     pub fn build(self) -> VariableInfo {
         self.variable_info
     }
 }
+
+/// Values may be written as YAML lists, which are read as their items separated by commas.
+fn deserialize_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    fn scalar(value: serde_yaml::Value) -> Option<String> {
+        match value {
+            serde_yaml::Value::Null => None,
+            serde_yaml::Value::Bool(value) => Some(value.to_string()),
+            serde_yaml::Value::Number(value) => Some(value.to_string()),
+            serde_yaml::Value::String(value) => Some(value),
+            serde_yaml::Value::Sequence(values) => {
+                Some(values.into_iter().filter_map(scalar).collect::<Vec<_>>().join(", "))
+            }
+            serde_yaml::Value::Mapping(_) => None,
+        }
+    }
+
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Mapping(_) => Err(D::Error::custom("expected a value or a list of values")),
+        value => Ok(scalar(value)),
+    }
+}
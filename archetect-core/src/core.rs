@@ -1,28 +1,118 @@
-use std::collections::HashSet;
+use std::cell::{OnceCell, RefCell};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
+use std::process::Command;
 use std::rc::Rc;
 
-use clap::crate_version;
-use log::{debug, trace};
+use linked_hash_map::LinkedHashMap;
+use log::{debug, info, trace, warn};
 use semver::Version;
+use serde_json::Value;
 
-use crate::config::RuleAction;
-use crate::rules::RulesContext;
+use crate::actions::custom::{ActionRegistry, CustomAction};
+use crate::archetype::GitIdentity;
+use crate::backup::Backup;
+use crate::config::{
+    AnswerHistory, ConflictStrategies, ConflictStrategy, RuleAction, TrustedSource, TrustedSources, VariableType,
+};
+use crate::encoding::Encoding;
+use crate::events::{Event, EventSubscriber};
+use crate::file_tree::FileTree;
+use crate::headers;
+use crate::index::{self, IndexedFile, SourceIndex};
+#[cfg(feature = "prompts")]
+use crate::input::TerminalPromptProvider;
+use crate::input::{Prompt, PromptProvider};
+use crate::output::{OutputStyle, Verbosity};
+use crate::protect::ProtectedFiles;
+use crate::provenance::AnswerProvenance;
+use crate::report::RenderedArchetype;
+use crate::rules::{CollisionPolicy, EmptyDirectoryPolicy, RulesContext};
+use crate::sandbox::SandboxConfig;
 use crate::system::{dot_home_layout, LayoutType, NativeSystemLayout, SystemLayout};
 use crate::system::SystemError;
-use crate::source::Source;
+use crate::source::{self, Source};
+use crate::validation::{OutputValidator, ValidatorRegistry};
+use crate::utils::sorted_entries;
+#[cfg(feature = "exec")]
+use crate::vendor::tera::extensions::commands::ShellCapture;
+use crate::vendor::tera::extensions::commands::{CommandPermit, CommandScope};
 use crate::vendor::tera::{Context, Tera};
+use crate::warnings::{self, RenderWarning};
 use crate::{ArchetectError, Archetype, ArchetypeError, RenderError};
 
+/// The shorthand prefixes that sources may begin with by default, and the start of the repository URLs they stand for.
+pub const DEFAULT_SOURCE_PREFIXES: [(&str, &str); 3] = [
+    ("gh", "https://github.com/"),
+    ("gl", "https://gitlab.com/"),
+    ("bb", "https://bitbucket.org/"),
+];
+
+/// How deeply archetypes may render other archetypes by default.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// How large, in bytes, the context of a render may grow by default before it is warned about.
+pub const DEFAULT_CONTEXT_SIZE_LIMIT: usize = 64 * 1024 * 1024;
+
 pub struct Archetect {
     tera: Tera,
     paths: Rc<Box<dyn SystemLayout>>,
     offline: bool,
     headless: bool,
+    submodules: bool,
+    lfs: bool,
+    clone_depth: Option<u32>,
+    metadata: bool,
+    backup_enabled: bool,
+    backup: Option<Backup>,
+    backups: Vec<Backup>,
+    git_identity: OnceCell<GitIdentity>,
     switches: HashSet<String>,
+    source_overrides: LinkedHashMap<String, String>,
+    host_aliases: LinkedHashMap<String, String>,
+    source_prefixes: LinkedHashMap<String, String>,
+    archetype_chain: Vec<Source>,
+    max_depth: usize,
+    context_size_limit: usize,
+    answer_provenance: Vec<AnswerProvenance>,
+    rendered_archetypes: Vec<RenderedArchetype>,
+    file_tree: Option<FileTree>,
+    source_tree: Option<Rc<FileTree>>,
+    steps: Vec<String>,
+    next_steps: Vec<String>,
+    keep_going: bool,
+    render_errors: Vec<RenderError>,
+    deny_warnings: bool,
+    validate: bool,
+    validators: ValidatorRegistry,
+    actions: ActionRegistry,
+    prompt_provider: Option<Rc<dyn PromptProvider>>,
+    subscribers: Vec<Rc<dyn EventSubscriber>>,
+    shared_answers: HashMap<String, (VariableType, Value)>,
+    answer_history: Option<AnswerHistory>,
+    warnings: Vec<RenderWarning>,
+    rendered_destinations: HashMap<PathBuf, RenderedDestination>,
+    folded_destinations: HashMap<String, PathBuf>,
+    protected: ProtectedFiles,
+    conflict_root: PathBuf,
+    conflict_strategies: ConflictStrategies,
+    default_conflict_strategy: Option<ConflictStrategy>,
+    only: Vec<String>,
+    skipped: Vec<String>,
+    trust_all: bool,
+    trusted: HashSet<(String, String)>,
+    allow_commands: bool,
+    command_permit: CommandPermit,
+    sandbox: Option<SandboxConfig>,
+    output_style: OutputStyle,
+    verbosity: Verbosity,
+    print_output: Option<RefCell<Box<dyn Write>>>,
+    held_locks: RefCell<HashMap<PathBuf, File>>,
 }
 
 impl Archetect {
@@ -34,8 +124,341 @@ impl Archetect {
         self.offline
     }
 
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Whether variables without answers take their defaults rather than being prompted for. Always true without a
+    /// [`PromptProvider`], which is the case by default when built without the `prompts` feature.
     pub fn headless(&self) -> bool {
-        self.headless
+        self.headless || self.prompt_provider.is_none()
+    }
+
+    pub fn set_headless(&mut self, headless: bool) {
+        self.headless = headless;
+    }
+
+    pub fn prompt_provider(&self) -> Option<Rc<dyn PromptProvider>> {
+        self.prompt_provider.clone()
+    }
+
+    pub fn output_style(&self) -> OutputStyle {
+        self.output_style
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Writes a line of output, such as from a `print` action, to stdout unless the builder was given somewhere else
+    /// for it with [`ArchetectBuilder::with_print_output`].
+    pub fn print(&self, message: &str) -> Result<(), ArchetectError> {
+        match &self.print_output {
+            Some(output) => {
+                let mut output = output.borrow_mut();
+                writeln!(output, "{}", message)?;
+                output.flush()?;
+            }
+            None => println!("{}", message),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn emit(&self, event: Event) {
+        for subscriber in &self.subscribers {
+            subscriber.notify(&event);
+        }
+    }
+
+    /// Whether any subscriber is listening for events, so that work done only to describe them can be skipped.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    pub fn submodules(&self) -> bool {
+        self.submodules
+    }
+
+    pub fn lfs(&self) -> bool {
+        self.lfs
+    }
+
+    /// How many commits of each ref remote archetypes are cloned with, if they are cloned shallowly.
+    pub fn clone_depth(&self) -> Option<u32> {
+        self.clone_depth
+    }
+
+    pub fn metadata(&self) -> bool {
+        self.metadata
+    }
+
+    /// The user's git identity, looked up once, as it is the same for every archetype rendered.
+    pub(crate) fn git_identity(&self) -> &GitIdentity {
+        self.git_identity.get_or_init(|| GitIdentity::detect(Path::new(".")))
+    }
+
+    /// Whether files in the destination are backed up before they are overwritten.
+    pub fn backup_enabled(&self) -> bool {
+        self.backup_enabled
+    }
+
+    /// The backups taken by renders and upgrades, of those that overwrote or added anything.
+    pub fn backups(&self) -> &[Backup] {
+        self.backups.as_slice()
+    }
+
+    /// Begins backing up the files of `destination` that are overwritten or added, if backups are enabled and one is not
+    /// already under way, returning whether it began one.
+    pub(crate) fn begin_backup(&mut self, destination: &Path) -> bool {
+        if !self.backup_enabled || self.backup.is_some() || self.in_memory() {
+            return false;
+        }
+        self.backup = Some(Backup::begin(destination));
+        true
+    }
+
+    /// Copies the file at `path` into the backup under way, if there is one, before it is overwritten, or notes that
+    /// it is added.
+    pub(crate) fn preserve(&mut self, path: &Path) -> Result<(), std::io::Error> {
+        match &mut self.backup {
+            Some(backup) => backup.preserve(path),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn finish_backup(&mut self) {
+        if let Some(backup) = self.backup.take() {
+            if !backup.is_empty() {
+                info!(
+                    "Backed up {} files, and noted {} added, to {}",
+                    backup.files().len(),
+                    backup.added().len(),
+                    backup.directory().display()
+                );
+                self.backups.push(backup);
+            }
+        }
+    }
+
+    pub fn set_metadata(&mut self, metadata: bool) {
+        self.metadata = metadata;
+    }
+
+    /// The number of archetypes currently being rendered, including the one executing; 0 when idle.
+    /// The components or source globs renders are limited to, which are none when archetypes render in full.
+    pub fn only(&self) -> &[String] {
+        &self.only
+    }
+
+    /// The components or source globs renders skip, whatever archetypes' rules say.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// The sandbox `exec` actions run in, unless their archetype declares its own.
+    pub fn sandbox(&self) -> Option<&SandboxConfig> {
+        self.sandbox.as_ref()
+    }
+
+    pub fn render_depth(&self) -> usize {
+        self.archetype_chain.len()
+    }
+
+    /// Every variable set while rendering, in order, along with where its value came from.
+    pub fn answer_provenance(&self) -> &[AnswerProvenance] {
+        self.answer_provenance.as_slice()
+    }
+
+    pub(crate) fn record_answer(&mut self, provenance: AnswerProvenance) {
+        self.answer_provenance.push(provenance);
+    }
+
+    /// Whether `identifier` was set as a sensitive variable during the render.
+    pub(crate) fn is_sensitive(&self, identifier: &str) -> bool {
+        self.answer_provenance
+            .iter()
+            .any(|answer| answer.identifier() == identifier && answer.sensitive())
+    }
+
+    /// Every archetype rendered, outermost first, each once however often it was rendered.
+    pub fn rendered_archetypes(&self) -> &[RenderedArchetype] {
+        self.rendered_archetypes.as_slice()
+    }
+
+    pub(crate) fn record_archetype(&mut self, archetype: RenderedArchetype) {
+        if !self.rendered_archetypes.contains(&archetype) {
+            self.rendered_archetypes.push(archetype);
+        }
+    }
+
+    /// Whether renders are currently directed into an in-memory [`FileTree`] rather than the filesystem.
+    pub fn in_memory(&self) -> bool {
+        self.file_tree.is_some()
+    }
+
+    pub(crate) fn replace_file_tree(&mut self, file_tree: Option<FileTree>) -> Option<FileTree> {
+        std::mem::replace(&mut self.file_tree, file_tree)
+    }
+
+    /// Reads templates from `source_tree`, rather than the filesystem, for archetypes loaded from memory.
+    pub(crate) fn replace_source_tree(&mut self, source_tree: Option<Rc<FileTree>>) -> Option<Rc<FileTree>> {
+        std::mem::replace(&mut self.source_tree, source_tree)
+    }
+
+    fn read_source(&self, path: &Path) -> Result<Vec<u8>, std::io::Error> {
+        match &self.source_tree {
+            Some(source_tree) => source_tree.get(path).map(|contents| contents.to_vec()).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} not found", path.display()))
+            }),
+            None => fs::read(path),
+        }
+    }
+
+    fn source_entries(&self, directory: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+        match &self.source_tree {
+            Some(source_tree) => Ok(source_tree.entries(directory).into_iter().map(|path| path.to_owned()).collect()),
+            None => sorted_entries(directory),
+        }
+    }
+
+    /// The size of the source file at `path`, and whether it looks binary, reading no more of it than needed to tell.
+    fn source_file_info(&self, path: &Path) -> Result<(u64, bool), std::io::Error> {
+        match &self.source_tree {
+            Some(source_tree) => {
+                let contents = source_tree.get(path).unwrap_or_default();
+                Ok((contents.len() as u64, index::is_binary(contents)))
+            }
+            None => {
+                let size = fs::metadata(path)?.len();
+                let mut head = Vec::with_capacity(index::BINARY_SNIFF_LEN);
+                File::open(path)?.take(index::BINARY_SNIFF_LEN as u64).read_to_end(&mut head)?;
+                Ok((size, index::is_binary(&head)))
+            }
+        }
+    }
+
+    fn source_is_dir(&self, path: &Path) -> bool {
+        match &self.source_tree {
+            Some(source_tree) => source_tree.is_dir(path),
+            None => path.is_dir(),
+        }
+    }
+
+    fn source_is_file(&self, path: &Path) -> bool {
+        match &self.source_tree {
+            Some(source_tree) => source_tree.is_file(path),
+            None => path.is_file(),
+        }
+    }
+
+    pub(crate) fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> Result<(), std::io::Error> {
+        match &mut self.file_tree {
+            Some(file_tree) => {
+                file_tree.create_dir_all(path);
+                Ok(())
+            }
+            None => fs::create_dir_all(path),
+        }
+    }
+
+    pub(crate) fn path_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        match &self.file_tree {
+            Some(file_tree) => file_tree.exists(path),
+            None => path.as_ref().exists(),
+        }
+    }
+
+    pub(crate) fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        match &self.file_tree {
+            Some(file_tree) => file_tree.is_file(path),
+            None => path.as_ref().is_file(),
+        }
+    }
+
+    /// Whether `path` is a directory with nothing in it.
+    pub(crate) fn is_empty_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        match &self.file_tree {
+            Some(file_tree) => file_tree.is_dir(path) && file_tree.entries(path).is_empty(),
+            None => fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none()),
+        }
+    }
+
+    /// Removes the directory at `path`, which must be empty.
+    pub(crate) fn remove_empty_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<(), std::io::Error> {
+        match &mut self.file_tree {
+            Some(file_tree) => {
+                file_tree.remove_dir(path);
+                Ok(())
+            }
+            None => fs::remove_dir(path),
+        }
+    }
+
+    pub(crate) fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        match &self.file_tree {
+            Some(file_tree) => file_tree.is_dir(path),
+            None => path.as_ref().is_dir(),
+        }
+    }
+
+    /// Reads a file as text, returning `None` if it does not exist.
+    pub(crate) fn read_contents<P: AsRef<Path>>(&self, path: P) -> Result<Option<String>, std::io::Error> {
+        let path = path.as_ref();
+        match &self.file_tree {
+            Some(file_tree) => Ok(file_tree.get(path).map(|contents| String::from_utf8_lossy(contents).into_owned())),
+            None if path.is_file() => fs::read_to_string(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The descriptions of the described actions performed so far, in the order they executed.
+    pub fn steps(&self) -> &[String] {
+        self.steps.as_slice()
+    }
+
+    pub(crate) fn record_step(&mut self, description: String) {
+        self.steps.push(description);
+    }
+
+    /// The rendered `next_steps` of the archetypes in the current render that completed, outermost last.
+    pub fn next_steps(&self) -> &[String] {
+        self.next_steps.as_slice()
+    }
+
+    pub(crate) fn add_next_steps(&mut self, next_steps: String) {
+        self.next_steps.push(next_steps);
+    }
+
+    /// Enters an archetype rendered from `source`, unless it is already being rendered, which would recurse forever, or
+    /// archetypes are already nested as deeply as allowed.
+    pub(crate) fn enter_archetype(&mut self, source: &Source) -> Result<(), ArchetectError> {
+        let cycle = self
+            .archetype_chain
+            .iter()
+            .any(|entered| entered.local_path() == source.local_path());
+        if cycle || self.archetype_chain.len() > self.max_depth {
+            let chain = self
+                .archetype_chain
+                .iter()
+                .chain(std::iter::once(source))
+                .map(|source| source.source().to_owned())
+                .collect();
+            return Err(if cycle {
+                ArchetectError::ArchetypeCycle(chain)
+            } else {
+                ArchetectError::MaxDepthExceeded {
+                    max_depth: self.max_depth,
+                    chain,
+                }
+            });
+        }
+        self.archetype_chain.push(source.clone());
+        Ok(())
+    }
+
+    pub(crate) fn exit_archetype(&mut self) {
+        self.archetype_chain.pop();
     }
 
     pub fn builder() -> ArchetectBuilder {
@@ -58,8 +481,67 @@ impl Archetect {
         &self.switches
     }
 
+    pub fn add_source_override<S: Into<String>, P: Into<String>>(&mut self, source: S, path: P) {
+        self.source_overrides.insert(source.into(), path.into());
+    }
+
+    /// Returns the local path configured to stand in for `source`, ignoring any `#gitref` suffix.
+    pub fn source_override(&self, source: &str) -> Option<&str> {
+        self.source_overrides
+            .get(source)
+            .or_else(|| source.split('#').next().and_then(|source| self.source_overrides.get(source)))
+            .map(|path| path.as_str())
+    }
+
+    /// Treats git repositories on `alias` as the same repositories on `host`, such as `ssh.github.com` for `github.com`,
+    /// so that they are cached once.
+    pub fn add_host_alias<A: Into<String>, H: Into<String>>(&mut self, alias: A, host: H) {
+        self.host_aliases.insert(alias.into().to_lowercase(), host.into().to_lowercase());
+    }
+
+    pub fn host_alias(&self, host: &str) -> Option<&str> {
+        self.host_aliases.get(&host.to_lowercase()).map(|host| host.as_str())
+    }
+
+    /// Lets sources begin with `prefix:` in place of `url`, the start of a repository URL up to the repository, such as
+    /// `corp` for `git@git.corp.example:`.
+    pub fn add_source_prefix<P: Into<String>, U: Into<String>>(&mut self, prefix: P, url: U) {
+        self.source_prefixes.insert(prefix.into(), url.into());
+    }
+
+    /// Expands a source beginning with a shorthand prefix, such as `gh:archetect/archetype-rust-cli#v2`, into the git
+    /// URL it stands for, keeping any `//subpath` and `#ref`. Other sources are returned as given.
+    pub fn expand_source(&self, source: &str) -> String {
+        let (url, rest) = match source
+            .split_once(':')
+            .and_then(|(prefix, rest)| self.source_prefixes.get(prefix).map(|url| (url, rest)))
+        {
+            Some((url, rest)) if !rest.starts_with('/') => (url, rest),
+            _ => return source.to_owned(),
+        };
+        let (repository, gitref) = match rest.split_once('#') {
+            Some((repository, gitref)) => (repository, Some(gitref)),
+            None => (rest, None),
+        };
+        let (repository, subpath) = match repository.split_once("//") {
+            Some((repository, subpath)) => (repository, Some(subpath)),
+            None => (repository, None),
+        };
+        let mut expanded = format!("{}{}.git", url, repository.trim_end_matches('/').trim_end_matches(".git"));
+        if let Some(subpath) = subpath {
+            expanded.push_str("//");
+            expanded.push_str(subpath);
+        }
+        if let Some(gitref) = gitref {
+            expanded.push('#');
+            expanded.push_str(gitref);
+        }
+        expanded
+    }
+
     pub fn load_archetype(&self, source: &str, relative_to: Option<Source>) -> Result<Archetype, ArchetypeError> {
         let source = Source::detect(self, source, relative_to)?;
+        source::verify_composed_archetypes(self, &source)?;
         let archetype = Archetype::from_source(&source)?;
         Ok(archetype)
     }
@@ -78,16 +560,41 @@ impl Archetect {
 
     pub fn render_contents<P: AsRef<Path>>(&mut self, path: P, context: &Context) -> Result<String, RenderError> {
         let path = path.as_ref();
-        let template = match fs::read_to_string(path) {
-            Ok(template) => template,
-            Err(error) => {
-                return Err(RenderError::FileRenderIOError {
-                    path: path.to_owned(),
-                    source: error,
+        let template = self.read_template(path, &Encoding::utf8())?;
+        self.render_template(path, &template, context)
+    }
+
+    /// Renders the template at `path` destined for `destination`, recording warnings for suspicious output.
+    fn render_file(
+        &mut self,
+        path: &Path,
+        destination: &Path,
+        context: &Context,
+        encoding: &Encoding,
+    ) -> Result<String, RenderError> {
+        let template = self.read_template(path, encoding)?;
+        let contents = self.render_template(path, &template, context)?;
+        for warning in warnings::inspect_render(path, destination, &template, &contents) {
+            self.add_warning(warning);
+        }
+        Ok(contents)
+    }
+
+    fn render_template(&mut self, path: &Path, template: &str, context: &Context) -> Result<String, RenderError> {
+        // Templates are only timed for subscribers, such as reports, and not where there is no clock.
+        let result = if !self.has_subscribers() || cfg!(target_arch = "wasm32") {
+            self.tera.render_str(template, context)
+        } else {
+            self.tera.render_str_timed(template, context).map(|(result, parse, render)| {
+                self.emit(Event::TemplateRendered {
+                    template: path.to_owned(),
+                    parse,
+                    render,
                 });
-            }
+                result
+            })
         };
-        match self.tera.render_str(&template, &context.clone()) {
+        match result {
             Ok(result) => Ok(result),
             Err(error) => {
                 Err(RenderError::FileRenderError {
@@ -98,6 +605,41 @@ impl Archetect {
         }
     }
 
+    /// Finds the files of `source` that rendering it with `rules_context` selects, in the order they are rendered.
+    pub fn index_directory<SRC: AsRef<Path>>(
+        &self,
+        source: SRC,
+        rules_context: &RulesContext,
+    ) -> Result<SourceIndex, std::io::Error> {
+        let mut index = SourceIndex::default();
+        self.index_entries(source.as_ref(), rules_context, &mut index)?;
+        Ok(index)
+    }
+
+    fn index_entries(
+        &self,
+        directory: &Path,
+        rules_context: &RulesContext,
+        index: &mut SourceIndex,
+    ) -> Result<(), std::io::Error> {
+        for path in self.source_entries(directory)? {
+            if self.source_is_dir(&path) {
+                if rules_context.is_excluded(&path) {
+                    continue;
+                }
+                if rules_context.is_selected(&path) {
+                    index.add_directory();
+                }
+                self.index_entries(&path, rules_context, index)?;
+            } else if self.source_is_file(&path) && rules_context.is_selected(&path) {
+                let action = rules_context.get_source_action(&path);
+                let (size, binary) = self.source_file_info(&path)?;
+                index.add_file(IndexedFile::new(path, size, binary, action));
+            }
+        }
+        Ok(())
+    }
+
     pub fn render_directory<SRC: Into<PathBuf>, DEST: Into<PathBuf>>(
         &mut self,
         context: &Context,
@@ -108,36 +650,136 @@ impl Archetect {
         let source = source.into();
         let destination = destination.into();
 
-        for entry in fs::read_dir(&source)? {
-            let entry = entry?;
-            let path = entry.path();
+        for path in self.source_entries(&source)? {
 
-            let action = rules_context.get_source_action(path.as_path());
+            let rule = rules_context.get_source_rule(path.as_path());
+            let action = rule.map(|rule| rule.action()).unwrap_or(RuleAction::RENDER);
+            let encoding = rule.and_then(|rule| rule.encoding()).cloned().unwrap_or_default();
+            let output_encoding = rule.and_then(|rule| rule.output_encoding()).cloned().unwrap_or_default();
+            let bom = rule.and_then(|rule| rule.bom());
 
-            if path.is_dir() {
-                let destination = self.render_destination(&destination, &path, &context)?;
-                debug!("Rendering   {:?}", &destination);
-                fs::create_dir_all(destination.as_path())?;
-                self.render_directory(context, path, destination, rules_context)?;
-            } else if path.is_file() {
-                let destination = self.render_destination(&destination, &path, &context)?;
+            if self.source_is_dir(&path) {
+                if rules_context.is_excluded(&path) {
+                    trace!("Skipping    {:?}", path);
+                    continue;
+                }
+                let destination = match self.render_destination(&destination, &path, &context) {
+                    Ok(destination) => destination,
+                    Err(error) => {
+                        self.defer_render_error(error)?;
+                        continue;
+                    }
+                };
+                let existed = self.path_exists(&destination);
+                if rules_context.is_selected(&path) {
+                    debug!("Rendering   {:?}", &destination);
+                    self.create_dir_all(destination.as_path())?;
+                }
+                self.render_directory(context, path, destination.clone(), rules_context)?;
+                if self.is_empty_dir(&destination) {
+                    match rules_context.empty_directories() {
+                        EmptyDirectoryPolicy::Keep => (),
+                        EmptyDirectoryPolicy::Gitkeep => self.write_contents(destination.join(".gitkeep"), "")?,
+                        EmptyDirectoryPolicy::Prune if !existed => {
+                            debug!("Pruning     {:?}", &destination);
+                            self.remove_empty_dir(&destination)?;
+                        }
+                        EmptyDirectoryPolicy::Prune => (),
+                    }
+                }
+            } else if self.source_is_file(&path) {
+                if !rules_context.is_selected(&path) {
+                    trace!("Unselected  {:?}", path);
+                    continue;
+                }
+                let destination = match self.render_destination(&destination, &path, &context) {
+                    Ok(destination) => destination,
+                    Err(error) => {
+                        self.defer_render_error(error)?;
+                        continue;
+                    }
+                };
+                // Directories are not created beforehand when only some of what they contain is selected.
+                if let Some(parent) = destination.parent().filter(|parent| !self.path_exists(parent)) {
+                    self.create_dir_all(parent)?;
+                }
                 match action {
                     RuleAction::RENDER => {
-                        if !destination.exists() {
-                            debug!("Rendering   {:?}", destination);
-                            let contents = self.render_contents(&path, &context)?;
-                            self.write_contents(destination, &contents)?;
-                        } else if rules_context.overwrite() {
-                            debug!("Overwriting {:?}", destination);
-                            let contents = self.render_contents(&path, &context)?;
-                            self.write_contents(destination, &contents)?;
+                        let exists = self.path_exists(&destination);
+                        // The destination's own conflict strategies take precedence over the archetype's rules.
+                        let strategy = self.conflict_strategy(&destination);
+                        let write = match strategy {
+                            Some(strategy) => strategy != ConflictStrategy::Ours,
+                            None => !exists || rules_context.overwrite(),
+                        };
+                        // Destinations already rendered during this run are re-rendered to check for conflicts.
+                        if write || self.rendered_destinations.contains_key(&destination) {
+                            let rendered = self
+                                .render_file(&path, &destination, context, &encoding)
+                                .and_then(|contents| self.apply_header(rules_context, &destination, contents, context))
+                                .map(|contents| match rules_context.editorconfig() {
+                                    Some(editorconfig) => editorconfig.settings_for(&destination).apply(&contents),
+                                    None => contents,
+                                });
+                            match rendered {
+                                Ok(contents) => {
+                                    let policy = rules_context.collisions();
+                                    if let Err(error) = self.record_destination(&destination, &path, contents.as_bytes(), policy) {
+                                        self.defer_render_error(error)?;
+                                    } else if let Some(strategy) = strategy.filter(|_| write) {
+                                        match self.resolve_conflict(&destination, contents, strategy)? {
+                                            Some(contents) => {
+                                                debug!("Resolving   {:?}", destination);
+                                                self.write_encoded(&destination, &contents, &output_encoding, bom)?;
+                                            }
+                                            None => trace!("Preserving  {:?}", destination),
+                                        }
+                                    } else if write {
+                                        if exists {
+                                            debug!("Overwriting {:?}", destination);
+                                        } else {
+                                            debug!("Rendering   {:?}", destination);
+                                        }
+                                        self.write_encoded(&destination, &contents, &output_encoding, bom)?;
+                                    } else {
+                                        trace!("Preserving  {:?}", destination);
+                                    }
+                                }
+                                Err(error) => self.defer_render_error(error)?,
+                            }
                         } else {
                             trace!("Preserving  {:?}", destination);
                         }
                     }
                     RuleAction::COPY => {
+                        let contents = self.read_source(&path)?;
+                        if let Some(strategy) = self.conflict_strategy(&destination) {
+                            // Copied files are not merged, only kept or replaced.
+                            let keep = fs::read(&destination)? == contents
+                                || match strategy {
+                                    ConflictStrategy::Ours => true,
+                                    ConflictStrategy::Ask => {
+                                        let strategy = self.ask_conflict(self.conflict_path(&destination)).map_err(|error| {
+                                            RenderError::ConflictError {
+                                                path: destination.clone(),
+                                                message: error.to_string(),
+                                            }
+                                        })?;
+                                        strategy == ConflictStrategy::Ours
+                                    }
+                                    ConflictStrategy::Theirs | ConflictStrategy::Merge => false,
+                                };
+                            if keep {
+                                trace!("Preserving  {:?}", destination);
+                                continue;
+                            }
+                        }
                         debug!("Copying     {:?}", destination);
-                        self.copy_contents(&path, &destination)?;
+                        if let Err(error) = self.record_destination(&destination, &path, &contents, rules_context.collisions()) {
+                            self.defer_render_error(error)?;
+                        } else {
+                            self.copy_contents(&path, &destination)?;
+                        }
                     }
                     RuleAction::SKIP => {
                         trace!("Skipping    {:?}", destination);
@@ -149,6 +791,389 @@ impl Archetect {
         Ok(())
     }
 
+    /// The destinations rendered so far during the current render.
+    pub(crate) fn rendered_destinations(&self) -> impl Iterator<Item = &Path> {
+        self.rendered_destinations.keys().map(|destination| destination.as_path())
+    }
+
+    /// Prepends the archetype's header to rendered contents, if the destination matches the header's patterns.
+    fn apply_header(
+        &mut self,
+        rules_context: &RulesContext,
+        destination: &Path,
+        contents: String,
+        context: &Context,
+    ) -> Result<String, RenderError> {
+        match rules_context.headers() {
+            Some(config) if config.matches(destination) => {
+                let header = self.render_string(config.contents(), context)?;
+                let syntax = config.comment_syntax(destination);
+                Ok(headers::prepend_header(&contents, &header, &syntax))
+            }
+            _ => Ok(contents),
+        }
+    }
+
+    /// Records that `source` rendered `contents` to `destination`, applying `policy` if a different source already
+    /// rendered something else there during this run.
+    fn record_destination(
+        &mut self,
+        destination: &Path,
+        source: &Path,
+        contents: &[u8],
+        policy: CollisionPolicy,
+    ) -> Result<(), RenderError> {
+        if !self.rendered_destinations.contains_key(destination) {
+            self.check_case_conflict(destination)?;
+        }
+        let rendered = RenderedDestination {
+            source: source.to_owned(),
+            fingerprint: farmhash::fingerprint64(contents),
+        };
+        let previous = match self.rendered_destinations.insert(destination.to_owned(), rendered) {
+            Some(previous) if previous.fingerprint != farmhash::fingerprint64(contents) => previous,
+            _ => return Ok(()),
+        };
+        match policy {
+            CollisionPolicy::Warn => {
+                self.add_warning(RenderWarning::DestinationCollision {
+                    destination: destination.to_owned(),
+                    first: previous.source,
+                    second: source.to_owned(),
+                });
+                Ok(())
+            }
+            CollisionPolicy::Error => Err(RenderError::DestinationConflict {
+                destination: destination.to_owned(),
+                first: previous.source,
+                second: source.to_owned(),
+            }),
+            CollisionPolicy::Ignore => Ok(()),
+        }
+    }
+
+    /// Destinations differing only by case are the same file on the case-insensitive filesystems Windows and macOS use
+    /// by default, where one would silently replace the other. That is an error there, and a warning elsewhere.
+    fn check_case_conflict(&mut self, destination: &Path) -> Result<(), RenderError> {
+        let folded = destination.to_string_lossy().to_lowercase();
+        let first = match self.folded_destinations.entry(folded) {
+            Entry::Occupied(entry) if entry.get() != destination => entry.get().to_owned(),
+            Entry::Occupied(_) => return Ok(()),
+            Entry::Vacant(entry) => {
+                entry.insert(destination.to_owned());
+                return Ok(());
+            }
+        };
+        let second = destination.to_owned();
+        if cfg!(any(windows, target_os = "macos")) && !self.in_memory() {
+            return Err(RenderError::CaseConflict { first, second });
+        }
+        self.add_warning(RenderWarning::CaseConflict { first, second });
+        Ok(())
+    }
+
+    /// With `keep_going` enabled, records a template error to be reported once rendering completes; otherwise, returns
+    /// it so that rendering stops.
+    fn defer_render_error(&mut self, error: RenderError) -> Result<(), RenderError> {
+        if self.keep_going {
+            debug!("Deferring: {}", error);
+            self.render_errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    pub fn keep_going(&self) -> bool {
+        self.keep_going
+    }
+
+    pub fn deny_warnings(&self) -> bool {
+        self.deny_warnings
+    }
+
+    pub fn validate(&self) -> bool {
+        self.validate
+    }
+
+    /// The validators run over rendered files when validation is enabled.
+    pub fn validators(&self) -> &ValidatorRegistry {
+        &self.validators
+    }
+
+    /// The custom actions scripts may use, beyond Archetect's own.
+    pub fn actions(&self) -> &ActionRegistry {
+        &self.actions
+    }
+
+    /// Likely mistakes noticed during the most recent render.
+    pub fn warnings(&self) -> &[RenderWarning] {
+        self.warnings.as_slice()
+    }
+
+    pub(crate) fn add_warning(&mut self, warning: RenderWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Warns, once per render, when `context` has grown past the context size limit.
+    pub(crate) fn check_context_size(&mut self, context: &Context) {
+        if context.size() <= self.context_size_limit
+            || self
+                .warnings
+                .iter()
+                .any(|warning| matches!(warning, RenderWarning::LargeContext { .. }))
+        {
+            return;
+        }
+        self.add_warning(RenderWarning::LargeContext {
+            size: context.size(),
+            limit: self.context_size_limit,
+        });
+    }
+
+    /// The files of the current render's destination that must not be overwritten.
+    pub(crate) fn set_protected(&mut self, protected: ProtectedFiles) {
+        self.protected = protected;
+    }
+
+    fn is_protected(&self, destination: &Path) -> bool {
+        if destination.exists() && self.protected.is_protected(destination) {
+            info!("Protecting  {:?}", destination);
+            return true;
+        }
+        false
+    }
+
+    /// The conflict strategies declared by the current render's destination, `root`.
+    pub(crate) fn set_conflict_strategies<P: Into<PathBuf>>(&mut self, root: P, strategies: ConflictStrategies) {
+        self.conflict_root = root.into();
+        self.conflict_strategies = strategies;
+    }
+
+    /// How existing files are treated when rendered over with different contents, where the destination does not say.
+    pub fn set_default_conflict_strategy(&mut self, strategy: Option<ConflictStrategy>) {
+        self.default_conflict_strategy = strategy;
+    }
+
+    fn conflict_path<'a>(&self, destination: &'a Path) -> &'a Path {
+        destination.strip_prefix(&self.conflict_root).unwrap_or(destination)
+    }
+
+    /// The strategy for an existing file that was not rendered earlier in this run, if the destination declares one or
+    /// there is a default.
+    fn conflict_strategy(&self, destination: &Path) -> Option<ConflictStrategy> {
+        if (self.conflict_strategies.is_empty() && self.default_conflict_strategy.is_none())
+            || self.rendered_destinations.contains_key(destination)
+            || !self.path_exists(destination)
+        {
+            return None;
+        }
+        self.conflict_strategies
+            .strategy_for(self.conflict_path(destination))
+            .or(self.default_conflict_strategy)
+    }
+
+    /// Asks whether to keep, replace, or merge the existing file at `path`, keeping it when there is no one to ask.
+    pub(crate) fn ask_conflict(&self, path: &Path) -> Result<ConflictStrategy, ArchetectError> {
+        let provider = match self.prompt_provider() {
+            Some(provider) if !self.headless => provider,
+            _ => {
+                warn!("Keeping {:?}, as there is no one to ask how to resolve its conflict", path);
+                return Ok(ConflictStrategy::Ours);
+            }
+        };
+        let options = vec!["keep".to_owned(), "replace".to_owned(), "merge".to_owned()];
+        let prompt = Prompt::new(
+            format!("conflict:{}", path.display()),
+            format!("`{}` differs from what the archetype rendered. Keep, replace, or merge it?", path.display()),
+        )
+        .with_variable_type(VariableType::Enum(options))
+        .with_default("keep");
+        self.emit(Event::PromptAsked(prompt.clone()));
+        let strategy = match provider.prompt(&prompt)? {
+            Some(Value::String(choice)) if choice == "replace" => ConflictStrategy::Theirs,
+            Some(Value::String(choice)) if choice == "merge" => ConflictStrategy::Merge,
+            _ => ConflictStrategy::Ours,
+        };
+        Ok(strategy)
+    }
+
+    /// Asks whether a remote archetype may run the commands it declares, unless it was always allowed before with the
+    /// same commands. Local archetypes are allowed, as is everything when trusting all. Without anyone to ask, untrusted
+    /// archetypes are not allowed.
+    pub(crate) fn check_trust(&mut self, archetype: &Archetype) -> Result<(), ArchetectError> {
+        let source = archetype.source();
+        let local = matches!(source, Source::LocalDirectory { .. } | Source::LocalFile { .. });
+        if self.trust_all || local {
+            return Ok(());
+        }
+        // Allowed for the commands it ran then, which another version of it loaded since may not share.
+        let fingerprint = archetype.fingerprint(self.allow_commands);
+        let trusted = (source.source().to_owned(), fingerprint.clone());
+        if self.trusted.contains(&trusted) {
+            return Ok(());
+        }
+        let path = self.layout().trusted_sources();
+        let mut sources = TrustedSources::load(&path).unwrap_or_else(|error| {
+            warn!("{}", error);
+            TrustedSources::default()
+        });
+        if sources.is_trusted(source.source(), &fingerprint) {
+            self.trusted.insert(trusted);
+            return Ok(());
+        }
+
+        let provider = match self.prompt_provider() {
+            Some(provider) if !self.headless => provider,
+            _ => return Err(ArchetectError::UntrustedSource(source.source().to_owned())),
+        };
+        let message = if sources.source(source.source()).is_some() {
+            format!("`{}` has changed the commands it runs since it was trusted. Allow them?", source.source())
+        } else {
+            format!("`{}` runs commands on this machine. Allow them?", source.source())
+        };
+        let options = vec!["allow once".to_owned(), "always allow".to_owned(), "deny".to_owned()];
+        let prompt = Prompt::new(format!("trust:{}", source.source()), message)
+            .with_variable_type(VariableType::Enum(options))
+            .with_default("deny");
+        self.emit(Event::PromptAsked(prompt.clone()));
+        match provider.prompt(&prompt)? {
+            Some(Value::String(choice)) if choice == "always allow" => {
+                sources.add_source(source.source(), TrustedSource::new(fingerprint, source.commit()));
+                if let Err(error) = sources.save(&path) {
+                    warn!("{}", error);
+                }
+            }
+            Some(Value::String(choice)) if choice == "allow once" => (),
+            _ => return Err(ArchetectError::UntrustedSource(source.source().to_owned())),
+        }
+        self.trusted.insert(trusted);
+        Ok(())
+    }
+
+    /// Permits the templates of `archetype` to run commands in `destination` if commands are allowed and the archetype
+    /// is trusted to, returning what was permitted before so that it can be restored once the archetype is rendered.
+    pub(crate) fn permit_commands(&mut self, archetype: &Archetype, destination: &Path) -> Option<CommandScope> {
+        let scope = if self.allow_commands {
+            match self.check_trust(archetype) {
+                Ok(()) => {
+                    let sandbox = archetype.configuration().sandbox().or_else(|| self.sandbox()).cloned();
+                    Some(CommandScope::new(destination, sandbox))
+                }
+                Err(error) => {
+                    warn!("Commands are disabled in templates: {}", error);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        self.restore_commands(scope)
+    }
+
+    pub(crate) fn restore_commands(&mut self, scope: Option<CommandScope>) -> Option<CommandScope> {
+        match self.command_permit.lock() {
+            Ok(mut permit) => std::mem::replace(&mut *permit, scope),
+            Err(_) => None,
+        }
+    }
+
+    /// Resolves rendered contents that differ from the existing file at `destination` by `strategy`, returning what to
+    /// write, if anything.
+    fn resolve_conflict(
+        &mut self,
+        destination: &Path,
+        rendered: String,
+        strategy: ConflictStrategy,
+    ) -> Result<Option<String>, RenderError> {
+        let existing = fs::read(destination)?;
+        if existing == rendered.as_bytes() {
+            return Ok(None);
+        }
+        let conflict_error = |message: String| RenderError::ConflictError {
+            path: destination.to_owned(),
+            message,
+        };
+        match strategy {
+            ConflictStrategy::Ours => Ok(None),
+            ConflictStrategy::Theirs => Ok(Some(rendered)),
+            ConflictStrategy::Ask => {
+                let strategy = self
+                    .ask_conflict(self.conflict_path(destination))
+                    .map_err(|error| conflict_error(error.to_string()))?;
+                self.resolve_conflict(destination, rendered, strategy)
+            }
+            ConflictStrategy::Merge => {
+                let existing =
+                    String::from_utf8(existing).map_err(|_| conflict_error("it is not UTF-8 text".to_owned()))?;
+                let (merged, conflicted) = merge_contents(&existing, &rendered).map_err(conflict_error)?;
+                if conflicted {
+                    self.add_warning(RenderWarning::MergeConflict {
+                        destination: destination.to_owned(),
+                    });
+                }
+                Ok(Some(merged))
+            }
+        }
+    }
+
+    /// Forgets the warnings and rendered destinations of any previous render.
+    pub(crate) fn reset_render_state(&mut self) {
+        self.warnings.clear();
+        self.rendered_destinations.clear();
+        self.folded_destinations.clear();
+        self.shared_answers.clear();
+        self.next_steps.clear();
+    }
+
+    /// Holds `lock`, taken on the file at `path`, until the outermost render finishes, such as one on a worktree
+    /// archetypes are read from while rendering.
+    #[cfg(feature = "git")]
+    pub(crate) fn hold_lock(&self, path: PathBuf, lock: File) {
+        self.held_locks.borrow_mut().insert(path, lock);
+    }
+
+    /// Takes back the lock held on the file at `path`, for it to be converted, or held again.
+    #[cfg(feature = "git")]
+    pub(crate) fn take_held_lock(&self, path: &Path) -> Option<File> {
+        self.held_locks.borrow_mut().remove(path)
+    }
+
+    pub(crate) fn release_locks(&self) {
+        self.held_locks.borrow_mut().clear();
+    }
+
+    pub fn answer_history(&self) -> Option<&AnswerHistory> {
+        self.answer_history.as_ref()
+    }
+
+    pub fn set_answer_history(&mut self, history: Option<AnswerHistory>) {
+        self.answer_history = history;
+    }
+
+    pub(crate) fn remember_answer(&mut self, identifier: &str, value: &Value) {
+        if let Some(history) = &mut self.answer_history {
+            history.remember(identifier, value);
+        }
+    }
+
+    /// The answer prompted for earlier in the run for a shared variable of the same name and type.
+    pub(crate) fn shared_answer(&self, identifier: &str, variable_type: &VariableType) -> Option<&Value> {
+        self.shared_answers
+            .get(identifier)
+            .filter(|(shared_type, _)| shared_type == variable_type)
+            .map(|(_, value)| value)
+    }
+
+    pub(crate) fn share_answer(&mut self, identifier: &str, variable_type: VariableType, value: Value) {
+        self.shared_answers.insert(identifier.to_owned(), (variable_type, value));
+    }
+
+    /// Takes the template errors deferred so far under `keep_going`.
+    pub fn take_render_errors(&mut self) -> Vec<RenderError> {
+        std::mem::take(&mut self.render_errors)
+    }
+
     fn render_destination<P: AsRef<Path>, C: AsRef<Path>>(
         &mut self,
         parent: P,
@@ -158,7 +1183,13 @@ impl Archetect {
         let mut destination = parent.as_ref().to_owned();
         let child = child.as_ref();
         let name = self.render_path(&child, &context)?;
-        destination.push(name);
+        // Names rendered with separators, such as a package rendered as a path, are chains of nested directories.
+        for segment in name.split(['/', '\\']).filter(|segment| !segment.is_empty() && *segment != ".") {
+            if segment == ".." {
+                return Err(RenderError::InvalidPathCharacters { path: child.to_owned() });
+            }
+            destination.push(segment);
+        }
         Ok(destination)
     }
 
@@ -176,30 +1207,163 @@ impl Archetect {
         }
     }
 
-    pub fn write_contents<P: AsRef<Path>>(&self, destination: P, contents: &str) -> Result<(), RenderError> {
+    pub fn write_contents<P: AsRef<Path>>(&mut self, destination: P, contents: &str) -> Result<(), RenderError> {
+        self.write_bytes(destination, contents.as_bytes())
+    }
+
+    pub fn write_bytes<P: AsRef<Path>>(&mut self, destination: P, contents: &[u8]) -> Result<(), RenderError> {
         let destination = destination.as_ref();
+        if let Some(file_tree) = &mut self.file_tree {
+            file_tree.insert(destination, contents);
+            self.emit(Event::FileWritten {
+                destination: destination.to_owned(),
+            });
+            return Ok(());
+        }
+        if self.is_protected(destination) {
+            return Ok(());
+        }
+        self.preserve(destination)?;
         let mut output = File::create(&destination)?;
-        output.write(contents.as_bytes())?;
+        output.write(contents)?;
+        self.emit(Event::FileWritten {
+            destination: destination.to_owned(),
+        });
         Ok(())
     }
 
-    pub fn copy_contents<S: AsRef<Path>, D: AsRef<Path>>(&self, source: S, destination: D) -> Result<(), RenderError> {
+    /// Writes rendered contents in `encoding`, adding or stripping a byte order mark according to `bom`. UTF-16 is
+    /// written with a byte order mark unless told otherwise; other encodings are left as rendered.
+    fn write_encoded(
+        &mut self,
+        destination: &Path,
+        contents: &str,
+        encoding: &Encoding,
+        bom: Option<bool>,
+    ) -> Result<(), RenderError> {
+        let contents = match bom.or(if encoding.is_utf16() { Some(true) } else { None }) {
+            Some(true) => format!("{}{}", BOM, contents.trim_start_matches(BOM)),
+            Some(false) => contents.trim_start_matches(BOM).to_owned(),
+            None => contents.to_owned(),
+        };
+        if encoding.is_utf8() {
+            return self.write_contents(destination, &contents);
+        }
+        let bytes = encoding.encode(&contents).map_err(|error| RenderError::FileEncodingError {
+            path: destination.to_owned(),
+            source: error,
+        })?;
+        self.write_bytes(destination, &bytes)
+    }
+
+    pub fn copy_contents<S: AsRef<Path>, D: AsRef<Path>>(&mut self, source: S, destination: D) -> Result<(), RenderError> {
         let source = source.as_ref();
         let destination = destination.as_ref();
+        if self.file_tree.is_some() || self.source_tree.is_some() {
+            let contents = self.read_source(source)?;
+            return self.write_bytes(destination, &contents);
+        }
+        if self.is_protected(destination) {
+            return Ok(());
+        }
+        self.preserve(destination)?;
         fs::copy(source, destination)?;
+        self.emit(Event::FileWritten {
+            destination: destination.to_owned(),
+        });
         Ok(())
     }
 
+    fn read_template(&self, path: &Path, encoding: &Encoding) -> Result<String, RenderError> {
+        let io_error = |error| RenderError::FileRenderIOError {
+            path: path.to_owned(),
+            source: error,
+        };
+        let bytes = self.read_source(path).map_err(io_error)?;
+        let template = if encoding.is_utf8() {
+            String::from_utf8(bytes)
+                .map_err(|error| io_error(std::io::Error::new(std::io::ErrorKind::InvalidData, error)))?
+        } else {
+            encoding.decode(&bytes).map_err(|error| RenderError::FileEncodingError {
+                path: path.to_owned(),
+                source: error,
+            })?
+        };
+        match template.strip_prefix(BOM) {
+            Some(template) => Ok(template.to_owned()),
+            None => Ok(template),
+        }
+    }
+
     pub fn version(&self) -> Version {
-        Version::parse(crate_version!()).unwrap()
+        Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
     }
 }
 
+/// What was written to a destination during the current render, for detecting conflicting writes.
+struct RenderedDestination {
+    source: PathBuf,
+    fingerprint: u64,
+}
+
+/// The byte order mark, which is dropped from templates so it cannot end up in the middle of rendered output.
+const BOM: char = '\u{feff}';
+
+#[cfg(not(feature = "git"))]
+fn merge_contents(_existing: &str, _rendered: &str) -> Result<(String, bool), String> {
+    Err("merging requires the `git` feature".to_owned())
+}
+
+/// Merges `rendered` into `existing` with `git merge-file`, returning the merged contents and whether they have conflict
+/// markers. With no common ancestor to merge from, lines the two do not share are conflicts.
+#[cfg(feature = "git")]
+fn merge_contents(existing: &str, rendered: &str) -> Result<(String, bool), String> {
+    let directory = tempfile::tempdir().map_err(|error| error.to_string())?;
+    let files = [("existing", existing), ("base", ""), ("rendered", rendered)];
+    for (name, contents) in files.iter() {
+        fs::write(directory.path().join(name), contents).map_err(|error| error.to_string())?;
+    }
+    let output = Command::new("git")
+        .current_dir(directory.path())
+        .args(["merge-file", "-p", "-L", "existing", "-L", "base", "-L", "rendered", "existing", "base", "rendered"])
+        .output()
+        .map_err(|error| error.to_string())?;
+    // git merge-file exits with the number of conflicts, or a negative value on error.
+    match output.status.code() {
+        Some(code) if (0..128).contains(&code) => Ok((String::from_utf8_lossy(&output.stdout).into_owned(), code > 0)),
+        _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+    }
+}
+
+
 pub struct ArchetectBuilder {
     layout: Option<Box<dyn SystemLayout>>,
     offline: bool,
     headless: bool,
+    submodules: bool,
+    lfs: bool,
+    clone_depth: Option<u32>,
+    metadata: bool,
+    backup: bool,
+    keep_going: bool,
+    deny_warnings: bool,
+    validate: bool,
+    validators: ValidatorRegistry,
+    actions: ActionRegistry,
+    prompt_provider: Option<Rc<dyn PromptProvider>>,
+    subscribers: Vec<Rc<dyn EventSubscriber>>,
+    answer_history: Option<AnswerHistory>,
     switches: HashSet<String>,
+    only: Vec<String>,
+    skipped: Vec<String>,
+    max_depth: usize,
+    context_size_limit: usize,
+    trust_all: bool,
+    allow_commands: bool,
+    sandbox: Option<SandboxConfig>,
+    output_style: OutputStyle,
+    verbosity: Verbosity,
+    print_output: Option<Box<dyn Write>>,
 }
 
 impl ArchetectBuilder {
@@ -208,21 +1372,114 @@ impl ArchetectBuilder {
             layout: None,
             offline: false,
             headless: false,
+            submodules: true,
+            lfs: true,
+            clone_depth: None,
+            metadata: false,
+            backup: false,
+            keep_going: false,
+            deny_warnings: false,
+            validate: false,
+            validators: ValidatorRegistry::default(),
+            actions: ActionRegistry::new(),
+            prompt_provider: None,
+            subscribers: vec![],
+            answer_history: None,
             switches: HashSet::new(),
+            only: vec![],
+            skipped: vec![],
+            max_depth: DEFAULT_MAX_DEPTH,
+            context_size_limit: DEFAULT_CONTEXT_SIZE_LIMIT,
+            trust_all: false,
+            allow_commands: false,
+            sandbox: None,
+            output_style: OutputStyle::default(),
+            verbosity: Verbosity::default(),
+            print_output: None,
         }
     }
 
     pub fn build(self) -> Result<Archetect, ArchetectError> {
-        let layout = dot_home_layout()?;
-        let paths = self.layout.unwrap_or_else(|| Box::new(layout));
+        // The home directory is only consulted without a layout, as there is none when embedded in a browser.
+        let paths: Box<dyn SystemLayout> = match self.layout {
+            Some(layout) => layout,
+            None => Box::new(dot_home_layout()?),
+        };
         let paths = Rc::new(paths);
+        let prompt_provider: Option<Rc<dyn PromptProvider>> = match self.prompt_provider {
+            Some(provider) => Some(provider),
+            #[cfg(feature = "prompts")]
+            None => Some(Rc::new(TerminalPromptProvider::new().with_style(self.output_style))),
+            #[cfg(not(feature = "prompts"))]
+            None => None,
+        };
+
+        // Commands are only permitted while rendering an archetype that may run them.
+        let command_permit = CommandPermit::default();
+        #[cfg_attr(not(feature = "exec"), allow(unused_mut))]
+        let mut tera = crate::vendor::tera::extensions::create_tera();
+        #[cfg(feature = "exec")]
+        tera.register_function("shell_capture", ShellCapture::new("shell_capture", command_permit.clone()));
+        #[cfg(feature = "exec")]
+        tera.register_function("cmd", ShellCapture::new("cmd", command_permit.clone()));
 
         Ok(Archetect {
-            tera: crate::vendor::tera::extensions::create_tera(),
+            tera,
             paths,
             offline: self.offline,
             headless: self.headless,
+            submodules: self.submodules,
+            lfs: self.lfs,
+            clone_depth: self.clone_depth,
+            metadata: self.metadata,
+            backup_enabled: self.backup,
+            backup: None,
+            backups: vec![],
+            git_identity: OnceCell::new(),
             switches: self.switches,
+            source_overrides: LinkedHashMap::new(),
+            host_aliases: LinkedHashMap::new(),
+            source_prefixes: DEFAULT_SOURCE_PREFIXES
+                .iter()
+                .map(|(prefix, url)| ((*prefix).to_owned(), (*url).to_owned()))
+                .collect(),
+            archetype_chain: vec![],
+            max_depth: self.max_depth,
+            context_size_limit: self.context_size_limit,
+            answer_provenance: vec![],
+            rendered_archetypes: vec![],
+            file_tree: None,
+            source_tree: None,
+            steps: vec![],
+            next_steps: vec![],
+            keep_going: self.keep_going,
+            render_errors: vec![],
+            deny_warnings: self.deny_warnings,
+            validate: self.validate,
+            validators: self.validators,
+            actions: self.actions,
+            prompt_provider,
+            output_style: self.output_style,
+            verbosity: self.verbosity,
+            print_output: self.print_output.map(RefCell::new),
+            held_locks: RefCell::new(HashMap::new()),
+            subscribers: self.subscribers,
+            shared_answers: HashMap::new(),
+            answer_history: self.answer_history,
+            warnings: vec![],
+            rendered_destinations: HashMap::new(),
+            folded_destinations: HashMap::new(),
+            protected: ProtectedFiles::default(),
+            conflict_root: PathBuf::new(),
+            conflict_strategies: ConflictStrategies::default(),
+            default_conflict_strategy: None,
+            only: self.only,
+            skipped: self.skipped,
+            trust_all: self.trust_all,
+            trusted: HashSet::new(),
+            allow_commands: self.allow_commands,
+            command_permit,
+            sandbox: self.sandbox,
         })
     }
 
@@ -249,10 +1506,167 @@ impl ArchetectBuilder {
         self.headless = headless;
         self
     }
+
+    pub fn with_submodules(mut self, submodules: bool) -> ArchetectBuilder {
+        self.submodules = submodules;
+        self
+    }
+
+    pub fn with_lfs(mut self, lfs: bool) -> ArchetectBuilder {
+        self.lfs = lfs;
+        self
+    }
+
+    /// Clones remote archetypes shallowly, with only `depth` commits of their branches, fetching a tag or commit on
+    /// its own, or the full history, should it not be among them. Repositories already cached in full stay so.
+    pub fn with_clone_depth(mut self, depth: u32) -> ArchetectBuilder {
+        self.clone_depth = Some(depth);
+        self
+    }
+
+    /// Writes a metadata file recording the archetype, revision, and answers into the destination of each render.
+    pub fn with_metadata(mut self, metadata: bool) -> ArchetectBuilder {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Copies files in the destination into `.archetect/backups/<timestamp>/` before a render or upgrade overwrites
+    /// them, and notes those it adds, so that [`crate::backup::restore`] can undo it.
+    pub fn with_backup(mut self, backup: bool) -> ArchetectBuilder {
+        self.backup = backup;
+        self
+    }
+
+    /// Allows every archetype to run the commands its script declares without asking, as when rendering archetypes
+    /// that are already vetted from a pipeline.
+    pub fn with_trust(mut self, trust_all: bool) -> ArchetectBuilder {
+        self.trust_all = trust_all;
+        self
+    }
+
+    /// Allows templates to run commands with `shell_capture()` or `cmd()`, subject to the same trust as `exec` actions.
+    pub fn with_allow_commands(mut self, allow_commands: bool) -> ArchetectBuilder {
+        self.allow_commands = allow_commands;
+        self
+    }
+
+    /// Runs the `exec` actions of archetypes that do not declare a sandbox of their own in `sandbox`.
+    pub fn with_sandbox(mut self, sandbox: SandboxConfig) -> ArchetectBuilder {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// How deeply archetypes may render other archetypes, [`DEFAULT_MAX_DEPTH`] unless set.
+    pub fn with_max_depth(mut self, max_depth: usize) -> ArchetectBuilder {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// How large, in bytes, the context of a render may grow before a warning is given, [`DEFAULT_CONTEXT_SIZE_LIMIT`]
+    /// unless set. The render continues either way.
+    pub fn with_context_size_limit(mut self, limit: usize) -> ArchetectBuilder {
+        self.context_size_limit = limit;
+        self
+    }
+
+    /// Collects errors rendering individual files and paths, reporting them together once rendering completes rather
+    /// than stopping at the first.
+    pub fn with_keep_going(mut self, keep_going: bool) -> ArchetectBuilder {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Fails renders that produce any [`RenderWarning`]s.
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> ArchetectBuilder {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Validates the rendered files of every archetype that has a validator for their extension, such as JSON, YAML,
+    /// TOML, and XML, reporting those that are invalid as [`RenderWarning`]s.
+    pub fn with_validate(mut self, validate: bool) -> ArchetectBuilder {
+        self.validate = validate;
+        self
+    }
+
+    /// Whether Archetect's own output and prompts use color and unicode. Detected from the terminal by default.
+    pub fn with_output_style(mut self, output_style: OutputStyle) -> ArchetectBuilder {
+        self.output_style = output_style;
+        self
+    }
+
+    /// How much the archetypes rendered report through `print`, `display`, and `exec` actions. See [`Verbosity`] for
+    /// what each level shows.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> ArchetectBuilder {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Where `print` actions, and anything else written with [`Archetect::print`], go rather than stdout; for when
+    /// stdout is spoken for, such as by a [`JsonLinesPromptProvider`](crate::input::JsonLinesPromptProvider).
+    pub fn with_print_output<W: Write + 'static>(mut self, output: W) -> ArchetectBuilder {
+        self.print_output = Some(Box::new(output));
+        self
+    }
+
+    /// Limits renders to a component archetypes declare, or to source paths matching a glob relative to the archetype,
+    /// leaving the rest of the destination untouched. May be given more than once.
+    pub fn with_only<S: Into<String>>(mut self, selection: S) -> ArchetectBuilder {
+        self.only.push(selection.into());
+        self
+    }
+
+    /// Skips rendering a component archetypes declare, or source paths matching a glob relative to the archetype,
+    /// overriding archetypes' rules. May be given more than once.
+    pub fn with_skip<S: Into<String>>(mut self, exclusion: S) -> ArchetectBuilder {
+        self.skipped.push(exclusion.into());
+        self
+    }
+
+    /// Validates rendered files with `extension` using `validator`, in place of any built in validator for it.
+    pub fn with_output_validator<E: AsRef<str>, V: OutputValidator + 'static>(
+        mut self,
+        extension: E,
+        validator: V,
+    ) -> ArchetectBuilder {
+        self.validators.register(extension, validator);
+        self
+    }
+
+    /// Answers prompts with `provider` rather than on the terminal.
+    pub fn with_prompt_provider<P: PromptProvider + 'static>(mut self, provider: P) -> ArchetectBuilder {
+        self.prompt_provider = Some(Rc::new(provider));
+        self
+    }
+
+    /// Notifies `subscriber` of each [`Event`] as it happens.
+    /// Offers the answers in `history` as the defaults at prompts, and remembers new answers in it. Save
+    /// [`Archetect::answer_history`] after rendering to keep them for the next run.
+    pub fn with_answer_history(mut self, history: AnswerHistory) -> ArchetectBuilder {
+        self.answer_history = Some(history);
+        self
+    }
+
+    pub fn with_event_subscriber<S: EventSubscriber + 'static>(mut self, subscriber: S) -> ArchetectBuilder {
+        self.subscribers.push(Rc::new(subscriber));
+        self
+    }
+
+    /// Executes `action` wherever a script uses `key`, which must begin with `x-`.
+    pub fn with_action<K: Into<String>, A: CustomAction + 'static>(
+        mut self,
+        key: K,
+        action: A,
+    ) -> Result<ArchetectBuilder, ArchetectError> {
+        self.actions.register(key, action)?;
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+
     use crate::system::{NativeSystemLayout, RootedSystemLayout};
 
     use super::*;
@@ -285,6 +1699,90 @@ mod tests {
         std::fs::create_dir_all(archetect.layout().git_cache_dir()).expect("Error creating directory");
     }
 
+    #[test]
+    fn test_source_override() {
+        let mut archetect = Archetect::build().unwrap();
+        archetect.add_source_override("git@github.com:archetect/archetype-rust-cli.git", "~/projects/rust-cli");
+
+        assert_eq!(
+            archetect.source_override("git@github.com:archetect/archetype-rust-cli.git#v1.0.0"),
+            Some("~/projects/rust-cli")
+        );
+        assert_eq!(archetect.source_override("git@github.com:archetect/archetype-java.git"), None);
+    }
+
+    #[test]
+    fn test_render_directory_order() {
+        let source = tempfile::tempdir().unwrap();
+        for file in ["zeta.txt", "alpha.txt", "mid/beta.txt", "Mid.txt", "gamma.txt"] {
+            let path = source.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        let destination = tempfile::tempdir().unwrap();
+
+        let written = Rc::new(RefCell::new(vec![]));
+        let recorded = written.clone();
+        let mut archetect = Archetect::builder()
+            .with_event_subscriber(move |event: &Event| {
+                if let Event::FileWritten { destination } = event {
+                    recorded.borrow_mut().push(destination.clone());
+                }
+            })
+            .build()
+            .unwrap();
+        archetect
+            .render_directory(&Context::new(), source.path(), destination.path(), &mut RulesContext::new())
+            .unwrap();
+        let written = written
+            .borrow()
+            .iter()
+            .map(|path| path.strip_prefix(destination.path()).unwrap().to_owned())
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(
+            written,
+            ["Mid.txt", "alpha.txt", "gamma.txt", "mid/beta.txt", "zeta.txt"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[test]
+    fn test_render_destination() {
+        let mut archetect = Archetect::builder().build().unwrap();
+        let mut context = Context::new();
+        context.insert("base_package", "com.acme.app");
+        context.insert("parent", "..");
+
+        let destination = archetect
+            .render_destination("src/main/java", "{{ base_package | package_to_path }}", &context)
+            .unwrap();
+        assert_eq!(destination, PathBuf::from("src/main/java/com/acme/app"));
+        assert!(archetect.render_destination("src", "{{ parent }}", &context).is_err());
+    }
+
+    #[test]
+    fn test_expand_source() {
+        let mut archetect = Archetect::build().unwrap();
+        assert_eq!(
+            archetect.expand_source("gh:archetect/archetype-rust-cli#v2"),
+            "https://github.com/archetect/archetype-rust-cli.git#v2"
+        );
+        assert_eq!(
+            archetect.expand_source("gl:acme/archetypes.git//rust/cli"),
+            "https://gitlab.com/acme/archetypes.git//rust/cli"
+        );
+        assert_eq!(archetect.expand_source("corp:platform/service-template"), "corp:platform/service-template");
+        assert_eq!(archetect.expand_source("C:/archetypes/rust-cli"), "C:/archetypes/rust-cli");
+
+        archetect.add_source_prefix("corp", "git@git.corp.example:");
+        assert_eq!(
+            archetect.expand_source("corp:platform/service-template"),
+            "git@git.corp.example:platform/service-template.git"
+        );
+    }
+
     mod templating {
         use crate::Archetect;
         use crate::vendor::tera::Context;
@@ -0,0 +1,335 @@
+use linked_hash_map::LinkedHashMap;
+
+use crate::actions::render::RenderAction;
+use crate::actions::ActionId;
+use crate::config::{VariableInfo, VariableType};
+use crate::Archetype;
+
+/// The formats archetype documentation is generated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+/// What is documented about an archetype, gathered from its configuration and script.
+struct ArchetypeDocs<'a> {
+    source: &'a str,
+    description: Option<&'a str>,
+    details: Vec<(&'static str, String)>,
+    variables: Vec<(&'a str, &'a VariableInfo)>,
+    components: Vec<&'a str>,
+    actions: LinkedHashMap<&'a str, usize>,
+    steps: Vec<&'a str>,
+}
+
+/// Documents an archetype for publishing, such as in a catalog: its description, the variables it asks for, the
+/// archetypes it renders as components, a summary of its actions, and an example invocation.
+pub fn generate(archetype: &Archetype, format: DocsFormat) -> String {
+    let docs = ArchetypeDocs::new(archetype);
+    match format {
+        DocsFormat::Markdown => docs.markdown(),
+        DocsFormat::Html => docs.html(),
+    }
+}
+
+impl<'a> ArchetypeDocs<'a> {
+    fn new(archetype: &'a Archetype) -> ArchetypeDocs<'a> {
+        let config = archetype.configuration();
+        let mut details = vec![];
+        if let Some(version) = config.version() {
+            details.push(("Version", version.to_string()));
+        }
+        for (label, values) in [
+            ("Authors", config.authors()),
+            ("Languages", config.languages()),
+            ("Frameworks", config.frameworks()),
+            ("Tags", config.tags()),
+        ] {
+            if !values.is_empty() {
+                details.push((label, values.join(", ")));
+            }
+        }
+
+        let mut docs = ArchetypeDocs {
+            source: archetype.source().source(),
+            description: config.description(),
+            details,
+            variables: vec![],
+            components: vec![],
+            actions: LinkedHashMap::new(),
+            steps: vec![],
+        };
+        for action in config.actions() {
+            docs.add_action(action);
+        }
+        docs
+    }
+
+    fn add_action(&mut self, action: &'a ActionId) {
+        match action {
+            ActionId::Set(variables) => {
+                // Variables with an explicit value are derived, rather than asked for.
+                let variables = variables.iter().filter(|(_, variable)| !variable.has_derived_value());
+                self.variables
+                    .extend(variables.map(|(identifier, variable)| (identifier.as_str(), variable)));
+            }
+            ActionId::Render(RenderAction::Archetype(options)) => self.components.push(options.source()),
+            ActionId::Step(options, _) => self.steps.extend(options.description()),
+            _ => (),
+        }
+        if !matches!(action, ActionId::Actions(_) | ActionId::Step(_, _)) {
+            *self.actions.entry(action.name()).or_insert(0) += 1;
+        }
+        for action in action.nested_actions() {
+            self.add_action(action);
+        }
+    }
+
+    /// The variables that must be answered for a headless render, with an example answer for each.
+    fn usage(&self) -> String {
+        let mut usage = format!("archetect render {} my-project", self.source);
+        for (identifier, variable) in &self.variables {
+            if variable.default().is_some() || !variable.required() {
+                continue;
+            }
+            let answer = variable.example().map(|example| example.to_owned()).unwrap_or_else(|| {
+                match variable.variable_type() {
+                    VariableType::Enum(options) => options.first().cloned().unwrap_or_default(),
+                    _ => format!("<{}>", identifier),
+                }
+            });
+            usage.push_str(&format!(" -a {}={}", identifier, shell_quote(&answer)));
+        }
+        usage
+    }
+
+    fn markdown(&self) -> String {
+        let mut markdown = format!("# {}\n\n", self.source);
+        if let Some(description) = self.description {
+            markdown.push_str(&format!("{}\n\n", description.trim()));
+        }
+        for (label, value) in &self.details {
+            markdown.push_str(&format!("- **{}:** {}\n", label, value));
+        }
+        if !self.details.is_empty() {
+            markdown.push('\n');
+        }
+
+        if !self.variables.is_empty() {
+            markdown.push_str("## Variables\n\n| Variable | Type | Default | Description |\n|---|---|---|---|\n");
+            for (identifier, variable) in &self.variables {
+                let default = variable
+                    .default()
+                    .map(|default| format!("`{}`", default))
+                    .unwrap_or_default();
+                markdown.push_str(&format!(
+                    "| `{}` | {} | {} | {} |\n",
+                    identifier,
+                    table_cell(&type_description(variable)),
+                    table_cell(&default),
+                    table_cell(&variable_description(variable)),
+                ));
+            }
+            markdown.push('\n');
+        }
+
+        if !self.components.is_empty() {
+            markdown.push_str("## Components\n\n");
+            for component in &self.components {
+                markdown.push_str(&format!("- `{}`\n", component));
+            }
+            markdown.push('\n');
+        }
+
+        if !self.actions.is_empty() {
+            markdown.push_str("## Actions\n\n");
+            for (action, count) in &self.actions {
+                markdown.push_str(&format!("- `{}` × {}\n", action, count));
+            }
+            for step in &self.steps {
+                markdown.push_str(&format!("- {}\n", step));
+            }
+            markdown.push('\n');
+        }
+
+        markdown.push_str(&format!("## Usage\n\n```sh\n{}\n```\n", self.usage()));
+        markdown
+    }
+
+    fn html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{source}</title>\n</head>\n<body>\n\
+             <h1>{source}</h1>\n",
+            source = escape(self.source)
+        );
+        if let Some(description) = self.description {
+            html.push_str(&format!("<p>{}</p>\n", escape(description.trim())));
+        }
+        if !self.details.is_empty() {
+            html.push_str("<ul>\n");
+            for (label, value) in &self.details {
+                html.push_str(&format!("<li><strong>{}:</strong> {}</li>\n", label, escape(value)));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        if !self.variables.is_empty() {
+            html.push_str(
+                "<h2>Variables</h2>\n<table>\n\
+                 <tr><th>Variable</th><th>Type</th><th>Default</th><th>Description</th></tr>\n",
+            );
+            for (identifier, variable) in &self.variables {
+                let default = variable
+                    .default()
+                    .map(|default| format!("<code>{}</code>", escape(default)))
+                    .unwrap_or_default();
+                html.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape(identifier),
+                    escape(&type_description(variable)),
+                    default,
+                    escape(&variable_description(variable)),
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        if !self.components.is_empty() {
+            html.push_str("<h2>Components</h2>\n<ul>\n");
+            for component in &self.components {
+                html.push_str(&format!("<li><code>{}</code></li>\n", escape(component)));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        if !self.actions.is_empty() {
+            html.push_str("<h2>Actions</h2>\n<ul>\n");
+            for (action, count) in &self.actions {
+                html.push_str(&format!("<li><code>{}</code> × {}</li>\n", escape(action), count));
+            }
+            for step in &self.steps {
+                html.push_str(&format!("<li>{}</li>\n", escape(step)));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        html.push_str(&format!(
+            "<h2>Usage</h2>\n<pre><code>{}</code></pre>\n</body>\n</html>\n",
+            escape(&self.usage())
+        ));
+        html
+    }
+}
+
+fn type_description(variable: &VariableInfo) -> String {
+    match variable.variable_type() {
+        VariableType::Enum(options) => format!("enum: {}", options.join(", ")),
+        variable_type => variable_type.name().to_owned(),
+    }
+}
+
+/// The variable's help, falling back to its prompt, along with any example and documentation link.
+fn variable_description(variable: &VariableInfo) -> String {
+    let mut description = variable
+        .help()
+        .or_else(|| variable.prompt())
+        .map(|description| description.trim().to_owned())
+        .unwrap_or_default();
+    if let Some(example) = variable.example() {
+        description.push_str(&format!(" Example: {}.", example));
+    }
+    if let Some(docs_url) = variable.docs_url() {
+        description.push_str(&format!(" See {}.", docs_url));
+    }
+    description.trim().to_owned()
+}
+
+fn table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn shell_quote(value: &str) -> String {
+    if value.chars().all(|c| c.is_alphanumeric() || "-_./:".contains(c)) {
+        value.to_owned()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::file_tree::FileTree;
+    use crate::Archetype;
+
+    use super::{generate, DocsFormat};
+
+    fn archetype() -> Archetype {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            indoc! {r#"
+                description: A REST service.
+                authors: ["Jane Doe"]
+                languages: [Rust]
+                script:
+                  - set:
+                      name:
+                        prompt: "Service Name:"
+                        example: billing
+                      database:
+                        type:
+                          enum: [postgres, mysql]
+                        help: Where the service keeps its data.
+                      port:
+                        type: int
+                        default: "8080"
+                      slug:
+                        value: "{{ name | train_case }}"
+                  - render:
+                      directory:
+                        source: contents
+                  - if:
+                      is-true: "{{ database == 'postgres' }}"
+                      then:
+                        - render:
+                            archetype:
+                              source: ../postgres
+                  - mkdir: logs
+                    description: Create a directory for logs
+            "#},
+        );
+        Archetype::from_files(files).unwrap()
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = generate(&archetype(), DocsFormat::Markdown);
+        assert!(markdown.contains("A REST service.\n\n- **Authors:** Jane Doe\n- **Languages:** Rust\n"));
+        assert!(markdown.contains("| `name` | string |  | Service Name: Example: billing. |\n"));
+        assert!(markdown.contains("| `database` | enum: postgres, mysql |  | Where the service keeps its data. |\n"));
+        assert!(markdown.contains("| `port` | int | `8080` |  |\n"));
+        assert!(!markdown.contains("`slug`"));
+        assert!(markdown.contains("## Components\n\n- `../postgres`\n"));
+        assert!(markdown.contains("- `render` × 2\n"));
+        assert!(markdown.contains("- Create a directory for logs\n"));
+        assert!(markdown.contains(" my-project -a name=billing -a database=postgres\n```\n"));
+    }
+
+    #[test]
+    fn test_html() {
+        let html = generate(&archetype(), DocsFormat::Html);
+        assert!(html.contains("<td><code>database</code></td><td>enum: postgres, mysql</td>"));
+        assert!(html.contains("<li><code>../postgres</code></li>"));
+    }
+}
@@ -0,0 +1,386 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::MatchOptions;
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// The `.editorconfig` files that apply to a destination, used to give rendered files the indentation, line endings,
+/// and final newline the destination expects.
+#[derive(Clone, Debug, Default)]
+pub struct EditorConfig {
+    root: PathBuf,
+    /// Files from the outermost, which has the lowest precedence, to the innermost.
+    files: Vec<EditorConfigFile>,
+}
+
+#[derive(Clone, Debug)]
+struct EditorConfigFile {
+    /// The path from the directory containing this file to the destination root, which rendered paths are resolved
+    /// against when matching sections.
+    offset: PathBuf,
+    sections: Vec<Section>,
+}
+
+#[derive(Clone, Debug)]
+struct Section {
+    patterns: Vec<glob::Pattern>,
+    properties: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndOfLine {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl EndOfLine {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::CrLf => "\r\n",
+            EndOfLine::Cr => "\r",
+        }
+    }
+}
+
+/// The EditorConfig properties in effect for a single file. Unset properties leave the rendered contents as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EditorSettings {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub end_of_line: Option<EndOfLine>,
+    pub charset: Option<String>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl EditorConfig {
+    /// Finds the `.editorconfig` files for `destination`, searching its directory and each parent until one is marked
+    /// `root = true`. An `.editorconfig` at the root of the archetype, if any, supplies defaults beneath those of the
+    /// destination. Only the archetype is consulted when `search_destination` is false, as when rendering in memory.
+    pub fn discover(
+        destination: &Path,
+        search_destination: bool,
+        archetype: &Path,
+    ) -> Result<Option<EditorConfig>, std::io::Error> {
+        let path = archetype.join(FILE_NAME);
+        let archetype = if path.is_file() {
+            Some(fs::read_to_string(path)?)
+        } else {
+            None
+        };
+        EditorConfig::discover_with(destination, search_destination, archetype.as_deref())
+    }
+
+    /// As [`EditorConfig::discover`], with the contents of the archetype's `.editorconfig`, if it has one.
+    pub fn discover_with(
+        destination: &Path,
+        search_destination: bool,
+        archetype: Option<&str>,
+    ) -> Result<Option<EditorConfig>, std::io::Error> {
+        let mut files = vec![];
+        let mut found_root = false;
+
+        if search_destination {
+            let absolute = destination.canonicalize()?;
+            for directory in absolute.ancestors() {
+                let path = directory.join(FILE_NAME);
+                if !path.is_file() {
+                    continue;
+                }
+                let offset = absolute.strip_prefix(directory).unwrap_or(Path::new("")).to_owned();
+                let (file, root) = EditorConfigFile::parse(&fs::read_to_string(path)?, offset);
+                files.push(file);
+                if root {
+                    found_root = true;
+                    break;
+                }
+            }
+        }
+
+        if let (false, Some(archetype)) = (found_root, archetype) {
+            let (file, _) = EditorConfigFile::parse(archetype, PathBuf::new());
+            files.push(file);
+        }
+
+        if files.is_empty() {
+            return Ok(None);
+        }
+        files.reverse();
+        Ok(Some(EditorConfig {
+            root: destination.to_owned(),
+            files,
+        }))
+    }
+
+    /// Parses a single `.editorconfig` located at the destination root.
+    pub fn parse<R: Into<PathBuf>>(contents: &str, root: R) -> EditorConfig {
+        let (file, _) = EditorConfigFile::parse(contents, PathBuf::new());
+        EditorConfig {
+            root: root.into(),
+            files: vec![file],
+        }
+    }
+
+    /// The settings for a file rendered to `path` within the destination.
+    pub fn settings_for<P: AsRef<Path>>(&self, path: P) -> EditorSettings {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+
+        let mut settings = EditorSettings::default();
+        let mut tab_width = None;
+        for file in &self.files {
+            let path = file.offset.join(relative);
+            for section in &file.sections {
+                if !section.patterns.iter().any(|pattern| pattern.matches_path_with(&path, options)) {
+                    continue;
+                }
+                for (key, value) in &section.properties {
+                    settings.set(key, value, &mut tab_width);
+                }
+            }
+        }
+        if settings.indent_size.is_none() {
+            settings.indent_size = tab_width;
+        }
+        settings
+    }
+}
+
+impl EditorConfigFile {
+    /// Parses the contents of an `.editorconfig`, returning whether it is marked as the root.
+    fn parse(contents: &str, offset: PathBuf) -> (EditorConfigFile, bool) {
+        let mut root = false;
+        let mut sections: Vec<Section> = vec![];
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                sections.push(Section {
+                    patterns: section_patterns(&line[1..line.len() - 1]),
+                    properties: vec![],
+                });
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim().to_ascii_lowercase();
+                let value = value.trim().to_ascii_lowercase();
+                match sections.last_mut() {
+                    Some(section) => section.properties.push((key, value)),
+                    None if key == "root" => root = value == "true",
+                    None => (),
+                }
+            }
+        }
+        (EditorConfigFile { offset, sections }, root)
+    }
+}
+
+/// Converts an EditorConfig section glob into glob patterns. Globs without a `/` match file names at any depth, and
+/// `{a,b}` alternatives are expanded into separate patterns.
+fn section_patterns(glob: &str) -> Vec<glob::Pattern> {
+    let glob = if glob.contains('/') {
+        glob.trim_start_matches('/').to_owned()
+    } else {
+        format!("**/{}", glob)
+    };
+    expand_braces(&glob)
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect()
+}
+
+fn expand_braces(glob: &str) -> Vec<String> {
+    let open = match glob.find('{') {
+        Some(open) => open,
+        None => return vec![glob.to_owned()],
+    };
+    let close = match glob[open..].find('}') {
+        Some(close) => open + close,
+        None => return vec![glob.to_owned()],
+    };
+    let (prefix, suffix) = (&glob[..open], &glob[close + 1..]);
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{}{}{}", prefix, alternative, suffix)))
+        .collect()
+}
+
+impl EditorSettings {
+    fn set(&mut self, key: &str, value: &str, tab_width: &mut Option<usize>) {
+        let unset = value == "unset";
+        match key {
+            "indent_style" => {
+                self.indent_style = match value {
+                    "space" => Some(IndentStyle::Space),
+                    "tab" => Some(IndentStyle::Tab),
+                    _ => None,
+                }
+            }
+            "indent_size" if value == "tab" => self.indent_size = *tab_width,
+            "indent_size" => self.indent_size = value.parse().ok(),
+            "tab_width" => *tab_width = value.parse().ok(),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(EndOfLine::Lf),
+                    "crlf" => Some(EndOfLine::CrLf),
+                    "cr" => Some(EndOfLine::Cr),
+                    _ => None,
+                }
+            }
+            "charset" if unset => self.charset = None,
+            "charset" => self.charset = Some(value.to_owned()),
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(value),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(value),
+            _ => (),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &EditorSettings::default()
+    }
+
+    /// Rewrites rendered contents to follow these settings. Indentation is converted between tabs and spaces,
+    /// trailing whitespace trimmed, line endings normalized, and the final newline added or removed. Of the charsets,
+    /// only a UTF-8 byte order mark is applied.
+    pub fn apply(&self, contents: &str) -> String {
+        if self.is_empty() {
+            return contents.to_owned();
+        }
+
+        let end_of_line = match self.end_of_line {
+            Some(end_of_line) => end_of_line.as_str(),
+            None if contents.contains("\r\n") => "\r\n",
+            None => "\n",
+        };
+        let final_newline = self
+            .insert_final_newline
+            .unwrap_or_else(|| contents.ends_with('\n') || contents.ends_with('\r'));
+
+        let contents = contents.trim_start_matches('\u{feff}');
+        let mut result = String::with_capacity(contents.len());
+        if self.charset.as_deref() == Some("utf-8-bom") {
+            result.push('\u{feff}');
+        }
+
+        let mut lines = contents.lines().collect::<Vec<&str>>();
+        if !final_newline {
+            while lines.last().map(|line| line.trim().is_empty()).unwrap_or(false) {
+                lines.pop();
+            }
+        }
+        for (index, line) in lines.iter().enumerate() {
+            let line = self.reindent(line);
+            if self.trim_trailing_whitespace == Some(true) {
+                result.push_str(line.trim_end());
+            } else {
+                result.push_str(&line);
+            }
+            if index + 1 < lines.len() || final_newline {
+                result.push_str(end_of_line);
+            }
+        }
+        result
+    }
+
+    fn reindent(&self, line: &str) -> String {
+        let (style, size) = match (self.indent_style, self.indent_size) {
+            (Some(style), Some(size)) if size > 0 => (style, size),
+            _ => return line.to_owned(),
+        };
+        let body = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - body.len()];
+        let width = indent.chars().map(|c| if c == '\t' { size } else { 1 }).sum::<usize>();
+        let indent = match style {
+            IndentStyle::Space => " ".repeat(width),
+            IndentStyle::Tab => format!("{}{}", "\t".repeat(width / size), " ".repeat(width % size)),
+        };
+        format!("{}{}", indent, body)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDITORCONFIG: &str = "
+root = true
+
+[*]
+end_of_line = lf
+insert_final_newline = true
+indent_style = space
+indent_size = 4
+
+[*.{yml,yaml}]
+indent_size = 2
+
+[Makefile]
+indent_style = tab
+
+[docs/**/*.md]
+trim_trailing_whitespace = false
+insert_final_newline = unset
+";
+
+    #[test]
+    fn test_settings_for() {
+        let config = EditorConfig::parse(EDITORCONFIG, "out");
+
+        let settings = config.settings_for("out/src/main.rs");
+        assert_eq!(settings.indent_style, Some(IndentStyle::Space));
+        assert_eq!(settings.indent_size, Some(4));
+        assert_eq!(settings.end_of_line, Some(EndOfLine::Lf));
+        assert_eq!(settings.insert_final_newline, Some(true));
+
+        assert_eq!(config.settings_for("out/.github/ci.yaml").indent_size, Some(2));
+        assert_eq!(config.settings_for("out/Makefile").indent_style, Some(IndentStyle::Tab));
+        assert_eq!(config.settings_for("out/docs/guide/intro.md").insert_final_newline, None);
+        assert_eq!(config.settings_for("out/README.md").insert_final_newline, Some(true));
+    }
+
+    #[test]
+    fn test_apply() {
+        let settings = EditorSettings {
+            indent_style: Some(IndentStyle::Space),
+            indent_size: Some(2),
+            end_of_line: Some(EndOfLine::Lf),
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(settings.apply("a:\r\n\tb: 1  \r\n\t\tc: 2"), "a:\n  b: 1\n    c: 2\n");
+
+        let settings = EditorSettings {
+            indent_style: Some(IndentStyle::Tab),
+            indent_size: Some(4),
+            insert_final_newline: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(settings.apply("all:\n    build\n      test\n\n"), "all:\n\tbuild\n\t  test");
+
+        assert_eq!(EditorSettings::default().apply("  keep  \r\n"), "  keep  \r\n");
+    }
+}
@@ -0,0 +1,224 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+/// The character encoding of a template or rendered file, declared by its label, such as `utf-16le`, `latin1`, or
+/// `shift_jis`. Templates are decoded from, and rendered files encoded to, their declared encoding; everything else is
+/// UTF-8. Encodings other than UTF-8 and UTF-16 need the `encodings` feature.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Encoding {
+    label: String,
+    kind: EncodingKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EncodingKind {
+    Utf8,
+    // The encoding standard only decodes UTF-16, so it is encoded here.
+    Utf16Le,
+    Utf16Be,
+    #[cfg(feature = "encodings")]
+    Other(&'static encoding_rs::Encoding),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum EncodingError {
+    #[error("Unknown encoding `{0}`")]
+    UnknownEncoding(String),
+    #[error("Contents are not valid {0}")]
+    Malformed(String),
+    #[error("`{character}` cannot be represented in {encoding}")]
+    Unmappable { encoding: String, character: char },
+}
+
+impl Encoding {
+    pub fn utf8() -> Encoding {
+        Encoding {
+            label: "utf-8".to_owned(),
+            kind: EncodingKind::Utf8,
+        }
+    }
+
+    pub fn for_label(label: &str) -> Result<Encoding, EncodingError> {
+        let kind = match label.trim().to_ascii_lowercase().as_str() {
+            "utf-16" | "utf-16le" => EncodingKind::Utf16Le,
+            "utf-16be" => EncodingKind::Utf16Be,
+            #[cfg(feature = "encodings")]
+            "latin1" | "latin-1" => EncodingKind::Other(encoding_rs::WINDOWS_1252),
+            #[cfg(feature = "encodings")]
+            other => match encoding_rs::Encoding::for_label(other.as_bytes()) {
+                Some(encoding) if encoding == encoding_rs::UTF_8 => EncodingKind::Utf8,
+                Some(encoding) if encoding == encoding_rs::UTF_16LE => EncodingKind::Utf16Le,
+                Some(encoding) if encoding == encoding_rs::UTF_16BE => EncodingKind::Utf16Be,
+                Some(encoding) if encoding.output_encoding() == encoding => EncodingKind::Other(encoding),
+                _ => return Err(EncodingError::UnknownEncoding(label.to_owned())),
+            },
+            #[cfg(not(feature = "encodings"))]
+            "utf-8" | "utf8" => EncodingKind::Utf8,
+            #[cfg(not(feature = "encodings"))]
+            _ => return Err(EncodingError::UnknownEncoding(label.to_owned())),
+        };
+        Ok(Encoding {
+            label: label.to_owned(),
+            kind,
+        })
+    }
+
+    pub fn is_utf8(&self) -> bool {
+        self.kind == EncodingKind::Utf8
+    }
+
+    pub fn is_utf16(&self) -> bool {
+        self.kind == EncodingKind::Utf16Le || self.kind == EncodingKind::Utf16Be
+    }
+
+    /// Decodes `bytes`, honoring a byte order mark if present.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, EncodingError> {
+        let (kind, bytes) = match bytes {
+            [0xef, 0xbb, 0xbf, rest @ ..] => (EncodingKind::Utf8, rest),
+            [0xff, 0xfe, rest @ ..] => (EncodingKind::Utf16Le, rest),
+            [0xfe, 0xff, rest @ ..] => (EncodingKind::Utf16Be, rest),
+            _ => (self.kind, bytes),
+        };
+        let contents = match kind {
+            EncodingKind::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            EncodingKind::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            EncodingKind::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            #[cfg(feature = "encodings")]
+            EncodingKind::Other(encoding) => {
+                let (contents, malformed) = encoding.decode_without_bom_handling(bytes);
+                Some(contents.into_owned()).filter(|_| !malformed)
+            }
+        };
+        contents.ok_or_else(|| EncodingError::Malformed(self.label.clone()))
+    }
+
+    pub fn encode(&self, contents: &str) -> Result<Vec<u8>, EncodingError> {
+        match self.kind {
+            EncodingKind::Utf8 => Ok(contents.as_bytes().to_vec()),
+            EncodingKind::Utf16Le => Ok(encode_utf16(contents, u16::to_le_bytes)),
+            EncodingKind::Utf16Be => Ok(encode_utf16(contents, u16::to_be_bytes)),
+            #[cfg(feature = "encodings")]
+            EncodingKind::Other(encoding) => {
+                let (bytes, _, unmappable) = encoding.encode(contents);
+                if unmappable {
+                    let character = contents
+                        .chars()
+                        .find(|character| encoding.encode(&character.to_string()).2)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    return Err(EncodingError::Unmappable {
+                        encoding: self.label.clone(),
+                        character,
+                    });
+                }
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+}
+
+fn encode_utf16(contents: &str, to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(contents.len() * 2);
+    for unit in contents.encode_utf16() {
+        bytes.extend_from_slice(&to_bytes(unit));
+    }
+    bytes
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let units = pairs.map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::utf8()
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+impl TryFrom<String> for Encoding {
+    type Error = EncodingError;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        Encoding::for_label(&label)
+    }
+}
+
+impl From<Encoding> for String {
+    fn from(encoding: Encoding) -> Self {
+        encoding.label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let samples = [
+            ("utf-8", "Café 日本"),
+            ("utf-16le", "Café 日本"),
+            ("utf-16be", "Café 日本"),
+            #[cfg(feature = "encodings")]
+            ("latin1", "Café"),
+            #[cfg(feature = "encodings")]
+            ("shift_jis", "日本語"),
+        ];
+        for (label, contents) in &samples {
+            let encoding = Encoding::for_label(label).unwrap();
+            let bytes = encoding.encode(contents).unwrap();
+            assert_eq!(&encoding.decode(&bytes).unwrap(), contents, "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(Encoding::for_label("UTF-16").unwrap().encode("\u{feff}A").unwrap(), vec![0xff, 0xfe, 0x41, 0x00]);
+        assert_eq!(
+            Encoding::for_label("klingon"),
+            Err(EncodingError::UnknownEncoding("klingon".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let utf8 = Encoding::utf8();
+        assert_eq!(utf8.decode(&[0xef, 0xbb, 0xbf, 0x41]).unwrap(), "A");
+        assert_eq!(utf8.decode(&[0xff, 0xfe, 0x41, 0x00]).unwrap(), "A");
+        assert_eq!(Encoding::for_label("utf-16be").unwrap().decode(&[0x00, 0x41]).unwrap(), "A");
+        assert_eq!(utf8.decode(&[0xc3]), Err(EncodingError::Malformed("utf-8".to_owned())));
+        assert!(Encoding::for_label("utf-16le").unwrap().decode(&[0x41]).is_err());
+        assert!(Encoding::for_label("utf-16le").unwrap().decode(&[0x00, 0xd8]).is_err());
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn test_encode_legacy() {
+        assert_eq!(Encoding::for_label("latin1").unwrap().encode("é").unwrap(), vec![0xe9]);
+        assert_eq!(
+            Encoding::for_label("latin1").unwrap().encode("日"),
+            Err(EncodingError::Unmappable {
+                encoding: "latin1".to_owned(),
+                character: '日'
+            })
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let encoding: Encoding = serde_yaml::from_str("UTF-16BE").unwrap();
+        assert_eq!(encoding.to_string(), "UTF-16BE");
+        assert!(serde_yaml::from_str::<Encoding>("klingon").is_err());
+    }
+}
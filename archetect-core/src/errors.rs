@@ -1,6 +1,14 @@
-use crate::config::{AnswerConfigError, CatalogError};
+use crate::batch::BatchError;
+use crate::config::{AnswerConfigError, UserConfigError};
+#[cfg(feature = "catalog")]
+use crate::config::CatalogError;
+use crate::encoding::EncodingError;
+use crate::metadata::MetadataError;
+use crate::report::ReportError;
 use crate::system::SystemError;
 use crate::source::SourceError;
+use crate::upgrade::UpgradeError;
+use crate::validation::InvalidOutput;
 use crate::ArchetypeError;
 use std::path::PathBuf;
 use std::fmt::{Display, Formatter};
@@ -18,15 +26,84 @@ pub enum ArchetectError {
     SystemError(#[from] SystemError),
     #[error(transparent)]
     SourceError(#[from] SourceError),
+    #[cfg(feature = "catalog")]
     #[error(transparent)]
     CatalogError(#[from] CatalogError),
     #[error(transparent)]
+    MetadataError(#[from] MetadataError),
+    #[error(transparent)]
+    ReportError(#[from] ReportError),
+    #[error(transparent)]
+    UpgradeError(#[from] UpgradeError),
+    #[error(transparent)]
+    BatchError(#[from] BatchError),
+    #[error(transparent)]
+    UserConfigError(#[from] UserConfigError),
+    #[error(transparent)]
     IoError(#[from] std::io::Error),
     #[error("Headless mode requires answers to be supplied for all variables, but no answer was supplied for the `{0}` \
     variable.")]
     HeadlessMissingAnswer(String),
     #[error("Headless mode attempted to use the default value for the `{identifier}` variable, however, {message}")]
     HeadlessInvalidDefault { identifier: String, default: String, message: String },
+    #[error("Headless mode requires a source to render, as one cannot be chosen from a catalog")]
+    HeadlessMissingSource,
+    #[error("No answer was supplied for the `{0}` prompt")]
+    UnansweredPrompt(String),
+    #[error("`{answer}` is not a valid answer for `{identifier}`")]
+    InvalidAnswer { identifier: String, answer: String },
+    #[error("Required environment variables are not set: {}", .0.join(", "))]
+    MissingEnvironmentVariables(Vec<String>),
+    #[error("{0} requires the `{1}` feature, which this build of Archetect does not include")]
+    FeatureDisabled(String, &'static str),
+    #[error("`{}` is outside the destination, which is all a sandboxed command can reach", .0.display())]
+    OutsideSandbox(PathBuf),
+    #[error("`{0}` is not trusted to run commands on this machine")]
+    UntrustedSource(String),
+    #[error("No action is registered for `{0}`")]
+    UnknownAction(String),
+    #[error("Custom action `{0}` must begin with `x-`")]
+    InvalidActionKey(String),
+    #[error("Required command `{0}` was not found on the PATH")]
+    CommandNotFound(String),
+    #[error("Error running `{command}`: {source}")]
+    CommandError { command: String, source: std::io::Error },
+    #[error("Invalid file mode `{0}`; expected an octal mode such as `755`")]
+    InvalidFileMode(String),
+    #[error("Managed block `{block}` in `{}` has no end marker", .path.display())]
+    UnterminatedManagedBlock { path: PathBuf, block: String },
+    #[error("No Cargo workspace was found at or above `{}`", .0.display())]
+    CargoWorkspaceNotFound(PathBuf),
+    #[error("Error updating Cargo manifest `{}`: {message}", .path.display())]
+    CargoManifestError { path: PathBuf, message: String },
+    #[error("No Gradle settings were found above `{}`", .0.display())]
+    GradleSettingsNotFound(PathBuf),
+    #[error("No parent pom.xml was found above `{}`", .0.display())]
+    MavenParentNotFound(PathBuf),
+    #[error("Error updating build file `{}`: {message}", .path.display())]
+    BuildFileError { path: PathBuf, message: String },
+    #[error("Error in allocation state `{}`: {message}", .path.display())]
+    AllocationStateError { path: PathBuf, message: String },
+    #[error("Every number from {start} to {end} is already allocated in `{}`", .path.display())]
+    AllocationExhausted { path: PathBuf, start: i64, end: i64 },
+    #[error("Timed out waiting for `{}` to be unlocked", .0.display())]
+    AllocationLocked(PathBuf),
+    #[error("`{}` is not a suitable destination for this archetype:{}", .destination.display(), .failures.iter().map(|failure| format!("\n  - It {}", failure)).collect::<String>())]
+    PreconditionsFailed { destination: PathBuf, failures: Vec<String> },
+    #[error("{0} of the batch's archetypes failed to render")]
+    BatchFailures(usize),
+    #[error("{} template(s) failed to render:{}", .0.len(), .0.iter().map(|error| format!("\n  - {}", error)).collect::<String>())]
+    RenderErrors(Vec<RenderError>),
+    #[error("`{}` renders itself: {}", .0.last().map(String::as_str).unwrap_or_default(), .0.join(" -> "))]
+    ArchetypeCycle(Vec<String>),
+    #[error("Archetypes are nested more than {max_depth} deep: {}", .chain.join(" -> "))]
+    MaxDepthExceeded { max_depth: usize, chain: Vec<String> },
+    #[error("No backup was found at `{}`", .0.display())]
+    BackupNotFound(PathBuf),
+    #[error("Rendering produced {0} warning(s), and warnings are denied")]
+    DeniedWarnings(usize),
+    #[error("{} rendered file(s) are not valid:{}", .0.len(), .0.iter().map(|invalid| format!("\n  - {}", invalid)).collect::<String>())]
+    InvalidOutput(Vec<InvalidOutput>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -54,6 +131,23 @@ pub enum RenderError {
         #[from]
         source: std::io::Error,
     },
+    DestinationConflict {
+        destination: PathBuf,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    FileEncodingError {
+        path: PathBuf,
+        source: EncodingError,
+    },
+    ConflictError {
+        path: PathBuf,
+        message: String,
+    },
+    CaseConflict {
+        first: PathBuf,
+        second: PathBuf,
+    },
 }
 
 impl Display for RenderError {
@@ -77,6 +171,18 @@ impl Display for RenderError {
             RenderError::IOError { source } => {
                 write!(f, "Rendering IO Error: {}", source)
             }
+            RenderError::DestinationConflict { destination, first, second } => {
+                write!(f, "`{:?}` and `{:?}` render different content to `{:?}`", first, second, destination)
+            }
+            RenderError::FileEncodingError { path, source } => {
+                write!(f, "Unable to render file `{:?}`: {}", path, source)
+            }
+            RenderError::ConflictError { path, message } => {
+                write!(f, "Unable to resolve conflict with `{:?}`: {}", path, message)
+            }
+            RenderError::CaseConflict { first, second } => {
+                write!(f, "`{:?}` and `{:?}` differ only by case, and would be the same file on this filesystem", first, second)
+            }
         }
     }
 }
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::input::Prompt;
+use crate::source::Source;
+
+/// Something that happened while rendering, delivered to each [`EventSubscriber`] as it happens. Variants may be added,
+/// so subscribers should ignore events they do not recognize.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A source was found locally, or fetched into the cache.
+    SourceResolved(Source),
+    /// A variable without an acceptable answer is about to be prompted for.
+    PromptAsked(Prompt),
+    /// A script action is about to execute. Actions are named by their keys, such as `render` or `x-acme-provision`.
+    ActionStarted {
+        action: String,
+    },
+    ActionFinished {
+        action: String,
+        succeeded: bool,
+    },
+    /// A directory is about to be rendered, writing this many files of this combined size, as found by indexing it.
+    /// Only indexed with subscribers.
+    RenderPlanned {
+        files: usize,
+        bytes: u64,
+    },
+    /// A file's template was rendered, taking `parse` to parse and `render` to render. Only timed with subscribers.
+    TemplateRendered {
+        template: PathBuf,
+        parse: Duration,
+        render: Duration,
+    },
+    /// A file was rendered or copied into the destination, or into the file tree when rendering in memory.
+    FileWritten {
+        destination: PathBuf,
+    },
+    /// The outermost archetype finished rendering. Deferred errors and denied warnings are reported afterwards.
+    RenderComplete {
+        files: usize,
+        warnings: usize,
+    },
+}
+
+/// Notified of each [`Event`], for progress reporting, metrics, or auditing. Closures taking an `&Event` are
+/// subscribers.
+pub trait EventSubscriber {
+    fn notify(&self, event: &Event);
+}
+
+impl<F: Fn(&Event)> EventSubscriber for F {
+    fn notify(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Shared subscribers, so that embedders can keep a handle on a subscriber given to Archetect.
+impl<S: EventSubscriber + ?Sized> EventSubscriber for Rc<S> {
+    fn notify(&self, event: &Event) {
+        self.as_ref().notify(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::file_tree::FileTree;
+    use crate::input::{Prompt, ScriptedPromptProvider};
+    use crate::{Archetect, Archetype};
+
+    use super::Event;
+
+    #[test]
+    fn test_events() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n",
+                "  - render:\n      directory:\n        source: contents\n",
+            ),
+        );
+        files.insert("contents/{{ name }}.txt", "{{ name }}");
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let recorded = events.clone();
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(ScriptedPromptProvider::new().with_answer("name", "billing"))
+            .with_event_subscriber(move |event: &Event| {
+                // Timings vary from run to run.
+                let event = match event {
+                    Event::TemplateRendered { template, .. } => Event::TemplateRendered {
+                        template: template.to_owned(),
+                        parse: Duration::ZERO,
+                        render: Duration::ZERO,
+                    },
+                    event => event.clone(),
+                };
+                recorded.borrow_mut().push(event)
+            })
+            .build()
+            .unwrap();
+        archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                Event::ActionStarted {
+                    action: "set".to_owned()
+                },
+                Event::PromptAsked(Prompt::new("name", "Name:")),
+                Event::ActionFinished {
+                    action: "set".to_owned(),
+                    succeeded: true
+                },
+                Event::ActionStarted {
+                    action: "render".to_owned()
+                },
+                Event::RenderPlanned { files: 1, bytes: 10 },
+                Event::TemplateRendered {
+                    template: PathBuf::from("contents/{{ name }}.txt"),
+                    parse: Duration::ZERO,
+                    render: Duration::ZERO,
+                },
+                Event::FileWritten {
+                    destination: PathBuf::from("billing.txt")
+                },
+                Event::ActionFinished {
+                    action: "render".to_owned(),
+                    succeeded: true
+                },
+                Event::RenderComplete { files: 1, warnings: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_resolved() {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        std::fs::write(archetype_dir.path().join("archetype.yml"), "script: []\n").unwrap();
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let recorded = events.clone();
+        let archetect = Archetect::builder()
+            .with_event_subscriber(move |event: &Event| recorded.borrow_mut().push(event.clone()))
+            .build()
+            .unwrap();
+        let archetype = archetect
+            .load_archetype(archetype_dir.path().to_str().unwrap(), None)
+            .unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![Event::SourceResolved(archetype.source().clone())]
+        );
+    }
+}
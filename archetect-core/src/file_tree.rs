@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// An in-memory destination for renders, keyed by paths relative to the destination root.
+///
+/// Its `Display` output lists every file with its contents in path order, making it suitable for snapshot tests.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileTree {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    directories: BTreeSet<PathBuf>,
+}
+
+impl FileTree {
+    pub fn new() -> FileTree {
+        Default::default()
+    }
+
+    pub fn insert<P: Into<PathBuf>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent);
+        }
+        self.files.insert(path, contents.into());
+    }
+
+    pub fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) {
+        for directory in path.as_ref().ancestors() {
+            if directory.as_os_str().is_empty() {
+                break;
+            }
+            self.directories.insert(directory.to_owned());
+        }
+    }
+
+    /// Removes the directory at `path` if nothing is within it, returning whether it was removed.
+    pub fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        let path = path.as_ref();
+        self.entries(path).is_empty() && self.directories.remove(path)
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&[u8]> {
+        self.files.get(path.as_ref()).map(|contents| contents.as_slice())
+    }
+
+    /// The contents of the file at `path`, if it exists and is valid UTF-8.
+    pub fn get_str<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.get(path).and_then(|contents| std::str::from_utf8(contents).ok())
+    }
+
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.contains_key(path.as_ref())
+    }
+
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.directories.contains(path.as_ref())
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.is_file(&path) || self.is_dir(&path)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &[u8])> {
+        self.files
+            .iter()
+            .map(|(path, contents)| (path.as_path(), contents.as_slice()))
+    }
+
+    /// The files and directories directly within `directory`, in path order. The root is the empty path.
+    pub fn entries<P: AsRef<Path>>(&self, directory: P) -> Vec<&Path> {
+        let directory = directory.as_ref();
+        let mut entries = self
+            .directories
+            .iter()
+            .chain(self.files.keys())
+            .map(|path| path.as_path())
+            .filter(|path| path.parent() == Some(directory))
+            .collect::<Vec<&Path>>();
+        entries.sort();
+        entries
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|path| path.as_path())
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl Display for FileTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (path, contents) in self.files() {
+            writeln!(f, "--- {}", path.display())?;
+            match std::str::from_utf8(contents) {
+                Ok(contents) => {
+                    write!(f, "{}", contents)?;
+                    if !contents.is_empty() && !contents.ends_with('\n') {
+                        writeln!(f)?;
+                    }
+                }
+                Err(_) => writeln!(f, "<binary, {} bytes>", contents.len())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_creates_parents() {
+        let mut tree = FileTree::new();
+        tree.insert("src/main/App.java", "class App {}");
+
+        assert!(tree.is_file("src/main/App.java"));
+        assert!(tree.is_dir("src/main"));
+        assert!(tree.is_dir("src"));
+        assert!(!tree.exists("src/test"));
+        assert_eq!(tree.get_str("src/main/App.java"), Some("class App {}"));
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut tree = FileTree::new();
+        tree.insert("src/main.rs", "fn main() {}");
+        tree.insert("src/api/mod.rs", "");
+        tree.insert("Cargo.toml", "[package]");
+
+        assert_eq!(tree.entries(""), vec![Path::new("Cargo.toml"), Path::new("src")]);
+        assert_eq!(tree.entries("src"), vec![Path::new("src/api"), Path::new("src/main.rs")]);
+        assert!(tree.entries("src/main.rs").is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut tree = FileTree::new();
+        tree.insert("README.md", "# Service\n");
+        tree.insert("src/lib.rs", "pub mod api;");
+        tree.insert("logo.png", vec![0x89, 0x50, 0x4e, 0x47, 0xff]);
+
+        assert_eq!(
+            tree.to_string(),
+            "--- README.md\n# Service\n--- logo.png\n<binary, 5 bytes>\n--- src/lib.rs\npub mod api;\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+#[cfg(feature = "exec")]
+use std::process::Command;
+
+#[cfg(feature = "exec")]
+use log::debug;
+use log::warn;
+
+#[cfg(feature = "exec")]
+use crate::actions::native;
+
+/// A formatter run over the files an archetype renders once its script completes, so that generated code matches the
+/// style of the destination. Either one of the built in integrations, named on its own, or a custom command.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FormatterConfig {
+    BuiltIn(BuiltInFormatter),
+    Custom(CustomFormatter),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum BuiltInFormatter {
+    #[serde(rename = "rustfmt")]
+    Rustfmt,
+    #[serde(rename = "prettier")]
+    Prettier,
+    #[serde(rename = "gofmt")]
+    Gofmt,
+    #[serde(rename = "black")]
+    Black,
+}
+
+/// A formatter command, invoked with its `args` followed by the paths of the files to format. It is trusted to the
+/// same degree as an `exec` action.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CustomFormatter {
+    command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    extensions: Vec<String>,
+}
+
+impl CustomFormatter {
+    pub fn new<C: Into<String>>(command: C) -> CustomFormatter {
+        CustomFormatter {
+            command: command.into(),
+            args: vec![],
+            extensions: vec![],
+        }
+    }
+
+    pub fn with_arg<A: Into<String>>(mut self, arg: A) -> CustomFormatter {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn with_extension<E: Into<String>>(mut self, extension: E) -> CustomFormatter {
+        self.extensions.push(extension.into());
+        self
+    }
+}
+
+impl FormatterConfig {
+    pub fn command(&self) -> &str {
+        match self {
+            FormatterConfig::BuiltIn(BuiltInFormatter::Rustfmt) => "rustfmt",
+            FormatterConfig::BuiltIn(BuiltInFormatter::Prettier) => "prettier",
+            FormatterConfig::BuiltIn(BuiltInFormatter::Gofmt) => "gofmt",
+            FormatterConfig::BuiltIn(BuiltInFormatter::Black) => "black",
+            FormatterConfig::Custom(formatter) => &formatter.command,
+        }
+    }
+
+    #[cfg(feature = "exec")]
+    fn args(&self) -> Vec<&str> {
+        match self {
+            FormatterConfig::BuiltIn(BuiltInFormatter::Rustfmt) => vec!["--edition", "2021"],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Prettier) => vec!["--write", "--log-level", "warn"],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Gofmt) => vec!["-w"],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Black) => vec!["--quiet"],
+            FormatterConfig::Custom(formatter) => formatter.args.iter().map(|arg| arg.as_str()).collect(),
+        }
+    }
+
+    fn extensions(&self) -> Vec<&str> {
+        match self {
+            FormatterConfig::BuiltIn(BuiltInFormatter::Rustfmt) => vec!["rs"],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Prettier) => vec![
+                "js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "less", "html", "vue", "md", "yaml",
+                "yml", "graphql",
+            ],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Gofmt) => vec!["go"],
+            FormatterConfig::BuiltIn(BuiltInFormatter::Black) => vec!["py", "pyi"],
+            FormatterConfig::Custom(formatter) => formatter.extensions.iter().map(|extension| extension.as_str()).collect(),
+        }
+    }
+
+    /// Whether this formatter handles a file, judged by its extension.
+    pub fn handles<P: AsRef<Path>>(&self, path: P) -> bool {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => self.extensions().iter().any(|candidate| candidate.eq_ignore_ascii_case(extension)),
+            None => false,
+        }
+    }
+
+    /// Formats the files this formatter handles. Formatting is best effort: a formatter that is not installed, or that
+    /// fails, is reported and the files are left as rendered.
+    pub fn format(&self, files: &[PathBuf]) {
+        let files = files.iter().filter(|file| self.handles(file)).collect::<Vec<&PathBuf>>();
+        if files.is_empty() {
+            return;
+        }
+
+        self.run(&files);
+    }
+
+    #[cfg(not(feature = "exec"))]
+    fn run(&self, files: &[&PathBuf]) {
+        warn!("[format] Built without the `exec` feature; leaving {} file(s) unformatted", files.len());
+    }
+
+    #[cfg(feature = "exec")]
+    fn run(&self, files: &[&PathBuf]) {
+        let command = self.command();
+        if native::which(command).is_none() {
+            warn!("[format] `{}` was not found on the PATH; leaving {} file(s) unformatted", command, files.len());
+            return;
+        }
+
+        debug!("[format] Formatting {} file(s) with `{}`", files.len(), command);
+        match Command::new(command).args(self.args()).args(files).output() {
+            Ok(output) if output.status.success() => (),
+            Ok(output) => {
+                warn!("[format] `{}` exited with {}", command, output.status);
+                for line in String::from_utf8_lossy(&output.stderr).lines() {
+                    warn!("[{}] {}", command, line);
+                }
+            }
+            Err(error) => warn!("[format] Error running `{}`: {}", command, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_formatters() {
+        let formatters: Vec<FormatterConfig> = serde_yaml::from_str(
+            "- rustfmt\n- command: terraform\n  args: [fmt]\n  extensions: [tf]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            formatters,
+            vec![
+                FormatterConfig::BuiltIn(BuiltInFormatter::Rustfmt),
+                FormatterConfig::Custom(CustomFormatter::new("terraform").with_arg("fmt").with_extension("tf")),
+            ]
+        );
+
+        assert!(formatters[0].handles("src/main.rs"));
+        assert!(!formatters[0].handles("main.tf"));
+        assert!(formatters[1].handles("infra/MAIN.TF"));
+        assert!(!formatters[1].handles("Makefile"));
+    }
+
+    #[cfg(all(unix, feature = "exec"))]
+    #[test]
+    fn test_format() {
+        let directory = tempfile::tempdir().unwrap();
+        let formatted = directory.path().join("main.txt");
+        let skipped = directory.path().join("README.md");
+        std::fs::write(&formatted, "unformatted").unwrap();
+        std::fs::write(&skipped, "unformatted").unwrap();
+
+        let formatter = FormatterConfig::Custom(
+            CustomFormatter::new("sh")
+                .with_arg("-c")
+                .with_arg("for file; do echo formatted > \"$file\"; done")
+                .with_arg("sh")
+                .with_extension("txt"),
+        );
+        formatter.format(&[formatted.clone(), skipped.clone()]);
+
+        assert_eq!(std::fs::read_to_string(formatted).unwrap(), "formatted\n");
+        assert_eq!(std::fs::read_to_string(skipped).unwrap(), "unformatted");
+    }
+}
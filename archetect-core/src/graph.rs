@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::actions::render::RenderAction;
+use crate::actions::ActionId;
+use crate::source::Source;
+use crate::{Archetect, ArchetectError, Archetype};
+
+/// The formats a composition graph is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+/// An archetype in a composition graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    source: String,
+    gitref: Option<String>,
+    commit: Option<String>,
+    version: Option<String>,
+    resolved: bool,
+}
+
+impl GraphNode {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn gitref(&self) -> Option<&str> {
+        self.gitref.as_deref()
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Whether the archetype was loaded. Sources templated with answers are only known once rendering, and are shown
+    /// as written.
+    pub fn resolved(&self) -> bool {
+        self.resolved
+    }
+
+    fn label(&self) -> Vec<String> {
+        let mut label = vec![self.source.clone()];
+        let revision = match (&self.gitref, &self.commit) {
+            (Some(gitref), Some(commit)) => Some(format!("{} ({})", gitref, short_commit(commit))),
+            (Some(gitref), None) => Some(gitref.clone()),
+            (None, Some(commit)) => Some(short_commit(commit).to_owned()),
+            (None, None) => None,
+        };
+        label.extend(revision);
+        label.extend(self.version.as_ref().map(|version| format!("v{}", version)));
+        if !self.resolved {
+            label.push("(templated)".to_owned());
+        }
+        label
+    }
+}
+
+/// The archetypes an archetype renders as components, and the archetypes those render in turn. An archetype used by
+/// several others is a single node.
+#[derive(Debug, Default)]
+pub struct ArchetypeGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl ArchetypeGraph {
+    /// The archetypes, starting with the root.
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    /// Each archetype's components, by index into [`nodes`](ArchetypeGraph::nodes).
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn to_format(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph archetypes {\n    node [shape=box];\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let label = node
+                .label()
+                .iter()
+                .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            let style = if node.resolved { "" } else { ", style=dashed" };
+            dot.push_str(&format!("    n{} [label=\"{}\"{}];\n", index, label, style));
+        }
+        for (from, to) in &self.edges {
+            dot.push_str(&format!("    n{} -> n{};\n", from, to));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph TD\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let label = node
+                .label()
+                .iter()
+                .map(|line| line.replace('"', "#quot;"))
+                .collect::<Vec<_>>()
+                .join("<br/>");
+            mermaid.push_str(&format!("    n{}[\"{}\"]\n", index, label));
+        }
+        for (from, to) in &self.edges {
+            mermaid.push_str(&format!("    n{} --> n{}\n", from, to));
+        }
+        mermaid
+    }
+}
+
+/// Loads `archetype`'s components, and theirs, into a graph. Components are found throughout the script, including
+/// those that are only rendered conditionally.
+pub fn graph(archetect: &Archetect, archetype: &Archetype) -> Result<ArchetypeGraph, ArchetectError> {
+    let mut builder = GraphBuilder::default();
+    builder.add_archetype(archetect, archetype)?;
+    Ok(builder.graph)
+}
+
+#[derive(Default)]
+struct GraphBuilder {
+    graph: ArchetypeGraph,
+    indexes: HashMap<(String, Option<String>), usize>,
+}
+
+impl GraphBuilder {
+    fn add_archetype(&mut self, archetect: &Archetect, archetype: &Archetype) -> Result<usize, ArchetectError> {
+        let source = archetype.source();
+        let key = (source_name(source), source.gitref().map(str::to_owned));
+        if let Some(index) = self.indexes.get(&key) {
+            return Ok(*index);
+        }
+        let index = self.add_node(GraphNode {
+            source: key.0.clone(),
+            gitref: key.1.clone(),
+            commit: source.commit(),
+            version: archetype.configuration().version().map(|version| version.to_string()),
+            resolved: true,
+        });
+        self.indexes.insert(key, index);
+
+        let mut components = vec![];
+        for action in archetype.configuration().actions() {
+            collect_components(action, &mut components);
+        }
+        for component in components {
+            let child = if component.contains("{{") || component.contains("{%") {
+                self.add_node(GraphNode {
+                    source: component.to_owned(),
+                    gitref: None,
+                    commit: None,
+                    version: None,
+                    resolved: false,
+                })
+            } else {
+                let child = archetect.load_archetype(component, Some(archetype.source().clone()))?;
+                self.add_archetype(archetect, &child)?
+            };
+            if !self.graph.edges.contains(&(index, child)) {
+                self.graph.edges.push((index, child));
+            }
+        }
+        Ok(index)
+    }
+
+    fn add_node(&mut self, node: GraphNode) -> usize {
+        self.graph.nodes.push(node);
+        self.graph.nodes.len() - 1
+    }
+}
+
+fn collect_components<'a>(action: &'a ActionId, components: &mut Vec<&'a str>) {
+    if let ActionId::Render(RenderAction::Archetype(options)) = action {
+        components.push(options.source());
+    }
+    for action in action.nested_actions() {
+        collect_components(action, components);
+    }
+}
+
+/// Local archetypes are reached through paths relative to each other, so are named by their canonical path to be
+/// recognised wherever they are used.
+fn source_name(source: &Source) -> String {
+    match source {
+        Source::LocalDirectory { path } => fs::canonicalize(path)
+            .unwrap_or_else(|_| path.clone())
+            .to_string_lossy()
+            .into_owned(),
+        _ => source.source().to_owned(),
+    }
+}
+
+fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use indoc::indoc;
+
+    use crate::Archetect;
+
+    use super::graph;
+
+    fn write_archetype(directory: &Path, script: &str) {
+        fs::create_dir_all(directory).unwrap();
+        fs::write(directory.join("archetype.yml"), script).unwrap();
+    }
+
+    #[test]
+    fn test_graph() {
+        let root = tempfile::tempdir().unwrap();
+        write_archetype(
+            &root.path().join("service"),
+            indoc! {r#"
+                version: 1.2.0
+                script:
+                  - render:
+                      archetype:
+                        source: ../rest
+                  - render:
+                      archetype:
+                        source: "../{{ database }}"
+                  - if:
+                      is-true: "{{ grpc }}"
+                      then:
+                        - render:
+                            archetype:
+                              source: ../grpc
+            "#},
+        );
+        let common = indoc! {r#"
+            script:
+              - render:
+                  archetype:
+                    source: ../common
+        "#};
+        write_archetype(&root.path().join("rest"), common);
+        write_archetype(&root.path().join("grpc"), common);
+        write_archetype(&root.path().join("common"), "script: []\n");
+
+        let archetect = Archetect::builder().build().unwrap();
+        let archetype = archetect
+            .load_archetype(root.path().join("service").to_str().unwrap(), None)
+            .unwrap();
+        let graph = graph(&archetect, &archetype).unwrap();
+
+        let names: Vec<String> = graph
+            .nodes()
+            .iter()
+            .map(|node| {
+                Path::new(node.source())
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            })
+            .collect();
+        assert_eq!(names, vec!["service", "rest", "common", "{{ database }}", "grpc"]);
+        assert_eq!(graph.nodes()[0].version(), Some("1.2.0"));
+        assert!(!graph.nodes()[3].resolved());
+        assert_eq!(graph.edges(), &[(1, 2), (0, 1), (0, 3), (4, 2), (0, 4)]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("n3 [label=\"../{{ database }}\\n(templated)\", style=dashed];"));
+        assert!(dot.contains("    n0 -> n4;\n"));
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("graph TD\n"));
+        assert!(mermaid.contains("    n4 --> n2\n"));
+    }
+}
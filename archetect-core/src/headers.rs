@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use linked_hash_map::LinkedHashMap;
+use log::warn;
+
+use crate::managed_block::CommentSyntax;
+
+/// A templated header, such as a license notice, that is prepended as a comment to every rendered file matching one
+/// of its patterns.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HeadersConfig {
+    contents: String,
+    patterns: Vec<String>,
+    /// Comment styles by file extension, such as `tf: "#"` or `vue: "<!-- -->"`, overriding the built in styles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<LinkedHashMap<String, String>>,
+}
+
+impl HeadersConfig {
+    pub fn new<C: Into<String>>(contents: C) -> HeadersConfig {
+        HeadersConfig {
+            contents: contents.into(),
+            patterns: vec![],
+            comments: None,
+        }
+    }
+
+    pub fn with_pattern<P: Into<String>>(mut self, pattern: P) -> HeadersConfig {
+        self.add_pattern(pattern);
+        self
+    }
+
+    pub fn add_pattern<P: Into<String>>(&mut self, pattern: P) {
+        self.patterns.push(pattern.into());
+    }
+
+    pub fn with_comment_style<E: Into<String>, S: Into<String>>(mut self, extension: E, style: S) -> HeadersConfig {
+        self.comments
+            .get_or_insert_with(LinkedHashMap::new)
+            .insert(extension.into(), style.into());
+        self
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether a rendered destination should receive the header.
+    pub fn matches<P: AsRef<Path>>(&self, destination: P) -> bool {
+        let destination = destination.as_ref();
+        self.patterns.iter().any(|pattern| match glob::Pattern::new(pattern) {
+            Ok(pattern) => pattern.matches_path(destination),
+            Err(error) => {
+                warn!("Ignoring invalid header pattern `{}`: {}", pattern, error);
+                false
+            }
+        })
+    }
+
+    /// The comment syntax to write the header in for a destination, preferring a configured style for its extension.
+    pub fn comment_syntax<P: AsRef<Path>>(&self, destination: P) -> CommentSyntax {
+        let destination = destination.as_ref();
+        let extension = destination.extension().and_then(|extension| extension.to_str());
+        let style = match (&self.comments, extension) {
+            (Some(comments), Some(extension)) => comments.get(extension),
+            _ => None,
+        };
+        match style.map(|style| (style, CommentSyntax::parse(style))) {
+            Some((_, Some(syntax))) => syntax,
+            Some((style, None)) => {
+                warn!("Ignoring invalid comment style `{}` for headers", style);
+                CommentSyntax::for_path(destination)
+            }
+            None => CommentSyntax::for_path(destination),
+        }
+    }
+}
+
+/// Prepends `header` to `contents` as a comment, after any shebang or XML declaration, separated from the contents by
+/// a blank line. Contents that already begin with the header are returned unchanged.
+pub fn prepend_header(contents: &str, header: &str, syntax: &CommentSyntax) -> String {
+    let mut header = header
+        .trim_end_matches('\n')
+        .lines()
+        .map(|line| syntax.comment(line))
+        .collect::<Vec<String>>()
+        .join("\n");
+    header.push('\n');
+
+    let preamble = if contents.starts_with("#!") || contents.starts_with("<?xml") {
+        contents.find('\n').map(|index| index + 1).unwrap_or(contents.len())
+    } else {
+        0
+    };
+    let (preamble, body) = contents.split_at(preamble);
+    if body.starts_with(&header) {
+        return contents.to_owned();
+    }
+
+    let mut result = String::with_capacity(contents.len() + header.len() + 1);
+    result.push_str(preamble);
+    if !preamble.is_empty() && !preamble.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(&header);
+    if !body.is_empty() {
+        result.push('\n');
+    }
+    result.push_str(body);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepend_header() {
+        let header = "Copyright 2024 Acme\n\nLicensed under MIT\n";
+        let expected = "// Copyright 2024 Acme\n//\n// Licensed under MIT\n\nfn main() {}\n";
+        assert_eq!(prepend_header("fn main() {}\n", header, &CommentSyntax::SLASHES), expected);
+        assert_eq!(prepend_header(expected, header, &CommentSyntax::SLASHES), expected);
+
+        assert_eq!(
+            prepend_header("#!/bin/sh\necho hi\n", "MIT", &CommentSyntax::HASH),
+            "#!/bin/sh\n# MIT\n\necho hi\n"
+        );
+        assert_eq!(
+            prepend_header("<?xml version=\"1.0\"?>\n<project/>\n", "MIT", &CommentSyntax::XML),
+            "<?xml version=\"1.0\"?>\n<!-- MIT -->\n\n<project/>\n"
+        );
+    }
+
+    #[test]
+    fn test_headers_config() {
+        let headers = HeadersConfig::new("MIT")
+            .with_pattern("*.rs")
+            .with_pattern("*/Dockerfile")
+            .with_comment_style("rs", "/* */");
+
+        assert!(headers.matches("service/src/main.rs"));
+        assert!(headers.matches("service/Dockerfile"));
+        assert!(!headers.matches("service/README.md"));
+
+        assert_eq!(headers.comment_syntax("src/main.rs"), CommentSyntax::C_BLOCK);
+        assert_eq!(headers.comment_syntax("Dockerfile"), CommentSyntax::HASH);
+    }
+}
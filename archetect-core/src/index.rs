@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::RuleAction;
+
+/// How much of a file is read to tell whether it is binary.
+pub(crate) const BINARY_SNIFF_LEN: usize = 8000;
+
+/// A file within a source directory, as found before rendering.
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    path: PathBuf,
+    size: u64,
+    binary: bool,
+    action: RuleAction,
+}
+
+impl IndexedFile {
+    pub(crate) fn new(path: PathBuf, size: u64, binary: bool, action: RuleAction) -> IndexedFile {
+        IndexedFile {
+            path,
+            size,
+            binary,
+            action,
+        }
+    }
+
+    /// The file's path within the archetype.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Whether the file looks binary, having a NUL byte near its start.
+    pub fn binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Whether the file is rendered, copied, or skipped, as the archetype's source rules say.
+    pub fn action(&self) -> &RuleAction {
+        &self.action
+    }
+
+    /// Whether the file is written to the destination, rather than skipped.
+    pub fn is_written(&self) -> bool {
+        !matches!(self.action, RuleAction::SKIP)
+    }
+}
+
+/// The files of a source directory that a render selects, found in one pass before any are rendered, so that the
+/// work ahead is known up front for progress and planning. Contents are not read, beyond enough of each file to tell
+/// whether it is binary. Files are in the order they are rendered.
+#[derive(Debug, Clone, Default)]
+pub struct SourceIndex {
+    files: Vec<IndexedFile>,
+    directories: usize,
+}
+
+impl SourceIndex {
+    pub(crate) fn add_file(&mut self, file: IndexedFile) {
+        self.files.push(file);
+    }
+
+    pub(crate) fn add_directory(&mut self) {
+        self.directories += 1;
+    }
+
+    pub fn files(&self) -> &[IndexedFile] {
+        &self.files
+    }
+
+    /// The number of directories within the source directory that are rendered.
+    pub fn directories(&self) -> usize {
+        self.directories
+    }
+
+    /// The files written to the destination.
+    pub fn written(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.files.iter().filter(|file| file.is_written())
+    }
+
+    /// The combined size of the files written to the destination.
+    pub fn written_size(&self) -> u64 {
+        self.written().map(|file| file.size).sum()
+    }
+}
+
+/// Whether `head`, the start of a file, looks binary.
+pub(crate) fn is_binary(head: &[u8]) -> bool {
+    head.iter().take(BINARY_SNIFF_LEN).any(|byte| *byte == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::config::{Pattern, RuleAction, RuleConfig};
+    use crate::rules::RulesContext;
+    use crate::Archetect;
+
+    #[test]
+    fn test_index_directory() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("src")).unwrap();
+        fs::create_dir_all(source.path().join("build")).unwrap();
+        fs::write(source.path().join("README.md"), "# {{ name }}\n").unwrap();
+        fs::write(source.path().join("src").join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        fs::write(source.path().join("build").join("output.log"), "log\n").unwrap();
+
+        let mut rules_context = RulesContext::new();
+        let mut rules = LinkedHashMap::new();
+        rules.insert(
+            "logs".to_owned(),
+            RuleConfig::new()
+                .with_pattern(Pattern::GLOB("**/*.log".to_owned()))
+                .with_action(RuleAction::SKIP),
+        );
+        rules_context.insert_path_rules(&rules);
+
+        let archetect = Archetect::builder().build().unwrap();
+        let index = archetect.index_directory(source.path(), &rules_context).unwrap();
+        let paths = index.files().iter().map(|file| file.path().to_owned()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                source.path().join("README.md"),
+                source.path().join("build").join("output.log"),
+                source.path().join("src").join("logo.png"),
+            ]
+        );
+        assert_eq!(index.directories(), 2);
+        assert!(!index.files()[0].binary());
+        assert!(index.files()[2].binary());
+        assert!(!index.files()[1].is_written());
+        assert_eq!(index.written().count(), 2);
+        assert_eq!(index.written_size(), 13 + 6);
+    }
+}
@@ -1,10 +1,31 @@
-use crate::config::{Catalog, CatalogEntry, CatalogError};
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(feature = "catalog")]
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
 
+#[cfg(feature = "catalog")]
+use crate::config::{Catalog, CatalogEntry, CatalogError};
+use crate::config::VariableType;
+use crate::output::OutputStyle;
+#[cfg(feature = "catalog")]
 use crate::source::{Source};
+#[cfg(feature = "catalog")]
 use crate::Archetect;
+use crate::ArchetectError;
 use crate::vendor::read_input::shortcut::input;
-use crate::vendor::read_input::InputBuild;
-use std::collections::{HashMap, HashSet};
+use crate::vendor::read_input::{InputBuild, InputBuilder};
+
+/// Lists with at least this many entries are filtered by typing, rather than selected from by number, when stderr is a
+/// terminal.
+#[cfg(feature = "fuzzy")]
+const FUZZY_SELECT_THRESHOLD: usize = 10;
+
+/// Answers accepted for boolean variables: the first four are true, and the rest false.
+pub(crate) const ACCEPTABLE_BOOLEANS: [&str; 8] = ["y", "yes", "true", "t", "n", "no", "false", "f"];
 
 pub fn you_are_sure(message: &str) -> bool {
     input::<bool>()
@@ -14,6 +35,549 @@ pub fn you_are_sure(message: &str) -> bool {
         .get()
 }
 
+/// A variable without an acceptable answer, for a [`PromptProvider`] to ask for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prompt {
+    identifier: String,
+    message: String,
+    variable_type: VariableType,
+    default: Option<String>,
+    required: bool,
+    group: Option<String>,
+    help: Option<String>,
+    example: Option<String>,
+    docs_url: Option<String>,
+}
+
+impl Prompt {
+    pub fn new<I: Into<String>, M: Into<String>>(identifier: I, message: M) -> Prompt {
+        Prompt {
+            identifier: identifier.into(),
+            message: message.into(),
+            variable_type: VariableType::String,
+            default: None,
+            required: true,
+            group: None,
+            help: None,
+            example: None,
+            docs_url: None,
+        }
+    }
+
+    pub fn with_variable_type(mut self, variable_type: VariableType) -> Prompt {
+        self.variable_type = variable_type;
+        self
+    }
+
+    pub fn with_default<D: Into<String>>(mut self, default: D) -> Prompt {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn with_required(mut self, required: bool) -> Prompt {
+        self.required = required;
+        self
+    }
+
+    pub fn with_group<G: Into<String>>(mut self, heading: G) -> Prompt {
+        self.group = Some(heading.into());
+        self
+    }
+
+    pub fn with_help<H: Into<String>>(mut self, help: H) -> Prompt {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_example<E: Into<String>>(mut self, example: E) -> Prompt {
+        self.example = Some(example.into());
+        self
+    }
+
+    pub fn with_docs_url<U: Into<String>>(mut self, docs_url: U) -> Prompt {
+        self.docs_url = Some(docs_url.into());
+        self
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// The variable's rendered prompt, such as `Project Name:`, or its identifier when it has none.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn variable_type(&self) -> &VariableType {
+        &self.variable_type
+    }
+
+    /// The value to suggest, already rendered.
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// The heading of the group the variable belongs to, such as `Persistence`.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    pub fn example(&self) -> Option<&str> {
+        self.example.as_deref()
+    }
+
+    pub fn docs_url(&self) -> Option<&str> {
+        self.docs_url.as_deref()
+    }
+
+    /// The help, example, and documentation link, one per line, or None if the variable has none of them.
+    pub fn help_text(&self) -> Option<String> {
+        let lines = [
+            self.help().map(|help| help.trim().to_owned()),
+            self.example().map(|example| format!("Example: {}", example)),
+            self.docs_url().map(|docs_url| format!("Docs: {}", docs_url)),
+        ];
+        let lines = lines.iter().flatten().cloned().collect::<Vec<_>>();
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
+    /// Converts a textual answer, such as one typed into a form, to a value of the variable's type, or None if it is
+    /// not acceptable. Lists are separated by commas.
+    pub fn parse(&self, answer: &str) -> Option<Value> {
+        match &self.variable_type {
+            VariableType::String if self.required && answer.is_empty() => None,
+            VariableType::String => Some(Value::String(answer.to_owned())),
+            VariableType::Int => answer.trim().parse::<i64>().ok().map(Value::from),
+            VariableType::Bool | VariableType::Switch => parse_bool(answer).map(Value::Bool),
+            VariableType::Enum(options) => options
+                .iter()
+                .find(|option| option.as_str() == answer)
+                .map(|option| Value::String(option.to_owned())),
+            VariableType::Array => {
+                let items = answer
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_owned()))
+                    .collect::<Vec<_>>();
+                if items.is_empty() && self.required {
+                    None
+                } else {
+                    Some(Value::Array(items))
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_bool(answer: &str) -> Option<bool> {
+    let answer = answer.trim().to_lowercase();
+    ACCEPTABLE_BOOLEANS
+        .iter()
+        .position(|acceptable| acceptable == &answer)
+        .map(|position| position < 4)
+}
+
+/// Answers prompts for variables that have no acceptable answer, on behalf of whatever is driving Archetect: the
+/// terminal by default, or a form in an IDE or web service when embedded.
+pub trait PromptProvider {
+    /// Answers `prompt` with a value of its variable's type, or None to leave the variable unset.
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError>;
+}
+
+/// Shared providers, so that embedders can keep a handle on a provider given to Archetect.
+impl<P: PromptProvider + ?Sized> PromptProvider for Rc<P> {
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError> {
+        self.as_ref().prompt(prompt)
+    }
+}
+
+/// Prompts on stderr, reading answers from stdin until they are acceptable. Each group's heading is printed before
+/// its first prompt.
+#[derive(Debug, Default)]
+pub struct TerminalPromptProvider {
+    group: RefCell<Option<String>>,
+    style: OutputStyle,
+}
+
+impl TerminalPromptProvider {
+    pub fn new() -> TerminalPromptProvider {
+        TerminalPromptProvider::default()
+    }
+
+    /// Whether selections use color and unicode symbols.
+    pub fn with_style(mut self, style: OutputStyle) -> TerminalPromptProvider {
+        self.style = style;
+        self
+    }
+}
+
+impl PromptProvider for TerminalPromptProvider {
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError> {
+        let group = prompt.group().map(str::to_owned);
+        if group.is_some() && group != *self.group.borrow() {
+            let heading = group.as_deref().unwrap_or_default();
+            eprintln!("\n{}\n{}", heading, "-".repeat(heading.chars().count()));
+        }
+        self.group.replace(group);
+
+        let help = prompt.help_text();
+        let mut message = format!("{} ", prompt.message());
+        if help.is_some() {
+            message.push_str("(? for help) ");
+        }
+        let help = help.as_deref();
+        let value = match prompt.variable_type() {
+            VariableType::Enum(options) => prompt_for_enum(message, options, prompt.default(), help, self.style),
+            VariableType::Bool | VariableType::Switch => prompt_for_bool(message, prompt.default(), help),
+            VariableType::Int => prompt_for_int(message, prompt.default(), help),
+            VariableType::Array => prompt_for_list(message, prompt, help),
+            VariableType::String => prompt_for_string(message, prompt.default(), prompt.required(), help),
+        };
+        Ok(value)
+    }
+}
+
+/// Reads answers as text, showing `help` rather than accepting an answer of `?`.
+fn text_input(help: Option<&str>) -> InputBuilder<String> {
+    let input_builder = input::<String>().prompting_on_stderr();
+    match help {
+        Some(help) => input_builder.add_err_test(|value: &String| value != "?", help),
+        None => input_builder,
+    }
+}
+
+fn prompt_for_string(mut prompt: String, default: Option<&str>, required: bool, help: Option<&str>) -> Option<Value> {
+    if let Some(default) = default {
+        prompt.push_str(format!("[{}] ", default).as_str());
+    };
+    let mut input_builder = text_input(help).msg(&prompt).repeat_msg(&prompt);
+
+    if required {
+        input_builder = input_builder
+            .add_test(|value| !value.is_empty())
+            .err("Please provide a value.");
+    }
+
+    let value = if let Some(default) = default {
+        input_builder.default(default.to_owned()).get()
+    } else {
+        input_builder.get()
+    };
+    Some(Value::String(value))
+}
+
+fn prompt_for_int(mut prompt: String, default: Option<&str>, help: Option<&str>) -> Option<Value> {
+    let default = default.and_then(|value| value.parse::<i64>().ok());
+
+    if let Some(default) = default {
+        prompt.push_str(format!("[{}] ", default).as_str());
+    }
+
+    let input_builder = text_input(help)
+        .msg(&prompt)
+        .add_test(|value| value.parse::<i64>().is_ok())
+        .err("Please specify an integer.")
+        .repeat_msg(&prompt);
+
+    let value = if let Some(default) = default {
+        input_builder.default(default.to_string()).get()
+    } else {
+        input_builder.get()
+    };
+
+    value.parse::<i64>().ok().map(Value::from)
+}
+
+fn prompt_for_bool(mut prompt: String, default: Option<&str>, help: Option<&str>) -> Option<Value> {
+    let default = default
+        .map(|value| value.to_lowercase())
+        .filter(|value| ACCEPTABLE_BOOLEANS.contains(&value.as_str()));
+
+    if let Some(default) = &default {
+        prompt.push_str(format!("[{}] ", default).as_str());
+    }
+
+    let input_builder = text_input(help)
+        .add_test(|value| parse_bool(value).is_some())
+        .msg(&prompt)
+        .err(format!("Please specify a value of {:?}.", ACCEPTABLE_BOOLEANS))
+        .repeat_msg(&prompt);
+
+    let value = if let Some(default) = default {
+        input_builder.default(default).get()
+    } else {
+        input_builder.get()
+    };
+
+    parse_bool(&value).map(Value::Bool)
+}
+
+fn prompt_for_list(mut message: String, prompt: &Prompt, help: Option<&str>) -> Option<Value> {
+    if let Some(default) = prompt.default() {
+        message.push_str(format!("[{}] ", default).as_str());
+    };
+
+    eprintln!("{}", &message);
+
+    let mut results = vec![];
+
+    loop {
+        let requirements_met = if let Some(default) = prompt.default() {
+            !default.trim().is_empty()
+        } else {
+            !results.is_empty()
+        };
+
+        let mut input_builder = text_input(help).msg(" - ").repeat_msg(" - ");
+
+        if prompt.required() {
+            input_builder = input_builder
+                .add_test(move |value| requirements_met || !value.trim().is_empty())
+                .err("This list requires at least one item.")
+        }
+        let item = input_builder.get();
+
+        if item.trim().is_empty() {
+            break;
+        }
+
+        results.push(Value::String(item));
+    }
+
+    if !results.is_empty() || !prompt.required() {
+        Some(Value::Array(results))
+    } else {
+        prompt.default().and_then(|default| prompt.parse(default))
+    }
+}
+
+fn prompt_for_enum(
+    prompt: String,
+    options: &[String],
+    default: Option<&str>,
+    help: Option<&str>,
+    style: OutputStyle,
+) -> Option<Value> {
+    let default = default.and_then(|default| options.iter().position(|option| option == default));
+    if let Some(index) = fuzzy_select(&prompt, options, default, style) {
+        return Some(Value::String(options[index].clone()));
+    }
+
+    eprintln!("{}", &prompt);
+
+    for (id, option) in options.iter().enumerate() {
+        eprintln!("{:>2}) {}", id + 1, option);
+    }
+
+    let mut message = String::from("Select an entry: ");
+    if let Some(index) = default {
+        message.push_str(format!("[{}] ", options[index]).as_str());
+    };
+
+    let choices = options.len();
+    let input_builder = text_input(help)
+        .msg(&message)
+        .add_test(move |value| value.parse::<usize>().is_ok_and(|choice| (1..=choices).contains(&choice)))
+        .err("Please enter the number of a selection from the list.")
+        .repeat_msg(&message);
+
+    let value = if let Some(index) = default {
+        input_builder.default((index + 1).to_string()).get()
+    } else {
+        input_builder.get()
+    };
+
+    let choice = value.parse::<usize>().ok()?;
+    Some(Value::String(options[choice - 1].clone()))
+}
+
+/// Selects from `items` by fuzzy type-ahead filtering, returning the index of the selection. Returns `None` for short
+/// lists, when stderr is not a terminal, or when the selection is abandoned with Escape, for the caller to fall back to
+/// a numbered list. The colorful theme, with its unicode symbols, is only used where both are.
+#[cfg(feature = "fuzzy")]
+fn fuzzy_select(prompt: &str, items: &[String], default: Option<usize>, style: OutputStyle) -> Option<usize> {
+    use std::io::IsTerminal;
+
+    use dialoguer::console::Term;
+    use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+    use dialoguer::FuzzySelect;
+
+    if items.len() < FUZZY_SELECT_THRESHOLD || !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let colorful = ColorfulTheme::default();
+    let theme: &dyn Theme = if style.color() && style.unicode() {
+        &colorful
+    } else {
+        &SimpleTheme
+    };
+    let mut select = FuzzySelect::with_theme(theme)
+        .with_prompt(prompt.trim())
+        .items(items)
+        .max_length(FUZZY_SELECT_THRESHOLD);
+    if let Some(default) = default {
+        select = select.default(default);
+    }
+    select.interact_on_opt(&Term::stderr()).ok().flatten()
+}
+
+#[cfg(not(feature = "fuzzy"))]
+fn fuzzy_select(_prompt: &str, _items: &[String], _default: Option<usize>, _style: OutputStyle) -> Option<usize> {
+    None
+}
+
+/// Answers prompts from answers supplied up front, falling back to their defaults, for tests and for embedders that
+/// collect answers before rendering. Prompts that cannot be answered are errors, rather than being asked again.
+#[derive(Debug, Default)]
+pub struct ScriptedPromptProvider {
+    answers: HashMap<String, String>,
+    prompted: RefCell<Vec<Prompt>>,
+}
+
+impl ScriptedPromptProvider {
+    pub fn new() -> ScriptedPromptProvider {
+        ScriptedPromptProvider::default()
+    }
+
+    pub fn with_answer<I: Into<String>, V: Into<String>>(mut self, identifier: I, answer: V) -> ScriptedPromptProvider {
+        self.add_answer(identifier, answer);
+        self
+    }
+
+    pub fn add_answer<I: Into<String>, V: Into<String>>(&mut self, identifier: I, answer: V) {
+        self.answers.insert(identifier.into(), answer.into());
+    }
+
+    /// The prompts asked so far, in order.
+    pub fn prompted(&self) -> Vec<Prompt> {
+        self.prompted.borrow().clone()
+    }
+}
+
+impl PromptProvider for ScriptedPromptProvider {
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError> {
+        self.prompted.borrow_mut().push(prompt.clone());
+        let answer = self
+            .answers
+            .get(prompt.identifier())
+            .map(|answer| answer.as_str())
+            .or_else(|| prompt.default())
+            .ok_or_else(|| ArchetectError::UnansweredPrompt(prompt.identifier().to_owned()))?;
+        match prompt.parse(answer) {
+            Some(value) => Ok(Some(value)),
+            None => Err(ArchetectError::InvalidAnswer {
+                identifier: prompt.identifier().to_owned(),
+                answer: answer.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Exchanges prompts and answers as lines of JSON, for programs that wrap Archetect and answer its prompts themselves,
+/// without a terminal. Each prompt is written as a line such as
+///
+/// ```json
+/// {"type":"prompt","identifier":"language","message":"Language:","variable_type":"enum","options":["rust","go"],"default":"rust","required":true}
+/// ```
+///
+/// and answered by a line such as `{"answer": "go"}`. Answers may be strings, as they would be typed, or JSON values
+/// of the variable's type. A null or missing answer accepts the default. An unacceptable answer is reported with a
+/// line such as `{"type":"invalid","identifier":"language","message":"..."}`, and the prompt is asked again.
+pub struct JsonLinesPromptProvider<R, W> {
+    reader: RefCell<R>,
+    writer: RefCell<W>,
+}
+
+impl<R: BufRead, W: Write> JsonLinesPromptProvider<R, W> {
+    pub fn new(reader: R, writer: W) -> JsonLinesPromptProvider<R, W> {
+        JsonLinesPromptProvider {
+            reader: RefCell::new(reader),
+            writer: RefCell::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader.into_inner(), self.writer.into_inner())
+    }
+
+    fn write_line(&self, message: &Value) -> Result<(), ArchetectError> {
+        let mut writer = self.writer.borrow_mut();
+        writeln!(writer, "{}", message)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<R: BufRead, W: Write> PromptProvider for JsonLinesPromptProvider<R, W> {
+    fn prompt(&self, prompt: &Prompt) -> Result<Option<Value>, ArchetectError> {
+        let mut message = json!({
+            "type": "prompt",
+            "identifier": prompt.identifier(),
+            "message": prompt.message(),
+            "variable_type": prompt.variable_type().name(),
+            "default": prompt.default(),
+            "required": prompt.required(),
+            "group": prompt.group(),
+            "help": prompt.help(),
+            "example": prompt.example(),
+            "docs_url": prompt.docs_url(),
+        });
+        if let VariableType::Enum(options) = prompt.variable_type() {
+            message["options"] = json!(options);
+        }
+        self.write_line(&message)?;
+
+        loop {
+            let mut line = String::new();
+            if self.reader.borrow_mut().read_line(&mut line)? == 0 {
+                return Err(ArchetectError::UnansweredPrompt(prompt.identifier().to_owned()));
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let problem = match serde_json::from_str::<Value>(&line) {
+                Ok(Value::Object(mut response)) => {
+                    let answer = match response.remove("answer").unwrap_or(Value::Null) {
+                        Value::Null => prompt.default().unwrap_or_default().to_owned(),
+                        Value::String(answer) => answer,
+                        Value::Array(items) => items
+                            .iter()
+                            .map(|item| item.as_str().map(str::to_owned).unwrap_or_else(|| item.to_string()))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        answer => answer.to_string(),
+                    };
+                    match prompt.parse(&answer) {
+                        Some(value) => return Ok(Some(value)),
+                        None => format!("{:?} is not a valid {} answer", answer, prompt.variable_type().name()),
+                    }
+                }
+                _ => "Expected an object such as {\"answer\": \"...\"}".to_owned(),
+            };
+            self.write_line(&json!({
+                "type": "invalid",
+                "identifier": prompt.identifier(),
+                "message": problem,
+            }))?;
+        }
+    }
+}
+
+#[cfg(feature = "catalog")]
 pub fn select_from_catalog(
     archetect: &Archetect,
     catalog: &Catalog,
@@ -49,8 +613,9 @@ pub fn select_from_catalog(
     }
 }
 
+#[cfg(feature = "catalog")]
 pub fn select_from_entries(
-    _archetect: &Archetect,
+    archetect: &Archetect,
     mut entry_items: Vec<CatalogEntry>,
 ) -> Result<CatalogEntry, CatalogError> {
     if entry_items.is_empty() {
@@ -58,6 +623,24 @@ pub fn select_from_entries(
     }
 
     loop {
+        if let Some(index) = fuzzy_select(
+            "Select an entry:",
+            &entry_labels(&entry_items),
+            None,
+            archetect.output_style(),
+        ) {
+            match entry_items.swap_remove(index) {
+                CatalogEntry::Group {
+                    description: _,
+                    entries,
+                } => {
+                    entry_items = entries;
+                    continue;
+                }
+                choice => return Ok(choice),
+            }
+        }
+
         let mut choices = entry_items
             .iter()
             .enumerate()
@@ -97,3 +680,241 @@ pub fn select_from_entries(
         }
     }
 }
+
+/// Entry descriptions, followed by a preview of each entry: the source of an archetype or catalog, or the size of a
+/// group.
+#[cfg(feature = "catalog")]
+fn entry_labels(entries: &[CatalogEntry]) -> Vec<String> {
+    let width = entries.iter().map(|entry| entry.description().len()).max().unwrap_or_default();
+    entries
+        .iter()
+        .map(|entry| match entry {
+            CatalogEntry::Group { description, entries } => {
+                format!("{:<width$}  ({} entries)", description, entries.len(), width = width)
+            }
+            CatalogEntry::Catalog { description, source } | CatalogEntry::Archetype { description, source } => {
+                format!("{:<width$}  {}", description, source, width = width)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use linked_hash_map::LinkedHashMap;
+    use serde_json::Value;
+
+    use crate::config::VariableType;
+    use crate::file_tree::FileTree;
+    use crate::{Archetect, ArchetectError, Archetype};
+
+    use super::{JsonLinesPromptProvider, Prompt, PromptProvider, ScriptedPromptProvider};
+
+    #[test]
+    fn test_parse() {
+        let prompt = Prompt::new("value", "Value:");
+        assert_eq!(prompt.parse("billing"), Some(Value::from("billing")));
+        assert_eq!(prompt.parse(""), None);
+        assert_eq!(prompt.clone().with_required(false).parse(""), Some(Value::from("")));
+
+        let prompt = prompt.with_variable_type(VariableType::Bool);
+        assert_eq!(prompt.parse("Yes"), Some(Value::Bool(true)));
+        assert_eq!(prompt.parse("f"), Some(Value::Bool(false)));
+        assert_eq!(prompt.parse("maybe"), None);
+
+        let prompt = prompt.with_variable_type(VariableType::Int);
+        assert_eq!(prompt.parse("8080"), Some(Value::from(8080)));
+        assert_eq!(prompt.parse("eighty"), None);
+
+        let prompt = prompt.with_variable_type(VariableType::Enum(vec!["maven".to_owned(), "gradle".to_owned()]));
+        assert_eq!(prompt.parse("gradle"), Some(Value::from("gradle")));
+        assert_eq!(prompt.parse("ant"), None);
+
+        let prompt = prompt.with_variable_type(VariableType::Array);
+        assert_eq!(prompt.parse("a, b,"), Some(Value::from(vec!["a", "b"])));
+        assert_eq!(prompt.parse(" "), None);
+    }
+
+    #[test]
+    fn test_help_text() {
+        let prompt = Prompt::new("port", "Port:");
+        assert_eq!(prompt.help_text(), None);
+
+        let prompt = prompt
+            .with_help("The port the service listens on.\n")
+            .with_example("8080")
+            .with_docs_url("https://example.com/ports");
+        assert_eq!(
+            prompt.help_text().as_deref(),
+            Some("The port the service listens on.\nExample: 8080\nDocs: https://example.com/ports")
+        );
+        assert_eq!(
+            Prompt::new("port", "Port:").with_example("8080").help_text().as_deref(),
+            Some("Example: 8080")
+        );
+    }
+
+    #[test]
+    fn test_scripted_prompts() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n",
+                "      name:\n        prompt: \"{{ 'Service' }} Name:\"\n",
+                "      port:\n        type: int\n        default: \"8080\"\n",
+                "  - render:\n      directory:\n        source: contents\n",
+            ),
+        );
+        files.insert("contents/{{ name }}.txt", "{{ port + 1 }}");
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let provider = Rc::new(ScriptedPromptProvider::new().with_answer("name", "billing"));
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(provider.clone())
+            .build()
+            .unwrap();
+        let rendered = archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+        assert_eq!(rendered.get_str("billing.txt"), Some("8081"));
+
+        let prompted = provider.prompted();
+        assert_eq!(prompted[0], Prompt::new("name", "Service Name:"));
+        assert_eq!(prompted[1].default(), Some("8080"));
+
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(ScriptedPromptProvider::new())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()),
+            Err(ArchetectError::UnansweredPrompt(identifier)) if identifier == "name"
+        ));
+
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(ScriptedPromptProvider::new().with_answer("name", "billing").with_answer("port", "http"))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()),
+            Err(ArchetectError::InvalidAnswer { identifier, .. }) if identifier == "port"
+        ));
+    }
+
+    #[cfg(feature = "catalog")]
+    #[test]
+    fn test_entry_labels() {
+        use crate::config::CatalogEntry;
+
+        let entries = vec![
+            CatalogEntry::Group {
+                description: "Rust".to_owned(),
+                entries: vec![],
+            },
+            CatalogEntry::Archetype {
+                description: "Python CLI".to_owned(),
+                source: "https://github.com/archetect/archetype-python-cli.git".to_owned(),
+            },
+        ];
+        assert_eq!(
+            super::entry_labels(&entries),
+            vec![
+                "Rust        (0 entries)",
+                "Python CLI  https://github.com/archetect/archetype-python-cli.git",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_lines_prompts() {
+        let responses = concat!(
+            "{\"answer\": \"many\"}\n",
+            "\n",
+            "not json\n",
+            "{\"answer\": 3}\n",
+            "{}\n",
+        );
+        let provider = JsonLinesPromptProvider::new(responses.as_bytes(), Vec::new());
+
+        let replicas = Prompt::new("replicas", "Replicas:").with_variable_type(VariableType::Int);
+        assert_eq!(provider.prompt(&replicas).unwrap(), Some(Value::from(3)));
+        let language = Prompt::new("language", "Language:")
+            .with_variable_type(VariableType::Enum(vec!["rust".to_owned(), "go".to_owned()]))
+            .with_default("rust");
+        assert_eq!(provider.prompt(&language).unwrap(), Some(Value::from("rust")));
+        assert!(matches!(
+            provider.prompt(&replicas),
+            Err(ArchetectError::UnansweredPrompt(identifier)) if identifier == "replicas"
+        ));
+
+        let (_, output) = provider.into_inner();
+        let output: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(output.len(), 5);
+        assert_eq!(output[0]["type"], "prompt");
+        assert_eq!(output[0]["variable_type"], "int");
+        assert_eq!(output[1]["message"], "\"many\" is not a valid int answer");
+        assert_eq!(output[2]["type"], "invalid");
+        assert_eq!(output[3]["options"], serde_json::json!(["rust", "go"]));
+        assert_eq!(output[4]["identifier"], "replicas");
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedOutput {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_with_print() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "script:\n  - set:\n      name:\n        prompt: \"Service Name:\"\n",
+                "  - print: \"Rendering {{ name }}\"\n",
+                "  - set:\n      port:\n        type: int\n",
+            ),
+        );
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let protocol = SharedOutput::default();
+        let printed = SharedOutput::default();
+        let responses = "{\"answer\": \"billing\"}\n{\"answer\": 8080}\n";
+        let mut archetect = Archetect::builder()
+            .with_prompt_provider(JsonLinesPromptProvider::new(responses.as_bytes(), protocol.clone()))
+            .with_print_output(printed.clone())
+            .build()
+            .unwrap();
+        archetype.render_in_memory(&mut archetect, &LinkedHashMap::new()).unwrap();
+
+        let messages: Vec<Value> = protocol
+            .contents()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["identifier"], "name");
+        assert_eq!(messages[1]["identifier"], "port");
+        assert_eq!(printed.contents(), "Rendering billing\n");
+    }
+}
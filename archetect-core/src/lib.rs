@@ -9,7 +9,7 @@ extern crate pretty_assertions;
 extern crate serde_derive;
 
 pub use crate::archetype::{Archetype, ArchetypeError};
-pub use crate::core::Archetect;
+pub use crate::core::{Archetect, DEFAULT_CONTEXT_SIZE_LIMIT, DEFAULT_MAX_DEPTH, DEFAULT_SOURCE_PREFIXES};
 pub use crate::errors::{ArchetectError, RenderError};
 
 mod core;
@@ -17,13 +17,43 @@ mod errors;
 
 pub mod actions;
 pub mod archetype;
+pub mod backup;
+pub mod batch;
+#[cfg(feature = "git")]
+pub mod cache;
+pub mod compare;
 pub mod config;
+pub mod docs;
+pub mod editorconfig;
+pub mod encoding;
+pub mod events;
+pub mod file_tree;
+pub mod formatters;
+pub mod graph;
+pub mod headers;
+pub mod index;
 pub mod input;
+pub mod managed_block;
+pub mod metadata;
+#[cfg(feature = "git")]
+pub mod outdated;
+pub mod output;
+pub mod protect;
+pub mod provenance;
 pub mod rendering;
+pub mod report;
 pub mod requirements;
 pub mod rules;
+pub mod sandbox;
+pub mod scope;
+pub mod selection;
+pub mod setup;
 pub mod system;
+pub mod target;
+pub mod upgrade;
+pub mod validation;
 pub mod vendor;
+pub mod warnings;
 pub mod source;
 mod utils;
 
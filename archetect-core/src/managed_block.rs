@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// How line comments are written in a file, used to mark the blocks of a file that Archetect manages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommentSyntax {
+    prefix: Cow<'static, str>,
+    suffix: Cow<'static, str>,
+}
+
+impl CommentSyntax {
+    pub const HASH: CommentSyntax = CommentSyntax::line("#");
+    pub const SLASHES: CommentSyntax = CommentSyntax::line("//");
+    pub const DASHES: CommentSyntax = CommentSyntax::line("--");
+    pub const SEMICOLON: CommentSyntax = CommentSyntax::line(";");
+    pub const C_BLOCK: CommentSyntax = CommentSyntax::block("/*", "*/");
+    pub const XML: CommentSyntax = CommentSyntax::block("<!--", "-->");
+
+    const fn line(prefix: &'static str) -> CommentSyntax {
+        CommentSyntax::block(prefix, "")
+    }
+
+    const fn block(prefix: &'static str, suffix: &'static str) -> CommentSyntax {
+        CommentSyntax {
+            prefix: Cow::Borrowed(prefix),
+            suffix: Cow::Borrowed(suffix),
+        }
+    }
+
+    /// Parses a comment style written as its delimiters separated by whitespace, such as `#` or `<!-- -->`.
+    pub fn parse(style: &str) -> Option<CommentSyntax> {
+        let mut delimiters = style.split_whitespace();
+        let prefix = delimiters.next()?.to_owned();
+        let suffix = delimiters.next().unwrap_or_default().to_owned();
+        if delimiters.next().is_some() {
+            return None;
+        }
+        Some(CommentSyntax {
+            prefix: Cow::Owned(prefix),
+            suffix: Cow::Owned(suffix),
+        })
+    }
+
+    /// Chooses the comment syntax for a file from its extension, falling back to `#`, which covers most
+    /// configuration files and dotfiles.
+    pub fn for_path<P: AsRef<Path>>(path: P) -> CommentSyntax {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("rs") | Some("c") | Some("h") | Some("cc") | Some("cpp") | Some("hpp") | Some("cs") | Some("java")
+            | Some("kt") | Some("kts") | Some("scala") | Some("groovy") | Some("gradle") | Some("go") | Some("js")
+            | Some("jsx") | Some("ts") | Some("tsx") | Some("swift") | Some("dart") | Some("proto") | Some("scss")
+            | Some("jsonc") => CommentSyntax::SLASHES,
+            Some("sql") | Some("lua") | Some("hs") | Some("elm") => CommentSyntax::DASHES,
+            Some("ini") | Some("clj") | Some("cljs") | Some("el") | Some("asm") => CommentSyntax::SEMICOLON,
+            Some("css") => CommentSyntax::C_BLOCK,
+            Some("md") | Some("markdown") | Some("html") | Some("htm") | Some("xml") | Some("svg") | Some("vue") => {
+                CommentSyntax::XML
+            }
+            _ => CommentSyntax::HASH,
+        }
+    }
+
+    /// Comments out a single line of text.
+    pub fn comment(&self, text: &str) -> String {
+        if self.suffix.is_empty() && text.is_empty() {
+            self.prefix.to_string()
+        } else if self.suffix.is_empty() {
+            format!("{} {}", self.prefix, text)
+        } else if text.is_empty() {
+            format!("{} {}", self.prefix, self.suffix)
+        } else {
+            format!("{} {} {}", self.prefix, text, self.suffix)
+        }
+    }
+
+    pub fn begin_marker(&self, id: &str) -> String {
+        self.comment(&format!("archetect:begin {}", id))
+    }
+
+    pub fn end_marker(&self) -> String {
+        self.comment("archetect:end")
+    }
+}
+
+/// A managed block whose begin marker has no matching end marker.
+#[derive(Debug, PartialEq)]
+pub struct UnterminatedBlock;
+
+/// Writes `contents` into the managed block `id` of `existing`, wrapped in begin and end markers. If the block is
+/// already present it is replaced in place, so applying the same block repeatedly leaves a single copy; otherwise the
+/// block is appended.
+pub fn upsert_block(
+    existing: &str,
+    id: &str,
+    contents: &str,
+    syntax: &CommentSyntax,
+) -> Result<String, UnterminatedBlock> {
+    let begin = syntax.begin_marker(id);
+    let end = syntax.end_marker();
+    let block = format!("{}\n{}\n{}\n", begin, contents.trim_end_matches('\n'), end);
+
+    let lines = existing.split_inclusive('\n').collect::<Vec<&str>>();
+    let start = lines.iter().position(|line| line.trim() == begin);
+    match start {
+        Some(start) => {
+            let finish = lines[start..]
+                .iter()
+                .position(|line| line.trim() == end)
+                .map(|offset| start + offset)
+                .ok_or(UnterminatedBlock)?;
+            let mut result = lines[..start].concat();
+            result.push_str(&block);
+            result.push_str(&lines[finish + 1..].concat());
+            Ok(result)
+        }
+        None => Ok(append(existing, &block)),
+    }
+}
+
+/// Appends `contents` to `existing`, starting it on a new line.
+pub fn append(existing: &str, contents: &str) -> String {
+    let mut result = existing.to_owned();
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(contents);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_syntax_for_path() {
+        assert_eq!(CommentSyntax::for_path(".gitignore").end_marker(), "# archetect:end");
+        assert_eq!(CommentSyntax::for_path("src/main.rs").begin_marker("mods"), "// archetect:begin mods");
+        assert_eq!(CommentSyntax::for_path("schema.SQL"), CommentSyntax::DASHES);
+        assert_eq!(CommentSyntax::for_path("README.md").end_marker(), "<!-- archetect:end -->");
+        assert_eq!(CommentSyntax::parse("<!-- -->"), Some(CommentSyntax::XML));
+        assert_eq!(CommentSyntax::parse(" ; "), Some(CommentSyntax::SEMICOLON));
+        assert_eq!(CommentSyntax::parse(""), None);
+    }
+
+    #[test]
+    fn test_upsert_block() {
+        let syntax = &CommentSyntax::HASH;
+        let first = upsert_block("target/", "rust", ".idea/\n", syntax).unwrap();
+        assert_eq!(first, "target/\n# archetect:begin rust\n.idea/\n# archetect:end\n");
+        assert_eq!(upsert_block(&first, "rust", ".idea/\n", syntax).unwrap(), first);
+
+        let existing = format!("{}*.log\n", first);
+        let updated = upsert_block(&existing, "rust", ".idea/\n*.iml", syntax).unwrap();
+        assert_eq!(
+            updated,
+            "target/\n# archetect:begin rust\n.idea/\n*.iml\n# archetect:end\n*.log\n"
+        );
+
+        let other = upsert_block(&updated, "node", "node_modules/", syntax).unwrap();
+        assert!(other.starts_with(&updated));
+        assert!(other.ends_with("# archetect:begin node\nnode_modules/\n# archetect:end\n"));
+
+        assert_eq!(
+            upsert_block("# archetect:begin rust\n.idea/\n", "rust", "", syntax),
+            Err(UnterminatedBlock)
+        );
+    }
+}
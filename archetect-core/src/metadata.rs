@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use linked_hash_map::LinkedHashMap;
+use serde_json::Value;
+
+use crate::config::AnswerInfo;
+use crate::vendor::tera::Context;
+
+pub const METADATA_FILE_NAME: &str = ".archetect-metadata.yml";
+
+/// Context keys populated by Archetect itself, rather than by answers or archetype variables.
+pub const BUILTIN_CONTEXT_KEYS: [&str; 5] = ["archetect", "archetype", "render", "loop", "item"];
+
+/// A record of what produced a rendered destination: the Archetect version, the archetype source and exact revision,
+/// and the answers in effect when rendering completed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RenderMetadata {
+    archetect: ArchetectMetadata,
+    archetype: ArchetypeMetadata,
+    timestamp: String,
+    #[serde(skip_serializing_if = "LinkedHashMap::is_empty", default)]
+    answers: LinkedHashMap<String, AnswerInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArchetectMetadata {
+    version: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArchetypeMetadata {
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    gitref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Error parsing render metadata `{path}`: {source}")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+    #[error("Error serializing render metadata: {0}")]
+    SerializeError(#[from] serde_yaml::Error),
+    #[error("Render metadata IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl RenderMetadata {
+    pub fn new<V: Into<String>, S: Into<String>, T: Into<String>>(
+        archetect_version: V,
+        source: S,
+        gitref: Option<String>,
+        commit: Option<String>,
+        timestamp: T,
+    ) -> RenderMetadata {
+        RenderMetadata {
+            archetect: ArchetectMetadata {
+                version: archetect_version.into(),
+            },
+            archetype: ArchetypeMetadata {
+                source: source.into(),
+                version: None,
+                gitref,
+                commit,
+            },
+            timestamp: timestamp.into(),
+            answers: LinkedHashMap::new(),
+        }
+    }
+
+    /// Describes a completed render from the built-in objects and answers in its final `context`.
+    pub fn from_context(context: &Context) -> RenderMetadata {
+        let json = context.clone().into_json();
+        let string = |pointer: &str| json.pointer(pointer).and_then(Value::as_str).map(|value| value.to_owned());
+        RenderMetadata::new(
+            string("/archetect/version").unwrap_or_default(),
+            string("/archetype/source").unwrap_or_default(),
+            string("/archetype/ref"),
+            string("/archetype/commit"),
+            string("/render/timestamp").unwrap_or_default(),
+        )
+        .with_archetype_version(string("/archetype/version"))
+        .with_context_answers(context)
+    }
+
+    pub fn with_archetype_version(mut self, version: Option<String>) -> RenderMetadata {
+        self.archetype.version = version;
+        self
+    }
+
+    /// Captures the answerable values in `context` as answers, skipping Archetect's built-in objects and any values
+    /// that cannot be expressed as a single answer string.
+    pub fn with_context_answers(mut self, context: &Context) -> RenderMetadata {
+        if let Value::Object(values) = context.clone().into_json() {
+            for (identifier, value) in values {
+                if BUILTIN_CONTEXT_KEYS.contains(&identifier.as_str()) {
+                    continue;
+                }
+                if let Some(answer) = value_to_answer(&value) {
+                    self.answers.insert(identifier, AnswerInfo::with_value(answer).build());
+                }
+            }
+        }
+        self
+    }
+
+    /// Leaves out the answers whose identifiers `excluded` picks, such as those to sensitive variables.
+    pub fn without_answers<F: Fn(&str) -> bool>(mut self, excluded: F) -> RenderMetadata {
+        self.answers = self
+            .answers
+            .into_iter()
+            .filter(|(identifier, _)| !excluded(identifier))
+            .collect();
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(destination: P) -> Result<Option<RenderMetadata>, MetadataError> {
+        let path = destination.as_ref().join(METADATA_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let metadata = fs::read_to_string(&path)?;
+        match serde_yaml::from_str::<RenderMetadata>(&metadata) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(source) => Err(MetadataError::ParseError { path, source }),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, destination: P) -> Result<(), MetadataError> {
+        let yaml = serde_yaml::to_string(&self)?;
+        fs::write(destination.as_ref().join(METADATA_FILE_NAME), yaml)?;
+        Ok(())
+    }
+
+    pub fn archetect_version(&self) -> &str {
+        &self.archetect.version
+    }
+
+    pub fn source(&self) -> &str {
+        &self.archetype.source
+    }
+
+    pub fn archetype_version(&self) -> Option<&str> {
+        self.archetype.version.as_deref()
+    }
+
+    pub fn gitref(&self) -> Option<&str> {
+        self.archetype.gitref.as_deref()
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.archetype.commit.as_deref()
+    }
+
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    pub fn answers(&self) -> &LinkedHashMap<String, AnswerInfo> {
+        &self.answers
+    }
+}
+
+pub(crate) fn value_to_answer(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.to_owned()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        Value::Array(items) => {
+            let items = items.iter().map(value_to_answer).collect::<Option<Vec<String>>>()?;
+            Some(items.join(", "))
+        }
+        Value::Null | Value::Object(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_answers() {
+        let mut context = Context::new();
+        context.insert("name", "Order Service");
+        context.insert("port", &8080);
+        context.insert("persistence", &true);
+        context.insert("environments", &vec!["dev", "prod"]);
+        context.insert("archetype", &"ignored");
+
+        let metadata = RenderMetadata::new("0.7.4", "~/archetypes/service", None, None, "2022-08-13T00:00:00+00:00")
+            .with_context_answers(&context);
+        let answers = metadata.answers();
+
+        assert_eq!(answers.get("name").unwrap().value(), Some("Order Service"));
+        assert_eq!(answers.get("port").unwrap().value(), Some("8080"));
+        assert_eq!(answers.get("persistence").unwrap().value(), Some("true"));
+        assert_eq!(answers.get("environments").unwrap().value(), Some("dev, prod"));
+        assert!(answers.get("archetype").is_none());
+    }
+
+    #[test]
+    fn test_from_context() {
+        let mut context = Context::new();
+        context.insert("archetect", &serde_json::json!({ "offline": false, "version": "0.7.4" }));
+        context.insert(
+            "archetype",
+            &serde_json::json!({ "source": "~/archetypes/service", "version": "1.2.0", "ref": null, "commit": null }),
+        );
+        context.insert("render", &serde_json::json!({ "timestamp": "2022-08-13T00:00:00+00:00" }));
+        context.insert("name", "Order Service");
+
+        let metadata = RenderMetadata::from_context(&context);
+        assert_eq!(metadata.archetect_version(), "0.7.4");
+        assert_eq!(metadata.source(), "~/archetypes/service");
+        assert_eq!(metadata.archetype_version(), Some("1.2.0"));
+        assert_eq!(metadata.gitref(), None);
+        assert_eq!(metadata.timestamp(), "2022-08-13T00:00:00+00:00");
+        assert_eq!(metadata.answers().get("name").unwrap().value(), Some("Order Service"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let destination = tempfile::tempdir().unwrap();
+        let metadata = RenderMetadata::new(
+            "0.7.4",
+            "git@github.com:archetect/archetype-rust-cli.git",
+            Some("v1.0.0".to_owned()),
+            Some("3f6a9c2e5b1d4a7f8c9e0b1a2d3c4e5f6a7b8c9d".to_owned()),
+            "2022-08-13T00:00:00+00:00",
+        );
+        metadata.save(destination.path()).unwrap();
+
+        let loaded = RenderMetadata::load(destination.path()).unwrap().unwrap();
+        assert_eq!(loaded.source(), "git@github.com:archetect/archetype-rust-cli.git");
+        assert_eq!(loaded.gitref(), Some("v1.0.0"));
+        assert_eq!(loaded.commit(), Some("3f6a9c2e5b1d4a7f8c9e0b1a2d3c4e5f6a7b8c9d"));
+    }
+}
@@ -0,0 +1,436 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use semver::Version;
+
+use crate::metadata::RenderMetadata;
+use crate::source::spec::SourceSpec;
+use crate::source::git::{find_default_branch, git_output, is_commit_sha, DEFAULT_BRANCHES};
+use crate::source::SourceError;
+use crate::utils::sorted_entries;
+use crate::Archetect;
+
+/// A git archetype source, and the revision of it in use: the ref it was requested at, and the commit that ref
+/// resolved to when it was cached or rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedSource {
+    location: String,
+    gitref: Option<String>,
+    commit: Option<String>,
+    cache_path: Option<PathBuf>,
+}
+
+impl TrackedSource {
+    pub fn new<L: Into<String>>(location: L) -> TrackedSource {
+        TrackedSource {
+            location: location.into(),
+            gitref: None,
+            commit: None,
+            cache_path: None,
+        }
+    }
+
+    pub fn with_gitref<G: Into<String>>(mut self, gitref: G) -> TrackedSource {
+        self.gitref = Some(gitref.into());
+        self
+    }
+
+    pub fn with_commit<C: Into<String>>(mut self, commit: C) -> TrackedSource {
+        self.commit = Some(commit.into());
+        self
+    }
+
+    /// A git source string, such as `git@github.com:archetect/archetype-rust-cli.git#v1.0.0`, at the commit its ref
+    /// resolves to in the git cache, if it has been cached.
+    pub fn parse(archetect: &Archetect, source: &str) -> Result<TrackedSource, SourceError> {
+        let spec = SourceSpec::parse(&archetect.expand_source(source))?;
+        let cache_path = spec
+            .git_cache_path(archetect)
+            .ok_or_else(|| SourceError::SourceUnsupported(format!("{} (only git sources are tracked)", source)))?;
+        let commit = if cache_path.exists() {
+            cached_commit(&cache_path, spec.gitref())
+        } else {
+            None
+        };
+        Ok(TrackedSource {
+            location: spec.location().to_owned(),
+            gitref: spec.gitref().map(str::to_owned),
+            commit,
+            cache_path: Some(cache_path).filter(|path| path.exists()),
+        })
+    }
+
+    /// The source a project was rendered from, at the commit recorded in its render metadata.
+    pub fn from_metadata(archetect: &Archetect, metadata: &RenderMetadata) -> Result<TrackedSource, SourceError> {
+        let mut source = TrackedSource::parse(archetect, metadata.source())?;
+        if let Some(gitref) = metadata.gitref() {
+            source.gitref = Some(gitref.to_owned());
+        }
+        if let Some(commit) = metadata.commit() {
+            source.commit = Some(commit.to_owned());
+        }
+        Ok(source)
+    }
+
+    /// The repository URL, without any `#ref`.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn gitref(&self) -> Option<&str> {
+        self.gitref.as_deref()
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+}
+
+/// Whether a newer revision of a source is available: a newer release tag for sources pinned to a tag or commit, or
+/// a newer commit for sources following a branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceStatus {
+    source: TrackedSource,
+    latest_tag: Option<String>,
+    latest_commit: Option<String>,
+}
+
+impl SourceStatus {
+    pub fn source(&self) -> &TrackedSource {
+        &self.source
+    }
+
+    pub fn latest_tag(&self) -> Option<&str> {
+        self.latest_tag.as_deref()
+    }
+
+    pub fn latest_commit(&self) -> Option<&str> {
+        self.latest_commit.as_deref()
+    }
+
+    pub fn is_outdated(&self) -> bool {
+        self.latest_tag.is_some() || self.latest_commit.is_some()
+    }
+}
+
+/// The archetype repositories in the git cache, once for each branch, tag, or commit of them checked out.
+pub fn cached_sources(archetect: &Archetect) -> Result<Vec<TrackedSource>, SourceError> {
+    let cache_dir = archetect.layout().git_cache_dir();
+    if !cache_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut sources = vec![];
+    for path in sorted_entries(&cache_dir)? {
+        if path.extension().is_none_or(|extension| extension != "git") || !path.join("HEAD").exists() {
+            continue;
+        }
+        let location = git_output(
+            Command::new("git")
+                .current_dir(&path)
+                .args(["remote", "get-url", "origin"]),
+        )?;
+        let worktrees = git_output(Command::new("git").current_dir(&path).args(["worktree", "list", "--porcelain"]))?;
+        for (worktree, commit) in parse_worktrees(&worktrees) {
+            sources.push(TrackedSource {
+                location: location.clone(),
+                gitref: checked_out_ref(&worktree).or_else(|| Some(commit.clone())),
+                commit: Some(commit),
+                cache_path: Some(path.clone()),
+            });
+        }
+    }
+    sources.sort_by(|left, right| {
+        left.location
+            .cmp(&right.location)
+            .then_with(|| left.gitref.cmp(&right.gitref))
+    });
+    Ok(sources)
+}
+
+/// The worktrees, and the commits they are at, listed by `git worktree list --porcelain`, leaving out the bare
+/// repository itself.
+pub(crate) fn parse_worktrees(output: &str) -> Vec<(PathBuf, String)> {
+    output
+        .split("\n\n")
+        .filter_map(|entry| {
+            let mut worktree = None;
+            let mut commit = None;
+            for line in entry.lines() {
+                if let Some(path) = line.strip_prefix("worktree ") {
+                    worktree = Some(PathBuf::from(path));
+                } else if let Some(head) = line.strip_prefix("HEAD ") {
+                    commit = Some(head.to_owned());
+                } else if line == "bare" {
+                    return None;
+                }
+            }
+            worktree.zip(commit)
+        })
+        .collect()
+}
+
+/// Checks the source's remote for newer tags and commits. Offline, the refs fetched into the git cache are checked
+/// instead.
+pub fn check(archetect: &Archetect, source: &TrackedSource) -> Result<SourceStatus, SourceError> {
+    let remote = if archetect.offline() {
+        match &source.cache_path {
+            Some(cache_path) => cache_path.display().to_string(),
+            None => return Err(SourceError::OfflineAndNotCached(source.location.clone())),
+        }
+    } else {
+        source.location.clone()
+    };
+    let listing = git_output(Command::new("git").args(["ls-remote", &remote]))?;
+    // A cache's own HEAD is whatever was last checked out, rather than the remote's default branch.
+    let listing = if archetect.offline() {
+        listing
+            .lines()
+            .filter(|line| !line.ends_with("\tHEAD"))
+            .collect::<Vec<&str>>()
+            .join("\n")
+    } else {
+        listing
+    };
+    let refs = RemoteRefs::parse(&listing);
+    Ok(status(source, &refs))
+}
+
+fn status(source: &TrackedSource, refs: &RemoteRefs) -> SourceStatus {
+    let mut status = SourceStatus {
+        source: source.clone(),
+        latest_tag: None,
+        latest_commit: None,
+    };
+    match source.gitref.as_deref() {
+        Some(gitref) if refs.tag(gitref).is_some() || is_commit_sha(gitref) => {
+            let current = parse_version(gitref).or_else(|| {
+                refs.tags
+                    .iter()
+                    .filter(|(_, commit)| commit.eq_ignore_ascii_case(gitref))
+                    .filter_map(|(tag, _)| parse_version(tag))
+                    .max()
+            });
+            if let (Some(current), Some((tag, latest))) = (current, refs.latest_release()) {
+                if latest > current {
+                    status.latest_tag = Some(tag.to_owned());
+                }
+            }
+        }
+        gitref => {
+            // Without a ref, sources follow the remote's default branch.
+            let head = match gitref {
+                Some(branch) => refs.branch(branch),
+                None => refs.head.as_deref().or_else(|| {
+                    DEFAULT_BRANCHES
+                        .iter()
+                        .find_map(|candidate| refs.branch(candidate))
+                }),
+            };
+            if let (Some(head), Some(commit)) = (head, &source.commit) {
+                if head != commit {
+                    status.latest_commit = Some(head.to_owned());
+                }
+            }
+        }
+    }
+    status
+}
+
+/// The branches and tags of a repository, as listed by `git ls-remote`, and the commit its default branch is at.
+#[derive(Debug, Default)]
+struct RemoteRefs {
+    head: Option<String>,
+    branches: Vec<(String, String)>,
+    tags: Vec<(String, String)>,
+}
+
+impl RemoteRefs {
+    fn parse(output: &str) -> RemoteRefs {
+        let mut refs = RemoteRefs::default();
+        for line in output.lines() {
+            let (commit, name) = match line.split_once('\t') {
+                Some((commit, name)) => (commit.trim().to_owned(), name.trim()),
+                None => continue,
+            };
+            if name == "refs/remotes/origin/HEAD" || (name == "HEAD" && refs.head.is_none()) {
+                refs.head = Some(commit);
+            } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+                // Annotated tags are listed a second time, peeled to the commit they point to.
+                let tag = tag.trim_end_matches("^{}");
+                match refs.tags.iter_mut().find(|(name, _)| name == tag) {
+                    Some(existing) => existing.1 = commit,
+                    None => refs.tags.push((tag.to_owned(), commit)),
+                }
+            } else if let Some(branch) = name
+                .strip_prefix("refs/heads/")
+                .or_else(|| name.strip_prefix("refs/remotes/origin/"))
+            {
+                if branch != "HEAD" {
+                    refs.branches.push((branch.to_owned(), commit));
+                }
+            }
+        }
+        refs
+    }
+
+    fn branch(&self, name: &str) -> Option<&str> {
+        self.branches
+            .iter()
+            .find(|(branch, _)| branch == name)
+            .map(|(_, commit)| commit.as_str())
+    }
+
+    fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, commit)| commit.as_str())
+    }
+
+    /// The tag of the highest release version, ignoring pre-releases.
+    fn latest_release(&self) -> Option<(&str, Version)> {
+        self.tags
+            .iter()
+            .filter_map(|(tag, _)| parse_version(tag).map(|version| (tag.as_str(), version)))
+            .filter(|(_, version)| !version.is_prerelease())
+            .max_by(|(_, left), (_, right)| left.cmp(right))
+    }
+}
+
+fn parse_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// The commit `gitref` resolves to in a cached repository, or its default branch when no ref is given.
+fn cached_commit(path: &Path, gitref: Option<&str>) -> Option<String> {
+    let candidates = match gitref {
+        Some(gitref) => vec![format!("origin/{}", gitref), gitref.to_owned()],
+        None => match find_default_branch(&path.to_string_lossy()) {
+            Ok(branch) => vec![format!("origin/{}", branch)],
+            Err(_) => vec![],
+        },
+    };
+    candidates.iter().find_map(|candidate| {
+        git_output(Command::new("git").current_dir(path).args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", candidate),
+        ]))
+        .ok()
+    })
+}
+
+/// The branch or tag checked out in a cached repository. The cache checks out remote branches detached, so a branch
+/// is recognised by the remote branch that points at HEAD.
+fn checked_out_ref(path: &Path) -> Option<String> {
+    let branches = git_output(Command::new("git").current_dir(path).args([
+        "branch",
+        "--remotes",
+        "--points-at",
+        "HEAD",
+        "--format=%(refname:short)",
+    ]))
+    .ok()?;
+    let branch = branches
+        .lines()
+        .filter_map(|branch| branch.trim().strip_prefix("origin/"))
+        .find(|branch| *branch != "HEAD");
+    if let Some(branch) = branch {
+        return Some(branch.to_owned());
+    }
+    let tags = git_output(
+        Command::new("git")
+            .current_dir(path)
+            .args(["tag", "--points-at", "HEAD"]),
+    )
+    .ok()?;
+    tags.lines().next().map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFS: &str = "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1\tHEAD
+a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1\trefs/heads/main
+b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2\trefs/heads/develop
+c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3\trefs/tags/v1.0.0
+d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4d4\trefs/tags/v1.2.0
+e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5\trefs/tags/v1.2.0^{}
+f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6f6\trefs/tags/v2.0.0-beta.1
+";
+
+    #[test]
+    fn test_parse_worktrees() {
+        let output = "worktree /cache/git/123.git\nbare\n\n\
+                      worktree /cache/git/123.worktrees/main-456\nHEAD a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1\ndetached\n";
+        assert_eq!(
+            parse_worktrees(output),
+            vec![(
+                PathBuf::from("/cache/git/123.worktrees/main-456"),
+                "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_refs() {
+        let refs = RemoteRefs::parse(REFS);
+        assert_eq!(refs.branch("main"), Some("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1"));
+        assert_eq!(refs.tag("v1.2.0"), Some("e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5"));
+        assert_eq!(refs.tags.len(), 3);
+        assert_eq!(refs.latest_release().map(|(tag, _)| tag), Some("v1.2.0"));
+    }
+
+    #[test]
+    fn test_pinned_status() {
+        let refs = RemoteRefs::parse(REFS);
+        let repository = "https://github.com/archetect/archetype-rust-cli.git";
+
+        let status = status_of(&refs, TrackedSource::new(repository).with_gitref("v1.0.0"));
+        assert_eq!(status.latest_tag(), Some("v1.2.0"));
+
+        let status = status_of(&refs, TrackedSource::new(repository).with_gitref("v1.2.0"));
+        assert!(!status.is_outdated());
+
+        // Commits are placed by the release tags that point to them.
+        let status = status_of(
+            &refs,
+            TrackedSource::new(repository).with_gitref("c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3c3"),
+        );
+        assert_eq!(status.latest_tag(), Some("v1.2.0"));
+    }
+
+    #[test]
+    fn test_branch_status() {
+        let refs = RemoteRefs::parse(REFS);
+        let repository = "https://github.com/archetect/archetype-rust-cli.git";
+
+        // Without a ref, sources follow the remote's HEAD, or else the first default branch present.
+        let status = status_of(
+            &refs,
+            TrackedSource::new(repository).with_commit("b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2"),
+        );
+        assert_eq!(status.latest_commit(), Some("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1"));
+
+        let headless = RemoteRefs::parse(REFS.trim_start_matches(|c| c != '\n'));
+        let status = status_of(
+            &headless,
+            TrackedSource::new(repository).with_commit("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1"),
+        );
+        assert_eq!(status.latest_commit(), Some("b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2"));
+
+        let status = status_of(
+            &refs,
+            TrackedSource::new(repository)
+                .with_gitref("main")
+                .with_commit("a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1"),
+        );
+        assert!(!status.is_outdated());
+    }
+
+    fn status_of(refs: &RemoteRefs, source: TrackedSource) -> SourceStatus {
+        status(&source, refs)
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// When to use a capability of the terminal, such as color: when the terminal appears to support it, or regardless.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for OutputChoice {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<OutputChoice, String> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(OutputChoice::Auto),
+            "always" => Ok(OutputChoice::Always),
+            "never" => Ok(OutputChoice::Never),
+            _ => Err(format!("`{}` is not one of auto, always, or never", value)),
+        }
+    }
+}
+
+impl OutputChoice {
+    fn resolve(self, supported: bool) -> bool {
+        match self {
+            OutputChoice::Auto => supported,
+            OutputChoice::Always => true,
+            OutputChoice::Never => false,
+        }
+    }
+}
+
+/// How Archetect styles its own output and prompts: whether it uses color, and whether it uses unicode symbols and
+/// emoji or sticks to ASCII, so that CI logs and dumb terminals get clean output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputStyle {
+    color: bool,
+    unicode: bool,
+}
+
+impl OutputStyle {
+    pub fn new(color: bool, unicode: bool) -> OutputStyle {
+        OutputStyle { color, unicode }
+    }
+
+    /// Uncolored ASCII output.
+    pub fn plain() -> OutputStyle {
+        OutputStyle::new(false, false)
+    }
+
+    /// Resolves the choices against the environment. Color is automatically used when stderr is a terminal other than
+    /// a dumb one, and `NO_COLOR` is unset; unicode when the terminal is not dumb and the locale is UTF-8.
+    pub fn detect(color: OutputChoice, unicode: OutputChoice) -> OutputStyle {
+        let term = env::var("TERM").ok();
+        let no_color = env::var("NO_COLOR").ok();
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|key| env::var(key).ok().filter(|value| !value.is_empty()));
+        OutputStyle {
+            color: color.resolve(color_supported(
+                std::io::stderr().is_terminal(),
+                term.as_deref(),
+                no_color.as_deref(),
+            )),
+            unicode: unicode.resolve(unicode_supported(term.as_deref(), locale.as_deref())),
+        }
+    }
+
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    pub fn unicode(&self) -> bool {
+        self.unicode
+    }
+
+    /// `symbol` where unicode is used, and `ascii` otherwise.
+    pub fn symbol<'a>(&self, symbol: &'a str, ascii: &'a str) -> &'a str {
+        if self.unicode {
+            symbol
+        } else {
+            ascii
+        }
+    }
+}
+
+impl Default for OutputStyle {
+    fn default() -> OutputStyle {
+        OutputStyle::detect(OutputChoice::Auto, OutputChoice::Auto)
+    }
+}
+
+/// How much Archetect, and the archetypes it renders, report:
+///
+/// | Verbosity | Logged | `print` and `display` | `exec` output | Summaries |
+/// |-----------|--------|-----------------------|---------------|-----------|
+/// | `Quiet` (`-q`) | errors | hidden | held back; logged as errors if the command fails | hidden |
+/// | `Normal` | info and above | shown | streamed, unless `collapse`d | shown |
+/// | `Verbose` (`-v`) | debug and above | shown | streamed, even when `collapse`d | shown |
+/// | `Trace` (`-vv`) | everything | shown | streamed, even when `collapse`d | shown |
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    Trace,
+}
+
+impl Verbosity {
+    /// The verbosity of `-q`, or `-v` given `verbose` times.
+    pub fn from_flags(quiet: bool, verbose: u64) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// The most detailed level logged.
+    pub fn log_level(&self) -> log::Level {
+        match self {
+            Verbosity::Quiet => log::Level::Error,
+            Verbosity::Normal => log::Level::Info,
+            Verbosity::Verbose => log::Level::Debug,
+            Verbosity::Trace => log::Level::Trace,
+        }
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        *self == Verbosity::Quiet
+    }
+}
+
+fn color_supported(terminal: bool, term: Option<&str>, no_color: Option<&str>) -> bool {
+    // Per https://no-color.org, NO_COLOR disables color when set to anything other than an empty string.
+    terminal && term != Some("dumb") && no_color.is_none_or(str::is_empty)
+}
+
+fn unicode_supported(term: Option<&str>, locale: Option<&str>) -> bool {
+    if term == Some("dumb") {
+        return false;
+    }
+    if cfg!(windows) {
+        return true;
+    }
+    locale.is_some_and(|locale| {
+        let locale = locale.to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!("Always".parse::<OutputChoice>(), Ok(OutputChoice::Always));
+        assert_eq!("never".parse::<OutputChoice>(), Ok(OutputChoice::Never));
+        assert!("sometimes".parse::<OutputChoice>().is_err());
+    }
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::Trace);
+        assert_eq!(Verbosity::from_flags(true, 1), Verbosity::Quiet);
+        assert_eq!(Verbosity::Quiet.log_level(), log::Level::Error);
+        assert_eq!(Verbosity::Verbose.log_level(), log::Level::Debug);
+        assert!(Verbosity::Verbose > Verbosity::Normal);
+    }
+
+    #[test]
+    fn test_color_supported() {
+        assert!(color_supported(true, Some("xterm-256color"), None));
+        assert!(color_supported(true, None, Some("")));
+        assert!(!color_supported(true, Some("xterm-256color"), Some("1")));
+        assert!(!color_supported(true, Some("dumb"), None));
+        assert!(!color_supported(false, Some("xterm-256color"), None));
+        assert!(OutputChoice::Always.resolve(false));
+        assert!(!OutputChoice::Never.resolve(true));
+    }
+
+    #[test]
+    fn test_unicode_supported() {
+        assert!(!unicode_supported(Some("dumb"), Some("en_US.UTF-8")));
+        if !cfg!(windows) {
+            assert!(unicode_supported(Some("xterm"), Some("en_US.UTF-8")));
+            assert!(unicode_supported(None, Some("C.utf8")));
+            assert!(!unicode_supported(Some("xterm"), Some("C")));
+            assert!(!unicode_supported(Some("xterm"), None));
+        }
+    }
+}
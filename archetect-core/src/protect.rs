@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+pub const PROTECT_FILE_NAME: &str = ".archetect-protect";
+
+/// Hand-maintained files in a destination that renders and upgrades must never overwrite, whatever an archetype's
+/// rules say. They are listed in the destination's `.archetect-protect` file as globs relative to the destination, one
+/// per line, with blank lines and lines starting with `#` ignored:
+///
+/// ```text
+/// # Maintained by hand since the initial render
+/// README.md
+/// src/legacy/**
+/// ```
+///
+/// Protected files that do not exist yet are still created.
+#[derive(Debug, Clone, Default)]
+pub struct ProtectedFiles {
+    root: PathBuf,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl ProtectedFiles {
+    /// The files protected by `destination`'s `.archetect-protect` file, which are none when it has none.
+    pub fn load<P: AsRef<Path>>(destination: P) -> Result<ProtectedFiles, std::io::Error> {
+        let destination = destination.as_ref();
+        let path = destination.join(PROTECT_FILE_NAME);
+        if !path.is_file() {
+            return Ok(ProtectedFiles::default());
+        }
+        Ok(ProtectedFiles::parse(destination, &fs::read_to_string(path)?))
+    }
+
+    pub fn parse<P: Into<PathBuf>>(root: P, contents: &str) -> ProtectedFiles {
+        let patterns = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| match glob::Pattern::new(line.trim_start_matches('/')) {
+                Ok(pattern) => Some(pattern),
+                Err(error) => {
+                    warn!("Ignoring invalid pattern `{}` in {}: {}", line, PROTECT_FILE_NAME, error);
+                    None
+                }
+            })
+            .collect();
+        ProtectedFiles {
+            root: root.into(),
+            patterns,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether `path`, within the destination, is protected.
+    pub fn is_protected<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.patterns.iter().any(|pattern| pattern.matches_path(relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_protected() {
+        let protected = ProtectedFiles::parse("service", "# Hand-maintained\n\nREADME.md\n/src/legacy/**\n*.lock\n");
+        assert!(protected.is_protected("service/README.md"));
+        assert!(protected.is_protected("service/src/legacy/billing/mod.rs"));
+        assert!(protected.is_protected("service/Cargo.lock"));
+        assert!(!protected.is_protected("service/src/main.rs"));
+        assert!(!protected.is_protected("service/docs/README.md"));
+        assert!(ProtectedFiles::parse(".", "# Nothing yet\n").is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+/// Where the value of a context variable came from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AnswerOrigin {
+    /// Supplied with `--answer` on the command line.
+    CommandLine,
+    /// Loaded from the answer file at the given path.
+    AnswerFile(String),
+    /// Passed down from the parent archetype with the given source.
+    Inherited(String),
+    /// Set by an explicit `value` on the variable definition.
+    Derived,
+    /// The variable's default, used without prompting.
+    Default,
+    /// Entered interactively.
+    Prompt,
+    /// Turned on by enabling the switch of the same name.
+    Switch,
+    /// Entered interactively for a shared variable of the same name, earlier in the run.
+    Shared,
+    /// Supplied by an embedding application without a more specific origin.
+    Unspecified,
+}
+
+impl Display for AnswerOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerOrigin::CommandLine => write!(f, "command line"),
+            AnswerOrigin::AnswerFile(path) => write!(f, "answer file {}", path),
+            AnswerOrigin::Inherited(source) => write!(f, "inherited from {}", source),
+            AnswerOrigin::Derived => write!(f, "derived"),
+            AnswerOrigin::Default => write!(f, "default"),
+            AnswerOrigin::Prompt => write!(f, "prompt"),
+            AnswerOrigin::Shared => write!(f, "shared prompt"),
+            AnswerOrigin::Switch => write!(f, "switch"),
+            AnswerOrigin::Unspecified => write!(f, "unspecified"),
+        }
+    }
+}
+
+/// A record of a single variable being set while rendering an archetype.
+#[derive(Clone, Debug)]
+pub struct AnswerProvenance {
+    archetype: String,
+    identifier: String,
+    value: Value,
+    origin: AnswerOrigin,
+    sensitive: bool,
+}
+
+impl AnswerProvenance {
+    pub fn new<A: Into<String>, I: Into<String>>(
+        archetype: A,
+        identifier: I,
+        value: Value,
+        origin: AnswerOrigin,
+    ) -> AnswerProvenance {
+        AnswerProvenance {
+            archetype: archetype.into(),
+            identifier: identifier.into(),
+            value,
+            origin,
+            sensitive: false,
+        }
+    }
+
+    /// Marks the answer as belonging to a sensitive variable, such as a password or token, to be left out of reports.
+    pub fn with_sensitive(mut self, sensitive: bool) -> AnswerProvenance {
+        self.sensitive = sensitive;
+        self
+    }
+
+    pub fn archetype(&self) -> &str {
+        &self.archetype
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn origin(&self) -> &AnswerOrigin {
+        &self.origin
+    }
+
+    pub fn sensitive(&self) -> bool {
+        self.sensitive
+    }
+}
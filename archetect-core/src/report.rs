@@ -0,0 +1,349 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::events::{Event, EventSubscriber};
+use crate::warnings::RenderWarning;
+use crate::{Archetect, ArchetectError};
+
+/// An archetype rendered during a run, at the revision and version it was rendered from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedArchetype {
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    gitref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+impl RenderedArchetype {
+    pub fn new<S: Into<String>>(
+        source: S,
+        version: Option<String>,
+        gitref: Option<String>,
+        commit: Option<String>,
+    ) -> RenderedArchetype {
+        RenderedArchetype {
+            source: source.into(),
+            version,
+            gitref,
+            commit,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn gitref(&self) -> Option<&str> {
+        self.gitref.as_deref()
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportedAnswer {
+    archetype: String,
+    identifier: String,
+    value: Value,
+    origin: String,
+}
+
+impl ReportedAnswer {
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportedAction {
+    action: String,
+    succeeded: bool,
+    duration_ms: u64,
+}
+
+impl ReportedAction {
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.succeeded
+    }
+}
+
+/// How many of the slowest templates a report lists.
+pub const SLOWEST_TEMPLATES: usize = 10;
+
+/// A template rendered during a run, with how long it took to parse and to render.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportedTemplate {
+    template: PathBuf,
+    parse_us: u64,
+    render_us: u64,
+}
+
+impl ReportedTemplate {
+    pub fn template(&self) -> &Path {
+        &self.template
+    }
+
+    pub fn parse_us(&self) -> u64 {
+        self.parse_us
+    }
+
+    pub fn render_us(&self) -> u64 {
+        self.render_us
+    }
+
+    fn total_us(&self) -> u64 {
+        self.parse_us + self.render_us
+    }
+}
+
+/// What a run of Archetect did, for pipelines to archive alongside what it generated: the archetypes rendered, the
+/// answers used, the files written, the actions executed and how long they took, the slowest templates, and any
+/// warnings. Answers to sensitive variables are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderReport {
+    archetect: String,
+    started: String,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    archetypes: Vec<RenderedArchetype>,
+    answers: Vec<ReportedAnswer>,
+    files: Vec<PathBuf>,
+    actions: Vec<ReportedAction>,
+    slowest_templates: Vec<ReportedTemplate>,
+    warnings: Vec<String>,
+    steps: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("Error serializing render report: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("Error serializing render report: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Error writing render report: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl RenderReport {
+    pub fn archetypes(&self) -> &[RenderedArchetype] {
+        &self.archetypes
+    }
+
+    pub fn answers(&self) -> &[ReportedAnswer] {
+        &self.answers
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn actions(&self) -> &[ReportedAction] {
+        &self.actions
+    }
+
+    /// The templates that took longest to parse and render, slowest first, to find those worth simplifying.
+    pub fn slowest_templates(&self) -> &[ReportedTemplate] {
+        &self.slowest_templates
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Writes the report as JSON when `path` ends with `.json`, and as YAML otherwise.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReportError> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::to_string_pretty(self)?,
+            _ => serde_yaml::to_string(self)?,
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Collects the files written and actions executed from the events of a run, to be combined with what [`Archetect`]
+/// recorded into a [`RenderReport`]. Subscribe a shared recorder, so that it can be asked for the report afterwards.
+pub struct ReportRecorder {
+    started: Instant,
+    timestamp: String,
+    files: RefCell<Vec<PathBuf>>,
+    actions: RefCell<Vec<ReportedAction>>,
+    running: RefCell<Vec<Instant>>,
+    templates: RefCell<Vec<ReportedTemplate>>,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl ReportRecorder {
+    pub fn new() -> ReportRecorder {
+        ReportRecorder {
+            started: Instant::now(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            files: RefCell::new(vec![]),
+            actions: RefCell::new(vec![]),
+            running: RefCell::new(vec![]),
+            templates: RefCell::new(vec![]),
+            warnings: RefCell::new(vec![]),
+        }
+    }
+
+    /// Keeps the warnings of a render, which [`Archetect`] forgets when the next render begins.
+    pub fn record_warnings(&self, warnings: &[RenderWarning]) {
+        self.warnings
+            .borrow_mut()
+            .extend(warnings.iter().map(|warning| warning.to_string()));
+    }
+
+    /// The report of the run so far, ending in `error` if it failed.
+    pub fn report(&self, archetect: &Archetect, error: Option<&ArchetectError>) -> RenderReport {
+        RenderReport {
+            archetect: env!("CARGO_PKG_VERSION").to_owned(),
+            started: self.timestamp.clone(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            error: error.map(|error| error.to_string()),
+            archetypes: archetect.rendered_archetypes().to_vec(),
+            answers: archetect
+                .answer_provenance()
+                .iter()
+                .filter(|answer| !answer.sensitive())
+                .map(|answer| ReportedAnswer {
+                    archetype: answer.archetype().to_owned(),
+                    identifier: answer.identifier().to_owned(),
+                    value: answer.value().clone(),
+                    origin: answer.origin().to_string(),
+                })
+                .collect(),
+            files: self.files.borrow().clone(),
+            actions: self.actions.borrow().clone(),
+            slowest_templates: self.slowest_templates(),
+            warnings: self.warnings.borrow().clone(),
+            steps: archetect.steps().to_vec(),
+        }
+    }
+
+    fn slowest_templates(&self) -> Vec<ReportedTemplate> {
+        let mut templates = self.templates.borrow().clone();
+        templates.sort_by_key(|template| std::cmp::Reverse(template.total_us()));
+        templates.truncate(SLOWEST_TEMPLATES);
+        templates
+    }
+}
+
+impl Default for ReportRecorder {
+    fn default() -> ReportRecorder {
+        ReportRecorder::new()
+    }
+}
+
+impl EventSubscriber for ReportRecorder {
+    fn notify(&self, event: &Event) {
+        match event {
+            Event::ActionStarted { .. } => self.running.borrow_mut().push(Instant::now()),
+            Event::ActionFinished { action, succeeded } => {
+                // Actions nest, so the most recently started action is the one finishing.
+                let started = self.running.borrow_mut().pop();
+                self.actions.borrow_mut().push(ReportedAction {
+                    action: action.to_owned(),
+                    succeeded: *succeeded,
+                    duration_ms: started.map(|started| started.elapsed().as_millis() as u64).unwrap_or_default(),
+                });
+            }
+            Event::FileWritten { destination } => self.files.borrow_mut().push(destination.to_owned()),
+            Event::TemplateRendered { template, parse, render } => {
+                self.templates.borrow_mut().push(ReportedTemplate {
+                    template: template.to_owned(),
+                    parse_us: micros(*parse),
+                    render_us: micros(*render),
+                })
+            }
+            _ => (),
+        }
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    use linked_hash_map::LinkedHashMap;
+
+    use crate::file_tree::FileTree;
+    use crate::{Archetect, Archetype};
+
+    use super::ReportRecorder;
+
+    #[test]
+    fn test_report() {
+        let mut files = FileTree::new();
+        files.insert(
+            "archetype.yml",
+            concat!(
+                "version: 1.2.0\n",
+                "script:\n  - set:\n      name:\n        value: billing\n",
+                "      token:\n        value: secret\n        sensitive: true\n",
+                "  - render:\n      directory:\n        source: contents\n",
+            ),
+        );
+        files.insert("contents/{{ name }}.txt", "{{ name }}");
+        let archetype = Archetype::from_files(files).unwrap();
+
+        let recorder = Rc::new(ReportRecorder::new());
+        let mut archetect = Archetect::builder()
+            .with_event_subscriber(recorder.clone())
+            .build()
+            .unwrap();
+        archetype
+            .render_in_memory(&mut archetect, &LinkedHashMap::new())
+            .unwrap();
+
+        let report = recorder.report(&archetect, None);
+        assert_eq!(report.archetypes().len(), 1);
+        assert_eq!(report.archetypes()[0].version(), Some("1.2.0"));
+        let answers: Vec<&str> = report.answers().iter().map(|answer| answer.identifier()).collect();
+        assert_eq!(answers, vec!["name"]);
+        assert_eq!(report.files(), &[PathBuf::from("billing.txt")]);
+        let actions: Vec<&str> = report.actions().iter().map(|action| action.action()).collect();
+        assert_eq!(actions, vec!["set", "render"]);
+        assert_eq!(report.slowest_templates().len(), 1);
+        assert_eq!(
+            report.slowest_templates()[0].template(),
+            PathBuf::from("contents/{{ name }}.txt")
+        );
+        assert!(report.error().is_none());
+
+        let yaml = serde_yaml::to_string(&report).unwrap();
+        assert!(yaml.contains("billing"));
+        assert!(!yaml.contains("secret"));
+    }
+}
@@ -1,25 +1,181 @@
+use crate::config::AnswerInfo;
+use crate::input::parse_bool;
 use crate::Archetect;
+use linked_hash_map::LinkedHashMap;
 use semver::{Version, VersionReq};
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
+use std::process::Command;
 
+/// What an archetype requires to be rendered, declared in `requirements.yml` beside `archetype.yml`: the Archetect
+/// version, the versions of the archetypes it composes, keyed by their sources as written in its script, and the tools
+/// its commands run:
+///
+/// ```yaml
+/// archetect: ">=0.7.0"
+/// archetypes:
+///   "git@github.com:archetect/archetype-rust-module.git": ">=1.2.0"
+///   ../persistence: "^2.0"
+/// tools:
+///   - tool: git
+///   - tool: docker
+///     version: ">=20"
+///     if: use_docker
+/// ```
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Requirements {
     #[serde(rename = "archetect")]
     archetect_requirement: VersionReq,
+    #[serde(default, skip_serializing_if = "LinkedHashMap::is_empty")]
+    archetypes: LinkedHashMap<String, VersionReq>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ToolRequirement>,
+}
+
+/// A tool that must be on the path, at a version if given, which is found from what `<tool> --version` prints. A tool
+/// with a condition is only required when the switch it names is enabled or the answer it names is true, so that
+/// tools for features that are not being rendered are not demanded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolRequirement {
+    tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<VersionReq>,
+    #[serde(rename = "if", skip_serializing_if = "Option::is_none")]
+    condition: Option<String>,
+}
+
+impl ToolRequirement {
+    pub fn new<T: Into<String>>(tool: T) -> ToolRequirement {
+        ToolRequirement {
+            tool: tool.into(),
+            version: None,
+            condition: None,
+        }
+    }
+
+    pub fn with_version(mut self, version: VersionReq) -> ToolRequirement {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn with_condition<C: Into<String>>(mut self, condition: C) -> ToolRequirement {
+        self.condition = Some(condition.into());
+        self
+    }
+
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    pub fn version(&self) -> Option<&VersionReq> {
+        self.version.as_ref()
+    }
+
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_deref()
+    }
+
+    /// Whether this tool is required, given the enabled `switches` and the `answers` known before rendering.
+    pub fn is_required(&self, switches: &HashSet<String>, answers: &LinkedHashMap<String, AnswerInfo>) -> bool {
+        let condition = match &self.condition {
+            Some(condition) => condition.trim(),
+            None => return true,
+        };
+        switches.contains(condition)
+            || answers
+                .get(condition)
+                .and_then(|answer| answer.value())
+                .and_then(parse_bool)
+                .unwrap_or_default()
+    }
+
+    pub fn verify(&self) -> Result<(), RequirementsError> {
+        let output = match Command::new(&self.tool).arg("--version").output() {
+            Ok(output) => output,
+            Err(_) => return Err(RequirementsError::ToolMissing(self.tool.clone())),
+        };
+        let requirement = match &self.version {
+            Some(requirement) => requirement,
+            None => return Ok(()),
+        };
+        let printed = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        match find_version(&printed) {
+            Some(version) if requirement.matches(&version) => Ok(()),
+            Some(version) => Err(RequirementsError::ToolVersion {
+                tool: self.tool.clone(),
+                version: version.to_string(),
+                requirement: requirement.to_string(),
+            }),
+            None => Err(RequirementsError::ToolVersionUnknown {
+                tool: self.tool.clone(),
+                requirement: requirement.to_string(),
+            }),
+        }
+    }
+}
+
+/// The first version in `printed`, with any missing minor or patch taken as zero, as in `Docker version 24.0, build`.
+fn find_version(printed: &str) -> Option<Version> {
+    printed
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|candidate| candidate.trim_matches('.'))
+        .find(|candidate| candidate.contains('.'))
+        .and_then(|candidate| {
+            let mut parts = candidate.split('.').map(|part| part.parse::<u64>().ok());
+            let major = parts.next()??;
+            let minor = parts.next().flatten().unwrap_or_default();
+            let patch = parts.next().flatten().unwrap_or_default();
+            Some(Version::new(major, minor, patch))
+        })
 }
 
 impl Requirements {
     pub fn new(archetect_version: VersionReq) -> Requirements {
         Requirements {
             archetect_requirement: archetect_version,
+            archetypes: LinkedHashMap::new(),
+            tools: vec![],
         }
     }
 
+    pub fn with_archetype<S: Into<String>>(mut self, source: S, requirement: VersionReq) -> Requirements {
+        self.archetypes.insert(source.into(), requirement);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: ToolRequirement) -> Requirements {
+        self.tools.push(tool);
+        self
+    }
+
+    pub fn tools(&self) -> &[ToolRequirement] {
+        &self.tools
+    }
+
     pub fn archetect_version(&self) -> &VersionReq {
         &self.archetect_requirement
     }
 
+    /// The versions required of the archetypes this one composes, keyed by their sources.
+    pub fn archetypes(&self) -> &LinkedHashMap<String, VersionReq> {
+        &self.archetypes
+    }
+
+    /// The version required of the archetype at `source`, which is matched regardless of any `#ref`.
+    pub fn archetype_version(&self, source: &str) -> Option<&VersionReq> {
+        let location = |source: &str| source.split('#').next().unwrap_or_default().to_owned();
+        let source = location(source);
+        self.archetypes
+            .iter()
+            .find(|(candidate, _)| location(candidate) == source)
+            .map(|(_, requirement)| requirement)
+    }
+
     pub fn load<P: Into<PathBuf>>(path: P) -> Result<Option<Requirements>, RequirementsError> {
         let mut path = path.into();
         if path.is_dir() {
@@ -60,6 +216,38 @@ impl Requirements {
             Ok(())
         }
     }
+
+    /// Checks the tools that are required given the enabled switches and the `answers` known before rendering.
+    pub fn verify_tools(
+        &self,
+        archetect: &Archetect,
+        answers: &LinkedHashMap<String, AnswerInfo>,
+    ) -> Result<(), RequirementsError> {
+        self.tools
+            .iter()
+            .filter(|tool| tool.is_required(archetect.switches(), answers))
+            .try_for_each(|tool| tool.verify())
+    }
+
+    /// Checks the `version` an archetype declares, or is tagged with, against what is required of `source`.
+    pub fn verify_archetype(&self, source: &str, version: Option<&Version>) -> Result<(), RequirementsError> {
+        let requirement = match self.archetype_version(source) {
+            Some(requirement) => requirement,
+            None => return Ok(()),
+        };
+        match version {
+            Some(version) if requirement.matches(version) => Ok(()),
+            Some(version) => Err(RequirementsError::ArchetypeVersion {
+                archetype: source.to_owned(),
+                version: version.to_string(),
+                requirement: requirement.to_string(),
+            }),
+            None => Err(RequirementsError::ArchetypeVersionUnknown {
+                archetype: source.to_owned(),
+                requirement: requirement.to_string(),
+            }),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -68,6 +256,24 @@ pub enum RequirementsError {
     DeserializationError { path: PathBuf, cause: serde_yaml::Error },
     #[error("Incompatible Archetect Version `{0}`. Requirements: {1}")]
     ArchetectVersion(Version, VersionReq),
+    #[error("Incompatible Archetype Version `{version}` of `{archetype}`. Requirements: {requirement}")]
+    ArchetypeVersion {
+        archetype: String,
+        version: String,
+        requirement: String,
+    },
+    #[error("`{archetype}` declares no version, and is not tagged with one. Requirements: {requirement}")]
+    ArchetypeVersionUnknown { archetype: String, requirement: String },
+    #[error("`{0}` is required, but was not found")]
+    ToolMissing(String),
+    #[error("Incompatible Version `{version}` of `{tool}`. Requirements: {requirement}")]
+    ToolVersion {
+        tool: String,
+        version: String,
+        requirement: String,
+    },
+    #[error("The version of `{tool}` could not be determined. Requirements: {requirement}")]
+    ToolVersionUnknown { tool: String, requirement: String },
     #[error("IO Error Reading Requirements File `{0}`.")]
     IoError(std::io::Error),
 }
@@ -77,3 +283,122 @@ impl From<std::io::Error> for RequirementsError {
         RequirementsError::IoError(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::source::SourceError;
+    use crate::ArchetypeError;
+
+    #[test]
+    fn test_archetype_requirements() {
+        let requirements: Requirements = serde_yaml::from_str(
+            "archetect: \">=0.1.0\"\narchetypes:\n  \"git@github.com:archetect/archetype-rust-module.git\": \">=1.2.0\"\n",
+        )
+        .unwrap();
+        let source = "git@github.com:archetect/archetype-rust-module.git#v1.1.0";
+        assert!(requirements.archetype_version(source).is_some());
+        assert!(requirements
+            .verify_archetype(source, Some(&Version::new(1, 2, 3)))
+            .is_ok());
+        assert!(matches!(
+            requirements.verify_archetype(source, Some(&Version::new(1, 1, 0))),
+            Err(RequirementsError::ArchetypeVersion { .. })
+        ));
+        assert!(matches!(
+            requirements.verify_archetype(source, None),
+            Err(RequirementsError::ArchetypeVersionUnknown { .. })
+        ));
+        assert!(requirements.verify_archetype("../other", None).is_ok());
+    }
+
+    #[test]
+    fn test_tool_requirements() {
+        let requirements: Requirements = serde_yaml::from_str(
+            "archetect: \"*\"\ntools:\n  - tool: cargo\n    version: \">=1.0\"\n  \
+             - tool: archetect-missing-tool\n    if: use_missing\n",
+        )
+        .unwrap();
+        let archetect = Archetect::builder().build().unwrap();
+        let mut answers = LinkedHashMap::new();
+        assert!(requirements.verify_tools(&archetect, &answers).is_ok());
+
+        answers.insert("use_missing".to_owned(), AnswerInfo::with_value("yes").build());
+        assert!(matches!(
+            requirements.verify_tools(&archetect, &answers),
+            Err(RequirementsError::ToolMissing(_))
+        ));
+
+        let requirements = Requirements::new(VersionReq::any())
+            .with_tool(ToolRequirement::new("cargo").with_version(VersionReq::parse(">=999").unwrap()));
+        assert!(matches!(
+            requirements.verify_tools(&archetect, &LinkedHashMap::new()),
+            Err(RequirementsError::ToolVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_version() {
+        assert_eq!(
+            find_version("Docker version 24.0.5, build ced0996"),
+            Some(Version::new(24, 0, 5))
+        );
+        assert_eq!(find_version("git version 2.39"), Some(Version::new(2, 39, 0)));
+        assert_eq!(find_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_verify_child_versions() {
+        let root = tempfile::tempdir().unwrap();
+        let parent = root.path().join("parent");
+        let child = root.path().join("child");
+        fs::create_dir_all(&parent).unwrap();
+        fs::create_dir_all(&child).unwrap();
+        fs::write(parent.join("archetype.yml"), "script: []\n").unwrap();
+        fs::write(
+            parent.join("requirements.yml"),
+            "archetect: \"*\"\narchetypes:\n  ../child: \"^2.0\"\n",
+        )
+        .unwrap();
+        fs::write(child.join("archetype.yml"), "version: 1.4.0\nscript: []\n").unwrap();
+
+        let archetect = Archetect::builder().build().unwrap();
+        // The parent is not loaded at all while a child it composes is incompatible, as its script might otherwise
+        // get partway through before coming to it.
+        let error = archetect.load_archetype(parent.to_str().unwrap(), None).err().unwrap();
+        assert!(matches!(
+            error,
+            ArchetypeError::SourceError(SourceError::RequirementsError {
+                cause: RequirementsError::ArchetypeVersion { .. },
+                ..
+            })
+        ));
+
+        fs::write(child.join("archetype.yml"), "version: 2.1.0\nscript: []\n").unwrap();
+        let parent_archetype = archetect.load_archetype(parent.to_str().unwrap(), None).unwrap();
+        assert!(archetect
+            .load_archetype("../child", Some(parent_archetype.source().clone()))
+            .is_ok());
+
+        fs::write(child.join("archetype.yml"), "version: 1.4.0\nscript: []\n").unwrap();
+        let error = archetect
+            .load_archetype("../child", Some(parent_archetype.source().clone()))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            ArchetypeError::SourceError(SourceError::RequirementsError {
+                cause: RequirementsError::ArchetypeVersion { .. },
+                ..
+            })
+        ));
+
+        fs::remove_dir_all(&child).unwrap();
+        assert!(matches!(
+            archetect.load_archetype(parent.to_str().unwrap(), None),
+            Err(ArchetypeError::SourceError(SourceError::SourceNotFound(_)))
+        ));
+    }
+}
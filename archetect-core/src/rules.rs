@@ -4,12 +4,29 @@ use linked_hash_map::LinkedHashMap;
 use log::trace;
 
 use crate::config::{Pattern, RuleAction, RuleConfig};
+use crate::editorconfig::EditorConfig;
+use crate::headers::HeadersConfig;
+use crate::selection::SourceSelection;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RulesContext {
     overwrite: bool,
+    #[serde(default)]
+    collisions: CollisionPolicy,
+    #[serde(default)]
+    empty_directories: EmptyDirectoryPolicy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<HeadersConfig>,
+    #[serde(skip)]
+    editorconfig: Option<EditorConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     path_rules: Option<LinkedHashMap<String, RuleConfig>>,
+    #[serde(skip)]
+    selection: Option<SourceSelection>,
+    #[serde(skip)]
+    exclusion: Option<SourceSelection>,
+    #[serde(skip)]
+    component: Option<String>,
     break_triggered: bool,
 }
 
@@ -17,7 +34,14 @@ impl RulesContext {
     pub fn new() -> RulesContext {
         RulesContext {
             overwrite: false,
+            collisions: CollisionPolicy::default(),
+            empty_directories: EmptyDirectoryPolicy::default(),
+            headers: None,
+            editorconfig: None,
             path_rules: None,
+            selection: None,
+            exclusion: None,
+            component: None,
             break_triggered: false,
         }
     }
@@ -30,6 +54,84 @@ impl RulesContext {
         self.overwrite
     }
 
+    pub fn set_collisions(&mut self, collisions: CollisionPolicy) {
+        self.collisions = collisions;
+    }
+
+    pub fn collisions(&self) -> CollisionPolicy {
+        self.collisions
+    }
+
+    pub fn set_empty_directories(&mut self, empty_directories: EmptyDirectoryPolicy) {
+        self.empty_directories = empty_directories;
+    }
+
+    pub fn empty_directories(&self) -> EmptyDirectoryPolicy {
+        self.empty_directories
+    }
+
+    pub fn set_headers(&mut self, headers: Option<HeadersConfig>) {
+        self.headers = headers;
+    }
+
+    pub fn headers(&self) -> Option<&HeadersConfig> {
+        self.headers.as_ref()
+    }
+
+    pub fn set_editorconfig(&mut self, editorconfig: Option<EditorConfig>) {
+        self.editorconfig = editorconfig;
+    }
+
+    pub fn editorconfig(&self) -> Option<&EditorConfig> {
+        self.editorconfig.as_ref()
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SourceSelection>) {
+        self.selection = selection;
+    }
+
+    /// Source paths to skip whatever the archetype's rules say, such as workflows the consumer does not want.
+    pub fn set_exclusion(&mut self, exclusion: Option<SourceSelection>) {
+        self.exclusion = exclusion;
+    }
+
+    pub fn is_excluded<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.exclusion.as_ref().is_some_and(|exclusion| exclusion.is_selected(path))
+    }
+
+    /// Whether the source `path` is to be rendered, which all are unless only part of the archetype was selected or
+    /// some of it excluded.
+    pub fn is_selected<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        !self.is_excluded(path) && self.selection.as_ref().is_none_or(|selection| selection.is_selected(path))
+    }
+
+    /// The component the actions executing belong to, should they have said.
+    pub fn component(&self) -> Option<&str> {
+        self.component.as_deref()
+    }
+
+    pub fn set_component(&mut self, component: Option<String>) {
+        self.component = component;
+    }
+
+    /// Whether an action that changes the destination other than by rendering source, such as `exec` or `append`, is
+    /// to run. All are unless only part of the archetype was selected, in which case only those in a component
+    /// selected are, or some of it was skipped, in which case those in a component skipped are not.
+    pub fn is_action_selected(&self) -> bool {
+        let component = self.component.as_deref();
+        let skipped = match (&self.exclusion, component) {
+            (Some(exclusion), Some(component)) => exclusion.includes(component),
+            _ => false,
+        };
+        let selected = match (&self.selection, component) {
+            (None, _) => true,
+            (Some(selection), Some(component)) => selection.includes(component),
+            (Some(_), None) => false,
+        };
+        selected && !skipped
+    }
+
     pub fn path_rules_mut(&mut self) -> Option<&mut LinkedHashMap<String, RuleConfig>> {
         self.path_rules.as_mut()
     }
@@ -63,9 +165,16 @@ impl RulesContext {
     }
 
     pub fn get_source_action<P: AsRef<Path>>(&self, path: P) -> RuleAction {
+        self.get_source_rule(path)
+            .map(|path_rule| path_rule.action())
+            .unwrap_or(RuleAction::RENDER)
+    }
+
+    /// The first source rule matching `path`, if any.
+    pub fn get_source_rule<P: AsRef<Path>>(&self, path: P) -> Option<&RuleConfig> {
         if let Some(path_rules) = self.path_rules() {
             let path = path.as_ref();
-            for (name, path_rule) in path_rules {
+            for (name, path_rule) in path_rules.iter().filter(|(_, path_rule)| path_rule.applies()) {
                 for pattern in path_rule.patterns() {
                     match pattern {
                         Pattern::GLOB(pattern) => {
@@ -78,7 +187,7 @@ impl RulesContext {
                                     pattern,
                                     path.display()
                                 );
-                                return path_rule.action().clone();
+                                return Some(path_rule);
                             }
                         }
                         _ => unimplemented!(),
@@ -86,10 +195,37 @@ impl RulesContext {
                 }
             }
         }
-        RuleAction::RENDER
+        None
     }
 }
 
+/// What to do when a destination rendered earlier in the same run is rendered again with different content, such as
+/// from a composed archetype or a loop.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum CollisionPolicy {
+    #[default]
+    #[serde(rename = "WARN", alias = "warn")]
+    Warn,
+    #[serde(rename = "ERROR", alias = "error")]
+    Error,
+    #[serde(rename = "IGNORE", alias = "ignore")]
+    Ignore,
+}
+
+/// What to do with directories that are empty once rendered, such as `src/test/resources`, which git would not keep.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum EmptyDirectoryPolicy {
+    #[default]
+    #[serde(rename = "KEEP", alias = "keep")]
+    Keep,
+    /// Add an empty `.gitkeep` file, so that the directory is committed.
+    #[serde(rename = "GITKEEP", alias = "gitkeep")]
+    Gitkeep,
+    /// Remove the directory, unless it existed before rendering.
+    #[serde(rename = "PRUNE", alias = "prune")]
+    Prune,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum WriteRule {
     #[serde(rename = "IF_MISSING")]
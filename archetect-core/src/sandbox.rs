@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::utils::resolve_path;
+use crate::ArchetectError;
+
+/// Where the destination is mounted within a sandbox.
+pub const SANDBOX_WORKSPACE: &str = "/workspace";
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerEngine {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl ContainerEngine {
+    pub fn program(&self) -> &'static str {
+        match self {
+            ContainerEngine::Docker => "docker",
+            ContainerEngine::Podman => "podman",
+        }
+    }
+}
+
+/// Runs `exec` actions in a container rather than on the machine itself, with only the destination mounted, so that
+/// commands from archetypes that are not trusted cannot reach anything else. Declared by an archetype, for an image
+/// with the tools its commands need, or set for every archetype when rendering:
+///
+/// ```yaml
+/// sandbox:
+///   image: node:20
+///   engine: podman
+///   network: false
+/// ```
+///
+/// Commands are run as the owner of the destination, so that the files they create belong to them.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SandboxConfig {
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    engine: Option<ContainerEngine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network: Option<bool>,
+}
+
+impl SandboxConfig {
+    pub fn new<I: Into<String>>(image: I) -> SandboxConfig {
+        SandboxConfig {
+            image: image.into(),
+            engine: None,
+            network: None,
+        }
+    }
+
+    pub fn with_engine(mut self, engine: ContainerEngine) -> SandboxConfig {
+        self.engine = Some(engine);
+        self
+    }
+
+    pub fn with_network(mut self, network: bool) -> SandboxConfig {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    pub fn engine(&self) -> ContainerEngine {
+        self.engine.unwrap_or_default()
+    }
+
+    /// Whether sandboxed commands may use the network, which they may unless disabled.
+    pub fn network(&self) -> bool {
+        self.network.unwrap_or(true)
+    }
+
+    /// The command running `program` with `args` and `env` in a container, with `destination` mounted at
+    /// [`SANDBOX_WORKSPACE`] and working in `cwd`, which must be within the destination.
+    pub fn command(
+        &self,
+        destination: &Path,
+        cwd: &Path,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<Command, ArchetectError> {
+        let destination = fs::canonicalize(destination)?;
+        let cwd = fs::canonicalize(cwd).unwrap_or_else(|_| resolve_path(cwd, false));
+        let workdir = match cwd.strip_prefix(&destination) {
+            Ok(relative) => relative
+                .components()
+                .fold(SANDBOX_WORKSPACE.to_owned(), |workdir, component| {
+                    format!("{}/{}", workdir, component.as_os_str().to_string_lossy())
+                }),
+            Err(_) => return Err(ArchetectError::OutsideSandbox(cwd)),
+        };
+
+        let mut command = Command::new(self.engine().program());
+        command
+            .args(["run", "--rm", "--volume"])
+            .arg(format!("{}:{}", destination.display(), SANDBOX_WORKSPACE))
+            .arg("--workdir")
+            .arg(workdir);
+        if !self.network() {
+            command.args(["--network", "none"]);
+        }
+        if let Some(user) = owner(&destination) {
+            command.arg("--user").arg(user);
+        }
+        for (key, value) in env {
+            command.arg("--env").arg(format!("{}={}", key, value));
+        }
+        command.arg(&self.image).arg(program).args(args);
+        Ok(command)
+    }
+}
+
+#[cfg(unix)]
+fn owner(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path).ok()?;
+    Some(format!("{}:{}", metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn owner(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let destination = tempfile::tempdir().unwrap();
+        fs::create_dir_all(destination.path().join("web")).unwrap();
+        let root = fs::canonicalize(destination.path()).unwrap();
+
+        let sandbox: SandboxConfig =
+            serde_yaml::from_str("image: node:20\nengine: podman\nnetwork: false\n").unwrap();
+        let command = sandbox
+            .command(
+                destination.path(),
+                &destination.path().join("web"),
+                "npm",
+                &["install".to_owned()],
+                &[("CI".to_owned(), "true".to_owned())],
+            )
+            .unwrap();
+        assert_eq!(command.get_program(), "podman");
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        assert_eq!(&args[..4], &["run", "--rm", "--volume", &format!("{}:/workspace", root.display())]);
+        assert_eq!(&args[4..8], &["--workdir", "/workspace/web", "--network", "none"]);
+        assert_eq!(&args[args.len() - 5..], &["--env", "CI=true", "node:20", "npm", "install"]);
+
+        let outside = tempfile::tempdir().unwrap();
+        let result = SandboxConfig::new("node:20").command(destination.path(), outside.path(), "npm", &[], &[]);
+        assert!(matches!(result, Err(ArchetectError::OutsideSandbox(_))));
+    }
+}
@@ -0,0 +1,93 @@
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::vendor::tera::Context;
+
+/// A scope within a context, such as the body of a `scope` or a loop, layered over the enclosing context rather than
+/// copied from it. Variables set within the scope shadow those of the enclosing context, and are discarded with the
+/// scope unless exported. The enclosing context is restored when the scope is dropped.
+pub struct ScopedContext<'a> {
+    outer: &'a mut Context,
+    inner: Context,
+    exports: Vec<String>,
+}
+
+impl<'a> ScopedContext<'a> {
+    pub fn new(outer: &'a mut Context) -> ScopedContext<'a> {
+        let inner = Context::with_parent(Arc::new(mem::take(outer)));
+        ScopedContext {
+            outer,
+            inner,
+            exports: vec![],
+        }
+    }
+
+    /// Keeps `key`, as set within the scope, in the enclosing context once the scope ends.
+    pub fn export<S: Into<String>>(&mut self, key: S) {
+        self.exports.push(key.into());
+    }
+}
+
+impl Deref for ScopedContext<'_> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        &self.inner
+    }
+}
+
+impl DerefMut for ScopedContext<'_> {
+    fn deref_mut(&mut self) -> &mut Context {
+        &mut self.inner
+    }
+}
+
+impl Drop for ScopedContext<'_> {
+    fn drop(&mut self) {
+        let (parent, mut locals) = mem::take(&mut self.inner).into_parts();
+        // The enclosing context is only copied should a clone of the scope have outlived it.
+        let mut outer = parent.map(Arc::unwrap_or_clone).unwrap_or_default();
+        for key in self.exports.drain(..) {
+            if let Some(value) = locals.remove(&key) {
+                outer.insert_shared(key, value);
+            }
+        }
+        *self.outer = outer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_scoped_context() {
+        let mut context = Context::new();
+        context.insert("name", "billing");
+        context.insert("items", &vec!["a", "b"]);
+
+        {
+            let mut scope = ScopedContext::new(&mut context);
+            assert_eq!(scope.get("name"), Some(&json!("billing")));
+            scope.insert("name", "payments");
+            scope.insert("item", "a");
+            scope.insert("count", &2);
+            scope.export("count");
+            assert_eq!(scope.get("name"), Some(&json!("payments")));
+            assert_eq!(scope.local_keys().collect::<Vec<_>>(), vec!["count", "item", "name"]);
+
+            let mut nested = ScopedContext::new(&mut scope);
+            nested.insert("item", "b");
+            assert_eq!(nested.get("name"), Some(&json!("payments")));
+            assert_eq!(nested.get("items"), Some(&json!(["a", "b"])));
+        }
+
+        assert_eq!(context.get("name"), Some(&json!("billing")));
+        assert_eq!(context.get("count"), Some(&json!(2)));
+        assert!(!context.contains_key("item"));
+        assert!(context.parent().is_none());
+    }
+}
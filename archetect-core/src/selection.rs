@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+use crate::config::ArchetypeConfig;
+
+/// The parts of an archetype to render when only some of it is to be regenerated, such as its CI pipeline or one
+/// module. Each selection is either the name of a component the archetype declares or a glob of source paths relative
+/// to the archetype:
+///
+/// ```yaml
+/// components:
+///   ci: [ "contents/.github/**" ]
+///   api: [ "contents/api", "contents/docs/api.md" ]
+/// ```
+///
+/// A source path is selected when it, or a directory containing it, matches. Scripts still run in full, so that
+/// everything selected renders with the same answers as before, though actions that change the destination other than
+/// by rendering, such as `exec` or `append`, only run when they name a component selected with `component:`.
+#[derive(Debug, Clone)]
+pub struct SourceSelection {
+    root: PathBuf,
+    selections: Vec<String>,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl SourceSelection {
+    pub fn resolve<P: Into<PathBuf>>(root: P, config: &ArchetypeConfig, selections: &[String]) -> SourceSelection {
+        let patterns = selections
+            .iter()
+            .flat_map(|selection| match config.component(selection) {
+                Some(patterns) => patterns.to_vec(),
+                None => vec![selection.to_owned()],
+            })
+            .filter_map(|pattern| {
+                match glob::Pattern::new(pattern.trim_start_matches('/').trim_end_matches('/')) {
+                    Ok(pattern) => Some(pattern),
+                    Err(error) => {
+                        warn!("Ignoring invalid selection `{}`: {}", pattern, error);
+                        None
+                    }
+                }
+            })
+            .collect();
+        SourceSelection {
+            root: root.into(),
+            selections: selections.to_vec(),
+            patterns,
+        }
+    }
+
+    /// Whether `component` was named among the selections.
+    pub fn includes(&self, component: &str) -> bool {
+        self.selections.iter().any(|selection| selection == component)
+    }
+
+    /// Whether the source `path`, within the archetype, is selected.
+    pub fn is_selected<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative
+            .ancestors()
+            .any(|ancestor| self.patterns.iter().any(|pattern| pattern.matches_path(ancestor)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_selected() {
+        let config = ArchetypeConfig::default().with_component("ci", vec!["contents/.github/**".to_owned()]);
+
+        let selection = SourceSelection::resolve("archetype", &config, &["ci".to_owned()]);
+        assert!(selection.is_selected("archetype/contents/.github/workflows/ci.yml"));
+        assert!(!selection.is_selected("archetype/contents/src/main.rs"));
+
+        let selection = SourceSelection::resolve("archetype", &config, &["contents/src/".to_owned()]);
+        assert!(selection.is_selected("archetype/contents/src"));
+        assert!(selection.is_selected("archetype/contents/src/{{ name }}/mod.rs"));
+        assert!(!selection.is_selected("archetype/contents/Cargo.toml"));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::config::AnswerConfig;
+#[cfg(feature = "catalog")]
+use crate::config::{Catalog, CatalogEntry};
+use crate::events::Event;
+use crate::input::{Prompt, PromptProvider};
+use crate::system::SystemLayout;
+use crate::{Archetect, ArchetectError};
+
+/// The heading grouping the prompts asked during setup.
+const SETUP_GROUP: &str = "Archetect Setup";
+
+/// What was created by [`run`].
+#[derive(Debug, Default, PartialEq)]
+pub struct Setup {
+    directories: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl Setup {
+    /// The directories of the layout that did not exist yet.
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    /// The configuration files written, such as the answers given for every archetype and the catalog.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+/// Whether Archetect has not been set up with `layout` yet, as its configuration directory does not exist.
+pub fn is_first_run(layout: &dyn SystemLayout) -> bool {
+    !layout.configs_dir().exists()
+}
+
+/// Sets up the layout of `archetect`, asking for the author's name and email, answered for every archetype, and for a
+/// catalog to choose archetypes from. The author defaults to the user's git identity. Existing configuration files are
+/// kept, and nothing is asked without anyone to ask.
+pub fn run(archetect: &Archetect) -> Result<Setup, ArchetectError> {
+    let layout = archetect.layout();
+    let mut setup = Setup::default();
+    for directory in [layout.configs_dir(), layout.git_cache_dir(), layout.catalog_cache_dir()] {
+        if !directory.exists() {
+            fs::create_dir_all(&directory)?;
+            setup.directories.push(directory);
+        }
+    }
+
+    let provider = match archetect.prompt_provider() {
+        Some(provider) if !archetect.headless() => provider,
+        _ => return Ok(setup),
+    };
+    let ask = |prompt: Prompt| ask(archetect, provider.as_ref(), prompt);
+
+    let answers_path = layout.answers_config();
+    if !answers_path.exists() {
+        let mut answers = AnswerConfig::default();
+        let identity = archetect.git_identity().clone();
+        for (identifier, message, detected) in [
+            ("author_name", "Your name, for archetypes that ask for their author:", identity.name),
+            ("author_email", "Your email address:", identity.email),
+        ] {
+            let prompt = Prompt::new(identifier, message);
+            let prompt = match detected {
+                Some(default) => prompt.with_default(default),
+                None => prompt,
+            };
+            if let Some(answer) = ask(prompt)? {
+                answers.add_answer(identifier, &answer);
+            }
+        }
+        answers.save(&answers_path).map_err(|source| ArchetectError::AnswerConfigError {
+            path: answers_path.display().to_string(),
+            source,
+        })?;
+        setup.files.push(answers_path);
+    }
+
+    #[cfg(feature = "catalog")]
+    {
+        let catalog_path = layout.catalog();
+        if !catalog_path.exists() {
+            let prompt = Prompt::new(
+                "catalog",
+                "A catalog to choose archetypes from, if any (directory, git URL, or shorthand):",
+            )
+            .with_example("gh:acme/archetypes");
+            if let Some(source) = ask(prompt)? {
+                let catalog = Catalog::new().with_entry(CatalogEntry::Catalog {
+                    description: "Default Catalog".to_owned(),
+                    source: archetect.expand_source(&source),
+                });
+                catalog.save_to_file(&catalog_path)?;
+                setup.files.push(catalog_path);
+            }
+        }
+    }
+
+    Ok(setup)
+}
+
+/// Asks `prompt`, returning the answer unless it was left blank.
+fn ask(archetect: &Archetect, provider: &dyn PromptProvider, prompt: Prompt) -> Result<Option<String>, ArchetectError> {
+    let prompt = prompt.with_group(SETUP_GROUP).with_required(false);
+    archetect.emit(Event::PromptAsked(prompt.clone()));
+    match provider.prompt(&prompt)? {
+        Some(Value::String(answer)) if !answer.trim().is_empty() => Ok(Some(answer.trim().to_owned())),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(all(test, feature = "catalog"))]
+mod tests {
+    use super::*;
+    use crate::input::ScriptedPromptProvider;
+    use crate::system::RootedSystemLayout;
+
+    #[test]
+    fn test_run() {
+        let root = tempfile::tempdir().unwrap();
+        let provider = ScriptedPromptProvider::new()
+            .with_answer("author_name", "Jane Doe")
+            .with_answer("author_email", "jane@example.com")
+            .with_answer("catalog", "gh:acme/archetypes");
+        let archetect = Archetect::builder()
+            .with_layout(RootedSystemLayout::new(root.path()).unwrap())
+            .with_prompt_provider(provider)
+            .build()
+            .unwrap();
+        let layout = archetect.layout();
+        assert!(is_first_run(layout.as_ref().as_ref()));
+
+        let setup = run(&archetect).unwrap();
+        assert!(!is_first_run(layout.as_ref().as_ref()));
+        assert!(setup.directories().contains(&layout.configs_dir()));
+        assert_eq!(setup.files(), &[layout.answers_config(), layout.catalog()]);
+
+        let answers = AnswerConfig::load(layout.answers_config()).unwrap();
+        assert_eq!(answers.answers().get("author_name").unwrap().value(), Some("Jane Doe"));
+        assert_eq!(answers.answers().get("author_email").unwrap().value(), Some("jane@example.com"));
+        let catalog = fs::read_to_string(layout.catalog()).unwrap();
+        assert!(catalog.contains("https://github.com/acme/archetypes.git"));
+
+        assert_eq!(run(&archetect).unwrap(), Setup::default());
+    }
+}
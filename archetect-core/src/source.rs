@@ -1,19 +1,27 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+#[cfg(feature = "git")]
+use std::process::Command;
 use std::sync::Mutex;
 
-use log::{debug, info};
 use regex::Regex;
-use url::Url;
+use semver::Version;
 
+use crate::config::ArchetypeConfig;
 use crate::requirements::{Requirements, RequirementsError};
+use crate::source::spec::SourceSpec;
 use crate::Archetect;
 
+pub mod blob;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod spec;
+
 #[derive(Clone, Debug, PartialOrd, PartialEq)]
 pub enum Source {
     RemoteGit { url: String, path: PathBuf, gitref: Option<String> },
     RemoteHttp { url: String, path: PathBuf },
+    RemoteBlob { url: String, path: PathBuf },
     LocalDirectory { path: PathBuf },
     LocalFile { path: PathBuf },
 }
@@ -38,6 +46,8 @@ pub enum SourceError {
     IoError(std::io::Error),
     #[error("Requirements Error in `{path}`: {cause}")]
     RequirementsError { path: String, cause: RequirementsError },
+    #[error("Pinned ref `{gitref}` resolved to `{head}` after checkout, but expected `{expected}`")]
+    PinnedRefMismatch { gitref: String, expected: String, head: String },
 }
 
 impl From<std::io::Error> for SourceError {
@@ -53,89 +63,20 @@ lazy_static! {
 
 impl Source {
     pub fn detect(archetect: &Archetect, path: &str, relative_to: Option<Source>) -> Result<Source, SourceError> {
-        let source = path;
-        let git_cache = archetect.layout().git_cache_dir();
-
-        let urlparts: Vec<&str> = path.split('#').collect();
-        if let Some(captures) = SSH_GIT_PATTERN.captures(&urlparts[0]) {
-
-            let cache_path = git_cache
-                .clone()
-                .join(get_cache_key(format!("{}/{}", &captures[1], &captures[2])));
-
-            let gitref = if urlparts.len() > 1 { Some(urlparts[1].to_owned()) } else { None };
-            if let Err(error) = cache_git_repo(urlparts[0], &gitref, &cache_path, archetect
-                .offline()) {
-                return Err(error);
-            }
-            verify_requirements(archetect, source, &cache_path)?;
-            return Ok(Source::RemoteGit {
-                url: path.to_owned(),
-                path: cache_path,
-                gitref,
-            });
-        };
-
-        if let Ok(url) = Url::parse(&path) {
-            if path.contains(".git") && url.has_host() {
-                let cache_path =
-                    git_cache
-                        .clone()
-                        .join(get_cache_key(format!("{}/{}", url.host_str().unwrap(), url.path())));
-                let gitref = url.fragment().map_or(None, |r| Some(r.to_owned()));
-                if let Err(error) = cache_git_repo(urlparts[0], &gitref, &cache_path, archetect.offline()) {
-                    return Err(error);
-                }
-                verify_requirements(archetect, source, &cache_path)?;
-                return Ok(Source::RemoteGit {
-                    url: path.to_owned(),
-                    path: cache_path,
-                    gitref,
-                });
-            }
-
-            if let Ok(local_path) = url.to_file_path() {
-                return if local_path.exists() {
-                    verify_requirements(archetect, source, &local_path)?;
-                    Ok(Source::LocalDirectory { path: local_path })
-                } else {
-                    Err(SourceError::SourceNotFound(local_path.display().to_string()))
-                };
-            }
-        }
-
-        if let Ok(path) = shellexpand::full(&path) {
-            let local_path = PathBuf::from(path.as_ref());
-            if local_path.is_relative() {
-                if let Some(parent) = relative_to {
-                    let local_path = parent.local_path().clone().join(local_path);
-                    if local_path.exists() && local_path.is_dir() {
-                        verify_requirements(archetect, source, &local_path)?;
-                        return Ok(Source::LocalDirectory { path: local_path });
-                    } else {
-                        return Err(SourceError::SourceNotFound(local_path.display().to_string()));
-                    }
-                }
-            }
-            if local_path.exists() {
-                if local_path.is_dir() {
-                    verify_requirements(archetect, source, &local_path)?;
-                    return Ok(Source::LocalDirectory { path: local_path });
-                } else {
-                    return Ok(Source::LocalFile { path: local_path });
-                }
-            } else {
-                return Err(SourceError::SourceNotFound(local_path.display().to_string()));
-            }
-        } else {
-            return Err(SourceError::SourceInvalidPath(path.to_string()));
-        }
+        SourceSpec::parse(&archetect.expand_source(path))?.resolve(archetect, relative_to)
     }
 
     pub fn directory(&self) -> &Path {
         match self {
             Source::RemoteGit { url: _, path, gitref: _ } => path.as_path(),
             Source::RemoteHttp { url: _, path } => path.as_path(),
+            Source::RemoteBlob { url: _, path } => {
+                if path.is_dir() {
+                    path.as_path()
+                } else {
+                    path.parent().unwrap_or(path)
+                }
+            }
             Source::LocalDirectory { path } => path.as_path(),
             Source::LocalFile { path } => path.parent().unwrap_or(path),
         }
@@ -145,15 +86,35 @@ impl Source {
         match self {
             Source::RemoteGit { url: _, path, gitref: _ } => path.as_path(),
             Source::RemoteHttp { url: _, path } => path.as_path(),
+            Source::RemoteBlob { url: _, path } => path.as_path(),
             Source::LocalDirectory { path } => path.as_path(),
             Source::LocalFile { path } => path.as_path(),
         }
     }
 
+    pub fn gitref(&self) -> Option<&str> {
+        match self {
+            Source::RemoteGit { url: _, path: _, gitref } => gitref.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The commit checked out for git sources, or for local directories that are git working copies.
+    pub fn commit(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "git")]
+            Source::RemoteGit { url: _, path, gitref: _ } | Source::LocalDirectory { path } => {
+                git::git_output(Command::new("git").current_dir(path).args(["rev-parse", "HEAD"])).ok()
+            }
+            _ => None,
+        }
+    }
+
     pub fn source(&self) -> &str {
         match self {
             Source::RemoteGit { url, path: _, gitref: _ } => url,
             Source::RemoteHttp { url, path: _ } => url,
+            Source::RemoteBlob { url, path: _ } => url,
             Source::LocalDirectory { path } => path.to_str().unwrap(),
             Source::LocalFile { path } => path.to_str().unwrap(),
         }
@@ -191,79 +152,57 @@ fn verify_requirements(archetect: &Archetect, source: &str, path: &Path) -> Resu
     Ok(())
 }
 
-fn cache_git_repo(url: &str, gitref: &Option<String>, cache_destination: &Path, offline: bool) -> Result<(),
-    SourceError> {
-    if !cache_destination.exists() {
-        if !offline && CACHED_PATHS.lock().unwrap().insert(url.to_owned()) {
-            info!("Cloning {}", url);
-            debug!("Cloning to {}", cache_destination.to_str().unwrap());
-            handle_git(Command::new("git").args(&["clone", &url, cache_destination.to_str().unwrap()]))?;
-        } else {
-            return Err(SourceError::OfflineAndNotCached(url.to_owned()));
-        }
-    } else {
-        if !offline && CACHED_PATHS.lock().unwrap().insert(url.to_owned()) {
-            info!("Fetching {}", url);
-            handle_git(Command::new("git").current_dir(&cache_destination).args(&["fetch"]))?;
+/// Resolves each archetype the requirements of the archetype at `parent` declare a version for, checking it against
+/// that version as it is, so that an incompatible child is reported when the parent is loaded rather than partway
+/// through its script.
+pub(crate) fn verify_composed_archetypes(archetect: &Archetect, parent: &Source) -> Result<(), SourceError> {
+    let requirements = match Requirements::load(parent.directory()) {
+        Ok(Some(requirements)) => requirements,
+        Ok(None) => return Ok(()),
+        Err(error) => {
+            return Err(SourceError::RequirementsError {
+                path: parent.source().to_owned(),
+                cause: error,
+            });
         }
-    }
-
-    let gitref = if let Some(gitref) = gitref {
-        gitref.to_owned()
-    } else {
-        find_default_branch(&cache_destination.to_str().unwrap())?
     };
-
-    let gitref_spec = if is_branch(&cache_destination.to_str().unwrap(), &gitref) {
-        format!("origin/{}", &gitref)
-    } else {
-        gitref
-    };
-
-    debug!("Checking out {}", gitref_spec);
-    handle_git(Command::new("git").current_dir(&cache_destination).args(&["checkout", &gitref_spec]))?;
-
-    Ok(())
-}
-
-fn is_branch(path: &str, gitref: &str) -> bool {
-    match handle_git(Command::new("git").current_dir(path)
-        .arg("show-ref")
-        .arg("-q")
-        .arg("--verify")
-        .arg(format!("refs/remotes/origin/{}", gitref))) {
-        Ok(_) => true,
-        Err(_) => false,
+    for source in requirements.archetypes().keys() {
+        Source::detect(archetect, source, Some(parent.clone()))?;
     }
+    Ok(())
 }
 
-fn find_default_branch(path: &str) -> Result<String, SourceError> {
-    for candidate in &["develop", "main", "master"] {
-        if is_branch(path, candidate) {
-            return Ok((*candidate).to_owned());
+/// Checks a source resolved for a parent archetype against the versions the parent's requirements declare for the
+/// archetypes it composes, so that an incompatible child is reported before anything is rendered from it. A child's
+/// version is the one it declares in `archetype.yml`, or else the version its git tag names.
+fn verify_child_requirements(spec: &SourceSpec, parent: &Source, child: &Source) -> Result<(), SourceError> {
+    let requirements = match Requirements::load(parent.directory()) {
+        Ok(Some(requirements)) => requirements,
+        Ok(None) => return Ok(()),
+        Err(error) => {
+            return Err(SourceError::RequirementsError {
+                path: parent.source().to_owned(),
+                cause: error,
+            });
         }
+    };
+    if requirements.archetype_version(spec.source()).is_none() {
+        return Ok(());
     }
-    Err(SourceError::NoDefaultBranch)
-}
-
-fn handle_git(command: &mut Command) -> Result<(), SourceError> {
-    if cfg!(target_os = "windows") {
-        command.stdin(Stdio::inherit());
-        command.stderr(Stdio::inherit());
-    }
-    match command.output() {
-        Ok(output) => match output.status.code() {
-            Some(0) => Ok(()),
-            Some(error_code) => Err(SourceError::RemoteSourceError(format!(
-                "Error Code: {}\n{}",
-                error_code,
-                String::from_utf8(output.stderr)
-                    .unwrap_or("Error reading error code from failed git command".to_owned())
-            ))),
-            None => Err(SourceError::RemoteSourceError("Git interrupted by signal".to_owned())),
-        },
-        Err(err) => Err(SourceError::IoError(err)),
-    }
+    let version = ArchetypeConfig::load(child.local_path())
+        .ok()
+        .and_then(|config| config.version().cloned())
+        .or_else(|| {
+            child
+                .gitref()
+                .and_then(|gitref| Version::parse(gitref.strip_prefix('v').unwrap_or(gitref)).ok())
+        });
+    requirements
+        .verify_archetype(spec.source(), version.as_ref())
+        .map_err(|cause| SourceError::RequirementsError {
+            path: parent.source().to_owned(),
+            cause,
+        })
 }
 
 #[cfg(test)]
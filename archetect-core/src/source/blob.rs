@@ -0,0 +1,300 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::{debug, info};
+use url::Url;
+
+use crate::source::{SourceError, CACHED_PATHS};
+
+const ARCHIVE_DIRECTORY: &str = "archive";
+
+/// Object storage providers that archetypes and catalogs may be distributed from.
+///
+/// Downloads are delegated to each provider's CLI, so the standard credential chain for that provider (environment,
+/// profiles, instance metadata, etc.) is honored without Archetect handling credentials itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlobProvider {
+    S3,
+    Gcs,
+    Azure,
+}
+
+impl BlobProvider {
+    pub fn from_scheme(scheme: &str) -> Option<BlobProvider> {
+        match scheme {
+            "s3" => Some(BlobProvider::S3),
+            "gs" => Some(BlobProvider::Gcs),
+            "az" => Some(BlobProvider::Azure),
+            _ => None,
+        }
+    }
+
+    pub fn feature(&self) -> &'static str {
+        match self {
+            BlobProvider::S3 => "s3",
+            BlobProvider::Gcs => "gcs",
+            BlobProvider::Azure => "azure",
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match self {
+            BlobProvider::S3 => cfg!(feature = "s3"),
+            BlobProvider::Gcs => cfg!(feature = "gcs"),
+            BlobProvider::Azure => cfg!(feature = "azure"),
+        }
+    }
+
+    fn download_command(&self, url: &Url, destination: &Path) -> Result<Command, SourceError> {
+        let destination = destination.to_str().unwrap();
+        let command = match self {
+            BlobProvider::S3 => {
+                let mut command = Command::new("aws");
+                command.args(["s3", "cp", "--only-show-errors", url.as_str(), destination]);
+                command
+            }
+            BlobProvider::Gcs => {
+                let mut command = Command::new("gcloud");
+                command.args(["storage", "cp", url.as_str(), destination]);
+                command
+            }
+            BlobProvider::Azure => {
+                // az://<account>/<container>/<blob path>
+                let account = url
+                    .host_str()
+                    .ok_or_else(|| SourceError::SourceInvalidPath(url.to_string()))?;
+                let mut segments = url.path().trim_start_matches('/').splitn(2, '/');
+                let container = segments.next().filter(|s| !s.is_empty());
+                let blob = segments.next().filter(|s| !s.is_empty());
+                let (container, blob) = match (container, blob) {
+                    (Some(container), Some(blob)) => (container, blob),
+                    _ => return Err(SourceError::SourceInvalidPath(url.to_string())),
+                };
+                let mut command = Command::new("az");
+                command.args([
+                    "storage",
+                    "blob",
+                    "download",
+                    "--only-show-errors",
+                    "--auth-mode",
+                    "login",
+                    "--account-name",
+                    account,
+                    "--container-name",
+                    container,
+                    "--name",
+                    blob,
+                    "--file",
+                    destination,
+                ]);
+                command
+            }
+        };
+        Ok(command)
+    }
+}
+
+/// Downloads a blob into `cache_destination`, extracting it if it is an archive, and returns the local path of the
+/// archetype directory or catalog file.
+pub fn cache_blob(
+    provider: BlobProvider,
+    url: &Url,
+    cache_destination: &Path,
+    offline: bool,
+) -> Result<PathBuf, SourceError> {
+    if !provider.enabled() {
+        return Err(SourceError::SourceUnsupported(format!(
+            "{} (rebuild with the `{}` feature enabled)",
+            url,
+            provider.feature()
+        )));
+    }
+
+    let file_name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| SourceError::SourceInvalidPath(url.to_string()))?
+        .to_owned();
+    let blob_path = cache_destination.join(&file_name);
+
+    if !blob_path.exists() {
+        if offline {
+            return Err(SourceError::OfflineAndNotCached(url.to_string()));
+        }
+        // Blobs that failed to download earlier in the run are tried again, rather than taken as refreshed.
+        CACHED_PATHS.lock().unwrap().insert(url.to_string());
+        info!("Downloading {}", url);
+        download(provider, url, cache_destination, &blob_path)?;
+    } else if !offline && CACHED_PATHS.lock().unwrap().insert(url.to_string()) {
+        info!("Refreshing {}", url);
+        download(provider, url, cache_destination, &blob_path)?;
+    }
+
+    match archive_type(&file_name) {
+        Some(archive) => {
+            let archive_dir = cache_destination.join(ARCHIVE_DIRECTORY);
+            if !archive_dir.exists() {
+                extract(archive, &blob_path, &archive_dir)?;
+            }
+            Ok(archive_root(&archive_dir)?)
+        }
+        None => Ok(blob_path),
+    }
+}
+
+/// Downloads the blob beside `blob_path`, only moving it into place once complete, so that an interrupted download is
+/// never mistaken for a cached blob.
+fn download(provider: BlobProvider, url: &Url, cache_destination: &Path, blob_path: &Path) -> Result<(), SourceError> {
+    fs::create_dir_all(cache_destination)?;
+    let partial = partial_path(blob_path);
+    if partial.exists() {
+        fs::remove_file(&partial)?;
+    }
+    debug!("Downloading to {}", partial.display());
+    if let Err(error) = handle_command(&mut provider.download_command(url, &partial)?) {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+    let archive_dir = cache_destination.join(ARCHIVE_DIRECTORY);
+    if archive_dir.exists() {
+        fs::remove_dir_all(&archive_dir)?;
+    }
+    fs::rename(&partial, blob_path)?;
+    Ok(())
+}
+
+fn partial_path(blob_path: &Path) -> PathBuf {
+    let mut file_name = blob_path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".partial");
+    blob_path.with_file_name(file_name)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArchiveType {
+    Tar,
+    Zip,
+}
+
+fn archive_type(file_name: &str) -> Option<ArchiveType> {
+    let file_name = file_name.to_lowercase();
+    if file_name.ends_with(".zip") {
+        Some(ArchiveType::Zip)
+    } else if [".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tar.xz"]
+        .iter()
+        .any(|extension| file_name.ends_with(extension))
+    {
+        Some(ArchiveType::Tar)
+    } else {
+        None
+    }
+}
+
+fn extract(archive: ArchiveType, archive_file: &Path, destination: &Path) -> Result<(), SourceError> {
+    fs::create_dir_all(destination)?;
+    debug!("Extracting {} to {}", archive_file.display(), destination.display());
+    let mut command = match archive {
+        ArchiveType::Tar => {
+            let mut command = Command::new("tar");
+            command.arg("-xf").arg(archive_file).arg("-C").arg(destination);
+            command
+        }
+        ArchiveType::Zip => {
+            let mut command = Command::new("unzip");
+            command.arg("-q").arg("-o").arg(archive_file).arg("-d").arg(destination);
+            command
+        }
+    };
+    handle_command(&mut command)
+}
+
+/// Archives commonly wrap their contents in a single top-level directory; if so, treat that as the archetype root.
+fn archive_root(archive_dir: &Path) -> Result<PathBuf, SourceError> {
+    let entries = fs::read_dir(archive_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<PathBuf>>();
+    if entries.len() == 1 && entries[0].is_dir() {
+        Ok(entries[0].clone())
+    } else {
+        Ok(archive_dir.to_owned())
+    }
+}
+
+fn handle_command(command: &mut Command) -> Result<(), SourceError> {
+    match command.output() {
+        Ok(output) => match output.status.code() {
+            Some(0) => Ok(()),
+            Some(error_code) => Err(SourceError::RemoteSourceError(format!(
+                "Error Code: {}\n{}",
+                error_code,
+                String::from_utf8(output.stderr).unwrap_or("Error reading error output from failed command".to_owned())
+            ))),
+            None => Err(SourceError::RemoteSourceError("Download interrupted by signal".to_owned())),
+        },
+        Err(err) => Err(SourceError::IoError(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_scheme() {
+        assert_eq!(BlobProvider::from_scheme("s3"), Some(BlobProvider::S3));
+        assert_eq!(BlobProvider::from_scheme("gs"), Some(BlobProvider::Gcs));
+        assert_eq!(BlobProvider::from_scheme("az"), Some(BlobProvider::Azure));
+        assert_eq!(BlobProvider::from_scheme("https"), None);
+    }
+
+    #[test]
+    fn test_archive_type() {
+        assert_eq!(archive_type("archetype.tar.gz"), Some(ArchiveType::Tar));
+        assert_eq!(archive_type("archetype.TGZ"), Some(ArchiveType::Tar));
+        assert_eq!(archive_type("archetype.zip"), Some(ArchiveType::Zip));
+        assert_eq!(archive_type("catalog.yml"), None);
+    }
+
+    #[test]
+    fn test_azure_requires_container_and_blob() {
+        let url = Url::parse("az://account/container").unwrap();
+        assert!(BlobProvider::Azure
+            .download_command(&url, Path::new("/tmp/blob"))
+            .is_err());
+
+        let url = Url::parse("az://account/container/path/archetype.tar.gz").unwrap();
+        assert!(BlobProvider::Azure
+            .download_command(&url, Path::new("/tmp/blob"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_partial_path() {
+        assert_eq!(
+            partial_path(Path::new("/cache/archetype.tar.gz")),
+            PathBuf::from("/cache/archetype.tar.gz.partial")
+        );
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn test_failed_download_is_retried() {
+        let cache = tempfile::tempdir().unwrap();
+        let destination = cache.path().join("blob");
+        let url = Url::parse("s3://archetect-missing-bucket/archetype.tar.gz").unwrap();
+        CACHED_PATHS.lock().unwrap().insert(url.to_string());
+
+        // Whether or not the download runs at all here, it is tried rather than taken as not cached, and leaves nothing
+        // behind to be taken as cached.
+        let result = cache_blob(BlobProvider::S3, &url, &destination, false);
+        assert!(!matches!(result, Err(SourceError::OfflineAndNotCached(_))));
+        assert!(!destination.join("archetype.tar.gz").exists());
+        assert!(!destination.join("archetype.tar.gz.partial").exists());
+        assert!(matches!(
+            cache_blob(BlobProvider::S3, &url, &destination, true),
+            Err(SourceError::OfflineAndNotCached(_))
+        ));
+    }
+}
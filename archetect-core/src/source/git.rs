@@ -0,0 +1,510 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use log::{debug, info};
+
+use crate::cache;
+use crate::source::{get_cache_key, SourceError, CACHED_PATHS};
+use crate::utils::{lock_file, open_lock, wait_for_lock};
+use crate::Archetect;
+
+/// The branches a git source follows when no ref is given, in order of preference.
+pub(crate) const DEFAULT_BRANCHES: [&str; 3] = ["develop", "main", "master"];
+
+/// How long to wait for another run to finish with a repository, which may be cloning something large.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Clones or fetches the bare repository at `repository`, then checks `gitref`, or the default branch, out into a
+/// worktree of its own, returning where. Each ref having its own worktree, runs needing different refs of the same
+/// repository do not disturb each other, and the worktree is not checked out again by others until `archetect` has
+/// finished rendering from it.
+pub(crate) fn cache_git_repo(
+    url: &str,
+    gitref: &Option<String>,
+    repository: &Path,
+    archetect: &Archetect,
+) -> Result<PathBuf, SourceError> {
+    // Runs sharing the cache take turns with the repository, as git does not expect clones, fetches, and worktrees
+    // being added to overlap.
+    let mut repository_lock = lock_repository(repository)?;
+    let offline = archetect.offline();
+    let depth = archetect.clone_depth();
+    if !repository.exists() {
+        if !offline && CACHED_PATHS.lock().unwrap().insert(repository.display().to_string()) {
+            info!("Cloning {}", url);
+            debug!("Cloning to {}", repository.display());
+            if let Err(error) = clone_bare(url, repository, depth) {
+                let _ = fs::remove_dir_all(repository);
+                return Err(error);
+            }
+        } else {
+            return Err(SourceError::OfflineAndNotCached(url.to_owned()));
+        }
+    } else if !offline && CACHED_PATHS.lock().unwrap().insert(repository.display().to_string()) {
+        info!("Fetching {}", url);
+        // Equivalent URLs share a repository, which is fetched from whichever was given last.
+        let origin = git_output(Command::new("git").current_dir(repository).args(["remote", "get-url", "origin"]))?;
+        if origin != url {
+            handle_git(Command::new("git").current_dir(repository).args(["remote", "set-url", "origin", url]))?;
+        }
+        // Only repositories cloned shallowly are fetched so, keeping the history of those cloned in full.
+        let depth = depth.filter(|_| is_shallow(repository));
+        handle_git(Command::new("git").current_dir(repository).arg("fetch").args(depth_arg(depth)).arg("origin"))?;
+        // Follow the remote's default branch should it have changed since the repository was cloned.
+        if let Err(error) = handle_git(
+            Command::new("git")
+                .current_dir(repository)
+                .args(["remote", "set-head", "origin", "--auto"]),
+        ) {
+            debug!("Could not update the default branch of {}: {}", url, error);
+        }
+    }
+
+    let gitref = if let Some(gitref) = gitref {
+        gitref.to_owned()
+    } else {
+        find_default_branch(&repository.to_string_lossy())?
+    };
+    if let Some(depth) = depth {
+        if !offline && is_shallow(repository) {
+            fetch_shallow_ref(repository, &gitref, depth)?;
+        }
+    }
+
+    let branch = is_branch(&repository.to_string_lossy(), &gitref);
+    let gitref_spec = if branch {
+        format!("origin/{}", &gitref)
+    } else {
+        gitref.clone()
+    };
+
+    let worktree = worktree_path(repository, &gitref);
+    // Runs hold a shared lock on the worktree they read from until their render finishes, so that it is only checked
+    // out again, or collected, once none are reading it.
+    let lock_path = worktree_lock_path(&worktree);
+    let worktree_lock = match archetect.take_held_lock(&lock_path) {
+        Some(lock) => lock,
+        None => open_lock(&lock_path)?,
+    };
+    let reusable = is_checked_out(repository, &worktree, &gitref_spec)
+        && wait_for_lock(&worktree_lock, true, Duration::ZERO)?;
+    if !reusable {
+        if !wait_for_lock(&worktree_lock, false, Duration::ZERO)? {
+            // Those reading the worktree may need the repository for archetypes of their own before they finish.
+            drop(repository_lock);
+            if !wait_for_lock(&worktree_lock, false, LOCK_TIMEOUT)? {
+                return Err(SourceError::RemoteSourceError(format!(
+                    "Timed out waiting for `{}` to be unlocked",
+                    lock_path.display()
+                )));
+            }
+            repository_lock = lock_repository(repository)?;
+        }
+        debug!("Checking out {} to {}", gitref_spec, worktree.display());
+        if let Err(error) = prepare_worktree(repository, &worktree, &gitref, &gitref_spec, branch, archetect) {
+            // Leave nothing half prepared to be mistaken for checked out by the next run.
+            let _ = fs::remove_dir_all(&worktree);
+            return Err(error);
+        }
+        wait_for_lock(&worktree_lock, true, Duration::ZERO)?;
+    }
+    cache::touch(&worktree);
+    drop(repository_lock);
+    archetect.hold_lock(lock_path, worktree_lock);
+
+    Ok(worktree)
+}
+
+/// Checks `gitref_spec` out into the worktree at `worktree`, with the submodules and LFS files it needs.
+fn prepare_worktree(
+    repository: &Path,
+    worktree: &Path,
+    gitref: &str,
+    gitref_spec: &str,
+    branch: bool,
+    archetect: &Archetect,
+) -> Result<(), SourceError> {
+    let offline = archetect.offline();
+    checkout_worktree(repository, worktree, gitref_spec)?;
+
+    if !branch {
+        verify_pinned_ref(worktree, gitref)?;
+    }
+
+    if archetect.submodules() && worktree.join(".gitmodules").exists() {
+        debug!("Updating submodules");
+        let mut command = Command::new("git");
+        command.current_dir(worktree).args(["submodule", "update", "--init", "--recursive"]);
+        if offline {
+            command.arg("--no-fetch");
+        }
+        handle_git(&mut command)?;
+    }
+
+    if archetect.lfs() && uses_lfs(worktree) {
+        // Without network access, populate files from whatever LFS objects are already in the local cache.
+        let lfs_command = if offline { "checkout" } else { "pull" };
+        debug!("Running git lfs {}", lfs_command);
+        handle_git(Command::new("git").current_dir(worktree).args(["lfs", lfs_command]))?;
+    }
+
+    Ok(())
+}
+
+/// Whether the worktree at `worktree` already has the commit `gitref_spec` names checked out.
+fn is_checked_out(repository: &Path, worktree: &Path, gitref_spec: &str) -> bool {
+    if !worktree.join(".git").exists() {
+        return false;
+    }
+    let head = git_output(Command::new("git").current_dir(worktree).args(["rev-parse", "HEAD"]));
+    let expected = git_output(
+        Command::new("git")
+            .current_dir(repository)
+            .args(["rev-parse", "--verify", &format!("{}^{{commit}}", gitref_spec)]),
+    );
+    matches!((head, expected), (Ok(head), Ok(expected)) if head == expected)
+}
+
+/// The file runs lock to read from, or check out, the worktree at `worktree`.
+pub(crate) fn worktree_lock_path(worktree: &Path) -> PathBuf {
+    let mut path = worktree.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Waits for exclusive use of the bare repository at `repository`, which is held until the file returned is dropped.
+/// The lock is beside the repository, as it may not have been cloned yet.
+pub(crate) fn lock_repository(repository: &Path) -> Result<File, SourceError> {
+    let lock = repository.with_extension("lock");
+    lock_file(&lock, LOCK_TIMEOUT)?.ok_or_else(|| {
+        SourceError::RemoteSourceError(format!("Timed out waiting for `{}` to be unlocked", lock.display()))
+    })
+}
+
+/// Clones `url` into a bare repository, tracking its branches as `origin/<branch>` as a regular clone would, with only
+/// `depth` commits of each branch if given.
+fn clone_bare(url: &str, repository: &Path, depth: Option<u32>) -> Result<(), SourceError> {
+    fs::create_dir_all(repository)?;
+    handle_git(Command::new("git").current_dir(repository).args(["init", "--bare", "--quiet"]))?;
+    handle_git(Command::new("git").current_dir(repository).args(["remote", "add", "origin", url]))?;
+    handle_git(Command::new("git").current_dir(repository).arg("fetch").args(depth_arg(depth)).arg("origin"))?;
+    handle_git(
+        Command::new("git")
+            .current_dir(repository)
+            .args(["remote", "set-head", "origin", "--auto"]),
+    )
+}
+
+fn depth_arg(depth: Option<u32>) -> Option<String> {
+    depth.map(|depth| format!("--depth={}", depth))
+}
+
+fn is_shallow(repository: &Path) -> bool {
+    repository.join("shallow").exists()
+}
+
+/// Whether `gitref` names a commit in the repository at `repository`, or one of its remote branches.
+fn resolves(repository: &Path, gitref: &str) -> bool {
+    [gitref.to_owned(), format!("origin/{}", gitref)].iter().any(|name| {
+        handle_git(
+            Command::new("git")
+                .current_dir(repository)
+                .args(["rev-parse", "--verify", "--quiet"])
+                .arg(format!("{}^{{commit}}", name)),
+        )
+        .is_ok()
+    })
+}
+
+/// Makes `gitref` available in the shallow repository at `repository`. A tag or commit that is not among the branches
+/// fetched is fetched on its own, and failing that, the repository is deepened to its full history.
+fn fetch_shallow_ref(repository: &Path, gitref: &str, depth: u32) -> Result<(), SourceError> {
+    if resolves(repository, gitref) {
+        return Ok(());
+    }
+    let depth = format!("--depth={}", depth);
+    let refspecs = [format!("+refs/tags/{0}:refs/tags/{0}", gitref), gitref.to_owned()];
+    for refspec in refspecs {
+        debug!("Fetching {} on its own", refspec);
+        let fetched = handle_git(
+            Command::new("git")
+                .current_dir(repository)
+                .args(["fetch", "--quiet", &depth, "origin", &refspec]),
+        )
+        .is_ok();
+        if fetched && resolves(repository, gitref) {
+            return Ok(());
+        }
+    }
+    debug!("Fetching the full history of {} to find {}", repository.display(), gitref);
+    handle_git(
+        Command::new("git")
+            .current_dir(repository)
+            .args(["fetch", "--unshallow", "--tags", "origin"]),
+    )
+}
+
+/// Where `gitref` of the bare repository at `repository` is checked out.
+pub(crate) fn worktree_path(repository: &Path, gitref: &str) -> PathBuf {
+    let name = gitref
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect::<String>();
+    repository
+        .with_extension("worktrees")
+        .join(format!("{}-{}", name, get_cache_key(gitref)))
+}
+
+/// Checks `gitref_spec` out, detached, into the worktree at `worktree`, adding the worktree if need be.
+fn checkout_worktree(repository: &Path, worktree: &Path, gitref_spec: &str) -> Result<(), SourceError> {
+    if worktree.join(".git").exists() {
+        return handle_git(
+            Command::new("git")
+                .current_dir(worktree)
+                .args(["checkout", "--quiet", "--force", "--detach", gitref_spec]),
+        );
+    }
+    // Forget worktrees whose directories were removed, so that this one can be added in their place.
+    handle_git(Command::new("git").current_dir(repository).args(["worktree", "prune"]))?;
+    handle_git(
+        Command::new("git")
+            .current_dir(repository)
+            .args(["worktree", "add", "--quiet", "--force", "--detach"])
+            .arg(worktree)
+            .arg(gitref_spec),
+    )
+}
+
+fn uses_lfs(path: &Path) -> bool {
+    match fs::read_to_string(path.join(".gitattributes")) {
+        Ok(attributes) => attributes.contains("filter=lfs"),
+        Err(_) => false,
+    }
+}
+
+/// Tags and commit SHAs pin an archetype to specific content, so confirm that HEAD landed on exactly that commit.
+fn verify_pinned_ref(path: &Path, gitref: &str) -> Result<(), SourceError> {
+    let head = git_output(Command::new("git").current_dir(path).args(["rev-parse", "HEAD"]))?;
+    let expected = if is_commit_sha(gitref) {
+        gitref.to_lowercase()
+    } else {
+        git_output(
+            Command::new("git")
+                .current_dir(path)
+                .args(["rev-parse", "--verify", &format!("{}^{{commit}}", gitref)]),
+        )?
+    };
+
+    if head != expected {
+        return Err(SourceError::PinnedRefMismatch {
+            gitref: gitref.to_owned(),
+            expected,
+            head,
+        });
+    }
+    debug!("Verified {} at {}", gitref, head);
+    Ok(())
+}
+
+pub(crate) fn is_commit_sha(gitref: &str) -> bool {
+    (gitref.len() == 40 || gitref.len() == 64) && gitref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_branch(path: &str, gitref: &str) -> bool {
+    match handle_git(Command::new("git").current_dir(path)
+        .arg("show-ref")
+        .arg("-q")
+        .arg("--verify")
+        .arg(format!("refs/remotes/origin/{}", gitref))) {
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+/// The branch the remote's HEAD points to, as recorded when the repository was cloned or last fetched, or else the
+/// first of the conventional default branches that it has.
+pub(crate) fn find_default_branch(path: &str) -> Result<String, SourceError> {
+    let head = git_output(Command::new("git").current_dir(path).args([
+        "symbolic-ref",
+        "--short",
+        "-q",
+        "refs/remotes/origin/HEAD",
+    ]));
+    if let Some(branch) = head.ok().as_deref().and_then(|head| head.strip_prefix("origin/")) {
+        if is_branch(path, branch) {
+            return Ok(branch.to_owned());
+        }
+    }
+    for candidate in &DEFAULT_BRANCHES {
+        if is_branch(path, candidate) {
+            return Ok((*candidate).to_owned());
+        }
+    }
+    Err(SourceError::NoDefaultBranch)
+}
+
+fn handle_git(command: &mut Command) -> Result<(), SourceError> {
+    git_output(command).map(|_| ())
+}
+
+pub(crate) fn git_output(command: &mut Command) -> Result<String, SourceError> {
+    if cfg!(target_os = "windows") {
+        command.stdin(Stdio::inherit());
+        command.stderr(Stdio::inherit());
+    }
+    match command.output() {
+        Ok(output) => match output.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+            Some(error_code) => Err(SourceError::RemoteSourceError(format!(
+                "Error Code: {}\n{}",
+                error_code,
+                String::from_utf8(output.stderr)
+                    .unwrap_or("Error reading error code from failed git command".to_owned())
+            ))),
+            None => Err(SourceError::RemoteSourceError("Git interrupted by signal".to_owned())),
+        },
+        Err(err) => Err(SourceError::IoError(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_commit_sha() {
+        assert!(is_commit_sha("3f6a9c2e5b1d4a7f8c9e0b1a2d3c4e5f6a7b8c9d"));
+        assert!(is_commit_sha("3F6A9C2E5B1D4A7F8C9E0B1A2D3C4E5F6A7B8C9D"));
+        assert!(!is_commit_sha("3f6a9c2"));
+        assert!(!is_commit_sha("v1.2.3"));
+        assert!(!is_commit_sha("zf6a9c2e5b1d4a7f8c9e0b1a2d3c4e5f6a7b8c9d"));
+    }
+
+    #[test]
+    fn test_find_default_branch() {
+        let root = tempfile::tempdir().unwrap();
+        let remote = root.path().join("remote");
+        let git = |dir: &Path, args: &[&str]| {
+            handle_git(
+                Command::new("git")
+                    .current_dir(dir)
+                    .args(["-c", "user.name=Archetect", "-c", "user.email=archetect@example.com"])
+                    .args(args),
+            )
+            .unwrap()
+        };
+        fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "-b", "trunk"]);
+        git(&remote, &["commit", "-q", "--allow-empty", "-m", "Initial"]);
+        git(&remote, &["branch", "main"]);
+        git(root.path(), &["clone", "-q", remote.to_str().unwrap(), "cache"]);
+
+        let cache = root.path().join("cache");
+        assert_eq!(find_default_branch(cache.to_str().unwrap()).unwrap(), "trunk");
+
+        git(&cache, &["remote", "set-head", "origin", "--delete"]);
+        assert_eq!(find_default_branch(cache.to_str().unwrap()).unwrap(), "main");
+    }
+
+    #[test]
+    fn test_cache_worktrees() {
+        let root = tempfile::tempdir().unwrap();
+        let remote = root.path().join("remote");
+        let git = |args: &[&str]| {
+            handle_git(
+                Command::new("git")
+                    .current_dir(&remote)
+                    .args(["-c", "user.name=Archetect", "-c", "user.email=archetect@example.com"])
+                    .args(args),
+            )
+            .unwrap()
+        };
+        fs::create_dir_all(&remote).unwrap();
+        git(&["init", "-q", "-b", "main"]);
+        fs::write(remote.join("archetype.yml"), "version: 1.0.0\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "Release"]);
+        git(&["tag", "v1.0.0"]);
+        fs::write(remote.join("archetype.yml"), "version: 2.0.0\n").unwrap();
+        git(&["commit", "-q", "-am", "Next"]);
+
+        let archetect = Archetect::builder().with_headless(true).build().unwrap();
+        let url = remote.to_str().unwrap();
+        let repository = root.path().join("cache").join("remote.git");
+        let main = cache_git_repo(url, &None, &repository, &archetect).unwrap();
+        let tagged = cache_git_repo(url, &Some("v1.0.0".to_owned()), &repository, &archetect).unwrap();
+        assert_ne!(main, tagged);
+        assert_eq!(fs::read_to_string(main.join("archetype.yml")).unwrap(), "version: 2.0.0\n");
+        assert_eq!(fs::read_to_string(tagged.join("archetype.yml")).unwrap(), "version: 1.0.0\n");
+
+        // Checking a ref out again reuses its worktree.
+        let again = cache_git_repo(url, &Some("v1.0.0".to_owned()), &repository, &archetect).unwrap();
+        assert_eq!(again, tagged);
+
+        // The repository is locked only while it is being used.
+        let lock = repository.with_extension("lock");
+        let held = lock_file(&lock, Duration::ZERO).unwrap();
+        assert!(held.is_some());
+        assert!(lock_file(&lock, Duration::ZERO).unwrap().is_none());
+        drop(held);
+
+        // Its worktrees are shared with other runs until the render reading them finishes.
+        let worktree_lock = worktree_lock_path(&tagged);
+        assert!(lock_file(&worktree_lock, Duration::ZERO).unwrap().is_none());
+        assert!(wait_for_lock(&open_lock(&worktree_lock).unwrap(), true, Duration::ZERO).unwrap());
+        let other = Archetect::builder().with_headless(true).build().unwrap();
+        assert_eq!(cache_git_repo(url, &Some("v1.0.0".to_owned()), &repository, &other).unwrap(), tagged);
+        archetect.release_locks();
+        other.release_locks();
+        assert!(lock_file(&worktree_lock, Duration::ZERO).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_cache_shallow() {
+        let root = tempfile::tempdir().unwrap();
+        let remote = root.path().join("remote");
+        let git = |args: &[&str]| {
+            git_output(
+                Command::new("git")
+                    .current_dir(&remote)
+                    .args(["-c", "user.name=Archetect", "-c", "user.email=archetect@example.com"])
+                    .args(args),
+            )
+            .unwrap()
+        };
+        fs::create_dir_all(&remote).unwrap();
+        git(&["init", "-q", "-b", "main"]);
+        for version in ["1.0.0", "1.1.0", "2.0.0"] {
+            fs::write(remote.join("archetype.yml"), format!("version: {}\n", version)).unwrap();
+            git(&["add", "."]);
+            git(&["commit", "-q", "-m", version]);
+        }
+        git(&["tag", "v1.0.0", "HEAD~2"]);
+        let commit = git(&["rev-parse", "HEAD~1"]);
+
+        let archetect = Archetect::builder().with_headless(true).with_clone_depth(1).build().unwrap();
+        let url = format!("file://{}", remote.display());
+        let repository = root.path().join("cache").join("remote.git");
+        let main = cache_git_repo(&url, &None, &repository, &archetect).unwrap();
+        assert_eq!(fs::read_to_string(main.join("archetype.yml")).unwrap(), "version: 2.0.0\n");
+        assert!(is_shallow(&repository));
+        assert!(!resolves(&repository, "v1.0.0"));
+
+        let tagged = cache_git_repo(&url, &Some("v1.0.0".to_owned()), &repository, &archetect).unwrap();
+        assert_eq!(fs::read_to_string(tagged.join("archetype.yml")).unwrap(), "version: 1.0.0\n");
+        let pinned = cache_git_repo(&url, &Some(commit), &repository, &archetect).unwrap();
+        assert_eq!(fs::read_to_string(pinned.join("archetype.yml")).unwrap(), "version: 1.1.0\n");
+        assert!(is_shallow(&repository));
+    }
+
+    #[test]
+    fn test_uses_lfs() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(!uses_lfs(directory.path()));
+
+        fs::write(directory.path().join(".gitattributes"), "*.png filter=lfs diff=lfs merge=lfs -text\n").unwrap();
+        assert!(uses_lfs(directory.path()));
+    }
+}
@@ -0,0 +1,501 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use log::info;
+use url::Url;
+
+use crate::events::Event;
+use crate::source::blob::{self, BlobProvider};
+#[cfg(feature = "git")]
+use crate::source::git::cache_git_repo;
+use crate::source::{
+    get_cache_key, verify_child_requirements, verify_requirements, Source, SourceError, SSH_GIT_PATTERN,
+};
+use crate::Archetect;
+
+const GIT_URL_SCHEMES: [&str; 4] = ["http", "https", "git", "ssh"];
+
+/// Hosts whose repository URLs, of an owner and a repository, are recognised without a `.git` suffix.
+const GIT_HOSTS: [&str; 3] = ["github.com", "gitlab.com", "bitbucket.org"];
+
+/// How a source string will be fetched, as determined by [`SourceSpec::parse`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SourceKind {
+    /// An scp-like git location, such as `git@github.com:archetect/archetype-rust-cli.git`.
+    SshGit,
+    /// A git repository addressed by URL, such as `https://github.com/archetect/archetype-rust-cli.git`.
+    UrlGit,
+    /// An object in blob storage, such as `s3://archetypes/rust-cli.tar.gz`.
+    Blob(BlobProvider),
+    /// A `file://` URL.
+    FileUrl,
+    /// A path on the local filesystem, which may be relative or begin with `~` or environment variables.
+    LocalPath,
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::SshGit => write!(f, "git (ssh)"),
+            SourceKind::UrlGit => write!(f, "git (url)"),
+            SourceKind::Blob(provider) => write!(f, "blob ({})", provider.feature()),
+            SourceKind::FileUrl => write!(f, "file url"),
+            SourceKind::LocalPath => write!(f, "local path"),
+        }
+    }
+}
+
+/// A source string classified without touching the network or the filesystem.
+///
+/// Parsing and resolving are separate so that a source can be validated and described before committing to any
+/// network activity: [`SourceSpec::parse`] is pure, while [`SourceSpec::resolve`] clones, fetches, or downloads.
+///
+/// Git sources may address an archetype within the repository by following the repository with `//` and a subpath,
+/// as in `git@github.com:archetect/archetypes.git//rust/cli#v1.0.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceSpec {
+    source: String,
+    kind: SourceKind,
+    location: String,
+    host: Option<String>,
+    path: String,
+    subpath: Option<String>,
+    gitref: Option<String>,
+}
+
+impl SourceSpec {
+    /// Classifies `source`, splitting off any `#ref` for git sources. This only validates the shape of the string;
+    /// whether the source actually exists is determined when it is resolved.
+    pub fn parse(source: &str) -> Result<SourceSpec, SourceError> {
+        if source.trim().is_empty() {
+            return Err(SourceError::SourceInvalidPath(source.to_owned()));
+        }
+
+        if is_windows_path(source) {
+            return Ok(SourceSpec::local(source, SourceKind::LocalPath, source));
+        }
+
+        let (location, gitref) = match source.split_once('#') {
+            Some((location, gitref)) if !gitref.is_empty() => (location, Some(gitref.to_owned())),
+            Some((location, _)) => (location, None),
+            None => (source, None),
+        };
+
+        if !location.contains("://") && !location.starts_with(['/', '.', '~']) {
+            let (location, subpath) = split_subpath(location);
+            if let Some(captures) = SSH_GIT_PATTERN.captures(location) {
+                return Ok(SourceSpec {
+                    source: source.to_owned(),
+                    kind: SourceKind::SshGit,
+                    location: location.to_owned(),
+                    host: Some(captures[1].to_owned()),
+                    path: captures[2].to_owned(),
+                    subpath,
+                    gitref,
+                });
+            }
+        }
+
+        let url = match Url::parse(source) {
+            Ok(url) => url,
+            Err(_) => return Ok(SourceSpec::local(source, SourceKind::LocalPath, source)),
+        };
+
+        if let Some(provider) = BlobProvider::from_scheme(url.scheme()) {
+            return Ok(SourceSpec {
+                source: source.to_owned(),
+                kind: SourceKind::Blob(provider),
+                location: source.to_owned(),
+                host: url.host_str().map(|host| host.to_owned()),
+                path: url.path().to_owned(),
+                subpath: None,
+                gitref: None,
+            });
+        }
+
+        if url.scheme() == "file" {
+            return match url.to_file_path() {
+                Ok(path) => Ok(SourceSpec::local(source, SourceKind::FileUrl, &path.display().to_string())),
+                Err(_) => Err(SourceError::SourceInvalidPath(source.to_owned())),
+            };
+        }
+
+        match url.host_str() {
+            Some(host)
+                if GIT_URL_SCHEMES.contains(&url.scheme())
+                    && (is_git_repository_path(url.path()) || is_hosted_repository_path(host, url.path())) =>
+            {
+                let (location, subpath) = split_subpath(location);
+                let (path, _) = split_subpath(url.path());
+                Ok(SourceSpec {
+                    source: source.to_owned(),
+                    kind: SourceKind::UrlGit,
+                    location: location.to_owned(),
+                    host: Some(host.to_owned()),
+                    path: path.to_owned(),
+                    subpath,
+                    gitref: url.fragment().filter(|gitref| !gitref.is_empty()).map(|gitref| gitref.to_owned()),
+                })
+            }
+            Some(_) => Err(SourceError::SourceUnsupported(source.to_owned())),
+            // Strings such as `name:variant` parse as URLs, but are more plausibly file names
+            None => Ok(SourceSpec::local(source, SourceKind::LocalPath, source)),
+        }
+    }
+
+    fn local(source: &str, kind: SourceKind, path: &str) -> SourceSpec {
+        SourceSpec {
+            source: source.to_owned(),
+            kind,
+            location: source.to_owned(),
+            host: None,
+            path: path.to_owned(),
+            subpath: None,
+            gitref: None,
+        }
+    }
+
+    /// The source string as given.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn kind(&self) -> SourceKind {
+        self.kind
+    }
+
+    /// The source string without its `#ref`, as handed to git or the blob provider.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// The repository or object path for remote sources, or the filesystem path for local ones.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The repository name for git sources, such as `archetect/archetype-rust-cli`.
+    pub fn repository(&self) -> Option<&str> {
+        match self.kind {
+            SourceKind::SshGit | SourceKind::UrlGit => {
+                Some(self.path.trim_matches('/').trim_end_matches(".git"))
+            }
+            _ => None,
+        }
+    }
+
+    /// The directory within a git repository that contains the archetype or catalog.
+    pub fn subpath(&self) -> Option<&str> {
+        self.subpath.as_deref()
+    }
+
+    pub fn gitref(&self) -> Option<&str> {
+        self.gitref.as_deref()
+    }
+
+    /// The repository a git source clones, as `host/repository`, which is the same however the repository is
+    /// addressed: over ssh or https, with or without `.git`, and on any alias of its host.
+    pub fn canonical_repository(&self, archetect: &Archetect) -> Option<String> {
+        let host = self.host()?.to_lowercase();
+        let host = host.trim_start_matches("www.");
+        let host = archetect.host_alias(host).unwrap_or(host);
+        Some(format!("{}/{}", host, self.repository()?))
+    }
+
+    /// Where the bare repository of a git source is cloned to in the cache, whether or not it has been cloned yet. Each
+    /// ref is checked out into a worktree of its own beside it.
+    pub fn git_cache_path(&self, archetect: &Archetect) -> Option<PathBuf> {
+        match self.kind {
+            SourceKind::SshGit | SourceKind::UrlGit => Some(
+                archetect
+                    .layout()
+                    .git_cache_dir()
+                    .join(format!("{}.git", get_cache_key(self.canonical_repository(archetect)?))),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        !matches!(self.kind, SourceKind::FileUrl | SourceKind::LocalPath)
+    }
+
+    /// Resolves this spec into a [`Source`] with a local path, honoring source overrides, and cloning, fetching, or
+    /// downloading remote sources into the cache as needed. Relative local paths are resolved against `relative_to`
+    /// when given, and the resolved source must satisfy any version `relative_to` requires of it.
+    pub fn resolve(&self, archetect: &Archetect, relative_to: Option<Source>) -> Result<Source, SourceError> {
+        let parent = relative_to.clone();
+        let source = self.fetch(archetect, relative_to)?;
+        if let Some(parent) = parent {
+            verify_child_requirements(self, &parent, &source)?;
+        }
+        archetect.emit(Event::SourceResolved(source.clone()));
+        Ok(source)
+    }
+
+    fn fetch(&self, archetect: &Archetect, relative_to: Option<Source>) -> Result<Source, SourceError> {
+        let source = self.source();
+
+        if let Some(local_path) = archetect.source_override(source) {
+            let local_path = match shellexpand::full(local_path) {
+                Ok(expanded) => PathBuf::from(expanded.as_ref()),
+                Err(_) => return Err(SourceError::SourceInvalidPath(local_path.to_owned())),
+            };
+            info!("Overriding {} with {}", source, local_path.display());
+            return if local_path.is_dir() {
+                verify_requirements(archetect, source, &local_path)?;
+                Ok(Source::LocalDirectory { path: local_path })
+            } else {
+                Err(SourceError::SourceNotFound(local_path.display().to_string()))
+            };
+        }
+
+        match self.kind {
+            #[cfg(not(feature = "git"))]
+            SourceKind::SshGit | SourceKind::UrlGit => Err(SourceError::SourceUnsupported(format!(
+                "{} (rebuild with the `git` feature enabled)",
+                source
+            ))),
+            #[cfg(feature = "git")]
+            SourceKind::SshGit | SourceKind::UrlGit => {
+                let cache_path = self.git_cache_path(archetect).unwrap();
+                let gitref = self.gitref().map(|gitref| gitref.to_owned());
+                let worktree = cache_git_repo(self.location(), &gitref, &cache_path, archetect)?;
+                let local_path = match self.subpath() {
+                    Some(subpath) => worktree.join(subpath),
+                    None => worktree,
+                };
+                if !local_path.exists() {
+                    return Err(SourceError::SourceNotFound(format!("{} ({})", source, local_path.display())));
+                }
+                verify_requirements(archetect, source, &local_path)?;
+                Ok(Source::RemoteGit {
+                    url: source.to_owned(),
+                    path: local_path,
+                    gitref,
+                })
+            }
+            SourceKind::Blob(provider) => {
+                let url = Url::parse(self.location()).map_err(|_| SourceError::SourceInvalidPath(source.to_owned()))?;
+                let cache_path = archetect
+                    .layout()
+                    .blob_cache_dir()
+                    .join(get_cache_key(format!("{}/{}", url.scheme(), url.as_str())));
+                let local_path = blob::cache_blob(provider, &url, &cache_path, archetect.offline())?;
+                if local_path.is_dir() {
+                    verify_requirements(archetect, source, &local_path)?;
+                }
+                Ok(Source::RemoteBlob {
+                    url: source.to_owned(),
+                    path: local_path,
+                })
+            }
+            SourceKind::FileUrl => {
+                let local_path = PathBuf::from(self.path());
+                if local_path.exists() {
+                    verify_requirements(archetect, source, &local_path)?;
+                    Ok(Source::LocalDirectory { path: local_path })
+                } else {
+                    Err(SourceError::SourceNotFound(local_path.display().to_string()))
+                }
+            }
+            SourceKind::LocalPath => {
+                let local_path = match shellexpand::full(self.path()) {
+                    Ok(expanded) => PathBuf::from(expanded.as_ref()),
+                    Err(_) => return Err(SourceError::SourceInvalidPath(source.to_owned())),
+                };
+                if local_path.is_relative() {
+                    if let Some(parent) = relative_to {
+                        let local_path = parent.local_path().join(local_path);
+                        return if local_path.exists() && local_path.is_dir() {
+                            verify_requirements(archetect, source, &local_path)?;
+                            Ok(Source::LocalDirectory { path: local_path })
+                        } else {
+                            Err(SourceError::SourceNotFound(local_path.display().to_string()))
+                        };
+                    }
+                }
+                if local_path.exists() {
+                    if local_path.is_dir() {
+                        verify_requirements(archetect, source, &local_path)?;
+                        Ok(Source::LocalDirectory { path: local_path })
+                    } else {
+                        Ok(Source::LocalFile { path: local_path })
+                    }
+                } else {
+                    Err(SourceError::SourceNotFound(local_path.display().to_string()))
+                }
+            }
+        }
+    }
+}
+
+fn is_windows_path(source: &str) -> bool {
+    let bytes = source.as_bytes();
+    let drive = bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/');
+    drive || source.starts_with(r"\\")
+}
+
+/// Splits a trailing `//subpath` from a git repository location.
+fn split_subpath(location: &str) -> (&str, Option<String>) {
+    match location.find(".git//") {
+        Some(index) => {
+            let (repository, subpath) = location.split_at(index + ".git".len());
+            let subpath = subpath.trim_matches('/');
+            (repository, Some(subpath.to_owned()).filter(|subpath| !subpath.is_empty()))
+        }
+        None => (location, None),
+    }
+}
+
+fn is_git_repository_path(path: &str) -> bool {
+    path.split('/').any(|segment| segment.ends_with(".git"))
+}
+
+fn is_hosted_repository_path(host: &str, path: &str) -> bool {
+    let host = host.to_lowercase();
+    GIT_HOSTS.contains(&host.trim_start_matches("www."))
+        && path.split('/').filter(|segment| !segment.is_empty()).count() == 2
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_edge_cases() {
+        let spec = SourceSpec::parse("https://user@example.com:8443/org/repo.git#v1.0").unwrap();
+        assert_eq!(spec.kind(), SourceKind::UrlGit);
+        assert_eq!(spec.host(), Some("example.com"));
+        assert_eq!(spec.gitref(), Some("v1.0"));
+
+        let spec = SourceSpec::parse("git@github.com:archetect/archetype-rust-cli.git#").unwrap();
+        assert_eq!(spec.kind(), SourceKind::SshGit);
+        assert_eq!(spec.gitref(), None);
+
+        assert_eq!(SourceSpec::parse(r"C:\archetypes\rust-cli").unwrap().kind(), SourceKind::LocalPath);
+        assert_eq!(SourceSpec::parse("C:/archetypes/rust.git").unwrap().kind(), SourceKind::LocalPath);
+        assert_eq!(SourceSpec::parse("./me@host:archetype").unwrap().kind(), SourceKind::LocalPath);
+        assert_eq!(SourceSpec::parse("file:///tmp/archetype").unwrap().kind(), SourceKind::FileUrl);
+        assert_eq!(SourceSpec::parse("file:///tmp/archetype").unwrap().path(), "/tmp/archetype");
+        assert!(matches!(
+            SourceSpec::parse("https://github.com/org/.github/archetype"),
+            Err(SourceError::SourceUnsupported(_))
+        ));
+        assert!(SourceSpec::parse("").is_err());
+    }
+
+    #[test]
+    fn test_repository_and_subpath() {
+        let spec = SourceSpec::parse("git@github.com:archetect/archetypes.git//rust/cli#v1.0.0").unwrap();
+        assert_eq!(spec.location(), "git@github.com:archetect/archetypes.git");
+        assert_eq!(spec.repository(), Some("archetect/archetypes"));
+        assert_eq!(spec.subpath(), Some("rust/cli"));
+        assert_eq!(spec.gitref(), Some("v1.0.0"));
+
+        let spec = SourceSpec::parse("https://github.com/archetect/archetypes.git//rust/cli/").unwrap();
+        assert_eq!(spec.location(), "https://github.com/archetect/archetypes.git");
+        assert_eq!(spec.path(), "/archetect/archetypes.git");
+        assert_eq!(spec.repository(), Some("archetect/archetypes"));
+        assert_eq!(spec.subpath(), Some("rust/cli"));
+        assert_eq!(spec.gitref(), None);
+
+        let spec = SourceSpec::parse("~/archetypes/rust-cli").unwrap();
+        assert_eq!(spec.repository(), None);
+        assert_eq!(spec.subpath(), None);
+        assert!(!spec.is_remote());
+    }
+
+    #[test]
+    fn test_canonical_repository() {
+        let mut archetect = Archetect::builder().build().unwrap();
+        let cache_path = |archetect: &Archetect, source: &str| {
+            SourceSpec::parse(source).unwrap().git_cache_path(archetect).unwrap()
+        };
+        let expected = cache_path(&archetect, "git@github.com:archetect/archetype-rust-cli.git");
+        assert_eq!(cache_path(&archetect, "https://github.com/archetect/archetype-rust-cli.git"), expected);
+        assert_eq!(cache_path(&archetect, "https://GitHub.com/archetect/archetype-rust-cli#v1.0.0"), expected);
+        assert_eq!(cache_path(&archetect, "ssh://git@github.com/archetect/archetype-rust-cli.git/"), expected);
+        assert_ne!(cache_path(&archetect, "ssh://git@ssh.github.com:443/archetect/archetype-rust-cli.git"), expected);
+
+        archetect.add_host_alias("ssh.github.com", "github.com");
+        assert_eq!(cache_path(&archetect, "ssh://git@ssh.github.com:443/archetect/archetype-rust-cli.git"), expected);
+
+        assert!(matches!(
+            SourceSpec::parse("https://example.com/archetect/archetype-rust-cli"),
+            Err(SourceError::SourceUnsupported(_))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn parse_never_panics(source in "\\PC*") {
+            let _ = SourceSpec::parse(&source);
+        }
+
+        #[test]
+        fn parse_ssh_git(
+            user in "[a-z][a-z0-9]{0,8}",
+            host in "[a-z][a-z0-9]{0,12}(\\.[a-z]{2,4}){1,2}",
+            repo in "[a-z][a-z0-9_-]{0,12}/[a-z][a-z0-9_-]{0,12}\\.git",
+            gitref in proptest::option::of("[a-zA-Z0-9][a-zA-Z0-9._/-]{0,16}"),
+        ) {
+            let location = format!("{}@{}:{}", user, host, repo);
+            let source = match &gitref {
+                Some(gitref) => format!("{}#{}", location, gitref),
+                None => location.clone(),
+            };
+            let spec = SourceSpec::parse(&source).unwrap();
+            prop_assert_eq!(spec.kind(), SourceKind::SshGit);
+            prop_assert_eq!(spec.location(), location.as_str());
+            prop_assert_eq!(spec.host(), Some(host.as_str()));
+            prop_assert_eq!(spec.path(), repo.as_str());
+            prop_assert_eq!(spec.gitref(), gitref.as_deref());
+        }
+
+        #[test]
+        fn parse_url_git(
+            scheme in "(https?|ssh|git)",
+            host in "[a-z][a-z0-9]{0,12}(\\.[a-z]{2,4}){1,2}",
+            repo in "[a-z][a-z0-9_-]{0,12}/[a-z][a-z0-9_-]{0,12}\\.git",
+            gitref in proptest::option::of("[a-zA-Z0-9][a-zA-Z0-9._/-]{0,16}"),
+        ) {
+            let location = format!("{}://{}/{}", scheme, host, repo);
+            let source = match &gitref {
+                Some(gitref) => format!("{}#{}", location, gitref),
+                None => location.clone(),
+            };
+            let spec = SourceSpec::parse(&source).unwrap();
+            prop_assert_eq!(spec.kind(), SourceKind::UrlGit);
+            prop_assert_eq!(spec.location(), location.as_str());
+            prop_assert_eq!(spec.host(), Some(host.as_str()));
+            prop_assert_eq!(spec.gitref(), gitref.as_deref());
+        }
+
+        #[test]
+        fn parse_windows_paths(
+            drive in "[a-zA-Z]",
+            separator in "[\\\\/]",
+            segments in proptest::collection::vec("[a-zA-Z0-9 _.@#-]{1,12}", 0..4),
+        ) {
+            let source = format!("{}:{}{}", drive, separator, segments.join(&separator));
+            let spec = SourceSpec::parse(&source).unwrap();
+            prop_assert_eq!(spec.kind(), SourceKind::LocalPath);
+            prop_assert_eq!(spec.path(), source.as_str());
+        }
+
+        #[test]
+        fn parse_relative_paths(source in "(\\.{1,2}/)?[a-zA-Z0-9_-]{1,12}(/[a-zA-Z0-9_.-]{1,12}){0,3}") {
+            let spec = SourceSpec::parse(&source).unwrap();
+            prop_assert_eq!(spec.kind(), SourceKind::LocalPath);
+            prop_assert_eq!(spec.location(), source.as_str());
+            prop_assert_eq!(spec.gitref(), None);
+        }
+    }
+}
@@ -27,13 +27,33 @@ pub trait SystemLayout {
         self.cache_dir().join("http")
     }
 
+    fn blob_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("blob")
+    }
+
     fn answers_config(&self) -> PathBuf {
         self.configs_dir().join("answers.yml")
     }
 
+    fn answer_history(&self) -> PathBuf {
+        self.configs_dir().join("history.yml")
+    }
+
     fn catalog(&self) -> PathBuf {
         self.configs_dir().join(CATALOG_FILE_NAME)
     }
+
+    fn overrides_config(&self) -> PathBuf {
+        self.configs_dir().join("overrides.yml")
+    }
+
+    fn trusted_sources(&self) -> PathBuf {
+        self.configs_dir().join("trusted.yml")
+    }
+
+    fn user_config(&self) -> PathBuf {
+        self.configs_dir().join("config.yml")
+    }
 }
 
 #[derive(Debug)]
@@ -98,8 +118,12 @@ impl Display for dyn SystemLayout {
         writeln!(f, "{}: {}", "Configs Directory", self.configs_dir().display())?;
         writeln!(f, "{}: {}", "User Answers", self.answers_config().display())?;
         writeln!(f, "{}: {}", "User Catalog", self.catalog().display())?;
+        writeln!(f, "{}: {}", "Source Overrides", self.overrides_config().display())?;
+        writeln!(f, "Trusted Sources: {}", self.trusted_sources().display())?;
+        writeln!(f, "User Config: {}", self.user_config().display())?;
         writeln!(f, "{}: {}", "Git Cache", self.git_cache_dir().display())?;
         writeln!(f, "{}: {}", "Catalog Cache", self.catalog_cache_dir().display())?;
+        writeln!(f, "{}: {}", "Blob Cache", self.blob_cache_dir().display())?;
         Ok(())
     }
 }
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
+use std::process::Command;
+
+#[cfg(feature = "git")]
+use crate::utils::command_output;
+
+/// Facts about the project an archetype renders into, exposed to archetypes as `target`, so that archetypes adding to
+/// an existing project can adapt to it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TargetInfo {
+    /// Whether the destination already has contents.
+    exists: bool,
+    ecosystems: Vec<Ecosystem>,
+    git_branch: Option<String>,
+    workspace: Option<WorkspaceInfo>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Maven,
+    Gradle,
+    Go,
+    Python,
+}
+
+/// The workspace, or multi-module build, that the destination falls within.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    ecosystem: Ecosystem,
+    root: String,
+}
+
+impl TargetInfo {
+    /// Inspects `destination`, and the directories above it, before anything is rendered into it.
+    pub fn detect(destination: &Path) -> TargetInfo {
+        let destination = match std::env::current_dir() {
+            Ok(current_dir) => current_dir.join(destination),
+            Err(_) => destination.to_owned(),
+        };
+
+        let exists = fs::read_dir(&destination)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        let ecosystems = Ecosystem::ALL
+            .iter()
+            .filter(|ecosystem| ecosystem.markers().iter().any(|marker| destination.join(marker).is_file()))
+            .copied()
+            .collect();
+        #[cfg(feature = "git")]
+        let git_branch = destination.ancestors().find(|path| path.is_dir()).and_then(|path| {
+            command_output(Command::new("git").current_dir(path).args(["symbolic-ref", "--short", "-q", "HEAD"]))
+        });
+        #[cfg(not(feature = "git"))]
+        let git_branch = None;
+        let workspace = destination.ancestors().find_map(WorkspaceInfo::detect);
+
+        TargetInfo {
+            exists,
+            ecosystems,
+            git_branch,
+            workspace,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+
+    pub fn ecosystems(&self) -> &[Ecosystem] {
+        &self.ecosystems
+    }
+
+    pub fn git_branch(&self) -> Option<&str> {
+        self.git_branch.as_deref()
+    }
+
+    pub fn workspace(&self) -> Option<&WorkspaceInfo> {
+        self.workspace.as_ref()
+    }
+}
+
+impl Ecosystem {
+    const ALL: [Ecosystem; 6] = [
+        Ecosystem::Cargo,
+        Ecosystem::Npm,
+        Ecosystem::Maven,
+        Ecosystem::Gradle,
+        Ecosystem::Go,
+        Ecosystem::Python,
+    ];
+
+    /// The files marking a project of this ecosystem.
+    fn markers(&self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Cargo => &["Cargo.toml"],
+            Ecosystem::Npm => &["package.json"],
+            Ecosystem::Maven => &["pom.xml"],
+            Ecosystem::Gradle => &["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts"],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Python => &["pyproject.toml", "setup.py", "requirements.txt"],
+        }
+    }
+}
+
+impl WorkspaceInfo {
+    fn detect(directory: &Path) -> Option<WorkspaceInfo> {
+        let read = |file: &str| fs::read_to_string(directory.join(file)).ok();
+        let ecosystem = if read("Cargo.toml").is_some_and(|manifest| {
+            manifest.lines().any(|line| line.trim_start().starts_with("[workspace"))
+        }) {
+            Ecosystem::Cargo
+        } else if directory.join("pnpm-workspace.yaml").is_file()
+            || read("package.json")
+                .and_then(|manifest| serde_json::from_str::<serde_json::Value>(&manifest).ok())
+                .is_some_and(|manifest| manifest.get("workspaces").is_some())
+        {
+            Ecosystem::Npm
+        } else if read("pom.xml").is_some_and(|pom| pom.contains("<modules>")) {
+            Ecosystem::Maven
+        } else if directory.join("settings.gradle").is_file() || directory.join("settings.gradle.kts").is_file() {
+            Ecosystem::Gradle
+        } else if directory.join("go.work").is_file() {
+            Ecosystem::Go
+        } else {
+            return None;
+        };
+        Some(WorkspaceInfo {
+            ecosystem,
+            root: directory.to_string_lossy().into_owned(),
+        })
+    }
+
+    pub fn ecosystem(&self) -> Ecosystem {
+        self.ecosystem
+    }
+
+    pub fn root(&self) -> PathBuf {
+        PathBuf::from(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("Cargo.toml"), "[workspace]\nmembers = [\"*\"]\n").unwrap();
+        let member = root.path().join("service");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"service\"\n").unwrap();
+        fs::write(member.join("package.json"), "{\"name\": \"service-ui\"}").unwrap();
+
+        let target = TargetInfo::detect(&member);
+        assert!(target.exists());
+        assert_eq!(target.ecosystems(), &[Ecosystem::Cargo, Ecosystem::Npm]);
+        let workspace = target.workspace().unwrap();
+        assert_eq!(workspace.ecosystem(), Ecosystem::Cargo);
+        assert_eq!(workspace.root(), root.path());
+
+        let target = TargetInfo::detect(&member.join("new-module"));
+        assert!(!target.exists());
+        assert!(target.ecosystems().is_empty());
+        assert_eq!(target.workspace().unwrap().root(), root.path());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let target = TargetInfo {
+            exists: true,
+            ecosystems: vec![Ecosystem::Maven],
+            git_branch: Some("main".to_owned()),
+            workspace: None,
+        };
+        let value = serde_json::to_value(&target).unwrap();
+        assert_eq!(value["ecosystems"][0], "maven");
+        assert_eq!(value["git_branch"], "main");
+    }
+}
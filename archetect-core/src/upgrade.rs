@@ -0,0 +1,411 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
+use std::process::Command;
+
+use linked_hash_map::LinkedHashMap;
+use log::{debug, info};
+use semver::Version;
+
+use crate::config::{AnswerConfig, AnswerInfo, ConflictStrategies, ConflictStrategy, ReleaseNotes};
+use crate::metadata::{RenderMetadata, METADATA_FILE_NAME};
+use crate::protect::ProtectedFiles;
+use crate::utils::sorted_entries;
+use crate::{Archetect, ArchetectError};
+
+const CHANGELOG_FILE_NAMES: [&str; 3] = ["CHANGELOG.md", "CHANGELOG", "changelog.md"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpgradeError {
+    #[error("`{0}` has no render metadata; it must have been rendered with `--metadata` to be upgraded")]
+    MissingMetadata(PathBuf),
+    #[error("Error merging `{path}`: {message}")]
+    MergeError { path: PathBuf, message: String },
+}
+
+/// What happened to a single file while upgrading a destination.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpgradeOutcome {
+    /// The file is new in the upgraded archetype and did not exist in the destination.
+    Added,
+    /// The destination file had not been modified since it was rendered, and was replaced.
+    Updated,
+    /// Local modifications and upstream changes were merged cleanly.
+    Merged,
+    /// Local modifications and upstream changes overlap; conflict markers were written into the file.
+    Conflicted,
+    /// The file was modified locally, and there is no recorded base to merge against, so it was left alone.
+    Kept,
+    /// The file was rendered by the previous version, but is no longer rendered by the upgraded archetype.
+    RemovedUpstream,
+    /// The file is protected by the destination's `.archetect-protect` file, so was left alone.
+    Protected,
+}
+
+#[derive(Debug)]
+pub struct UpgradeSummary {
+    from: RenderMetadata,
+    to: Option<RenderMetadata>,
+    files: LinkedHashMap<PathBuf, UpgradeOutcome>,
+    changelog: Option<String>,
+    releases: Vec<ReleaseNotes>,
+}
+
+impl UpgradeSummary {
+    pub fn from(&self) -> &RenderMetadata {
+        &self.from
+    }
+
+    pub fn to(&self) -> Option<&RenderMetadata> {
+        self.to.as_ref()
+    }
+
+    pub fn files(&self) -> &LinkedHashMap<PathBuf, UpgradeOutcome> {
+        &self.files
+    }
+
+    pub fn changelog(&self) -> Option<&str> {
+        self.changelog.as_deref()
+    }
+
+    /// Release notes declared by the upgraded archetype for the versions crossed by this upgrade.
+    pub fn releases(&self) -> &[ReleaseNotes] {
+        self.releases.as_slice()
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        self.files.values().any(|outcome| outcome == &UpgradeOutcome::Conflicted)
+    }
+}
+
+/// Re-renders the archetype recorded in `destination`'s metadata at `gitref` (or its default branch), and three-way
+/// merges the result into `destination` using a render of the originally recorded commit as the merge base.
+pub fn upgrade<D: AsRef<Path>>(
+    archetect: &mut Archetect,
+    destination: D,
+    gitref: Option<&str>,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+) -> Result<UpgradeSummary, ArchetectError> {
+    let destination = destination.as_ref();
+    let from = match RenderMetadata::load(destination)? {
+        Some(metadata) => metadata,
+        None => return Err(UpgradeError::MissingMetadata(destination.to_owned()).into()),
+    };
+
+    let source = from.source().split('#').next().unwrap_or_default().to_owned();
+    let mut upgrade_answers = from.answers().clone();
+    for (identifier, answer) in answers {
+        upgrade_answers.insert(identifier.to_owned(), answer.clone());
+    }
+
+    let metadata_enabled = archetect.metadata();
+    archetect.set_metadata(true);
+    let backing_up = archetect.begin_backup(destination);
+    let result = render_and_merge(archetect, destination, &source, gitref, &from, &upgrade_answers)
+        .and_then(|(files, changelog, releases, new_render)| {
+            let to = RenderMetadata::load(new_render.path())?;
+            if let Some(to) = &to {
+                archetect.preserve(&destination.join(METADATA_FILE_NAME))?;
+                to.save(destination)?;
+            }
+            Ok((files, changelog, releases, to))
+        });
+    if backing_up {
+        archetect.finish_backup();
+    }
+    archetect.set_metadata(metadata_enabled);
+    let (files, changelog, releases, to) = result?;
+
+    Ok(UpgradeSummary {
+        from,
+        to,
+        files,
+        changelog,
+        releases,
+    })
+}
+
+type MergeResult = (
+    LinkedHashMap<PathBuf, UpgradeOutcome>,
+    Option<String>,
+    Vec<ReleaseNotes>,
+    tempfile::TempDir,
+);
+
+fn render_and_merge(
+    archetect: &mut Archetect,
+    destination: &Path,
+    source: &str,
+    gitref: Option<&str>,
+    from: &RenderMetadata,
+    answers: &LinkedHashMap<String, AnswerInfo>,
+) -> Result<MergeResult, ArchetectError> {
+    let base_render = tempfile::tempdir()?;
+    let base = if let Some(commit) = from.commit() {
+        info!("Rendering {} at {} as the merge base", source, commit);
+        let archetype = archetect.load_archetype(&format!("{}#{}", source, commit), None)?;
+        archetype.render(archetect, base_render.path(), answers)?;
+        Some(base_render.path())
+    } else {
+        None
+    };
+
+    let new_render = tempfile::tempdir()?;
+    let new_source = match gitref {
+        Some(gitref) => format!("{}#{}", source, gitref),
+        None => source.to_owned(),
+    };
+    info!("Rendering {}", new_source);
+    let archetype = archetect.load_archetype(&new_source, None)?;
+    archetype.render(archetect, new_render.path(), answers)?;
+    let changelog = CHANGELOG_FILE_NAMES
+        .iter()
+        .map(|name| archetype.source().directory().join(name))
+        .find(|path| path.is_file())
+        .and_then(|path| fs::read_to_string(path).ok());
+    let from_version = from.archetype_version().and_then(|version| Version::parse(version).ok());
+    let releases = archetype
+        .configuration()
+        .releases_since(from_version.as_ref())
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let protected = ProtectedFiles::load(destination)?;
+    let conflicts = AnswerConfig::load(destination)
+        .map(|config| config.conflicts().clone())
+        .unwrap_or_default();
+    let mut files = LinkedHashMap::new();
+    for relative in relative_files(new_render.path())? {
+        let outcome = merge_file(archetect, &relative, base, new_render.path(), destination, &protected, &conflicts)?;
+        debug!("{:?} {}", outcome, relative.display());
+        files.insert(relative, outcome);
+    }
+    if let Some(base) = base {
+        for relative in relative_files(base)? {
+            if !files.contains_key(&relative) {
+                files.insert(relative, UpgradeOutcome::RemovedUpstream);
+            }
+        }
+    }
+
+    Ok((files, changelog, releases, new_render))
+}
+
+fn merge_file(
+    archetect: &mut Archetect,
+    relative: &Path,
+    base: Option<&Path>,
+    new_render: &Path,
+    destination: &Path,
+    protected: &ProtectedFiles,
+    conflicts: &ConflictStrategies,
+) -> Result<UpgradeOutcome, ArchetectError> {
+    let theirs = new_render.join(relative);
+    let ours = destination.join(relative);
+
+    if ours.exists() && protected.is_protected(&ours) {
+        return Ok(UpgradeOutcome::Protected);
+    }
+
+    if !ours.exists() {
+        if let Some(parent) = ours.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        archetect.preserve(&ours)?;
+        fs::copy(&theirs, &ours)?;
+        return Ok(UpgradeOutcome::Added);
+    }
+
+    let ours_contents = fs::read(&ours)?;
+    let theirs_contents = fs::read(&theirs)?;
+    if ours_contents == theirs_contents {
+        return Ok(UpgradeOutcome::Updated);
+    }
+
+    let strategy = match conflicts.strategy_for(relative) {
+        Some(ConflictStrategy::Ask) => Some(archetect.ask_conflict(relative)?),
+        strategy => strategy,
+    };
+    match strategy {
+        Some(ConflictStrategy::Ours) => return Ok(UpgradeOutcome::Kept),
+        Some(ConflictStrategy::Theirs) => {
+            archetect.preserve(&ours)?;
+            fs::write(&ours, theirs_contents)?;
+            return Ok(UpgradeOutcome::Updated);
+        }
+        _ => (),
+    }
+
+    let base = match base.map(|base| base.join(relative)).filter(|base| base.exists()) {
+        Some(base) => base,
+        None => return Ok(UpgradeOutcome::Kept),
+    };
+
+    archetect.preserve(&ours)?;
+    if fs::read(&base)? == ours_contents {
+        fs::write(&ours, theirs_contents)?;
+        return Ok(UpgradeOutcome::Updated);
+    }
+
+    git_merge_file(ours, &base, &theirs, relative)
+}
+
+#[cfg(not(feature = "git"))]
+fn git_merge_file(_ours: PathBuf, _base: &Path, _theirs: &Path, relative: &Path) -> Result<UpgradeOutcome, ArchetectError> {
+    Err(ArchetectError::FeatureDisabled(format!("Merging `{}`", relative.display()), "git"))
+}
+
+/// Merges the changes from `base` to `theirs` into `ours` with `git merge-file`, leaving conflict markers where they
+/// overlap.
+#[cfg(feature = "git")]
+fn git_merge_file(ours: PathBuf, base: &Path, theirs: &Path, _relative: &Path) -> Result<UpgradeOutcome, ArchetectError> {
+    let output = Command::new("git")
+        .args(["merge-file", "-L", "local", "-L", "previous", "-L", "upgraded"])
+        .arg(&ours)
+        .arg(base)
+        .arg(theirs)
+        .output()?;
+    // git merge-file exits with the number of conflicts, or a negative value on error.
+    match output.status.code() {
+        Some(0) => Ok(UpgradeOutcome::Merged),
+        Some(code) if (1..128).contains(&code) => Ok(UpgradeOutcome::Conflicted),
+        _ => Err(UpgradeError::MergeError {
+            path: ours,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        }
+        .into()),
+    }
+}
+
+fn relative_files(root: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut results = vec![];
+    collect_files(root, root, &mut results)?;
+    results.sort();
+    Ok(results)
+}
+
+fn collect_files(root: &Path, directory: &Path, results: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for path in sorted_entries(directory)? {
+        if path.is_dir() {
+            collect_files(root, &path, results)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            if relative != Path::new(METADATA_FILE_NAME) {
+                results.push(relative.to_owned());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_file_outcomes() {
+        let base = tempfile::tempdir().unwrap();
+        let new_render = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+
+        // Unmodified locally: replaced by the upgraded render
+        fs::write(base.path().join("unmodified.txt"), "one\n").unwrap();
+        fs::write(destination.path().join("unmodified.txt"), "one\n").unwrap();
+        fs::write(new_render.path().join("unmodified.txt"), "two\n").unwrap();
+
+        // New upstream
+        fs::write(new_render.path().join("added.txt"), "added\n").unwrap();
+
+        // Modified locally, no base to compare against
+        fs::write(destination.path().join("local.txt"), "local\n").unwrap();
+        fs::write(new_render.path().join("local.txt"), "upstream\n").unwrap();
+
+        // Unmodified locally, but protected
+        fs::write(base.path().join("protected.txt"), "one\n").unwrap();
+        fs::write(destination.path().join("protected.txt"), "one\n").unwrap();
+        fs::write(new_render.path().join("protected.txt"), "two\n").unwrap();
+        let protected = ProtectedFiles::parse(destination.path(), "protected.txt\n");
+
+        // Modified locally, no base to compare against, but replaced by its conflict strategy
+        fs::write(destination.path().join("generated.txt"), "local\n").unwrap();
+        fs::write(new_render.path().join("generated.txt"), "upstream\n").unwrap();
+        let conflicts = ConflictStrategies::new().with_strategy("generated.txt", ConflictStrategy::Theirs);
+        let mut archetect = Archetect::builder().with_backup(true).build().unwrap();
+        assert!(archetect.begin_backup(destination.path()));
+
+        let outcome = merge_file(
+            &mut archetect,
+            Path::new("unmodified.txt"),
+            Some(base.path()),
+            new_render.path(),
+            destination.path(),
+            &protected,
+            &conflicts,
+        );
+        assert_eq!(outcome.unwrap(), UpgradeOutcome::Updated);
+        assert_eq!(fs::read_to_string(destination.path().join("unmodified.txt")).unwrap(), "two\n");
+
+        let outcome = merge_file(
+            &mut archetect,
+            Path::new("added.txt"),
+            Some(base.path()),
+            new_render.path(),
+            destination.path(),
+            &protected,
+            &conflicts,
+        );
+        assert_eq!(outcome.unwrap(), UpgradeOutcome::Added);
+
+        let outcome = merge_file(
+            &mut archetect,
+            Path::new("local.txt"),
+            Some(base.path()),
+            new_render.path(),
+            destination.path(),
+            &protected,
+            &conflicts,
+        );
+        assert_eq!(outcome.unwrap(), UpgradeOutcome::Kept);
+        assert_eq!(fs::read_to_string(destination.path().join("local.txt")).unwrap(), "local\n");
+
+        let outcome = merge_file(
+            &mut archetect,
+            Path::new("protected.txt"),
+            Some(base.path()),
+            new_render.path(),
+            destination.path(),
+            &protected,
+            &conflicts,
+        );
+        assert_eq!(outcome.unwrap(), UpgradeOutcome::Protected);
+        assert_eq!(fs::read_to_string(destination.path().join("protected.txt")).unwrap(), "one\n");
+
+        let outcome = merge_file(
+            &mut archetect,
+            Path::new("generated.txt"),
+            Some(base.path()),
+            new_render.path(),
+            destination.path(),
+            &protected,
+            &conflicts,
+        );
+        assert_eq!(outcome.unwrap(), UpgradeOutcome::Updated);
+        assert_eq!(fs::read_to_string(destination.path().join("generated.txt")).unwrap(), "upstream\n");
+
+        archetect.finish_backup();
+        let backup = &archetect.backups()[0];
+        assert_eq!(backup.files(), &[PathBuf::from("unmodified.txt"), PathBuf::from("generated.txt")]);
+        assert_eq!(backup.added(), &[PathBuf::from("added.txt")]);
+        assert_eq!(fs::read_to_string(backup.directory().join("generated.txt")).unwrap(), "local\n");
+    }
+
+    #[test]
+    fn test_relative_files_skips_metadata() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("src")).unwrap();
+        fs::write(root.path().join("src/main.rs"), "").unwrap();
+        fs::write(root.path().join(METADATA_FILE_NAME), "").unwrap();
+
+        assert_eq!(relative_files(root.path()).unwrap(), vec![PathBuf::from("src/main.rs")]);
+    }
+}
@@ -1,3 +1,104 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Runs `command`, returning its trimmed standard output if it could be run and succeeded. Nothing is run when built
+/// for wasm, where processes cannot be spawned.
+pub(crate) fn command_output(command: &mut Command) -> Option<String> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    match command.output() {
+        Ok(output) if output.status.success() => Some(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+        _ => None,
+    }
+}
+
+/// Resolves a destination against the current directory, unless rendering in memory, and removes `.` and `..`
+/// components without touching the filesystem, as it may not exist yet. Actions editing a project's build files use
+/// this to search above the destination.
+pub(crate) fn resolve_path(path: &Path, in_memory: bool) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(current_dir) if !in_memory => current_dir.join(path),
+        _ => path.to_owned(),
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The paths of the entries in `directory`, sorted, as the order they are read in differs between platforms and
+/// filesystems.
+pub(crate) fn sorted_entries(directory: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Takes an exclusive lock on the file at `path`, creating it if need be, and waiting up to `timeout` for whoever holds
+/// it to let go; `None` if they did not. The lock is the operating system's, held until the file returned is dropped,
+/// so one left by a process that died does not hold anything up. The file itself is left in place, as removing it
+/// would let a process that had already opened it lock a different file than the next one to come along.
+pub(crate) fn lock_file(path: &Path, timeout: Duration) -> io::Result<Option<File>> {
+    let file = open_lock(path)?;
+    Ok(wait_for_lock(&file, false, timeout)?.then_some(file))
+}
+
+/// Opens the file at `path` to be locked with [`wait_for_lock`], creating it if need be.
+pub(crate) fn open_lock(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().write(true).create(true).truncate(false).open(path)
+}
+
+/// Locks `file`, shared or exclusively, waiting up to `timeout` for whoever holds it otherwise to let go; false if they
+/// did not. A lock `file` already holds is converted to the kind asked for.
+pub(crate) fn wait_for_lock(file: &File, shared: bool, timeout: Duration) -> io::Result<bool> {
+    let started = Instant::now();
+    loop {
+        let locked = if shared { file.try_lock_shared() } else { file.try_lock() };
+        match locked {
+            Ok(()) => return Ok(true),
+            Err(TryLockError::WouldBlock) if started.elapsed() >= timeout => return Ok(false),
+            Err(TryLockError::WouldBlock) => thread::sleep(LOCK_RETRY),
+            // Where there are no file locks to take, there is nothing to wait for either.
+            Err(TryLockError::Error(error)) if error.kind() == io::ErrorKind::Unsupported => return Ok(true),
+            Err(TryLockError::Error(error)) => return Err(error),
+        }
+    }
+}
+
+/// The components of `path` below `base`, if it lies strictly within it.
+pub(crate) fn relative_components(path: &Path, base: &Path) -> Option<Vec<String>> {
+    let components = path
+        .strip_prefix(base)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>();
+    if components.is_empty() {
+        None
+    } else {
+        Some(components)
+    }
+}
+
 #[cfg(test)]
 pub mod testing {
     pub fn strip_newline(input: &str) -> &str {
@@ -7,3 +108,39 @@ pub mod testing {
             .unwrap_or(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("state/ports.yml.lock");
+
+        let held = lock_file(&path, Duration::ZERO).unwrap();
+        assert!(held.is_some());
+        assert!(lock_file(&path, Duration::from_millis(100)).unwrap().is_none());
+        drop(held);
+        assert!(lock_file(&path, Duration::ZERO).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_shared_lock() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("worktree.lock");
+
+        let reading = open_lock(&path).unwrap();
+        let also_reading = open_lock(&path).unwrap();
+        assert!(wait_for_lock(&reading, true, Duration::ZERO).unwrap());
+        assert!(wait_for_lock(&also_reading, true, Duration::ZERO).unwrap());
+        assert!(lock_file(&path, Duration::ZERO).unwrap().is_none());
+
+        // Whoever holds the only shared lock may take it exclusively, then share it again.
+        drop(also_reading);
+        assert!(wait_for_lock(&reading, false, Duration::ZERO).unwrap());
+        assert!(!wait_for_lock(&open_lock(&path).unwrap(), true, Duration::ZERO).unwrap());
+        assert!(wait_for_lock(&reading, true, Duration::ZERO).unwrap());
+        assert!(wait_for_lock(&open_lock(&path).unwrap(), true, Duration::ZERO).unwrap());
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+#[cfg(feature = "toml")]
+use toml_edit::DocumentMut;
+
+use crate::Archetect;
+
+/// Checks the syntax of rendered files of one kind, catching template mistakes that leave files unparsable.
+pub trait OutputValidator {
+    fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput>;
+}
+
+/// What to do with rendered files that fail validation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ValidationPolicy {
+    #[default]
+    #[serde(rename = "WARN", alias = "warn")]
+    Warn,
+    #[serde(rename = "ERROR", alias = "error")]
+    Error,
+}
+
+/// The kinds of output that rendered files are validated as.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputKind {
+    Json,
+    Yaml,
+    Toml,
+    Xml,
+    CargoManifest,
+    KubernetesManifest,
+    HelmChart,
+    /// Output checked by a validator registered outside of Archetect.
+    Custom(String),
+}
+
+/// A rendered file that failed validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidOutput {
+    destination: PathBuf,
+    kind: OutputKind,
+    message: String,
+}
+
+/// The validators run over rendered files, keyed by file extension.
+#[derive(Clone)]
+pub struct ValidatorRegistry {
+    validators: HashMap<String, Rc<dyn OutputValidator>>,
+}
+
+impl Display for OutputKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputKind::Json => write!(f, "JSON"),
+            OutputKind::Yaml => write!(f, "YAML"),
+            OutputKind::Toml => write!(f, "TOML"),
+            OutputKind::Xml => write!(f, "XML"),
+            OutputKind::CargoManifest => write!(f, "a Cargo manifest"),
+            OutputKind::KubernetesManifest => write!(f, "a Kubernetes manifest"),
+            OutputKind::HelmChart => write!(f, "a Helm chart"),
+            OutputKind::Custom(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl InvalidOutput {
+    pub fn new<M: Into<String>>(destination: &Path, kind: OutputKind, message: M) -> InvalidOutput {
+        InvalidOutput {
+            destination: destination.to_owned(),
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn destination(&self) -> &Path {
+        &self.destination
+    }
+
+    pub fn kind(&self) -> &OutputKind {
+        &self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for InvalidOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` is not valid {}: {}",
+            self.destination.display(),
+            self.kind,
+            self.message
+        )
+    }
+}
+
+impl ValidatorRegistry {
+    /// A registry without any validators.
+    pub fn new() -> ValidatorRegistry {
+        ValidatorRegistry {
+            validators: HashMap::new(),
+        }
+    }
+
+    /// Validates files with `extension` using `validator`, replacing any validator already registered for it.
+    pub fn register<E: AsRef<str>, V: OutputValidator + 'static>(&mut self, extension: E, validator: V) {
+        self.validators.insert(
+            extension.as_ref().trim_start_matches('.').to_lowercase(),
+            Rc::new(validator),
+        );
+    }
+
+    pub fn with_validator<E: AsRef<str>, V: OutputValidator + 'static>(
+        mut self,
+        extension: E,
+        validator: V,
+    ) -> ValidatorRegistry {
+        self.register(extension, validator);
+        self
+    }
+
+    pub fn validator(&self, destination: &Path) -> Option<&dyn OutputValidator> {
+        let extension = destination.extension()?.to_str()?.to_lowercase();
+        self.validators.get(&extension).map(|validator| validator.as_ref())
+    }
+
+    /// Validates a rendered file with the validator registered for its extension. Files without one are not checked.
+    pub fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+        match self.validator(destination) {
+            Some(validator) => validator.validate(destination, contents),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Validators for JSON, YAML, TOML, and XML. TOML is only validated with the `toml` feature.
+impl Default for ValidatorRegistry {
+    fn default() -> Self {
+        #[cfg_attr(not(feature = "toml"), allow(unused_mut))]
+        let mut registry = ValidatorRegistry::new()
+            .with_validator("json", JsonValidator)
+            .with_validator("yaml", YamlValidator)
+            .with_validator("yml", YamlValidator)
+            .with_validator("xml", XmlValidator);
+        #[cfg(feature = "toml")]
+        registry.register("toml", TomlValidator);
+        registry
+    }
+}
+
+/// Checks JSON syntax. `tsconfig` and `jsconfig` files allow comments, so are not checked.
+pub struct JsonValidator;
+
+impl OutputValidator for JsonValidator {
+    fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+        let file_name = file_name(destination);
+        if file_name.starts_with("tsconfig") || file_name.starts_with("jsconfig") {
+            return Ok(());
+        }
+        serde_json::from_str::<serde_json::Value>(contents)
+            .map(|_| ())
+            .map_err(|error| InvalidOutput::new(destination, OutputKind::Json, error.to_string()))
+    }
+}
+
+/// Checks each document in a YAML stream. Documents declaring an `apiVersion` and `kind` are checked as Kubernetes
+/// manifests, and a `Chart.yaml` as a Helm chart.
+pub struct YamlValidator;
+
+impl OutputValidator for YamlValidator {
+    fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+        let invalid = |(kind, message)| InvalidOutput::new(destination, kind, message);
+        if file_name(destination) == "Chart.yaml" {
+            return validate_helm_chart(contents).map_err(invalid);
+        }
+        for (index, document) in serde_yaml::Deserializer::from_str(contents).enumerate() {
+            let document = Value::deserialize(document)
+                .map_err(|error| InvalidOutput::new(destination, OutputKind::Yaml, error.to_string()))?;
+            if let Value::Mapping(manifest) = &document {
+                if manifest.contains_key(&key("apiVersion")) && manifest.contains_key(&key("kind")) {
+                    validate_kubernetes_manifest(manifest).map_err(|message| {
+                        InvalidOutput::new(
+                            destination,
+                            OutputKind::KubernetesManifest,
+                            format!("document {}: {}", index + 1, message),
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks TOML syntax, and that a `Cargo.toml` declares a package or a workspace.
+#[cfg(feature = "toml")]
+pub struct TomlValidator;
+
+#[cfg(feature = "toml")]
+impl OutputValidator for TomlValidator {
+    fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+        let document = contents
+            .parse::<DocumentMut>()
+            .map_err(|error| InvalidOutput::new(destination, OutputKind::Toml, error.to_string().trim()))?;
+        if file_name(destination) == "Cargo.toml" {
+            validate_cargo_manifest(&document)
+                .map_err(|message| InvalidOutput::new(destination, OutputKind::CargoManifest, message))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that XML is well formed: a single root element, with every element closed in order.
+pub struct XmlValidator;
+
+impl OutputValidator for XmlValidator {
+    fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+        check_xml(contents).map_err(|message| InvalidOutput::new(destination, OutputKind::Xml, message))
+    }
+}
+
+/// Whether `file` is a template within a Helm chart, which Helm renders itself, so it is not yet valid YAML.
+pub(crate) fn is_helm_template(archetect: &Archetect, file: &Path) -> bool {
+    file.ancestors().skip(1).any(|directory| {
+        directory.file_name().is_some_and(|name| name == "templates")
+            && directory.parent().is_some_and(|chart| {
+                archetect
+                    .read_contents(chart.join("Chart.yaml"))
+                    .is_ok_and(|chart| chart.is_some())
+            })
+    })
+}
+
+fn file_name(destination: &Path) -> &str {
+    destination
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+fn validate_kubernetes_manifest(manifest: &Mapping) -> Result<(), String> {
+    non_empty_string(manifest, "apiVersion")?;
+    let kind = non_empty_string(manifest, "kind")?;
+    // Lists carry their resources as items, rather than being resources themselves.
+    if kind.ends_with("List") {
+        return Ok(());
+    }
+
+    let metadata = match manifest.get(&key("metadata")) {
+        Some(Value::Mapping(metadata)) => metadata,
+        _ => return Err("`metadata` is missing or not a mapping".to_owned()),
+    };
+    match metadata.get(&key("name")) {
+        Some(Value::String(name)) if is_resource_name(name) => (),
+        // Generated names are completed by the API server, so only their presence is checked.
+        None if metadata.get(&key("generateName")).is_some_and(|name| name.is_string()) => (),
+        Some(Value::String(name)) => {
+            return Err(format!(
+                "`metadata.name` `{}` must be lowercase alphanumerics, `-`, and `.`",
+                name
+            ))
+        }
+        _ => return Err("`metadata.name` is missing or not a string".to_owned()),
+    }
+    for field in ["labels", "annotations"] {
+        match metadata.get(&key(field)) {
+            None | Some(Value::Null) => (),
+            Some(Value::Mapping(values)) => {
+                if let Some((name, _)) = values.iter().find(|(_, value)| !value.is_string()) {
+                    return Err(format!(
+                        "`metadata.{}` values must be strings, but `{}` is not",
+                        field,
+                        name.as_str().unwrap_or_default()
+                    ));
+                }
+            }
+            Some(_) => return Err(format!("`metadata.{}` is not a mapping", field)),
+        }
+    }
+    Ok(())
+}
+
+fn validate_helm_chart(contents: &str) -> Result<(), (OutputKind, String)> {
+    let chart = serde_yaml::from_str::<Value>(contents).map_err(|error| (OutputKind::Yaml, error.to_string()))?;
+    let chart = match &chart {
+        Value::Mapping(chart) => chart,
+        _ => return Err((OutputKind::HelmChart, "the chart is not a mapping".to_owned())),
+    };
+    for field in ["apiVersion", "name", "version"] {
+        non_empty_string(chart, field).map_err(|message| (OutputKind::HelmChart, message))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "toml")]
+fn validate_cargo_manifest(manifest: &DocumentMut) -> Result<(), &'static str> {
+    match manifest.get("package") {
+        Some(package) => match package.get("name").and_then(|name| name.as_str()) {
+            Some(name) if !name.is_empty() => Ok(()),
+            _ => Err("`package.name` is missing or not a string"),
+        },
+        None if manifest.contains_key("workspace") => Ok(()),
+        None => Err("neither a `[package]` nor a `[workspace]` is declared"),
+    }
+}
+
+fn key(name: &str) -> Value {
+    Value::String(name.to_owned())
+}
+
+fn non_empty_string<'a>(mapping: &'a Mapping, field: &str) -> Result<&'a str, String> {
+    match mapping.get(&key(field)) {
+        Some(Value::String(value)) if !value.is_empty() => Ok(value),
+        _ => Err(format!("`{}` is missing or not a string", field)),
+    }
+}
+
+/// Whether `name` is a DNS subdomain, as most resources require of their names.
+fn is_resource_name(name: &str) -> bool {
+    name.len() <= 253
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+}
+
+/// Checks that `contents` is well formed XML, without resolving entities or checking it against a schema.
+fn check_xml(contents: &str) -> Result<(), String> {
+    let line = |offset: usize| contents[..offset].matches('\n').count() + 1;
+    let mut open: Vec<(&str, usize)> = vec![];
+    let mut root_seen = false;
+    let mut offset = 0;
+
+    while let Some(start) = contents[offset..].find('<').map(|start| offset + start) {
+        if open.is_empty() && !contents[offset..start].trim().is_empty() {
+            return Err(format!("line {}: text outside of the root element", line(offset)));
+        }
+        let markup = &contents[start..];
+        let terminator = if markup.starts_with("<!--") {
+            "-->"
+        } else if markup.starts_with("<![CDATA[") {
+            "]]>"
+        } else if markup.starts_with("<?") {
+            "?>"
+        } else {
+            ">"
+        };
+        let end = if terminator == ">" {
+            tag_end(markup)
+        } else {
+            markup.find(terminator).map(|end| end + terminator.len())
+        }
+        .ok_or_else(|| format!("line {}: unterminated markup", line(start)))?;
+        offset = start + end;
+
+        if markup.starts_with("<![CDATA[") && open.is_empty() {
+            return Err(format!("line {}: CDATA outside of the root element", line(start)));
+        }
+        if markup.starts_with("<!") || markup.starts_with("<?") {
+            continue;
+        }
+
+        let tag = &markup[1..end - 1];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some((expected, _)) if expected == name => (),
+                Some((expected, opened)) => {
+                    return Err(format!(
+                        "line {}: `</{}>` closes `<{}>` from line {}",
+                        line(start),
+                        name,
+                        expected,
+                        line(opened)
+                    ))
+                }
+                None => return Err(format!("line {}: `</{}>` closes nothing", line(start), name)),
+            }
+            continue;
+        }
+
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if name.is_empty() {
+            return Err(format!("line {}: an element is missing its name", line(start)));
+        }
+        if open.is_empty() && root_seen {
+            return Err(format!("line {}: `<{}>` is a second root element", line(start), name));
+        }
+        root_seen = true;
+        if !tag.ends_with('/') {
+            open.push((name, start));
+        }
+    }
+
+    if let Some((name, opened)) = open.pop() {
+        return Err(format!("line {}: `<{}>` is never closed", line(opened), name));
+    }
+    if !contents[offset..].trim().is_empty() {
+        return Err(format!("line {}: text outside of the root element", line(offset)));
+    }
+    if !root_seen {
+        return Err("there is no root element".to_owned());
+    }
+    Ok(())
+}
+
+/// The length of the tag at the start of `markup`, allowing for `>` within quoted attribute values.
+fn tag_end(markup: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(destination: &str, contents: &str) -> Option<(OutputKind, String)> {
+        ValidatorRegistry::default()
+            .validate(Path::new(destination), contents)
+            .err()
+            .map(|invalid| (invalid.kind().clone(), invalid.message().to_owned()))
+    }
+
+    #[test]
+    fn test_validate_syntax() {
+        assert_eq!(invalid("config/app.yml", "name: service\nports: [8080]\n"), None);
+        assert_eq!(
+            invalid("config/app.yml", "name: service\n  port: 8080\n").unwrap().0,
+            OutputKind::Yaml
+        );
+        assert_eq!(
+            invalid("package.json", "{\"name\": \"shop\",}").unwrap().0,
+            OutputKind::Json
+        );
+        assert_eq!(invalid("tsconfig.json", "{ // comments are allowed\n}"), None);
+        #[cfg(feature = "toml")]
+        assert_eq!(invalid("rustfmt.toml", "max_width = ").unwrap().0, OutputKind::Toml);
+        assert_eq!(invalid("README.md", "{ not checked"), None);
+    }
+
+    #[test]
+    fn test_validate_xml() {
+        let pom = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- Generated -->
+<project xmlns=\"http://maven.apache.org/POM/4.0.0\">
+    <name>a &lt; b</name>
+    <description><![CDATA[<not a tag>]]></description>
+    <properties filter=\"a>b\"/>
+</project>
+";
+        assert_eq!(invalid("pom.xml", pom), None);
+        assert_eq!(
+            invalid("pom.xml", "<project>\n  <name>shop</artifactId>\n</project>"),
+            Some((
+                OutputKind::Xml,
+                "line 2: `</artifactId>` closes `<name>` from line 2".to_owned()
+            ))
+        );
+        assert_eq!(
+            invalid("layout.XML", "<project>\n  <modules>\n</project>").unwrap().1,
+            "line 3: `</project>` closes `<modules>` from line 2"
+        );
+        assert_eq!(
+            invalid("pom.xml", "<project>\n</project>\n<project/>").unwrap().1,
+            "line 3: `<project>` is a second root element"
+        );
+        assert!(invalid("pom.xml", "<project>").is_some());
+        assert!(invalid("pom.xml", "").is_some());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_validate_cargo_manifest() {
+        assert_eq!(invalid("Cargo.toml", "[package]\nname = \"service\"\n"), None);
+        assert_eq!(invalid("Cargo.toml", "[workspace]\nmembers = []\n"), None);
+        assert_eq!(
+            invalid("service/Cargo.toml", "[package]\nname = \"\"\n").unwrap().0,
+            OutputKind::CargoManifest
+        );
+        assert_eq!(
+            invalid("Cargo.toml", "[dependencies]\n").unwrap().0,
+            OutputKind::CargoManifest
+        );
+    }
+
+    #[test]
+    fn test_validate_kubernetes_manifest() {
+        let manifests = "apiVersion: v1
+kind: Service
+metadata:
+  name: billing
+  labels:
+    app: billing
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: Billing
+";
+        assert_eq!(
+            invalid("deploy/billing.yaml", manifests),
+            Some((
+                OutputKind::KubernetesManifest,
+                "document 2: `metadata.name` `Billing` must be lowercase alphanumerics, `-`, and `.`".to_owned()
+            ))
+        );
+
+        let labels = "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: billing\n  labels:\n    tier: 2\n";
+        assert_eq!(
+            invalid("billing.yaml", labels).unwrap().1,
+            "document 1: `metadata.labels` values must be strings, but `tier` is not"
+        );
+        assert!(invalid("billing.yaml", "apiVersion: v1\nkind: Pod\n").is_some());
+        assert_eq!(invalid("billing.yaml", "apiVersion: v1\nkind: List\nitems: []\n"), None);
+    }
+
+    #[test]
+    fn test_validate_helm_chart() {
+        assert_eq!(
+            invalid("chart/Chart.yaml", "apiVersion: v2\nname: billing\nversion: 0.1.0\n"),
+            None
+        );
+        assert_eq!(
+            invalid("chart/Chart.yaml", "apiVersion: v2\nname: billing\n"),
+            Some((OutputKind::HelmChart, "`version` is missing or not a string".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_custom_validator() {
+        struct Properties;
+
+        impl OutputValidator for Properties {
+            fn validate(&self, destination: &Path, contents: &str) -> Result<(), InvalidOutput> {
+                match contents
+                    .lines()
+                    .position(|line| !line.is_empty() && !line.contains('='))
+                {
+                    Some(index) => Err(InvalidOutput::new(
+                        destination,
+                        OutputKind::Custom("properties".to_owned()),
+                        format!("line {} is not a property", index + 1),
+                    )),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        let registry = ValidatorRegistry::new().with_validator(".properties", Properties);
+        let destination = Path::new("application.properties");
+        assert!(registry.validate(destination, "port=8080\n").is_ok());
+        assert_eq!(
+            registry.validate(destination, "port 8080\n").unwrap_err().to_string(),
+            "`application.properties` is not valid properties: line 1 is not a property"
+        );
+        assert!(registry.validate(Path::new("broken.json"), "{").is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::validation::InvalidOutput;
+
+/// Rendered lines longer than this are reported as likely mistakes, such as a runaway loop or a missing newline.
+pub const LONG_LINE_THRESHOLD: usize = 200;
+
+/// A likely mistake noticed while rendering. Warnings do not stop a render unless warnings are denied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderWarning {
+    /// The rendered output still contains template delimiters, suggesting a template that was escaped or copied
+    /// without being rendered.
+    UnrenderedTemplate { source: PathBuf, destination: PathBuf },
+    /// A template with content rendered to an empty file.
+    EmptyRender { source: PathBuf, destination: PathBuf },
+    /// Two source files rendered different content to the same destination during one run.
+    DestinationCollision { destination: PathBuf, first: PathBuf, second: PathBuf },
+    /// A rendered file contains a line longer than [`LONG_LINE_THRESHOLD`].
+    LongLine { destination: PathBuf, line: usize, length: usize },
+    /// A rendered file of a recognized kind, such as YAML or a Cargo manifest, is not valid. Only reported when output
+    /// validation is enabled.
+    InvalidOutput(InvalidOutput),
+    /// A rendered file was merged into an existing one, leaving conflict markers where they disagree.
+    MergeConflict { destination: PathBuf },
+    /// Two rendered paths differ only by case, and would be the same file on a case-insensitive filesystem.
+    CaseConflict { first: PathBuf, second: PathBuf },
+    /// The context of the render grew past its limit, as when loops gather large datasets into variables.
+    LargeContext { size: usize, limit: usize },
+}
+
+impl Display for RenderWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderWarning::UnrenderedTemplate { source, destination } => write!(
+                f,
+                "`{}` contains unrendered template delimiters (from `{}`)",
+                destination.display(),
+                source.display()
+            ),
+            RenderWarning::EmptyRender { source, destination } => write!(
+                f,
+                "`{}` rendered to an empty file at `{}`",
+                source.display(),
+                destination.display()
+            ),
+            RenderWarning::DestinationCollision {
+                destination,
+                first,
+                second,
+            } => write!(
+                f,
+                "`{}` and `{}` both render to `{}`",
+                first.display(),
+                second.display(),
+                destination.display()
+            ),
+            RenderWarning::LongLine {
+                destination,
+                line,
+                length,
+            } => write!(
+                f,
+                "`{}` line {} is {} characters long",
+                destination.display(),
+                line,
+                length
+            ),
+            RenderWarning::InvalidOutput(invalid) => write!(f, "{}", invalid),
+            RenderWarning::MergeConflict { destination } => {
+                write!(f, "`{}` was merged with conflicts to resolve", destination.display())
+            }
+            RenderWarning::CaseConflict { first, second } => write!(
+                f,
+                "`{}` and `{}` differ only by case, and would be the same file on Windows and macOS",
+                first.display(),
+                second.display()
+            ),
+            RenderWarning::LargeContext { size, limit } => write!(
+                f,
+                "the render context has grown to {} bytes, past its limit of {} bytes",
+                size, limit
+            ),
+        }
+    }
+}
+
+/// Inspects a rendered file for likely mistakes.
+pub fn inspect_render(source: &Path, destination: &Path, template: &str, contents: &str) -> Vec<RenderWarning> {
+    let mut warnings = vec![];
+    if contents.contains("{{") || contents.contains("{%") {
+        warnings.push(RenderWarning::UnrenderedTemplate {
+            source: source.to_owned(),
+            destination: destination.to_owned(),
+        });
+    }
+    if !template.trim().is_empty() && contents.trim().is_empty() {
+        warnings.push(RenderWarning::EmptyRender {
+            source: source.to_owned(),
+            destination: destination.to_owned(),
+        });
+    }
+    let long_line = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.chars().count()))
+        .find(|(_, length)| *length > LONG_LINE_THRESHOLD);
+    if let Some((line, length)) = long_line {
+        warnings.push(RenderWarning::LongLine {
+            destination: destination.to_owned(),
+            line,
+            length,
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_render() {
+        let source = Path::new("contents/{{ name }}.txt");
+        let destination = Path::new("service.txt");
+
+        assert!(inspect_render(source, destination, "{{ name }}", "service").is_empty());
+
+        let warnings = inspect_render(source, destination, "{% raw %}{{ name }}{% endraw %}", "{{ name }}");
+        assert!(matches!(warnings.as_slice(), [RenderWarning::UnrenderedTemplate { .. }]));
+
+        let warnings = inspect_render(source, destination, "{% if false %}content{% endif %}", "\n");
+        assert!(matches!(warnings.as_slice(), [RenderWarning::EmptyRender { .. }]));
+
+        let contents = format!("short\n{}\n", "x".repeat(LONG_LINE_THRESHOLD + 1));
+        let warnings = inspect_render(source, destination, "{{ long }}", &contents);
+        assert_eq!(
+            warnings,
+            vec![RenderWarning::LongLine {
+                destination: destination.to_owned(),
+                line: 2,
+                length: LONG_LINE_THRESHOLD + 1
+            }]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * A C interface to Archetect.
+ *
+ * Each function takes a JSON request as a NUL-terminated UTF-8 string, writes a JSON response to `response`, and
+ * returns one of the ARCHETECT_* codes below. Failed responses are of the form
+ * {"code": 3, "error": "source", "message": "..."}. Responses must be released with archetect_free_string.
+ *
+ * Calls may be made from any thread, but each call blocks until Archetect finishes, including any git clone.
+ */
+#ifndef ARCHETECT_H
+#define ARCHETECT_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ARCHETECT_OK 0
+#define ARCHETECT_INVALID_ARGUMENT 1
+#define ARCHETECT_INVALID_REQUEST 2
+#define ARCHETECT_SOURCE 3
+#define ARCHETECT_ARCHETYPE 4
+#define ARCHETECT_MISSING_ANSWER 5
+#define ARCHETECT_RENDER 6
+#define ARCHETECT_IO 7
+#define ARCHETECT_FEATURE_DISABLED 8
+#define ARCHETECT_ERROR 9
+#define ARCHETECT_PANIC 99
+
+/* {"source": "...", "offline": false} */
+int32_t archetect_resolve(const char *request, char **response);
+
+/* {"source": "..."} or {"files": {"archetype.yml": "...", ...}} */
+int32_t archetect_describe(const char *request, char **response);
+
+/* {"source": "...", "destination": "...", "answers": {...}, "switches": [...], "offline": false, "validate": false,
+ *  "trust": false}
+ * Without a destination, renders in memory, and the response includes the rendered "files". */
+int32_t archetect_render(const char *request, char **response);
+
+void archetect_free_string(char *response);
+
+/* A static string that must not be released. */
+const char *archetect_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,122 @@
+use archetect_core::source::SourceError;
+use archetect_core::{ArchetectError, ArchetypeError};
+use serde_json::{json, Value};
+
+/// Codes returned by every `archetect_*` function. These are part of the C interface, and their values must not
+/// change; new codes may be added.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    Ok = 0,
+    /// A null pointer, or a request that is not UTF-8.
+    InvalidArgument = 1,
+    /// A request that is not JSON, or not the JSON the function expects.
+    InvalidRequest = 2,
+    /// The archetype source could not be found or fetched.
+    Source = 3,
+    /// The archetype's configuration is missing or invalid.
+    Archetype = 4,
+    /// A variable has no answer, and none can be prompted for.
+    MissingAnswer = 5,
+    /// Templates failed to render, or rendered invalid output or denied warnings.
+    Render = 6,
+    Io = 7,
+    /// The request needs a cargo feature this build does not include.
+    FeatureDisabled = 8,
+    /// Any other error from Archetect.
+    Archetect = 9,
+    /// Archetect panicked. The panic does not cross the C interface.
+    Panic = 99,
+}
+
+impl ErrorCode {
+    /// The code's name, as reported in error responses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCode::Ok => "ok",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Source => "source",
+            ErrorCode::Archetype => "archetype",
+            ErrorCode::MissingAnswer => "missing_answer",
+            ErrorCode::Render => "render",
+            ErrorCode::Io => "io",
+            ErrorCode::FeatureDisabled => "feature_disabled",
+            ErrorCode::Archetect => "archetect",
+            ErrorCode::Panic => "panic",
+        }
+    }
+}
+
+/// A failed request, answered with `{"code": 3, "error": "source", "message": "..."}`.
+#[derive(Debug)]
+pub struct FfiError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl FfiError {
+    pub fn new<M: Into<String>>(code: ErrorCode, message: M) -> FfiError {
+        FfiError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "code": self.code as i32,
+            "error": self.code.name(),
+            "message": self.message,
+        })
+    }
+}
+
+impl From<ArchetectError> for FfiError {
+    fn from(error: ArchetectError) -> FfiError {
+        let code = match &error {
+            ArchetectError::SourceError(_) => ErrorCode::Source,
+            ArchetectError::ArchetypeError(error) => archetype_error_code(error),
+            ArchetectError::HeadlessMissingAnswer(_) | ArchetectError::HeadlessInvalidDefault { .. } => {
+                ErrorCode::MissingAnswer
+            }
+            ArchetectError::RenderError(_)
+            | ArchetectError::RenderErrors(_)
+            | ArchetectError::InvalidOutput(_)
+            | ArchetectError::DeniedWarnings(_) => ErrorCode::Render,
+            ArchetectError::IoError(_) => ErrorCode::Io,
+            ArchetectError::FeatureDisabled(_, _) => ErrorCode::FeatureDisabled,
+            _ => ErrorCode::Archetect,
+        };
+        FfiError::new(code, error.to_string())
+    }
+}
+
+impl From<ArchetypeError> for FfiError {
+    fn from(error: ArchetypeError) -> FfiError {
+        FfiError::new(archetype_error_code(&error), error.to_string())
+    }
+}
+
+impl From<SourceError> for FfiError {
+    fn from(error: SourceError) -> FfiError {
+        FfiError::new(ErrorCode::Source, error.to_string())
+    }
+}
+
+fn archetype_error_code(error: &ArchetypeError) -> ErrorCode {
+    match error {
+        ArchetypeError::SourceError(_) => ErrorCode::Source,
+        ArchetypeError::RenderError(_) => ErrorCode::Render,
+        ArchetypeError::IoError(_) => ErrorCode::Io,
+        _ => ErrorCode::Archetype,
+    }
+}
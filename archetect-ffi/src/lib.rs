@@ -0,0 +1,253 @@
+//! A C interface to Archetect, for platforms that embed it rather than running the `archetect` binary.
+//!
+//! Each function takes a JSON request as a NUL-terminated UTF-8 string, writes a JSON response to `response`, and
+//! returns an [`ErrorCode`]. Successful responses carry the function's result; failed responses are of the form
+//! `{"code": 3, "error": "source", "message": "..."}`. Responses are owned by the caller, and must be released with
+//! [`archetect_free_string`]. Renders are always headless, so every variable must be answered or have a default.
+//!
+//! `include/archetect.h` declares these functions for C.
+#[macro_use]
+extern crate serde_derive;
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub use crate::errors::{ErrorCode, FfiError};
+
+mod errors;
+mod requests;
+
+static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Fetches a source, cloning or downloading it as `archetect render` would, and reports where it was cached.
+///
+/// Request: `{"source": "https://github.com/archetect/archetype-rust-cli.git#v1.0", "offline": false}`
+///
+/// # Safety
+///
+/// `request` must be null or a NUL-terminated string, and `response` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn archetect_resolve(request: *const c_char, response: *mut *mut c_char) -> i32 {
+    call(request, response, requests::resolve)
+}
+
+/// Loads an archetype, responding with its configuration: its description, authors, script, and so on.
+///
+/// Request: `{"source": "..."}`, or `{"files": {"archetype.yml": "...", ...}}` for an archetype held in memory.
+///
+/// # Safety
+///
+/// `request` must be null or a NUL-terminated string, and `response` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn archetect_describe(request: *const c_char, response: *mut *mut c_char) -> i32 {
+    call(request, response, requests::describe)
+}
+
+/// Renders an archetype to `destination`, or in memory when there is no destination, in which case the response
+/// includes the rendered `files`, and any `binary_files` encoded as base64. Remote archetypes only run the commands
+/// they declare when the request sets `trust`.
+///
+/// Request: `{"source": "...", "destination": "/tmp/out", "answers": {"name": "billing"}, "switches": []}`
+///
+/// # Safety
+///
+/// `request` must be null or a NUL-terminated string, and `response` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn archetect_render(request: *const c_char, response: *mut *mut c_char) -> i32 {
+    call(request, response, requests::render)
+}
+
+/// Releases a response. Null is ignored.
+///
+/// # Safety
+///
+/// `response` must be null or a string written by one of the `archetect_*` functions, and not already released.
+#[no_mangle]
+pub unsafe extern "C" fn archetect_free_string(response: *mut c_char) {
+    if !response.is_null() {
+        drop(CString::from_raw(response));
+    }
+}
+
+/// The version of Archetect, as a static string that must not be released.
+#[no_mangle]
+pub extern "C" fn archetect_version() -> *const c_char {
+    VERSION.as_ptr() as *const c_char
+}
+
+unsafe fn call<R, F>(request: *const c_char, response: *mut *mut c_char, operation: F) -> i32
+where
+    R: DeserializeOwned,
+    F: FnOnce(R) -> Result<Value, FfiError>,
+{
+    if !response.is_null() {
+        *response = ptr::null_mut();
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let request = parse_request(request)?;
+        operation(request)
+    }))
+    .unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Archetect panicked".to_owned());
+        Err(FfiError::new(ErrorCode::Panic, message))
+    });
+
+    let (code, body) = match result {
+        Ok(body) => (ErrorCode::Ok, body),
+        Err(error) => (error.code(), error.to_json()),
+    };
+    if !response.is_null() {
+        // Serialized JSON escapes any NUL, so this cannot fail.
+        *response = CString::new(body.to_string()).map_or(ptr::null_mut(), CString::into_raw);
+    }
+    code as i32
+}
+
+unsafe fn parse_request<R: DeserializeOwned>(request: *const c_char) -> Result<R, FfiError> {
+    if request.is_null() {
+        return Err(FfiError::new(ErrorCode::InvalidArgument, "The request is null"));
+    }
+    let request = CStr::from_ptr(request).to_str().map_err(|error| {
+        FfiError::new(
+            ErrorCode::InvalidArgument,
+            format!("The request is not UTF-8: {}", error),
+        )
+    })?;
+    serde_json::from_str(request)
+        .map_err(|error| FfiError::new(ErrorCode::InvalidRequest, format!("Invalid request: {}", error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::fs;
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn invoke(function: unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> i32, request: &str) -> (i32, Value) {
+        let request = CString::new(request).unwrap();
+        let mut response = ptr::null_mut();
+        unsafe {
+            let code = function(request.as_ptr(), &mut response);
+            let body = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+            archetect_free_string(response);
+            (code, body)
+        }
+    }
+
+    fn archetype() -> tempfile::TempDir {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            "description: A service\nscript:\n  - set:\n      name:\n        prompt: \"Name:\"\n  - render:\n      directory:\n        source: contents\n",
+        )
+        .unwrap();
+        let contents = archetype_dir.path().join("contents").join("{{ name }}");
+        fs::create_dir_all(&contents).unwrap();
+        fs::write(contents.join("README.md"), "# {{ name | title_case }}\n").unwrap();
+        archetype_dir
+    }
+
+    #[test]
+    fn test_describe() {
+        let archetype_dir = archetype();
+        let request = json!({ "source": archetype_dir.path() }).to_string();
+
+        let (code, response) = invoke(archetect_describe, &request);
+        assert_eq!(code, 0);
+        assert_eq!(response["config"]["description"], "A service");
+
+        let (code, response) = invoke(archetect_resolve, &request);
+        assert_eq!(code, 0);
+        assert_eq!(response["kind"], "local path");
+        assert_eq!(response["remote"], false);
+    }
+
+    #[test]
+    fn test_render() {
+        let archetype_dir = archetype();
+        let request = json!({ "source": archetype_dir.path(), "answers": { "name": "billing" } }).to_string();
+        let (code, response) = invoke(archetect_render, &request);
+        assert_eq!(code, 0);
+        assert_eq!(response["files"], json!({ "billing/README.md": "# Billing\n" }));
+
+        let destination = tempfile::tempdir().unwrap();
+        let request = json!({
+            "source": archetype_dir.path(),
+            "destination": destination.path(),
+            "answers": { "name": "billing" },
+            "trust": true,
+        })
+        .to_string();
+        let (code, response) = invoke(archetect_render, &request);
+        assert_eq!(code, 0);
+        assert!(response.get("files").is_none());
+        assert_eq!(
+            fs::read_to_string(destination.path().join("billing/README.md")).unwrap(),
+            "# Billing\n"
+        );
+    }
+
+    #[test]
+    fn test_render_files() {
+        let request = json!({
+            "files": {
+                "archetype.yml": concat!(
+                    "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n      enabled:\n        prompt: \"Enabled:\"\n",
+                    "  - render:\n      directory:\n        source: contents\n",
+                ),
+                "contents/{{ name }}.txt": "{{ enabled }}",
+            },
+            "answers": { "name": "flags", "enabled": true },
+        })
+        .to_string();
+        let (code, response) = invoke(archetect_render, &request);
+        assert_eq!(code, 0);
+        assert_eq!(response["files"]["flags.txt"], "true");
+    }
+
+    #[test]
+    fn test_errors() {
+        let archetype_dir = archetype();
+        let (code, response) = invoke(archetect_render, &json!({ "source": archetype_dir.path() }).to_string());
+        assert_eq!(code, ErrorCode::MissingAnswer as i32);
+        assert_eq!(response["code"], 5);
+        assert_eq!(response["error"], "missing_answer");
+
+        let (code, response) = invoke(archetect_describe, "{\"source\": \"/does/not/exist\"}");
+        assert_eq!(code, ErrorCode::Source as i32);
+        assert_eq!(response["error"], "source");
+
+        let (code, _) = invoke(archetect_describe, "{\"source\": \"a\", \"files\": {}}");
+        assert_eq!(code, ErrorCode::InvalidRequest as i32);
+
+        let (code, _) = invoke(archetect_render, "not json");
+        assert_eq!(code, ErrorCode::InvalidRequest as i32);
+
+        let mut response = ptr::null_mut();
+        assert_eq!(
+            unsafe { archetect_render(ptr::null(), &mut response) },
+            ErrorCode::InvalidArgument as i32
+        );
+        unsafe { archetect_free_string(response) };
+    }
+
+    #[test]
+    fn test_version() {
+        let version = unsafe { CStr::from_ptr(archetect_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
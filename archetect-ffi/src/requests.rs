@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use archetect_core::config::AnswerInfo;
+use archetect_core::file_tree::FileTree;
+use archetect_core::source::spec::SourceSpec;
+use archetect_core::{Archetect, Archetype};
+use linked_hash_map::LinkedHashMap;
+use serde_json::{json, Value};
+
+use crate::errors::{ErrorCode, FfiError};
+
+/// A source to fetch, without loading the archetype within it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResolveRequest {
+    /// A source, as given to `archetect render`.
+    source: String,
+    offline: bool,
+}
+
+/// The archetype to describe or render: either a `source`, or the archetype's `files`, keyed by their paths relative
+/// to the archetype's root.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DescribeRequest {
+    source: Option<String>,
+    files: Option<BTreeMap<String, String>>,
+    offline: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderRequest {
+    source: Option<String>,
+    files: Option<BTreeMap<String, String>>,
+    offline: bool,
+    /// Renders to this directory, or in memory when absent, returning the rendered files.
+    destination: Option<PathBuf>,
+    /// Answers by variable. Values other than strings are rendered as JSON, so that `true` answers `"true"`.
+    answers: LinkedHashMap<String, Value>,
+    switches: Vec<String>,
+    validate: bool,
+    /// Allows the archetype to run the commands it declares, as there is no one to ask.
+    trust: bool,
+}
+
+pub fn resolve(request: ResolveRequest) -> Result<Value, FfiError> {
+    let archetect = archetect(request.offline)?;
+    let spec = SourceSpec::parse(&archetect.expand_source(&request.source))?;
+    let source = spec.resolve(&archetect, None)?;
+
+    Ok(json!({
+        "source": spec.source(),
+        "kind": spec.kind().to_string(),
+        "remote": spec.is_remote(),
+        "ref": source.gitref(),
+        "commit": source.commit(),
+        "local_path": source.local_path(),
+    }))
+}
+
+pub fn describe(request: DescribeRequest) -> Result<Value, FfiError> {
+    let archetect = archetect(request.offline)?;
+    let archetype = load(&archetect, request.source.as_deref(), request.files)?;
+
+    Ok(json!({
+        "source": request.source,
+        "local_path": request.source.as_ref().map(|_| archetype.source().local_path()),
+        "config": archetype.configuration(),
+    }))
+}
+
+pub fn render(request: RenderRequest) -> Result<Value, FfiError> {
+    let mut archetect = Archetect::builder()
+        .with_headless(true)
+        .with_offline(request.offline)
+        .with_validate(request.validate)
+        .with_trust(request.trust)
+        .build()?;
+    for switch in request.switches {
+        archetect.enable_switch(switch);
+    }
+    let archetype = load(&archetect, request.source.as_deref(), request.files)?;
+
+    let mut answers = LinkedHashMap::new();
+    for (identifier, value) in request.answers {
+        let value = match value {
+            Value::String(value) => value,
+            value => value.to_string(),
+        };
+        answers.insert(identifier, AnswerInfo::with_value(value).build());
+    }
+
+    let files = match &request.destination {
+        Some(destination) => {
+            archetype.render(&mut archetect, destination, &answers)?;
+            None
+        }
+        None => Some(archetype.render_in_memory(&mut archetect, &answers)?),
+    };
+
+    let mut response = json!({
+        "destination": request.destination,
+        "warnings": archetect.warnings().iter().map(|warning| warning.to_string()).collect::<Vec<_>>(),
+        "steps": archetect.steps(),
+    });
+    if let Some(files) = files {
+        let (text, binary) = file_contents(&files);
+        response["files"] = json!(text);
+        response["binary_files"] = json!(binary);
+    }
+    Ok(response)
+}
+
+fn archetect(offline: bool) -> Result<Archetect, FfiError> {
+    Ok(Archetect::builder().with_headless(true).with_offline(offline).build()?)
+}
+
+fn load(
+    archetect: &Archetect,
+    source: Option<&str>,
+    files: Option<BTreeMap<String, String>>,
+) -> Result<Archetype, FfiError> {
+    match (source, files) {
+        (Some(source), None) => Ok(archetect.load_archetype(source, None)?),
+        (None, Some(files)) => {
+            let mut tree = FileTree::new();
+            for (path, contents) in files {
+                tree.insert(path, contents);
+            }
+            Ok(Archetype::from_files(tree)?)
+        }
+        _ => Err(FfiError::new(
+            ErrorCode::InvalidRequest,
+            "Exactly one of `source` or `files` is required",
+        )),
+    }
+}
+
+/// Splits rendered files into those that are UTF-8, and the rest, encoded as base64.
+fn file_contents(files: &FileTree) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    let mut text = BTreeMap::new();
+    let mut binary = BTreeMap::new();
+    for (path, contents) in files.files() {
+        let path = path.to_string_lossy().into_owned();
+        match std::str::from_utf8(contents) {
+            Ok(contents) => text.insert(path, contents.to_owned()),
+            Err(_) => binary.insert(path, base64::encode(contents)),
+        };
+    }
+    (text, binary)
+}
@@ -0,0 +1,228 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use archetect_core::config::AnswerInfo;
+use linked_hash_map::LinkedHashMap;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict};
+
+create_exception!(
+    pyarchetect,
+    ArchetectError,
+    PyException,
+    "Raised when an archetype cannot be loaded or rendered."
+);
+
+/// Archetect's settings, shared by the archetypes it loads. Renders are always headless, so every variable must be
+/// answered or have a default, and remote archetypes only run the commands they declare with `trust=True`.
+#[pyclass(unsendable, module = "pyarchetect")]
+pub struct Archetect {
+    archetect: archetect_core::Archetect,
+}
+
+#[pymethods]
+impl Archetect {
+    #[new]
+    #[pyo3(signature = (*, offline = false, switches = Vec::new(), validate = false, trust = false))]
+    fn new(offline: bool, switches: Vec<String>, validate: bool, trust: bool) -> PyResult<Archetect> {
+        let mut archetect = archetect_core::Archetect::builder()
+            .with_headless(true)
+            .with_offline(offline)
+            .with_validate(validate)
+            .with_trust(trust)
+            .build()
+            .map_err(error)?;
+        for switch in switches {
+            archetect.enable_switch(switch);
+        }
+        Ok(Archetect { archetect })
+    }
+
+    /// Loads an archetype from a local path or a git URL, as given to `archetect render`.
+    fn load(slf: &Bound<'_, Self>, source: &str) -> PyResult<Archetype> {
+        let archetype = slf.borrow().archetect.load_archetype(source, None).map_err(error)?;
+        Ok(Archetype {
+            archetect: slf.clone().unbind(),
+            archetype,
+        })
+    }
+
+    /// Warnings from the most recent render, as messages.
+    #[getter]
+    fn warnings(&self) -> Vec<String> {
+        self.archetect
+            .warnings()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+}
+
+#[pyclass(unsendable, module = "pyarchetect")]
+pub struct Archetype {
+    archetect: Py<Archetect>,
+    archetype: archetect_core::Archetype,
+}
+
+#[pymethods]
+impl Archetype {
+    /// The archetype's configuration, as a dict: its description, authors, languages, script, and so on.
+    fn describe(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let config = serde_json::to_string(self.archetype.configuration()).map_err(error)?;
+        Ok(py.import("json")?.call_method1("loads", (config,))?.unbind())
+    }
+
+    /// Renders the archetype to `dest`, or in memory when `dest` is None, returning the rendered files by path: `str`
+    /// for text, and `bytes` for anything else.
+    #[pyo3(signature = (answers = None, dest = None))]
+    fn render(
+        &self,
+        py: Python<'_>,
+        answers: Option<&Bound<'_, PyDict>>,
+        dest: Option<PathBuf>,
+    ) -> PyResult<Option<PyObject>> {
+        let answers = match answers {
+            Some(answers) => to_answers(answers)?,
+            None => LinkedHashMap::new(),
+        };
+        let mut archetect = self.archetect.borrow_mut(py);
+        let archetect = &mut archetect.archetect;
+
+        match dest {
+            Some(dest) => {
+                self.archetype.render(archetect, dest, &answers).map_err(error)?;
+                Ok(None)
+            }
+            None => {
+                let files = self.archetype.render_in_memory(archetect, &answers).map_err(error)?;
+                let rendered = PyDict::new(py);
+                for (path, contents) in files.files() {
+                    let path = path.to_string_lossy();
+                    match std::str::from_utf8(contents) {
+                        Ok(contents) => rendered.set_item(path, contents)?,
+                        Err(_) => rendered.set_item(path, PyBytes::new(py, contents))?,
+                    }
+                }
+                Ok(Some(rendered.into_any().unbind()))
+            }
+        }
+    }
+}
+
+/// Converts answers to strings, as they would be given on the command line. Booleans answer `true` or `false`, as
+/// Archetect's own boolean variables expect, rather than Python's `True` or `False`.
+fn to_answers(answers: &Bound<'_, PyDict>) -> PyResult<LinkedHashMap<String, AnswerInfo>> {
+    let mut results = LinkedHashMap::new();
+    for (identifier, value) in answers.iter() {
+        let value = if let Ok(value) = value.downcast::<PyBool>() {
+            value.is_true().to_string()
+        } else {
+            value.str()?.to_string()
+        };
+        results.insert(identifier.extract()?, AnswerInfo::with_value(value).build());
+    }
+    Ok(results)
+}
+
+fn error<E: Display>(error: E) -> PyErr {
+    ArchetectError::new_err(error.to_string())
+}
+
+#[pymodule]
+fn pyarchetect(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Archetect>()?;
+    module.add_class::<Archetype>()?;
+    module.add("ArchetectError", module.py().get_type::<ArchetectError>())?;
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::fs;
+
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    fn run(code: &str, archetype: &std::path::Path) -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "pyarchetect")?;
+            super::pyarchetect(&module)?;
+            let locals = PyDict::new(py);
+            locals.set_item("pyarchetect", module)?;
+            locals.set_item("source", archetype.to_str().unwrap())?;
+            py.run(&CString::new(code).unwrap(), None, Some(&locals))
+        })
+    }
+
+    fn archetype() -> tempfile::TempDir {
+        let archetype_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            archetype_dir.path().join("archetype.yml"),
+            concat!(
+                "description: A service\n",
+                "script:\n  - set:\n      name:\n        prompt: \"Name:\"\n      enabled:\n        prompt: \"Enabled:\"\n",
+                "  - render:\n      directory:\n        source: contents\n",
+            ),
+        )
+        .unwrap();
+        let contents = archetype_dir.path().join("contents").join("{{ name }}");
+        fs::create_dir_all(&contents).unwrap();
+        fs::write(contents.join("README.md"), "# {{ name | title_case }} {{ enabled }}\n").unwrap();
+        archetype_dir
+    }
+
+    #[test]
+    fn test_describe_and_render() {
+        let archetype_dir = archetype();
+        run(
+            r##"
+archetype = pyarchetect.Archetect().load(source)
+assert archetype.describe()["description"] == "A service"
+files = archetype.render({"name": "billing", "enabled": True})
+assert files == {"billing/README.md": "# Billing true\n"}, files
+"##,
+            archetype_dir.path(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_render_destination() {
+        let archetype_dir = archetype();
+        let destination = tempfile::tempdir().unwrap();
+        run(
+            &format!(
+                "assert pyarchetect.Archetect(trust=True).load(source).render({{'name': 'billing', 'enabled': 1}}, {:?}) is None",
+                destination.path().to_str().unwrap()
+            ),
+            archetype_dir.path(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.path().join("billing/README.md")).unwrap(),
+            "# Billing 1\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let archetype_dir = archetype();
+        run(
+            r#"
+archetype = pyarchetect.Archetect().load(source)
+try:
+    archetype.render({"name": "billing"})
+    raise AssertionError("expected an error")
+except pyarchetect.ArchetectError as error:
+    assert "enabled" in str(error), error
+"#,
+            archetype_dir.path(),
+        )
+        .unwrap();
+    }
+}
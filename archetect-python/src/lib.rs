@@ -0,0 +1,15 @@
+//! The `pyarchetect` Python module, for driving Archetect from Python:
+//!
+//! ```python
+//! import pyarchetect
+//!
+//! archetect = pyarchetect.Archetect(offline=True)
+//! archetype = archetect.load("https://github.com/archetect/archetype-rust-cli.git")
+//! print(archetype.describe()["description"])
+//! archetype.render({"name": "billing"}, "services")
+//! ```
+//!
+//! Build the module with `maturin build` from this directory. The bindings are behind the `python` feature, which
+//! maturin enables.
+#[cfg(feature = "python")]
+mod bindings;